@@ -1,4 +1,13 @@
+use crate::bench::Fixture;
 use crate::coordinate_system::geographic::LLBBox;
+use crate::coordinate_system::transformation::Projection;
+use crate::feature_filter::FeatureFilter;
+use crate::ground::TerrainInterpolation;
+use crate::ground_cover::GroundCover;
+use crate::ground_provider::TerrainSource;
+use crate::retrieve_data::QueryFilter;
+use crate::theme::Theme;
+use crate::time_of_day::TimeOfDay;
 use clap::Parser;
 use std::path::PathBuf;
 use std::time::Duration;
@@ -7,14 +16,47 @@ use std::time::Duration;
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 pub struct Args {
-    /// Bounding box of the area (min_lat,min_lng,max_lat,max_lng) (required)
-    #[arg(long, allow_hyphen_values = true, value_parser = LLBBox::from_str)]
-    pub bbox: LLBBox,
+    /// Bounding box of the area (min_lat,min_lng,max_lat,max_lng) (required unless --place is
+    /// given)
+    #[arg(
+        long,
+        allow_hyphen_values = true,
+        value_parser = LLBBox::from_str,
+        required_unless_present = "place",
+        conflicts_with = "place"
+    )]
+    pub bbox: Option<LLBBox>,
+
+    /// Name of the place to generate, such as "Bamberg, Germany", looked up with Nominatim
+    /// instead of giving --bbox (optional)
+    #[arg(long)]
+    pub place: Option<String>,
+
+    /// Generate the area found for --place without asking for confirmation (optional)
+    #[arg(long, short = 'y')]
+    pub yes: bool,
 
-    /// JSON file containing OSM data (optional)
+    /// JSON, .osm XML or .osm.pbf file containing OSM data, generated without downloading. PBF
+    /// extracts are read within the bounding box only (optional)
     #[arg(long, group = "location")]
     pub file: Option<String>,
 
+    /// GeoJSON file of custom features with OSM tags as properties, such as planned buildings,
+    /// added to the OSM data (optional)
+    #[arg(long)]
+    pub geojson: Option<PathBuf>,
+
+    /// Parse a JSON --file element by element, keeping node coordinates in a temporary file, to
+    /// generate very large inputs with less memory (optional)
+    #[arg(long, requires = "file", conflicts_with = "geojson")]
+    pub streaming: bool,
+
+    /// Configuration file of the features to generate by their tags, such as
+    /// `exclude = ["power=*", "barrier=fence"]` or `include_only = ["building", "highway"]`
+    /// (optional)
+    #[arg(long, value_parser = FeatureFilter::load)]
+    pub feature_filter: Option<FeatureFilter>,
+
     /// JSON file to save OSM data to (optional)
     #[arg(long, group = "location")]
     pub save_json_file: Option<String>,
@@ -23,14 +65,47 @@ pub struct Args {
     #[arg(long, value_parser = validate_minecraft_world_path)]
     pub path: PathBuf,
 
+    /// Distance in meters around the bbox to download features from, so that features crossing
+    /// the border are complete up to the edge of the world (optional)
+    #[arg(
+        long,
+        default_value_t = 0.0,
+        allow_hyphen_values = true,
+        value_parser = parse_context_margin
+    )]
+    pub context_margin: f64,
+
     /// Downloader method (requests/curl/wget) (optional)
     #[arg(long, default_value = "requests")]
     pub downloader: String,
 
+    /// Overpass API server to download from before the public servers (optional)
+    #[arg(long)]
+    pub overpass_url: Option<String>,
+
+    /// Comma-separated OSM keys or key=value pairs to download instead of the usual ones, and
+    /// keys prefixed with - to leave out, such as "building,highway" or "-landuse" (optional)
+    #[arg(long, allow_hyphen_values = true, value_parser = QueryFilter::from_str)]
+    pub query_filter: Option<QueryFilter>,
+
+    /// Download OSM data without reading or writing the cache of earlier downloads (optional)
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Download OSM data again, replacing the cached download of the area (optional)
+    #[arg(long, conflicts_with = "no_cache")]
+    pub refresh: bool,
+
     /// World scale to use, in blocks per meter
     #[arg(long, default_value_t = 1.0)]
     pub scale: f64,
 
+    /// Map projection, transverse Mercator true to shape, or equirectangular scaling the degrees
+    /// with the latitude correction of earlier versions
+    /// (transverse-mercator/equirectangular)
+    #[arg(long, value_enum, default_value_t = Projection::TransverseMercator)]
+    pub projection: Projection,
+
     /// Ground level to use in the Minecraft world
     #[arg(long, default_value_t = -62)]
     pub ground_level: i32,
@@ -39,6 +114,50 @@ pub struct Args {
     #[arg(long)]
     pub terrain: bool,
 
+    /// Source of the terrain heights with --terrain, real elevation data or generated without
+    /// internet access (dem/opentopography/file/noise/heightmap) (optional)
+    #[arg(long, value_enum, default_value_t = TerrainSource::Dem)]
+    pub terrain_source: TerrainSource,
+
+    /// Grayscale PNG heightmap used with --terrain-source heightmap, white being highest (optional)
+    #[arg(long)]
+    pub heightmap: Option<PathBuf>,
+
+    /// API key of OpenTopography used with --terrain-source opentopography, also read from
+    /// OPENTOPOGRAPHY_API_KEY (optional)
+    #[arg(long, env = "OPENTOPOGRAPHY_API_KEY", hide_env_values = true)]
+    pub elevation_api_key: Option<String>,
+
+    /// GeoTIFF or ESRI ASCII grid (.asc) of elevations in meters in WGS84 coordinates, used
+    /// with --terrain-source file (optional)
+    #[arg(long)]
+    pub elevation_file: Option<PathBuf>,
+
+    /// Radius in blocks of an extra Gaussian smoothing of the terrain heights, 0 for none (optional)
+    #[arg(long, default_value_t = 0.0)]
+    pub terrain_smoothing: f64,
+
+    /// Interpolation of the terrain heights between the points of the elevation data
+    /// (nearest/bilinear/bicubic) (optional)
+    #[arg(long, value_enum, default_value_t = TerrainInterpolation::Bilinear)]
+    pub terrain_interpolation: TerrainInterpolation,
+
+    /// Altitude in meters above which real terrain is covered with snow (optional)
+    #[arg(long, default_value_t = 2800.0)]
+    pub snow_line: f64,
+
+    /// Altitude in meters above which real terrain turns to bare rock (optional)
+    #[arg(long, default_value_t = 2000.0)]
+    pub tree_line: f64,
+
+    /// Carve a small cave system behind every cave entrance (optional)
+    #[arg(long)]
+    pub cave_systems: bool,
+
+    /// Light a beacon on the summits of major peaks (optional)
+    #[arg(long)]
+    pub peak_beacons: bool,
+
     /// Enable interior generation (optional)
     #[arg(long, default_value_t = true, action = clap::ArgAction::SetTrue)]
     pub interior: bool,
@@ -47,10 +166,78 @@ pub struct Args {
     #[arg(long, default_value_t = true, action = clap::ArgAction::SetTrue)]
     pub roof: bool,
 
+    /// Give large flat commercial roofs a green roof (optional)
+    #[arg(long)]
+    pub green_roofs: bool,
+
     /// Enable filling ground (optional)
     #[arg(long, default_value_t = false, action = clap::ArgAction::SetFalse)]
     pub fillground: bool,
 
+    /// Theme of the world, detected from the location by default (auto/temperate/arid)
+    #[arg(long, value_enum, default_value_t = Theme::Auto)]
+    pub theme: Theme,
+
+    /// Surface of the ground outside of mapped features, defaults to the theme's ground
+    /// (grass/sand/desert/snow/void) (optional)
+    #[arg(long, value_enum)]
+    pub ground_cover: Option<GroundCover>,
+
+    /// Density of the grass, ferns and flowers on meadows, grassland and lawns, 1.0 being
+    /// the default density (optional)
+    #[arg(long, default_value_t = 1.0)]
+    pub meadow_density: f64,
+
+    /// Time of day of the world, night adds lit windows, shop fronts and lamp posts
+    /// (day/night) (optional)
+    #[arg(long, value_enum, default_value_t = TimeOfDay::Day)]
+    pub time: TimeOfDay,
+
+    /// Zlib compression level (0-9) used for region files (optional)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=9))]
+    pub compression_level: Option<u32>,
+
+    /// Rewrite region files after saving to drop unused space (optional)
+    #[arg(long)]
+    pub compact_regions: bool,
+
+    /// Place waymarks along hiking, cycling and skiing routes (optional)
+    #[arg(long)]
+    pub trail_markers: bool,
+
+    /// Record which OSM element placed each block, for use with `arnis inspect` (optional)
+    #[arg(long)]
+    pub ownership_map: bool,
+
+    /// Write index.json with the coordinates of named roads and places, for use with
+    /// `arnis locate` and map tools (optional)
+    #[arg(long)]
+    pub index: bool,
+
+    /// Generate the world without saving it and report the blocks placed by each kind of
+    /// feature and the conflicts between them (optional)
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Rewrite only the chunks of the OSM elements changed since the world at --path was last
+    /// generated with this flag and the same settings, keeping the other chunks and what was
+    /// built in them (optional)
+    #[arg(long, conflicts_with = "dry_run")]
+    pub incremental: bool,
+
+    /// Generate the world in chunk-aligned tiles on all CPU cores (optional)
+    #[arg(long)]
+    pub parallel: bool,
+
+    /// Generate the elements grouped by named suburb or district, logging each one (optional)
+    #[arg(long)]
+    pub chapters: bool,
+
+    /// Skip the check for a newer version at startup, also disabled by setting
+    /// ARNIS_NO_UPDATE_CHECK (optional)
+    #[arg(long)]
+    pub no_update_check: bool,
+
     /// Enable debug mode (optional)
     #[arg(long)]
     pub debug: bool,
@@ -64,6 +251,73 @@ pub struct Args {
     pub spawn_point: Option<(f64, f64)>,
 }
 
+impl Args {
+    /// Bounding box of the area, which main looks up first if it is given with --place
+    pub fn llbbox(&self) -> LLBBox {
+        self.bbox
+            .expect("The bounding box is known before the world is generated")
+    }
+}
+
+/// Arguments of the `arnis inspect` subcommand
+#[derive(Parser, Debug)]
+#[command(
+    name = "arnis inspect",
+    about = "Reports which OSM elements placed the blocks at a coordinate"
+)]
+pub struct InspectArgs {
+    /// X coordinate of the column to inspect
+    #[arg(allow_hyphen_values = true)]
+    pub x: i32,
+
+    /// Z coordinate of the column to inspect
+    #[arg(allow_hyphen_values = true)]
+    pub z: i32,
+
+    /// Path to the generated world, created with --ownership-map
+    #[arg(long)]
+    pub path: PathBuf,
+}
+
+/// Arguments of the `arnis locate` subcommand
+#[derive(Parser, Debug)]
+#[command(
+    name = "arnis locate",
+    about = "Prints the coordinates of named roads and places for use with /tp"
+)]
+pub struct LocateArgs {
+    /// Name or part of the name to look for, ignoring case
+    pub name: String,
+
+    /// Path to the generated world, created with --index
+    #[arg(long)]
+    pub path: PathBuf,
+}
+
+/// Arguments of the `arnis bench` subcommand
+#[derive(Parser, Debug)]
+#[command(
+    name = "arnis bench",
+    about = "Times every stage of generating fixture towns or an OSM extract"
+)]
+pub struct BenchArgs {
+    /// Fixture town to generate, all of them if not given
+    #[arg(long, value_enum)]
+    pub fixture: Option<Fixture>,
+
+    /// JSON, .osm XML or .osm.pbf file of OSM data to generate instead of the fixture towns
+    #[arg(long, requires = "bbox")]
+    pub file: Option<String>,
+
+    /// Bounding box of the data in --file (min_lat,min_lng,max_lat,max_lng)
+    #[arg(long, allow_hyphen_values = true, value_parser = LLBBox::from_str)]
+    pub bbox: Option<LLBBox>,
+
+    /// Number of runs of every world, of which the fastest is reported
+    #[arg(long, default_value_t = 3)]
+    pub runs: usize,
+}
+
 fn validate_minecraft_world_path(path: &str) -> Result<PathBuf, String> {
     let mc_world_path = PathBuf::from(path);
     if !mc_world_path.exists() {
@@ -79,6 +333,16 @@ fn validate_minecraft_world_path(path: &str) -> Result<PathBuf, String> {
     Ok(mc_world_path)
 }
 
+fn parse_context_margin(arg: &str) -> Result<f64, String> {
+    let margin: f64 = arg.parse().map_err(|e| format!("{e}"))?;
+    if !margin.is_finite() || margin < 0.0 {
+        return Err(format!(
+            "The context margin must be at least 0 meters: {arg}"
+        ));
+    }
+    Ok(margin)
+}
+
 fn parse_duration(arg: &str) -> Result<std::time::Duration, std::num::ParseIntError> {
     let seconds = arg.parse()?;
     Ok(std::time::Duration::from_secs(seconds))
@@ -138,4 +402,47 @@ mod tests {
         // let cmd = ["arnis", "--gui"];
         // assert!(Args::try_parse_from(cmd.iter()).is_ok());
     }
+
+    #[test]
+    fn test_context_margin() {
+        let tmpdir = minecraft_tmpdir();
+        let tmp_path = tmpdir.path().to_str().unwrap();
+        let parse = |margin: &str| {
+            let cmd = [
+                "arnis",
+                "--path",
+                tmp_path,
+                "--bbox",
+                "1,2,3,4",
+                "--context-margin",
+                margin,
+            ];
+            Args::try_parse_from(cmd.iter()).map(|args| args.context_margin)
+        };
+
+        assert_eq!(parse("150").unwrap(), 150.0);
+        assert_eq!(parse("0").unwrap(), 0.0);
+        assert!(parse("-20").is_err());
+        assert!(parse("NaN").is_err());
+        assert!(parse("inf").is_err());
+    }
+
+    #[test]
+    fn test_place_instead_of_bbox() {
+        let tmpdir = minecraft_tmpdir();
+        let tmp_path = tmpdir.path().to_str().unwrap();
+
+        let cmd = ["arnis", "--path", tmp_path, "--place", "Bamberg, Germany"];
+        let args = Args::try_parse_from(cmd.iter()).unwrap();
+        assert_eq!(args.place.as_deref(), Some("Bamberg, Germany"));
+        assert!(args.bbox.is_none());
+
+        let cmd = [
+            "arnis", "--path", tmp_path, "--place", "Bamberg", "--bbox", "1,2,3,4",
+        ];
+        assert!(Args::try_parse_from(cmd.iter()).is_err());
+
+        let cmd = ["arnis", "--path", tmp_path, "--yes"];
+        assert!(Args::try_parse_from(cmd.iter()).is_err());
+    }
 }
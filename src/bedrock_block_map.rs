@@ -69,6 +69,27 @@ pub fn to_bedrock_block(block: Block) -> BedrockBlock {
             )],
         ),
 
+        // Fern is a kind of "tallgrass" in Bedrock as well
+        "fern" => BedrockBlock::with_states(
+            "tallgrass",
+            vec![(
+                "tall_grass_type",
+                BedrockBlockStateValue::String("fern".to_string()),
+            )],
+        ),
+
+        // Hanging dripstone is marked by a flag in Bedrock
+        "pointed_dripstone" => BedrockBlock::with_states(
+            "pointed_dripstone",
+            vec![
+                ("hanging", BedrockBlockStateValue::Bool(true)),
+                (
+                    "dripstone_thickness",
+                    BedrockBlockStateValue::String("tip".to_string()),
+                ),
+            ],
+        ),
+
         // Tall grass needs height state
         "tall_grass" => BedrockBlock::with_states(
             "double_plant",
@@ -102,6 +123,30 @@ pub fn to_bedrock_block(block: Block) -> BedrockBlock {
             ],
         ),
 
+        // Spruce leaves with persistence
+        "spruce_leaves" => BedrockBlock::with_states(
+            "leaves",
+            vec![
+                (
+                    "old_leaf_type",
+                    BedrockBlockStateValue::String("spruce".to_string()),
+                ),
+                ("persistent_bit", BedrockBlockStateValue::Bool(true)),
+            ],
+        ),
+
+        // Jungle leaves with persistence
+        "jungle_leaves" => BedrockBlock::with_states(
+            "leaves",
+            vec![
+                (
+                    "old_leaf_type",
+                    BedrockBlockStateValue::String("jungle".to_string()),
+                ),
+                ("persistent_bit", BedrockBlockStateValue::Bool(true)),
+            ],
+        ),
+
         // Oak log with axis (default up_down)
         "oak_log" => BedrockBlock::with_states(
             "oak_log",
@@ -129,6 +174,15 @@ pub fn to_bedrock_block(block: Block) -> BedrockBlock {
             )],
         ),
 
+        // Jungle log with axis
+        "jungle_log" => BedrockBlock::with_states(
+            "jungle_log",
+            vec![(
+                "pillar_axis",
+                BedrockBlockStateValue::String("y".to_string()),
+            )],
+        ),
+
         // Stone slab (bottom half by default)
         "stone_slab" => BedrockBlock::with_states(
             "stone_block_slab",
@@ -533,8 +587,16 @@ pub fn to_bedrock_block(block: Block) -> BedrockBlock {
         "red_nether_bricks" => BedrockBlock::simple("red_nether_brick"),
         "snow_block" => BedrockBlock::simple("snow"),
         "dirt_path" => BedrockBlock::simple("grass_path"),
+        "magma_block" => BedrockBlock::simple("magma"),
         "dead_bush" => BedrockBlock::simple("deadbush"),
+        "sugar_cane" => BedrockBlock::simple("reeds"),
+        "beetroots" => BedrockBlock::simple("beetroot"),
         "note_block" => BedrockBlock::simple("noteblock"),
+        "cave_air" => BedrockBlock::simple("air"),
+        "light" => BedrockBlock::with_states(
+            "light_block",
+            vec![("block_light_level", BedrockBlockStateValue::Int(15))],
+        ),
 
         // Oak items mapped to dark_oak in Bedrock (or generic equivalents)
         "oak_pressure_plate" => BedrockBlock::simple("wooden_pressure_plate"),
@@ -588,6 +650,11 @@ pub fn to_bedrock_block_with_properties(
         return convert_log(java_name, props_map);
     }
 
+    // Handle wall signs with facing property
+    if java_name == "oak_wall_sign" {
+        return convert_wall_sign(props_map);
+    }
+
     // Fall back to basic conversion without properties
     to_bedrock_block(block)
 }
@@ -650,6 +717,33 @@ fn convert_stairs(
     }
 }
 
+/// Convert Java wall sign to Bedrock format with proper orientation.
+fn convert_wall_sign(
+    props: Option<&std::collections::HashMap<String, fastnbt::Value>>,
+) -> BedrockBlock {
+    // Bedrock: 2=north, 3=south, 4=west, 5=east
+    let direction = match props.and_then(|props| props.get("facing")) {
+        Some(fastnbt::Value::String(facing)) => match facing.as_str() {
+            "south" => 3,
+            "west" => 4,
+            "east" => 5,
+            _ => 2,
+        },
+        _ => 2,
+    };
+
+    let mut states = HashMap::new();
+    states.insert(
+        "facing_direction".to_string(),
+        BedrockBlockStateValue::Int(direction),
+    );
+
+    BedrockBlock {
+        name: "minecraft:wall_sign".to_string(),
+        states,
+    }
+}
+
 /// Convert Java slab block to Bedrock format with proper type.
 fn convert_slab(
     java_name: &str,
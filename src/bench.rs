@@ -0,0 +1,321 @@
+//! `arnis bench`, which generates fixture towns or an OSM extract and reports the time of
+//! every stage and the number of generated blocks, so that performance work can be compared
+//! across releases.
+//!
+//! The fixture towns are laid out by a fixed pattern of streets, houses and parks. They need no
+//! internet access and every release processes exactly the same data.
+
+use crate::args::{Args, BenchArgs};
+use crate::coordinate_system::geographic::LLBBox;
+use crate::data_processing;
+use crate::ground::Ground;
+use crate::osm_parser;
+use crate::retrieve_data;
+use clap::Parser;
+use colored::Colorize;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Latitude and longitude of the south-west corner of the fixture towns
+const FIXTURE_ORIGIN: (f64, f64) = (48.0, 11.0);
+/// Side length of a city block of the fixture towns in meters
+const FIXTURE_BLOCK_SIZE: f64 = 100.0;
+/// Every this many city blocks is a park instead of houses
+const FIXTURE_PARK_INTERVAL: usize = 7;
+/// Meters per degree of latitude
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// Fixture towns of `arnis bench`
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fixture {
+    /// 4 by 4 city blocks
+    Small,
+    /// 12 by 12 city blocks
+    Medium,
+}
+
+impl Fixture {
+    fn blocks_per_side(self) -> usize {
+        match self {
+            Fixture::Small => 4,
+            Fixture::Medium => 12,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Fixture::Small => "small",
+            Fixture::Medium => "medium",
+        }
+    }
+}
+
+static RECORDING: AtomicBool = AtomicBool::new(false);
+static STAGES: Mutex<Vec<(&'static str, Instant)>> = Mutex::new(Vec::new());
+static BLOCKS: AtomicU64 = AtomicU64::new(0);
+
+/// Marks the start of a stage of the world generation while a benchmark runs
+pub fn mark_stage(name: &'static str) {
+    if is_recording() {
+        STAGES.lock().unwrap().push((name, Instant::now()));
+    }
+}
+
+/// Returns true while a benchmark runs
+pub fn is_recording() -> bool {
+    RECORDING.load(Ordering::Relaxed)
+}
+
+/// Records the number of blocks of the generated world before it is saved
+pub fn record_block_count(count: u64) {
+    BLOCKS.store(count, Ordering::Relaxed);
+}
+
+/// Timings and size of one generated world
+struct RunReport {
+    stages: Vec<(&'static str, Duration)>,
+    elements: usize,
+    blocks: u64,
+}
+
+impl RunReport {
+    fn total(&self) -> Duration {
+        self.stages.iter().map(|(_, duration)| *duration).sum()
+    }
+}
+
+pub fn run(bench_args: BenchArgs) -> Result<(), String> {
+    let mut datasets: Vec<(String, Value, LLBBox)> = Vec::new();
+    if let Some(file) = &bench_args.file {
+        let bbox = bench_args.bbox.ok_or("--file requires --bbox")?;
+        let data = retrieve_data::fetch_data_from_file(file, &bbox).map_err(|e| e.to_string())?;
+        datasets.push((file.clone(), data, bbox));
+    } else {
+        let fixtures = match bench_args.fixture {
+            Some(fixture) => vec![fixture],
+            None => vec![Fixture::Small, Fixture::Medium],
+        };
+        for fixture in fixtures {
+            let (data, bbox) = fixture_town(fixture.blocks_per_side());
+            datasets.push((fixture.name().to_string(), data, bbox));
+        }
+    }
+
+    let path = std::env::temp_dir().join("arnis-bench");
+    let mut reports: Vec<(String, RunReport)> = Vec::new();
+    for (name, data, bbox) in datasets {
+        let mut fastest: Option<RunReport> = None;
+        for _ in 0..bench_args.runs.max(1) {
+            let report = run_once(&data, bbox, &path)?;
+            if fastest.as_ref().is_none_or(|f| report.total() < f.total()) {
+                fastest = Some(report);
+            }
+        }
+        reports.extend(fastest.map(|report| (name, report)));
+    }
+    let _ = std::fs::remove_dir_all(&path);
+
+    println!("\n{}", "Benchmark results (fastest run)".bold());
+    for (name, report) in &reports {
+        println!(
+            "{}: {} elements, {} blocks",
+            name.bold(),
+            report.elements,
+            report.blocks
+        );
+        for (stage, duration) in &report.stages {
+            println!("  {stage:<20} {:>8.2} s", duration.as_secs_f64());
+        }
+        println!("  {:<20} {:>8.2} s", "total", report.total().as_secs_f64());
+    }
+    Ok(())
+}
+
+/// Generates one world into an empty directory, recording the stages on the way
+fn run_once(data: &Value, bbox: LLBBox, path: &Path) -> Result<RunReport, String> {
+    let _ = std::fs::remove_dir_all(path);
+    std::fs::create_dir_all(path.join("region")).map_err(|e| e.to_string())?;
+    let args = Args::try_parse_from([
+        "arnis".to_string(),
+        "--bbox".to_string(),
+        format!(
+            "{},{},{},{}",
+            bbox.min().lat(),
+            bbox.min().lng(),
+            bbox.max().lat(),
+            bbox.max().lng()
+        ),
+        "--path".to_string(),
+        path.display().to_string(),
+    ])
+    .map_err(|e| e.to_string())?;
+
+    let data = data.clone();
+    STAGES.lock().unwrap().clear();
+    RECORDING.store(true, Ordering::Relaxed);
+
+    mark_stage("parsing");
+    let (mut elements, xzbbox) = osm_parser::parse_osm_data(
+        data,
+        bbox,
+        args.scale,
+        args.projection,
+        args.context_margin,
+        false,
+    );
+    elements.sort_by_key(osm_parser::get_priority);
+    let element_count = elements.len();
+    let ground = Ground::new_flat(args.ground_level);
+    let result = data_processing::generate_world(elements, xzbbox, bbox, ground, &args);
+    mark_stage("end");
+
+    RECORDING.store(false, Ordering::Relaxed);
+    result?;
+
+    let marks = std::mem::take(&mut *STAGES.lock().unwrap());
+    let stages = marks
+        .windows(2)
+        .map(|pair| (pair[0].0, pair[1].1 - pair[0].1))
+        .collect();
+    Ok(RunReport {
+        stages,
+        elements: element_count,
+        blocks: BLOCKS.load(Ordering::Relaxed),
+    })
+}
+
+/// Collects the elements of a fixture town in Overpass JSON
+struct FixtureBuilder {
+    elements: Vec<Value>,
+    next_id: u64,
+}
+
+impl FixtureBuilder {
+    /// Adds a node at the given meters east and north of the origin and returns its id
+    fn node(&mut self, x: f64, z: f64, tags: Option<Value>) -> u64 {
+        let (lat, lon) = fixture_lat_lng(x, z);
+        self.next_id += 1;
+        let mut node = json!({ "type": "node", "id": self.next_id, "lat": lat, "lon": lon });
+        if let Some(tags) = tags {
+            node["tags"] = tags;
+        }
+        self.elements.push(node);
+        self.next_id
+    }
+
+    fn way(&mut self, nodes: Vec<u64>, tags: Value) {
+        self.next_id += 1;
+        let way = json!({ "type": "way", "id": self.next_id, "nodes": nodes, "tags": tags });
+        self.elements.push(way);
+    }
+
+    /// Adds a closed square way with its south-west corner at the given position
+    fn square(&mut self, x: f64, z: f64, side: f64, tags: Value) {
+        let corners = [(x, z), (x + side, z), (x + side, z + side), (x, z + side)];
+        let mut nodes: Vec<u64> = corners
+            .iter()
+            .map(|&(x, z)| self.node(x, z, None))
+            .collect();
+        nodes.push(nodes[0]);
+        self.way(nodes, tags);
+    }
+}
+
+/// Converts meters east and north of the origin of the fixture towns to latitude and longitude
+fn fixture_lat_lng(x: f64, z: f64) -> (f64, f64) {
+    let (origin_lat, origin_lng) = FIXTURE_ORIGIN;
+    let meters_per_degree_lng = METERS_PER_DEGREE * origin_lat.to_radians().cos();
+    (
+        origin_lat + z / METERS_PER_DEGREE,
+        origin_lng + x / meters_per_degree_lng,
+    )
+}
+
+/// Builds the Overpass data of a town of square city blocks with the given number of blocks
+/// per side, returning it with its bounding box
+fn fixture_town(blocks_per_side: usize) -> (Value, LLBBox) {
+    let mut town = FixtureBuilder {
+        elements: Vec::new(),
+        next_id: 0,
+    };
+
+    // Streets along the borders of the city blocks, every fourth one a main road
+    let size = blocks_per_side as f64 * FIXTURE_BLOCK_SIZE;
+    for i in 0..=blocks_per_side {
+        let offset = i as f64 * FIXTURE_BLOCK_SIZE;
+        let highway = if i % 4 == 0 {
+            "secondary"
+        } else {
+            "residential"
+        };
+        let nodes = vec![town.node(0.0, offset, None), town.node(size, offset, None)];
+        town.way(
+            nodes,
+            json!({ "highway": highway, "name": format!("{} Street", i + 1) }),
+        );
+        let nodes = vec![town.node(offset, 0.0, None), town.node(offset, size, None)];
+        town.way(
+            nodes,
+            json!({ "highway": highway, "name": format!("{} Avenue", i + 1) }),
+        );
+    }
+
+    for block in 0..blocks_per_side * blocks_per_side {
+        let x = (block % blocks_per_side) as f64 * FIXTURE_BLOCK_SIZE;
+        let z = (block / blocks_per_side) as f64 * FIXTURE_BLOCK_SIZE;
+        if block % FIXTURE_PARK_INTERVAL == 0 {
+            town.square(x + 10.0, z + 10.0, 80.0, json!({ "leisure": "park" }));
+            town.node(x + 50.0, z + 50.0, Some(json!({ "natural": "tree" })));
+            continue;
+        }
+        // Four houses of different heights per block
+        for (house, (dx, dz)) in [(12.0, 12.0), (55.0, 12.0), (12.0, 55.0), (55.0, 55.0)]
+            .into_iter()
+            .enumerate()
+        {
+            let levels = (2 + (block + house) % 4).to_string();
+            let building = if house % 2 == 0 {
+                "house"
+            } else {
+                "apartments"
+            };
+            let tags = json!({ "building": building, "building:levels": levels });
+            town.square(x + dx, z + dz, 33.0, tags);
+        }
+    }
+
+    let margin = 10.0;
+    let (min_lat, min_lng) = fixture_lat_lng(-margin, -margin);
+    let (max_lat, max_lng) = fixture_lat_lng(size + margin, size + margin);
+    let bbox = LLBBox::new(min_lat, min_lng, max_lat, max_lng).unwrap();
+    (json!({ "elements": town.elements }), bbox)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_fixture_has_houses_and_parks() {
+        let (data, _) = fixture_town(Fixture::Small.blocks_per_side());
+        let ways: Vec<&Value> = data["elements"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|element| element["type"] == "way")
+            .collect();
+        let count = |key: &str| {
+            ways.iter()
+                .filter(|way| way["tags"][key].is_string())
+                .count()
+        };
+
+        // Blocks 0, 7 and 14 of the 16 are parks
+        assert_eq!(count("leisure"), 3);
+        assert_eq!(count("building"), 13 * 4);
+        assert_eq!(count("highway"), 10);
+    }
+}
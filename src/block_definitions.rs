@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use crate::colors::RGBTuple;
+use crate::element_rng::element_rng;
 
 // Enums for stair properties
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -266,6 +267,29 @@ impl Block {
             185 => "quartz_stairs",
             186 => "polished_andesite_stairs",
             187 => "nether_brick_stairs",
+            188 => "cave_air",
+            189 => "oak_wall_sign",
+            190 => "acacia_log",
+            191 => "acacia_leaves",
+            192 => "light",
+            193 => "sea_lantern",
+            194 => "spruce_leaves",
+            195 => "jungle_log",
+            196 => "jungle_leaves",
+            197 => "sugar_cane",
+            198 => "beetroots",
+            199 => "turtle_egg",
+            200 => "mangrove_log",
+            201 => "mangrove_leaves",
+            202 => "mangrove_roots",
+            203 => "blue_ice",
+            204 => "chain",
+            205 => "fern",
+            206 => "dripstone_block",
+            207 => "pointed_dripstone",
+            208 => "beacon",
+            209 => "basalt",
+            210 => "magma_block",
             _ => panic!("Invalid id"),
         }
     }
@@ -278,12 +302,58 @@ impl Block {
                 map
             })),
 
+            207 => Some(Value::Compound({
+                let mut map: HashMap<String, Value> = HashMap::new();
+                map.insert(
+                    "vertical_direction".to_string(),
+                    Value::String("down".to_string()),
+                );
+                map.insert("thickness".to_string(), Value::String("tip".to_string()));
+                map
+            })),
+
             49 => Some(Value::Compound({
                 let mut map: HashMap<String, Value> = HashMap::new();
                 map.insert("persistent".to_string(), Value::String("true".to_string()));
                 map
             })),
 
+            191 => Some(Value::Compound({
+                let mut map: HashMap<String, Value> = HashMap::new();
+                map.insert("persistent".to_string(), Value::String("true".to_string()));
+                map
+            })),
+
+            194 => Some(Value::Compound({
+                let mut map: HashMap<String, Value> = HashMap::new();
+                map.insert("persistent".to_string(), Value::String("true".to_string()));
+                map
+            })),
+
+            196 => Some(Value::Compound({
+                let mut map: HashMap<String, Value> = HashMap::new();
+                map.insert("persistent".to_string(), Value::String("true".to_string()));
+                map
+            })),
+
+            192 => Some(Value::Compound({
+                let mut map: HashMap<String, Value> = HashMap::new();
+                map.insert("level".to_string(), Value::String("15".to_string()));
+                map
+            })),
+
+            24 => Some(Value::Compound({
+                let mut map: HashMap<String, Value> = HashMap::new();
+                map.insert("moisture".to_string(), Value::String("7".to_string()));
+                map
+            })),
+
+            198 => Some(Value::Compound({
+                let mut map: HashMap<String, Value> = HashMap::new();
+                map.insert("age".to_string(), Value::String("3".to_string()));
+                map
+            })),
+
             105 => Some(Value::Compound({
                 let mut map: HashMap<String, Value> = HashMap::new();
                 map.insert("age".to_string(), Value::String("7".to_string()));
@@ -697,6 +767,29 @@ pub const SMOOTH_SANDSTONE_STAIRS: Block = Block::new(184);
 pub const QUARTZ_STAIRS: Block = Block::new(185);
 pub const POLISHED_ANDESITE_STAIRS: Block = Block::new(186);
 pub const NETHER_BRICK_STAIRS: Block = Block::new(187);
+pub const CAVE_AIR: Block = Block::new(188);
+pub const OAK_WALL_SIGN: Block = Block::new(189);
+pub const ACACIA_LOG: Block = Block::new(190);
+pub const ACACIA_LEAVES: Block = Block::new(191);
+pub const LIGHT: Block = Block::new(192);
+pub const SEA_LANTERN: Block = Block::new(193);
+pub const SPRUCE_LEAVES: Block = Block::new(194);
+pub const JUNGLE_LOG: Block = Block::new(195);
+pub const JUNGLE_LEAVES: Block = Block::new(196);
+pub const SUGAR_CANE: Block = Block::new(197);
+pub const BEETROOTS: Block = Block::new(198);
+pub const TURTLE_EGG: Block = Block::new(199);
+pub const MANGROVE_LOG: Block = Block::new(200);
+pub const MANGROVE_LEAVES: Block = Block::new(201);
+pub const MANGROVE_ROOTS: Block = Block::new(202);
+pub const BLUE_ICE: Block = Block::new(203);
+pub const CHAIN: Block = Block::new(204);
+pub const FERN: Block = Block::new(205);
+pub const DRIPSTONE_BLOCK: Block = Block::new(206);
+pub const POINTED_DRIPSTONE: Block = Block::new(207);
+pub const BEACON: Block = Block::new(208);
+pub const BASALT: Block = Block::new(209);
+pub const MAGMA_BLOCK: Block = Block::new(210);
 
 /// Maps a block to its corresponding stair variant
 #[inline]
@@ -751,7 +844,7 @@ pub static WINDOW_VARIATIONS: [Block; 7] = [
 // Window types for different building styles
 pub fn get_window_block_for_building_type(building_type: &str) -> Block {
     use rand::Rng;
-    let mut rng = rand::thread_rng();
+    let mut rng = element_rng();
 
     match building_type {
         "residential" | "house" | "apartment" => {
@@ -787,7 +880,7 @@ pub fn get_window_block_for_building_type(building_type: &str) -> Block {
 // Random floor block selection
 pub fn get_random_floor_block() -> Block {
     use rand::Rng;
-    let mut rng = rand::thread_rng();
+    let mut rng = element_rng();
 
     let floor_options = [
         WHITE_CONCRETE,
@@ -934,7 +1027,7 @@ static DEFINED_COLORS: &[ColorBlockMapping] = &[
 // Function to randomly select building wall block with alternatives
 pub fn get_building_wall_block_for_color(color: RGBTuple) -> Block {
     use rand::Rng;
-    let mut rng = rand::thread_rng();
+    let mut rng = element_rng();
 
     // Find the closest color match
     let closest_color = DEFINED_COLORS
@@ -952,7 +1045,7 @@ pub fn get_building_wall_block_for_color(color: RGBTuple) -> Block {
 // Function to get a random fallback building block when no color attribute is specified
 pub fn get_fallback_building_block() -> Block {
     use rand::Rng;
-    let mut rng = rand::thread_rng();
+    let mut rng = element_rng();
 
     let fallback_options = [
         BLACKSTONE,
@@ -989,7 +1082,7 @@ pub fn get_fallback_building_block() -> Block {
 // Function to get a random castle wall block
 pub fn get_castle_wall_block() -> Block {
     use rand::Rng;
-    let mut rng = rand::thread_rng();
+    let mut rng = element_rng();
 
     let castle_wall_options = [
         STONE_BRICKS,
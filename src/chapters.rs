@@ -0,0 +1,203 @@
+//! Chapters of the world, which group the elements by the named suburb or district they lie in
+//! with `--chapters`.
+//!
+//! Elements are given to the named place area around their middle, preferring the coarser
+//! kinds of places. Within a chapter the elements keep their processing order. Elements outside
+//! of every named area form the last chapter. With `--parallel` the world is split into tiles
+//! instead.
+
+use crate::osm_parser::ProcessedElement;
+
+/// Values of the place tag that form chapters, from the coarsest to the finest
+const CHAPTER_PLACES: [&str; 5] = [
+    "borough",
+    "city_district",
+    "suburb",
+    "quarter",
+    "neighbourhood",
+];
+
+/// Elements of one named area, or of the rest of the world if it has no name
+pub struct Chapter {
+    pub name: Option<String>,
+    /// Indices of the elements in their processing order
+    pub elements: Vec<usize>,
+}
+
+impl Chapter {
+    /// Returns the progress line printed when the chapter starts, such as
+    /// "Generating Altstadt… 1,234 buildings"
+    pub fn heading(&self, elements: &[ProcessedElement]) -> String {
+        let buildings = self
+            .elements
+            .iter()
+            .filter(|&&i| elements[i].tags().contains_key("building"))
+            .count();
+        let name = self.name.as_deref().unwrap_or("the rest of the world");
+        format!("Generating {name}… {} buildings", group_digits(buildings))
+    }
+}
+
+/// Formats a number with commas between groups of three digits
+fn group_digits(number: usize) -> String {
+    let digits = number.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// A named place area with the edges of its outline
+struct NamedArea {
+    name: String,
+    level: usize,
+    edges: Vec<((i32, i32), (i32, i32))>,
+}
+
+impl NamedArea {
+    /// Even-odd test, which also works for outer rings split over several ways
+    fn contains(&self, (x, z): (f64, f64)) -> bool {
+        let mut inside = false;
+        for &((ax, az), (bx, bz)) in &self.edges {
+            let (ax, az, bx, bz) = (ax as f64, az as f64, bx as f64, bz as f64);
+            if (az > z) != (bz > z) && x < ax + (z - az) / (bz - az) * (bx - ax) {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+}
+
+/// Returns the named place area described by an element, if it is one
+fn named_area(element: &ProcessedElement) -> Option<NamedArea> {
+    let tags = element.tags();
+    let name = tags.get("name")?;
+    let level = CHAPTER_PLACES
+        .iter()
+        .position(|place| tags.get("place").map(String::as_str) == Some(place))?;
+
+    let rings: Vec<Vec<(i32, i32)>> = match element {
+        ProcessedElement::Way(way) => vec![way.nodes.iter().map(|n| (n.x, n.z)).collect()],
+        ProcessedElement::Relation(rel) => rel
+            .outer_ways()
+            .map(|way| way.nodes.iter().map(|n| (n.x, n.z)).collect())
+            .collect(),
+        ProcessedElement::Node(_) => return None,
+    };
+    let edges: Vec<((i32, i32), (i32, i32))> = rings
+        .iter()
+        .flat_map(|ring| ring.windows(2).map(|pair| (pair[0], pair[1])))
+        .collect();
+    if edges.len() < 3 {
+        return None;
+    }
+
+    Some(NamedArea {
+        name: name.clone(),
+        level,
+        edges,
+    })
+}
+
+/// Returns the average position of the nodes of an element, None if it has no nodes
+fn middle(element: &ProcessedElement) -> Option<(f64, f64)> {
+    let points: Vec<(i32, i32)> = match element {
+        ProcessedElement::Node(node) => vec![(node.x, node.z)],
+        ProcessedElement::Way(way) => way.nodes.iter().map(|n| (n.x, n.z)).collect(),
+        ProcessedElement::Relation(rel) => rel
+            .members
+            .iter()
+            .flat_map(|member| member.way.nodes.iter().map(|n| (n.x, n.z)))
+            .collect(),
+    };
+    if points.is_empty() {
+        return None;
+    }
+    let count = points.len() as f64;
+    let (sum_x, sum_z) = points.iter().fold((0.0, 0.0), |(sx, sz), &(x, z)| {
+        (sx + x as f64, sz + z as f64)
+    });
+    Some((sum_x / count, sum_z / count))
+}
+
+/// Groups the elements into one chapter per named area, followed by the rest of the world
+pub fn group_elements(elements: &[ProcessedElement]) -> Vec<Chapter> {
+    let mut areas: Vec<NamedArea> = elements.iter().filter_map(named_area).collect();
+    // Coarser places first, so that an element goes to the suburb rather than its quarter
+    areas.sort_by_key(|area| area.level);
+
+    let mut chapters: Vec<Chapter> = areas
+        .iter()
+        .map(|area| Chapter {
+            name: Some(area.name.clone()),
+            elements: Vec::new(),
+        })
+        .collect();
+    let mut rest = Chapter {
+        name: None,
+        elements: Vec::new(),
+    };
+
+    for (i, element) in elements.iter().enumerate() {
+        let area =
+            middle(element).and_then(|point| areas.iter().position(|area| area.contains(point)));
+        match area {
+            Some(area) => chapters[area].elements.push(i),
+            None => rest.elements.push(i),
+        }
+    }
+
+    chapters.retain(|chapter| !chapter.elements.is_empty());
+    chapters.push(rest);
+    chapters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osm_parser::{ProcessedNode, ProcessedWay};
+    use std::collections::HashMap;
+
+    fn node(id: u64, x: i32, z: i32) -> ProcessedNode {
+        ProcessedNode {
+            id,
+            tags: HashMap::new(),
+            x,
+            z,
+        }
+    }
+
+    fn square(id: u64, place: &str, name: &str, size: i32) -> ProcessedElement {
+        let corners = [(0, 0), (size, 0), (size, size), (0, size), (0, 0)];
+        ProcessedElement::Way(ProcessedWay {
+            id,
+            nodes: corners.iter().map(|&(x, z)| node(0, x, z)).collect(),
+            tags: HashMap::from([
+                ("place".to_string(), place.to_string()),
+                ("name".to_string(), name.to_string()),
+            ]),
+        })
+    }
+
+    #[test]
+    fn test_elements_go_to_coarsest_area_around_them() {
+        let elements = vec![
+            square(1, "quarter", "Altstadt", 50),
+            square(2, "suburb", "Mitte", 100),
+            ProcessedElement::Node(node(3, 20, 20)),
+            ProcessedElement::Node(node(4, 500, 500)),
+        ];
+        let chapters = group_elements(&elements);
+
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].name.as_deref(), Some("Mitte"));
+        assert_eq!(chapters[0].elements, vec![0, 1, 2]);
+        assert_eq!(chapters[1].name, None);
+        assert_eq!(chapters[1].elements, vec![3]);
+        assert_eq!(group_digits(1234567), "1,234,567");
+    }
+}
@@ -17,7 +17,7 @@ pub fn clip_way_to_bbox(nodes: &[ProcessedNode], xzbbox: &XZBBox) -> Vec<Process
     let is_closed = is_closed_polygon(nodes);
 
     if !is_closed {
-        return clip_polyline_to_bbox(nodes, xzbbox);
+        return clip_polyline_to_bbox(nodes, xzbbox).concat();
     }
 
     // If all nodes are inside the bbox, return unchanged
@@ -63,6 +63,23 @@ pub fn clip_way_to_bbox(nodes: &[ProcessedNode], xzbbox: &XZBBox) -> Vec<Process
     assign_node_ids_preserving_endpoints(nodes, polygon, way_id)
 }
 
+/// Clips a way to the bounding box like `clip_way_to_bbox`, but splits paths that leave the
+/// bbox and come back into one piece per stretch inside.
+pub fn clip_way_to_bbox_pieces(
+    nodes: &[ProcessedNode],
+    xzbbox: &XZBBox,
+) -> Vec<Vec<ProcessedNode>> {
+    if is_closed_polygon(nodes) {
+        let clipped = clip_way_to_bbox(nodes, xzbbox);
+        return if clipped.is_empty() {
+            Vec::new()
+        } else {
+            vec![clipped]
+        };
+    }
+    clip_polyline_to_bbox(nodes, xzbbox)
+}
+
 /// Clips a water polygon ring to bbox using Sutherland-Hodgman (post-ring-merge).
 pub fn clip_water_ring_to_bbox(
     ring: &[ProcessedNode],
@@ -169,8 +186,10 @@ fn is_ring_outside_bbox(
     all_left || all_right || all_top || all_bottom
 }
 
-/// Clips a polyline (open path) to the bounding box.
-fn clip_polyline_to_bbox(nodes: &[ProcessedNode], xzbbox: &XZBBox) -> Vec<ProcessedNode> {
+/// Clips a polyline (open path) to the bounding box. A path that leaves the bbox and comes back
+/// is split into one piece per stretch inside, so that no straight segment is drawn across the
+/// area between where it leaves and where it comes back.
+fn clip_polyline_to_bbox(nodes: &[ProcessedNode], xzbbox: &XZBBox) -> Vec<Vec<ProcessedNode>> {
     if nodes.is_empty() {
         return Vec::new();
     }
@@ -180,7 +199,20 @@ fn clip_polyline_to_bbox(nodes: &[ProcessedNode], xzbbox: &XZBBox) -> Vec<Proces
     let max_x = xzbbox.max_x() as f64;
     let max_z = xzbbox.max_z() as f64;
 
-    let mut result = Vec::new();
+    let mut pieces: Vec<Vec<ProcessedNode>> = Vec::new();
+    let mut piece: Vec<ProcessedNode> = Vec::new();
+    // Nodes emitted so far, which keeps the ids of the synthetic nodes apart
+    let mut emitted: u64 = 0;
+    let mut synthetic_node = |point: (f64, f64)| {
+        let node = ProcessedNode {
+            id: nodes[0].id.wrapping_mul(10000000).wrapping_add(emitted),
+            x: point.0.round() as i32,
+            z: point.1.round() as i32,
+            tags: HashMap::new(),
+        };
+        emitted += 1;
+        node
+    };
 
     for i in 0..nodes.len() {
         let current = &nodes[i];
@@ -188,7 +220,7 @@ fn clip_polyline_to_bbox(nodes: &[ProcessedNode], xzbbox: &XZBBox) -> Vec<Proces
         let current_inside = point_in_bbox(current_point, min_x, min_z, max_x, max_z);
 
         if current_inside {
-            result.push(current.clone());
+            piece.push(current.clone());
         }
 
         if i + 1 < nodes.len() {
@@ -202,16 +234,11 @@ fn clip_polyline_to_bbox(nodes: &[ProcessedNode], xzbbox: &XZBBox) -> Vec<Proces
                     find_bbox_intersections(current_point, next_point, min_x, min_z, max_x, max_z);
 
                 for intersection in intersections {
-                    let synthetic_id = nodes[0]
-                        .id
-                        .wrapping_mul(10000000)
-                        .wrapping_add(result.len() as u64);
-                    result.push(ProcessedNode {
-                        id: synthetic_id,
-                        x: intersection.0.round() as i32,
-                        z: intersection.1.round() as i32,
-                        tags: HashMap::new(),
-                    });
+                    piece.push(synthetic_node(intersection));
+                }
+                // The path leaves the bbox, what comes back in later is another piece
+                if current_inside {
+                    pieces.push(std::mem::take(&mut piece));
                 }
             } else if !current_inside && !next_inside {
                 // Both endpoints outside, segment might still cross through bbox
@@ -230,48 +257,44 @@ fn clip_polyline_to_bbox(nodes: &[ProcessedNode], xzbbox: &XZBBox) -> Vec<Proces
                             .unwrap_or(std::cmp::Ordering::Equal)
                     });
 
-                    for intersection in intersections {
-                        let synthetic_id = nodes[0]
-                            .id
-                            .wrapping_mul(10000000)
-                            .wrapping_add(result.len() as u64);
-                        result.push(ProcessedNode {
-                            id: synthetic_id,
-                            x: intersection.0.round() as i32,
-                            z: intersection.1.round() as i32,
-                            tags: HashMap::new(),
-                        });
-                    }
+                    let crossing = intersections.into_iter().map(&mut synthetic_node).collect();
+                    pieces.push(crossing);
                 }
             }
         }
     }
+    if !piece.is_empty() {
+        pieces.push(piece);
+    }
+    pieces.retain(|piece| !piece.is_empty());
 
     // Preserve endpoint IDs where possible
-    if result.len() >= 2 {
-        let tolerance = 50.0;
-        if let Some(first_orig) = nodes.first() {
-            if matches_endpoint(
-                (result[0].x as f64, result[0].z as f64),
+    let tolerance = 50.0;
+    if let (Some(first_piece), Some(first_orig)) = (pieces.first_mut(), nodes.first()) {
+        if first_piece.len() >= 2
+            && matches_endpoint(
+                (first_piece[0].x as f64, first_piece[0].z as f64),
                 first_orig,
                 tolerance,
-            ) {
-                result[0].id = first_orig.id;
-            }
+            )
+        {
+            first_piece[0].id = first_orig.id;
         }
-        if let Some(last_orig) = nodes.last() {
-            let last_idx = result.len() - 1;
-            if matches_endpoint(
-                (result[last_idx].x as f64, result[last_idx].z as f64),
+    }
+    if let (Some(last_piece), Some(last_orig)) = (pieces.last_mut(), nodes.last()) {
+        let last_idx = last_piece.len() - 1;
+        if last_piece.len() >= 2
+            && matches_endpoint(
+                (last_piece[last_idx].x as f64, last_piece[last_idx].z as f64),
                 last_orig,
                 tolerance,
-            ) {
-                result[last_idx].id = last_orig.id;
-            }
+            )
+        {
+            last_piece[last_idx].id = last_orig.id;
         }
     }
 
-    result
+    pieces
 }
 
 /// Sutherland-Hodgman polygon clipping with edge-specific clamping.
@@ -704,3 +727,53 @@ fn assign_node_ids_preserving_endpoints(
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nodes(points: &[(i32, i32)]) -> Vec<ProcessedNode> {
+        points
+            .iter()
+            .enumerate()
+            .map(|(i, &(x, z))| ProcessedNode {
+                id: i as u64 + 1,
+                tags: HashMap::new(),
+                x,
+                z,
+            })
+            .collect()
+    }
+
+    fn coordinates(piece: &[ProcessedNode]) -> Vec<(i32, i32)> {
+        piece.iter().map(|node| (node.x, node.z)).collect()
+    }
+
+    #[test]
+    fn test_path_leaving_and_reentering_is_split() {
+        let xzbbox = XZBBox::rect_from_xz_lengths(100.0, 100.0).unwrap();
+
+        let path = nodes(&[(10, 50), (150, 50), (150, 80), (10, 80)]);
+        let pieces = clip_way_to_bbox_pieces(&path, &xzbbox);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(coordinates(&pieces[0]), vec![(10, 50), (100, 50)]);
+        assert_eq!(coordinates(&pieces[1]), vec![(100, 80), (10, 80)]);
+        assert_eq!(pieces[0][0].id, 1);
+        assert_eq!(pieces[1][1].id, 4);
+        assert_ne!(pieces[0][1].id, pieces[1][0].id);
+
+        // A path crossing the bbox without a node inside
+        let crossing = nodes(&[(-20, 30), (120, 30)]);
+        let pieces = clip_way_to_bbox_pieces(&crossing, &xzbbox);
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(coordinates(&pieces[0]), vec![(0, 30), (100, 30)]);
+
+        let outside = nodes(&[(150, 10), (150, 90)]);
+        assert!(clip_way_to_bbox_pieces(&outside, &xzbbox).is_empty());
+
+        // Closed polygons stay in one piece
+        let mut square = nodes(&[(50, 50), (150, 50), (150, 150), (50, 150)]);
+        square.push(square[0].clone());
+        assert_eq!(clip_way_to_bbox_pieces(&square, &xzbbox).len(), 1);
+    }
+}
@@ -66,6 +66,18 @@ impl XZBBoxRect {
             && xzpoint.z >= self.min.z
             && xzpoint.z <= self.max.z
     }
+
+    /// Returns the rectangle grown by the given number of blocks on every side
+    pub fn expanded(&self, margin: i32) -> Self {
+        let margin = XZVector {
+            dx: margin,
+            dz: margin,
+        };
+        Self {
+            min: self.min - margin,
+            max: self.max + margin,
+        }
+    }
 }
 
 impl fmt::Display for XZBBoxRect {
@@ -57,6 +57,13 @@ impl XZBBox {
         }
     }
 
+    /// Return the bbox grown by the given number of blocks on every side
+    pub fn expanded(&self, margin: i32) -> Self {
+        match self {
+            Self::Rect(r) => Self::Rect(r.expanded(margin)),
+        }
+    }
+
     /// Return the circumscribed rectangle of the current XZBBox shape
     pub fn bounding_rect(&self) -> XZBBoxRect {
         match self {
@@ -51,6 +51,24 @@ impl LLBBox {
         self.max
     }
 
+    /// Returns the bounding box grown by the given distance in meters on every side
+    pub fn expanded(&self, margin_meters: f64) -> Self {
+        const METERS_PER_DEGREE: f64 = 111_320.0;
+
+        let center_lat = (self.min.lat() + self.max.lat()) / 2.0;
+        let lat_margin = margin_meters / METERS_PER_DEGREE;
+        let lng_margin =
+            margin_meters / (METERS_PER_DEGREE * center_lat.to_radians().cos().max(0.01));
+
+        Self::new(
+            (self.min.lat() - lat_margin).max(-90.0),
+            (self.min.lng() - lng_margin).max(-180.0),
+            (self.max.lat() + lat_margin).min(90.0),
+            (self.max.lng() + lng_margin).min(180.0),
+        )
+        .unwrap_or(*self)
+    }
+
     pub fn contains(&self, llpoint: &LLPoint) -> bool {
         llpoint.lat() >= self.min().lat()
             && llpoint.lat() <= self.max().lat()
@@ -115,6 +133,15 @@ mod tests {
         assert_eq!(bbox_result.unwrap(), arnis_correct);
     }
 
+    #[test]
+    fn test_expanded() {
+        let bbox = LLBBox::new(0., 0., 1., 1.).unwrap();
+        let expanded = bbox.expanded(1113.2);
+        assert!((expanded.min().lat() + 0.01).abs() < 1e-9);
+        assert!((expanded.max().lng() - 1.01).abs() < 1e-5);
+        assert_eq!(bbox.expanded(0.0), bbox);
+    }
+
     #[test]
     fn test_out_of_order() {
         // Violates values in vals_in_order
@@ -1,14 +1,38 @@
 use super::cartesian::{XZBBox, XZPoint};
 use super::geographic::{LLBBox, LLPoint};
 
-/// Transform geographic space (within llbbox) to a local tangential cartesian space (within xzbbox)
+/// Radius of the earth in meters, taken as a sphere
+const EARTH_RADIUS: f64 = 6_371_000.0;
+/// Points projected along each edge of the bbox to find the extent of the world
+const EDGE_SAMPLES: usize = 16;
+
+/// Map projection from latitude and longitude to blocks
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Projection {
+    /// Transverse Mercator centred on the area, true to shape everywhere
+    #[default]
+    TransverseMercator,
+    /// Degrees scaled linearly, with longitudes shortened by the cosine of the middle latitude
+    /// so that shapes are not stretched east–west. The world is exactly the rectangle of the
+    /// bbox, as in worlds generated by earlier versions.
+    Equirectangular,
+}
+
+/// Transform geographic space (within llbbox) to a local cartesian space (within xzbbox) with a
+/// transverse Mercator projection centred on the bbox, which keeps shapes and distances true
+/// across the area instead of stretching them east–west away from the middle latitude
 pub struct CoordTransformer {
-    len_lat: f64,
-    len_lng: f64,
+    projection: Projection,
+    llbbox: LLBBox,
+    /// Central meridian and latitude of origin of the projection, in radians
+    origin_lng: f64,
+    origin_lat: f64,
+    scale: f64,
+    /// Projected coordinates in meters of the west and north edges of the world
+    min_easting: f64,
+    max_northing: f64,
     scale_factor_x: f64,
     scale_factor_z: f64,
-    min_lat: f64,
-    min_lng: f64,
 }
 
 impl CoordTransformer {
@@ -23,6 +47,7 @@ impl CoordTransformer {
     pub fn llbbox_to_xzbbox(
         llbbox: &LLBBox,
         scale: f64,
+        projection: Projection,
     ) -> Result<(CoordTransformer, XZBBox), String> {
         let err_header = "Construct LLBBox to XZBBox transformation failed".to_string();
 
@@ -30,42 +55,124 @@ impl CoordTransformer {
             return Err(format!("{}: scale <= 0.0", &err_header));
         }
 
-        let (scale_factor_z, scale_factor_x) = geo_distance(llbbox.min(), llbbox.max());
-        let scale_factor_z: f64 = scale_factor_z.floor() * scale;
-        let scale_factor_x: f64 = scale_factor_x.floor() * scale;
+        let (min, max) = (llbbox.min(), llbbox.max());
+        let mut transformer = Self {
+            projection,
+            llbbox: *llbbox,
+            origin_lng: ((min.lng() + max.lng()) / 2.0).to_radians(),
+            origin_lat: ((min.lat() + max.lat()) / 2.0).to_radians(),
+            scale,
+            min_easting: 0.0,
+            max_northing: 0.0,
+            scale_factor_x: 0.0,
+            scale_factor_z: 0.0,
+        };
+
+        match projection {
+            Projection::TransverseMercator => {
+                // Meridians and parallels are curved in the projection, so the extent of the bbox
+                // is found along all of its edges rather than at the corners only
+                let (mut min_easting, mut max_easting) = (f64::INFINITY, f64::NEG_INFINITY);
+                let (mut min_northing, mut max_northing) = (f64::INFINITY, f64::NEG_INFINITY);
+                for i in 0..=EDGE_SAMPLES {
+                    let t = i as f64 / EDGE_SAMPLES as f64;
+                    let lat = min.lat() + t * (max.lat() - min.lat());
+                    let lng = min.lng() + t * (max.lng() - min.lng());
+                    for (lat, lng) in [
+                        (lat, min.lng()),
+                        (lat, max.lng()),
+                        (min.lat(), lng),
+                        (max.lat(), lng),
+                    ] {
+                        let (easting, northing) = transformer.project(lat, lng);
+                        min_easting = min_easting.min(easting);
+                        max_easting = max_easting.max(easting);
+                        min_northing = min_northing.min(northing);
+                        max_northing = max_northing.max(northing);
+                    }
+                }
+                transformer.min_easting = min_easting;
+                transformer.max_northing = max_northing;
+                transformer.scale_factor_x = ((max_easting - min_easting) * scale).floor();
+                transformer.scale_factor_z = ((max_northing - min_northing) * scale).floor();
+            }
+            Projection::Equirectangular => {
+                // Lengths through the middle of the bbox, rounded down to whole meters
+                let (len_z, len_x) = geo_distance(min, max);
+                transformer.scale_factor_x = len_x.floor() * scale;
+                transformer.scale_factor_z = len_z.floor() * scale;
+            }
+        }
+
+        let xzbbox =
+            XZBBox::rect_from_xz_lengths(transformer.scale_factor_x, transformer.scale_factor_z)
+                .map_err(|e| format!("{}:\n{}", &err_header, e))?;
 
-        let xzbbox = XZBBox::rect_from_xz_lengths(scale_factor_x, scale_factor_z)
-            .map_err(|e| format!("{}:\n{}", &err_header, e))?;
-
-        Ok((
-            Self {
-                len_lat: llbbox.max().lat() - llbbox.min().lat(),
-                len_lng: llbbox.max().lng() - llbbox.min().lng(),
-                scale_factor_x,
-                scale_factor_z,
-                min_lat: llbbox.min().lat(),
-                min_lng: llbbox.min().lng(),
-            },
-            xzbbox,
-        ))
+        Ok((transformer, xzbbox))
     }
 
     pub fn transform_point(&self, llpoint: LLPoint) -> XZPoint {
-        // Calculate the relative position within the bounding box
-        let rel_x: f64 = (llpoint.lng() - self.min_lng) / self.len_lng;
-        let rel_z: f64 = 1.0 - (llpoint.lat() - self.min_lat) / self.len_lat;
+        let (x, z) = match self.projection {
+            Projection::TransverseMercator => {
+                let (easting, northing) = self.project(llpoint.lat(), llpoint.lng());
 
-        // Apply scaling factors for each dimension and convert to Minecraft coordinates
-        let x: i32 = (rel_x * self.scale_factor_x) as i32;
-        let z: i32 = (rel_z * self.scale_factor_z) as i32;
+                // Minecraft's x grows to the east and z to the south
+                (
+                    (easting - self.min_easting) * self.scale,
+                    (self.max_northing - northing) * self.scale,
+                )
+            }
+            Projection::Equirectangular => {
+                // Calculate the relative position within the bounding box
+                let (min, max) = (self.llbbox.min(), self.llbbox.max());
+                let rel_x: f64 = (llpoint.lng() - min.lng()) / (max.lng() - min.lng());
+                let rel_z: f64 = 1.0 - (llpoint.lat() - min.lat()) / (max.lat() - min.lat());
+
+                (rel_x * self.scale_factor_x, rel_z * self.scale_factor_z)
+            }
+        };
+
+        XZPoint::new(x as i32, z as i32)
+    }
 
-        XZPoint::new(x, z)
+    /// Returns the latitude and longitude of a position in the world, the inverse of
+    /// `transform_point`
+    pub fn xz_to_lat_lng(&self, x: f64, z: f64) -> (f64, f64) {
+        match self.projection {
+            Projection::TransverseMercator => {
+                let easting = x / self.scale + self.min_easting;
+                let northing = self.max_northing - z / self.scale;
+
+                let d = northing / EARTH_RADIUS + self.origin_lat;
+                let e = easting / EARTH_RADIUS;
+                let lat = (d.sin() / e.cosh()).asin();
+                let lng = self.origin_lng + e.sinh().atan2(d.cos());
+                (lat.to_degrees(), lng.to_degrees())
+            }
+            Projection::Equirectangular => {
+                let (min, max) = (self.llbbox.min(), self.llbbox.max());
+                (
+                    max.lat() - z / self.scale_factor_z * (max.lat() - min.lat()),
+                    min.lng() + x / self.scale_factor_x * (max.lng() - min.lng()),
+                )
+            }
+        }
+    }
+
+    /// Projects a position with the transverse Mercator projection to easting and northing in
+    /// meters from the origin of the projection
+    fn project(&self, lat: f64, lng: f64) -> (f64, f64) {
+        let (lat, d_lng) = (lat.to_radians(), lng.to_radians() - self.origin_lng);
+        let b = lat.cos() * d_lng.sin();
+        let easting = EARTH_RADIUS * b.atanh();
+        let northing = EARTH_RADIUS * (lat.tan().atan2(d_lng.cos()) - self.origin_lat);
+        (easting, northing)
     }
 }
 
 // (lat meters, lon meters)
 #[inline]
-pub fn geo_distance(a: LLPoint, b: LLPoint) -> (f64, f64) {
+fn geo_distance(a: LLPoint, b: LLPoint) -> (f64, f64) {
     let z: f64 = lat_distance(a.lat(), b.lat());
 
     // distance between two lons depends on their latitude. In this case we'll just average them
@@ -77,46 +184,22 @@ pub fn geo_distance(a: LLPoint, b: LLPoint) -> (f64, f64) {
 // Haversine but optimized for a latitude delta of 0
 // returns meters
 fn lon_distance(lat: f64, lon1: f64, lon2: f64) -> f64 {
-    const R: f64 = 6_371_000.0;
     let d_lon: f64 = (lon2 - lon1).to_radians();
     let a: f64 =
         lat.to_radians().cos() * lat.to_radians().cos() * (d_lon / 2.0).sin() * (d_lon / 2.0).sin();
     let c: f64 = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
 
-    R * c
+    EARTH_RADIUS * c
 }
 
 // Haversine but optimized for a longitude delta of 0
 // returns meters
 fn lat_distance(lat1: f64, lat2: f64) -> f64 {
-    const R: f64 = 6_371_000.0;
     let d_lat: f64 = (lat2 - lat1).to_radians();
     let a: f64 = (d_lat / 2.0).sin() * (d_lat / 2.0).sin();
     let c: f64 = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
 
-    R * c
-}
-
-// copied legacy code
-// Function to convert latitude and longitude to Minecraft coordinates.
-// Function to convert latitude and longitude to Minecraft coordinates.
-#[cfg(test)]
-pub fn lat_lon_to_minecraft_coords(
-    lat: f64,
-    lon: f64,
-    bbox: LLBBox, // (min_lon, min_lat, max_lon, max_lat)
-    scale_factor_z: f64,
-    scale_factor_x: f64,
-) -> (i32, i32) {
-    // Calculate the relative position within the bounding box
-    let rel_x: f64 = (lon - bbox.min().lng()) / (bbox.max().lng() - bbox.min().lng());
-    let rel_z: f64 = 1.0 - (lat - bbox.min().lat()) / (bbox.max().lat() - bbox.min().lat());
-
-    // Apply scaling factors for each dimension and convert to Minecraft coordinates
-    let x: i32 = (rel_x * scale_factor_x) as i32;
-    let z: i32 = (rel_z * scale_factor_z) as i32;
-
-    (x, z)
+    EARTH_RADIUS * c
 }
 
 #[cfg(test)]
@@ -124,6 +207,35 @@ mod test {
     use super::*;
     use crate::test_utilities::get_llbbox_arnis;
 
+    // Distance in meters along the surface of the earth
+    fn haversine(a: (f64, f64), b: (f64, f64)) -> f64 {
+        let (lat1, lat2) = (a.0.to_radians(), b.0.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lng = (b.1 - a.1).to_radians();
+        let h = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lng / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS * h.sqrt().asin()
+    }
+
+    // copied legacy code
+    // Function to convert latitude and longitude to Minecraft coordinates.
+    fn lat_lon_to_minecraft_coords(
+        lat: f64,
+        lon: f64,
+        bbox: LLBBox, // (min_lon, min_lat, max_lon, max_lat)
+        scale_factor_z: f64,
+        scale_factor_x: f64,
+    ) -> (i32, i32) {
+        // Calculate the relative position within the bounding box
+        let rel_x: f64 = (lon - bbox.min().lng()) / (bbox.max().lng() - bbox.min().lng());
+        let rel_z: f64 = 1.0 - (lat - bbox.min().lat()) / (bbox.max().lat() - bbox.min().lat());
+
+        // Apply scaling factors for each dimension and convert to Minecraft coordinates
+        let x: i32 = (rel_x * scale_factor_x) as i32;
+        let z: i32 = (rel_z * scale_factor_z) as i32;
+
+        (x, z)
+    }
+
     fn test_llxztransform_one_scale_one_factor(
         scale: f64,
         test_latfactor: f64,
@@ -135,7 +247,9 @@ mod test {
             llbbox.min().lng() + (llbbox.max().lng() - llbbox.min().lng()) * test_lngfactor,
         )
         .unwrap();
-        let (transformer, xzbbox_new) = CoordTransformer::llbbox_to_xzbbox(&llbbox, scale).unwrap();
+        let (transformer, xzbbox_new) =
+            CoordTransformer::llbbox_to_xzbbox(&llbbox, scale, Projection::Equirectangular)
+                .unwrap();
 
         // legacy xzbbox creation
         let (scale_factor_z, scale_factor_x) = geo_distance(llbbox.min(), llbbox.max());
@@ -162,9 +276,9 @@ mod test {
         assert_eq!(xzbbox_new.max_z(), xzbbox_old.max_z());
     }
 
-    // this ensures that transformer.transform_point == legacy lat_lon_to_minecraft_coords
+    // this ensures that the equirectangular transform_point == legacy lat_lon_to_minecraft_coords
     #[test]
-    pub fn test_llxztransform() {
+    pub fn test_llxztransform_matches_legacy() {
         test_llxztransform_one_scale_one_factor(1.0, 0.5, 0.5);
         test_llxztransform_one_scale_one_factor(3.0, 0.1, 0.2);
         test_llxztransform_one_scale_one_factor(10.0, -1.2, 2.0);
@@ -172,14 +286,94 @@ mod test {
         test_llxztransform_one_scale_one_factor(0.1, 0.2, 0.7);
     }
 
+    #[test]
+    pub fn test_llxztransform() {
+        let llbbox = get_llbbox_arnis();
+        let (transformer, xzbbox) =
+            CoordTransformer::llbbox_to_xzbbox(&llbbox, 1.0, Projection::default()).unwrap();
+
+        // The south-west and north-east corners lie at the edges of the world
+        let south_west = transformer.transform_point(llbbox.min());
+        let north_east = transformer.transform_point(llbbox.max());
+        assert!(south_west.x <= 1 && north_east.x >= xzbbox.max_x() - 1);
+        assert!(north_east.z <= 1 && south_west.z >= xzbbox.max_z() - 1);
+
+        // At scale 1 a block is a meter in every direction
+        let (min, max) = (llbbox.min(), llbbox.max());
+        let width = haversine((min.lat(), min.lng()), (min.lat(), max.lng()));
+        let height = haversine((min.lat(), min.lng()), (max.lat(), min.lng()));
+        assert!(((north_east.x - south_west.x) as f64 - width).abs() < 2.0);
+        assert!(((south_west.z - north_east.z) as f64 - height).abs() < 2.0);
+
+        // Scale 2 doubles the size of the world
+        let (_, xzbbox_double) =
+            CoordTransformer::llbbox_to_xzbbox(&llbbox, 2.0, Projection::default()).unwrap();
+        assert!((xzbbox_double.max_x() - 2 * xzbbox.max_x()).abs() <= 1);
+    }
+
+    #[test]
+    pub fn test_shapes_are_not_stretched_at_high_latitudes() {
+        // Tromsø, where a degree of longitude is less than half as long as one of latitude
+        let llbbox = LLBBox::new(69.60, 18.80, 69.70, 19.20).unwrap();
+        let (transformer, _) =
+            CoordTransformer::llbbox_to_xzbbox(&llbbox, 1.0, Projection::default()).unwrap();
+
+        // A square of 200 m at the north-west corner keeps its shape
+        let corner = (69.695_f64, 18.81);
+        let d_lat = 200.0 / EARTH_RADIUS;
+        let d_lng = d_lat / corner.0.to_radians().cos();
+        let a = transformer.transform_point(LLPoint::new(corner.0, corner.1).unwrap());
+        let b = transformer
+            .transform_point(LLPoint::new(corner.0, corner.1 + d_lng.to_degrees()).unwrap());
+        let c = transformer
+            .transform_point(LLPoint::new(corner.0 - d_lat.to_degrees(), corner.1).unwrap());
+        let east = (((b.x - a.x).pow(2) + (b.z - a.z).pow(2)) as f64).sqrt();
+        let south = (((c.x - a.x).pow(2) + (c.z - a.z).pow(2)) as f64).sqrt();
+        assert!((east - 200.0).abs() < 3.0, "east side {east}");
+        assert!((south - 200.0).abs() < 3.0, "south side {south}");
+    }
+
+    #[test]
+    pub fn test_inverse_transform() {
+        let llbbox = get_llbbox_arnis();
+        for projection in [Projection::TransverseMercator, Projection::Equirectangular] {
+            let (transformer, _) =
+                CoordTransformer::llbbox_to_xzbbox(&llbbox, 3.0, projection).unwrap();
+            let llpoint = LLPoint::new(54.63, 9.93).unwrap();
+            let xzpoint = transformer.transform_point(llpoint);
+            let (lat, lng) = transformer.xz_to_lat_lng(xzpoint.x as f64, xzpoint.z as f64);
+            assert!((lat - llpoint.lat()).abs() < 1e-4);
+            assert!((lng - llpoint.lng()).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    pub fn test_equirectangular_corrects_longitudes_by_latitude() {
+        // Tromsø, where a degree of longitude is less than half as long as one of latitude
+        let llbbox = LLBBox::new(69.60, 18.80, 69.70, 19.20).unwrap();
+        let (transformer, xzbbox) =
+            CoordTransformer::llbbox_to_xzbbox(&llbbox, 1.0, Projection::Equirectangular).unwrap();
+
+        // The corners of the bbox are the corners of the world
+        let south_west = transformer.transform_point(llbbox.min());
+        let north_east = transformer.transform_point(llbbox.max());
+        assert_eq!((south_west.x, north_east.z), (0, 0));
+        assert!((north_east.x - xzbbox.max_x()).abs() <= 1);
+        assert!((south_west.z - xzbbox.max_z()).abs() <= 1);
+
+        let ratio = xzbbox.max_x() as f64 / xzbbox.max_z() as f64;
+        let expected = 4.0 * 69.65_f64.to_radians().cos();
+        assert!((ratio - expected).abs() < 0.01, "aspect ratio {ratio}");
+    }
+
     // this ensures that invalid inputs can be handled correctly
     #[test]
     pub fn test_invalid_construct() {
         let llbbox = get_llbbox_arnis();
-        let obj = CoordTransformer::llbbox_to_xzbbox(&llbbox, 0.0);
+        let obj = CoordTransformer::llbbox_to_xzbbox(&llbbox, 0.0, Projection::default());
         assert!(obj.is_err());
 
-        let obj = CoordTransformer::llbbox_to_xzbbox(&llbbox, -1.2);
+        let obj = CoordTransformer::llbbox_to_xzbbox(&llbbox, -1.2, Projection::default());
         assert!(obj.is_err());
     }
 }
@@ -1,18 +1,35 @@
 use crate::args::Args;
-use crate::block_definitions::{BEDROCK, DIRT, GRASS_BLOCK, STONE};
-use crate::coordinate_system::cartesian::XZBBox;
+use crate::bench;
+use crate::block_definitions::{BEDROCK, STONE};
+use crate::chapters::{self, Chapter};
+use crate::coordinate_system::cartesian::{XZBBox, XZPoint};
 use crate::coordinate_system::geographic::LLBBox;
+use crate::element_processing::highways::HighwayConnectivityMap;
 use crate::element_processing::*;
+use crate::element_rng;
+use crate::floodfill::flood_fill_area;
 use crate::ground::Ground;
+use crate::ground_cover::{AlpineZone, GroundCover};
+use crate::i18n::tr;
 use crate::map_renderer;
-use crate::osm_parser::ProcessedElement;
+use crate::osm_parser::{
+    is_trail_route, is_water_element, ProcessedElement, ProcessedNode, ProcessedRelation,
+    ProcessedWay,
+};
 use crate::progress::{emit_gui_progress_update, emit_map_preview_ready, emit_open_mcworld_file};
 #[cfg(feature = "gui")]
 use crate::telemetry::{send_log, LogLevel};
-use crate::world_editor::{WorldEditor, WorldFormat};
+use crate::theme::{Climate, Theme};
+use crate::tiles;
+use crate::world_editor::{BlockOwner, RegionFileOptions, WorldEditor, WorldFormat};
 use colored::Colorize;
+use fnv::FnvHasher;
 use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 pub const MIN_Y: i32 = -64;
 
@@ -42,6 +59,45 @@ pub fn generate_world(
     generate_world_with_options(elements, xzbbox, llbbox, ground, args, options).map(|_| ())
 }
 
+/// Hashes the settings that change the blocks of the world, which an incrementally generated
+/// world must keep to reuse its chunks
+fn settings_hash(args: &Args) -> u64 {
+    let settings = format!(
+        "{:?}",
+        (
+            (
+                args.llbbox(),
+                args.scale,
+                args.projection,
+                args.ground_level
+            ),
+            (
+                args.terrain,
+                args.terrain_source,
+                args.terrain_smoothing,
+                args.terrain_interpolation
+            ),
+            (
+                args.snow_line,
+                args.tree_line,
+                args.cave_systems,
+                args.peak_beacons
+            ),
+            (args.interior, args.roof, args.green_roofs, args.fillground),
+            (
+                args.theme,
+                args.ground_cover,
+                args.meadow_density,
+                args.time
+            ),
+            (args.trail_markers, &args.feature_filter),
+        )
+    );
+    let mut hasher = FnvHasher::default();
+    settings.hash(&mut hasher);
+    hasher.finish()
+}
+
 /// Generate world with explicit format options (used by GUI for Bedrock support)
 pub fn generate_world_with_options(
     elements: Vec<ProcessedElement>,
@@ -51,8 +107,14 @@ pub fn generate_world_with_options(
     args: &Args,
     options: GenerationOptions,
 ) -> Result<PathBuf, String> {
+    // Roundabouts split into several ways are joined so they can be closed into rings
+    let elements = highways::join_roundabout_ways(elements);
+
     let output_path = options.path.clone();
     let world_format = options.format;
+    if args.incremental && world_format != WorldFormat::JavaAnvil {
+        return Err("Incremental generation is only supported for Java worlds".to_string());
+    }
     let mut editor: WorldEditor = WorldEditor::new_with_format_and_name(
         options.path,
         &xzbbox,
@@ -62,16 +124,42 @@ pub fn generate_world_with_options(
         options.spawn_point,
     );
 
-    println!("{} Processing data...", "[4/7]".bold());
+    editor.set_region_options(RegionFileOptions {
+        compression_level: args.compression_level,
+        compact: args.compact_regions,
+    });
+
+    let theme = args.theme.resolve(&args.llbbox());
+    if args.theme == Theme::Auto && theme == Theme::Arid {
+        println!("Detected an arid region, using the arid theme");
+    }
+    editor.set_theme(theme);
+    editor.set_time_of_day(args.time);
+    editor.set_climate(Climate::new(theme, args.ground_cover));
+
+    if args.ownership_map {
+        editor.enable_ownership_map();
+    }
+    if args.index {
+        editor.enable_index();
+    }
+    editor.enable_plan(&elements);
+    if args.incremental {
+        editor.enable_manifest(settings_hash(args), &elements);
+    }
+
+    println!("{} {}", "[4/7]".bold(), tr("step_processing_data"));
+    bench::mark_stage("processing elements");
 
     // Build highway connectivity map once before processing
     let highway_connectivity = highways::build_highway_connectivity_map(&elements);
+    let stop_routes = transit_routes::stop_routes(&elements);
 
     // Set ground reference in the editor to enable elevation-aware block placement
     editor.set_ground(&ground);
 
-    println!("{} Processing terrain...", "[5/7]".bold());
-    emit_gui_progress_update(25.0, "Processing terrain...");
+    println!("{} {}", "[5/7]".bold(), tr("step_processing_terrain"));
+    emit_gui_progress_update(25.0, tr("step_processing_terrain"));
 
     // Process data
     let elements_count: usize = elements.len();
@@ -85,111 +173,240 @@ pub fn generate_world_with_options(
     let mut current_progress_prcs: f64 = 25.0;
     let mut last_emitted_progress: f64 = current_progress_prcs;
 
-    for element in &elements {
-        process_pb.inc(1);
-        current_progress_prcs += progress_increment_prcs;
-        if (current_progress_prcs - last_emitted_progress).abs() > 0.25 {
-            emit_gui_progress_update(current_progress_prcs, "");
-            last_emitted_progress = current_progress_prcs;
+    let mut deferred = DeferredElements::default();
+
+    let chapters = if args.chapters {
+        chapters::group_elements(&elements)
+    } else {
+        vec![Chapter {
+            name: None,
+            elements: (0..elements_count).collect(),
+        }]
+    };
+
+    if args.parallel {
+        // Each tile is generated by its own editor, border-crossing elements in every tile
+        // they touch. Only the first of these tiles collects the deferred elements.
+        let (min, max) = (editor.get_min_coords(), editor.get_max_coords());
+        let tiles = tiles::partition(min, max, tiles::TILE_CHUNKS);
+        let margin = (tiles::ELEMENT_MARGIN * args.scale.max(1.0)).ceil() as i32;
+        let assignments = tiles::assign_elements(&elements, &tiles, margin);
+
+        let mut home_tiles: Vec<usize> = vec![usize::MAX; elements_count];
+        for (tile_index, indices) in assignments.iter().enumerate().rev() {
+            for &i in indices {
+                home_tiles[i] = tile_index;
+            }
         }
 
-        if args.debug {
-            process_pb.set_message(format!(
-                "(Element ID: {} / Type: {})",
-                element.id(),
-                element.kind()
-            ));
-        } else {
-            process_pb.set_message("");
+        // The elements of every tile, split by chapter in their processing order
+        let mut chapter_indices: Vec<usize> = vec![0; elements_count];
+        for (chapter_index, chapter) in chapters.iter().enumerate() {
+            for &i in &chapter.elements {
+                chapter_indices[i] = chapter_index;
+            }
         }
+        let mut tile_chapters: Vec<Vec<Vec<usize>>> =
+            vec![vec![Vec::new(); chapters.len()]; tiles.len()];
+        for (tile_index, indices) in assignments.iter().enumerate() {
+            for &i in indices {
+                tile_chapters[tile_index][chapter_indices[i]].push(i);
+            }
+        }
+
+        let task_count: usize = assignments.iter().map(Vec::len).sum();
+        process_pb.set_length(task_count as u64);
+        // Progress is sent in the same steps of a quarter percent as without tiles
+        let tasks_per_update = ((task_count as f64 / 180.0) as usize).max(1);
+        let tasks_done = AtomicUsize::new(0);
+
+        let mut tile_editors: Vec<(WorldEditor, DeferredElements)> = tiles
+            .iter()
+            .map(|&tile| (editor.tile_editor(tile), DeferredElements::default()))
+            .collect();
+        for (chapter_index, chapter) in chapters.iter().enumerate() {
+            if args.chapters {
+                process_pb.println(chapter.heading(&elements));
+            }
+            tile_editors.par_iter_mut().enumerate().for_each(
+                |(tile_index, (tile_editor, tile_deferred))| {
+                    for &i in &tile_chapters[tile_index][chapter_index] {
+                        let element = &elements[i];
+                        process_pb.inc(1);
+                        let done = tasks_done.fetch_add(1, Ordering::Relaxed) + 1;
+                        #[allow(clippy::manual_is_multiple_of)]
+                        if done % tasks_per_update == 0 {
+                            let progress = 25.0 + 45.0 * done as f64 / task_count as f64;
+                            emit_gui_progress_update(progress, "");
+                        }
+                        if args.debug {
+                            process_pb.set_message(format!(
+                                "(Element ID: {} / Type: {})",
+                                element.id(),
+                                element.kind()
+                            ));
+                        }
 
-        match element {
-            ProcessedElement::Way(way) => {
-                if way.tags.contains_key("building") || way.tags.contains_key("building:part") {
-                    buildings::generate_buildings(&mut editor, way, args, None);
-                } else if way.tags.contains_key("highway") {
-                    highways::generate_highways(&mut editor, element, args, &highway_connectivity);
-                } else if way.tags.contains_key("landuse") {
-                    landuse::generate_landuse(&mut editor, way, args);
-                } else if way.tags.contains_key("natural") {
-                    natural::generate_natural(&mut editor, element, args);
-                } else if way.tags.contains_key("amenity") {
-                    amenities::generate_amenities(&mut editor, element, args);
-                } else if way.tags.contains_key("leisure") {
-                    leisure::generate_leisure(&mut editor, way, args);
-                } else if way.tags.contains_key("barrier") {
-                    barriers::generate_barriers(&mut editor, element);
-                } else if let Some(val) = way.tags.get("waterway") {
-                    if val == "dock" {
-                        // docks count as water areas
-                        water_areas::generate_water_area_from_way(&mut editor, way, &xzbbox);
-                    } else {
-                        waterways::generate_waterways(&mut editor, way);
+                        let mut elsewhere = DeferredElements::default();
+                        let deferred = if home_tiles[i] == tile_index {
+                            &mut *tile_deferred
+                        } else {
+                            &mut elsewhere
+                        };
+                        generate_element(
+                            tile_editor,
+                            element,
+                            args,
+                            &highway_connectivity,
+                            deferred,
+                        );
                     }
-                } else if way.tags.contains_key("bridge") {
-                    //bridges::generate_bridges(&mut editor, way, ground_level); // TODO FIX
-                } else if way.tags.contains_key("railway") {
-                    railways::generate_railways(&mut editor, way);
-                } else if way.tags.contains_key("roller_coaster") {
-                    railways::generate_roller_coaster(&mut editor, way);
-                } else if way.tags.contains_key("aeroway") || way.tags.contains_key("area:aeroway")
-                {
-                    highways::generate_aeroway(&mut editor, way, args);
-                } else if way.tags.get("service") == Some(&"siding".to_string()) {
-                    highways::generate_siding(&mut editor, way);
-                } else if way.tags.contains_key("man_made") {
-                    man_made::generate_man_made(&mut editor, element, args);
-                }
+                },
+            );
+        }
+
+        // Merged in the order of the tiles, so the result does not depend on the threads
+        for (tile_editor, tile_deferred) in tile_editors {
+            editor.merge_tile(tile_editor);
+            deferred.append(tile_deferred);
+        }
+        emit_gui_progress_update(70.0, "");
+    } else {
+        for chapter in &chapters {
+            if args.chapters {
+                process_pb.println(chapter.heading(&elements));
             }
-            ProcessedElement::Node(node) => {
-                if node.tags.contains_key("door") || node.tags.contains_key("entrance") {
-                    doors::generate_doors(&mut editor, node);
-                } else if node.tags.contains_key("natural")
-                    && node.tags.get("natural") == Some(&"tree".to_string())
-                {
-                    natural::generate_natural(&mut editor, element, args);
-                } else if node.tags.contains_key("amenity") {
-                    amenities::generate_amenities(&mut editor, element, args);
-                } else if node.tags.contains_key("barrier") {
-                    barriers::generate_barrier_nodes(&mut editor, node);
-                } else if node.tags.contains_key("highway") {
-                    highways::generate_highways(&mut editor, element, args, &highway_connectivity);
-                } else if node.tags.contains_key("tourism") {
-                    tourisms::generate_tourisms(&mut editor, node);
-                } else if node.tags.contains_key("man_made") {
-                    man_made::generate_man_made_nodes(&mut editor, node);
+            for &i in &chapter.elements {
+                let element = &elements[i];
+                process_pb.inc(1);
+                current_progress_prcs += progress_increment_prcs;
+                if (current_progress_prcs - last_emitted_progress).abs() > 0.25 {
+                    emit_gui_progress_update(current_progress_prcs, "");
+                    last_emitted_progress = current_progress_prcs;
                 }
-            }
-            ProcessedElement::Relation(rel) => {
-                if rel.tags.contains_key("building") || rel.tags.contains_key("building:part") {
-                    buildings::generate_building_from_relation(&mut editor, rel, args);
-                } else if rel.tags.contains_key("water")
-                    || rel
-                        .tags
-                        .get("natural")
-                        .map(|val| val == "water" || val == "bay")
-                        .unwrap_or(false)
-                {
-                    water_areas::generate_water_areas_from_relation(&mut editor, rel, &xzbbox);
-                } else if rel.tags.contains_key("natural") {
-                    natural::generate_natural_from_relation(&mut editor, rel, args);
-                } else if rel.tags.contains_key("landuse") {
-                    landuse::generate_landuse_from_relation(&mut editor, rel, args);
-                } else if rel.tags.get("leisure") == Some(&"park".to_string()) {
-                    leisure::generate_leisure_from_relation(&mut editor, rel, args);
-                } else if rel.tags.contains_key("man_made") {
-                    man_made::generate_man_made(
-                        &mut editor,
-                        &ProcessedElement::Relation(rel.clone()),
-                        args,
-                    );
+
+                if args.debug {
+                    process_pb.set_message(format!(
+                        "(Element ID: {} / Type: {})",
+                        element.id(),
+                        element.kind()
+                    ));
+                } else {
+                    process_pb.set_message("");
                 }
+
+                generate_element(
+                    &mut editor,
+                    element,
+                    args,
+                    &highway_connectivity,
+                    &mut deferred,
+                );
             }
         }
     }
 
+    deferred
+        .elevated_highways
+        .sort_by_key(|element| highways::get_highway_layer(element));
+    for element in deferred.elevated_highways {
+        editor.set_surface_layer(Some(land_water::surface_layer(element)));
+        start_element(&mut editor, block_owner(element));
+        highways::generate_highways(&mut editor, element, args, &highway_connectivity);
+    }
+
+    editor.set_surface_layer(Some(0));
+    for way in deferred.station_halls {
+        start_element(
+            &mut editor,
+            BlockOwner {
+                kind: "way",
+                id: way.id,
+            },
+        );
+        railway_stations::generate_station_hall(&mut editor, way, args);
+    }
+    for element in deferred.bus_stops {
+        start_element(&mut editor, block_owner(element));
+        let routes = stop_routes
+            .get(&(element.kind(), element.id()))
+            .map_or(&[][..], Vec::as_slice);
+        bus_stops::generate_bus_stop(&mut editor, element, routes);
+    }
+    for node in deferred.accessible_entrances {
+        start_element(
+            &mut editor,
+            BlockOwner {
+                kind: "node",
+                id: node.id,
+            },
+        );
+        doors::generate_entrance_ramp(&mut editor, node);
+    }
+    for way in deferred.water_structures {
+        start_element(
+            &mut editor,
+            BlockOwner {
+                kind: "way",
+                id: way.id,
+            },
+        );
+        waterway_structures::generate_waterway_structure(&mut editor, way, args);
+    }
+    for way in deferred.marinas {
+        start_element(
+            &mut editor,
+            BlockOwner {
+                kind: "way",
+                id: way.id,
+            },
+        );
+        leisure::generate_leisure(&mut editor, way, args);
+    }
+
+    editor.set_block_owner(None);
+    coastline::generate_ocean(&mut editor, &deferred.coastlines);
+
+    for way in deferred.beaches {
+        start_element(
+            &mut editor,
+            BlockOwner {
+                kind: "way",
+                id: way.id,
+            },
+        );
+        natural::generate_beach(&mut editor, way, args);
+    }
+    editor.set_block_owner(None);
+
+    // Give the blocks claimed by several features to the ones of the highest priority
+    let resolved = editor.commit_plan();
+    if resolved > 0 && args.debug {
+        println!("Resolved {resolved} blocks claimed by several features");
+    }
+
+    // Settle columns claimed by both land and water before the ground is generated
+    land_water::reconcile_land_and_water(&mut editor);
+    editor.set_surface_layer(None);
+
+    // Keep roads and plazas passable below trees, overhangs and low decks
+    clearance::clear_above_roads(&mut editor);
+
+    street_signs::generate_street_signs(&mut editor, &elements);
+
+    if args.index {
+        for element in &elements {
+            add_index_entry(&mut editor, element);
+        }
+    }
+
     process_pb.finish();
 
+    if args.dry_run {
+        print_plan_report(&editor, &elements);
+        return Ok(output_path);
+    }
+
     // Generate ground layer
     let total_blocks: u64 = xzbbox.bounding_rect().total_blocks();
     let desired_updates: u64 = 1500;
@@ -197,8 +414,9 @@ pub fn generate_world_with_options(
 
     let mut block_counter: u64 = 0;
 
-    println!("{} Generating ground...", "[6/7]".bold());
-    emit_gui_progress_update(70.0, "Generating ground...");
+    println!("{} {}", "[6/7]".bold(), tr("step_generating_ground"));
+    bench::mark_stage("ground");
+    emit_gui_progress_update(70.0, tr("step_generating_ground"));
 
     let ground_pb: ProgressBar = ProgressBar::new(total_blocks);
     ground_pb.set_style(
@@ -213,19 +431,50 @@ pub fn generate_world_with_options(
     let total_iterations_grnd: f64 = total_blocks as f64;
     let progress_increment_grnd: f64 = 20.0 / total_iterations_grnd;
 
-    let groundlayer_block = GRASS_BLOCK;
+    let ground_cover = args.ground_cover.unwrap_or(match editor.theme() {
+        Theme::Arid => GroundCover::Desert,
+        _ => GroundCover::Grass,
+    });
 
     for x in xzbbox.min_x()..=xzbbox.max_x() {
         for z in xzbbox.min_z()..=xzbbox.max_z() {
-            // Add default dirt and grass layer if there isn't a stone layer already
-            if !editor.check_for_block(x, 0, z, Some(&[STONE])) {
-                editor.set_block(groundlayer_block, x, 0, z, None, None);
-                editor.set_block(DIRT, x, -1, z, None, None);
-                editor.set_block(DIRT, x, -2, z, None, None);
+            // Mountains above the tree line are bare rock and snow whatever the ground cover
+            let alpine_zone = editor
+                .get_ground()
+                .and_then(|ground| {
+                    ground.altitude(XZPoint::new(x - xzbbox.min_x(), z - xzbbox.min_z()))
+                })
+                .and_then(|altitude| {
+                    AlpineZone::at(altitude, args.tree_line, args.snow_line, x, z)
+                });
+
+            // Add the filler ground layer if there isn't a stone layer already
+            let filler = match alpine_zone {
+                Some(zone) if ground_cover != GroundCover::Void => Some(zone.blocks_at(x, z)),
+                _ => ground_cover.blocks_at(x, z),
+            };
+            if let Some((surface_block, subsurface_block)) = filler {
+                if !editor.check_for_block(x, 0, z, Some(&[STONE])) {
+                    let is_untouched = !editor.block_at(x, 0, z);
+                    editor.set_block(surface_block, x, 0, z, None, None);
+                    editor.set_block(subsurface_block, x, -1, z, None, None);
+                    editor.set_block(subsurface_block, x, -2, z, None, None);
+
+                    // Break up plain filler ground with some plants
+                    let decoration = match alpine_zone {
+                        Some(zone) => zone.decoration(),
+                        None => ground_cover.decoration_at(x, z),
+                    };
+                    if is_untouched {
+                        if let Some(decoration) = decoration {
+                            editor.set_block(decoration, x, 1, z, None, None);
+                        }
+                    }
+                }
             }
 
             // Fill underground with stone
-            if args.fillground {
+            if args.fillground && filler.is_some() {
                 // Fill from bedrock+1 to 3 blocks below ground with stone
                 editor.fill_blocks_absolute(
                     STONE,
@@ -255,6 +504,13 @@ pub fn generate_world_with_options(
                 last_emitted_progress = gui_progress_grnd;
             }
         }
+
+        // The ground is the last to change a region, so the regions up to this column are
+        // written now and their blocks freed. The last ones are written with the world.
+        if (x + 1).rem_euclid(512) == 0 && x < xzbbox.max_x() {
+            let region_x = x >> 9;
+            editor.flush_regions(|other_x, _| other_x <= region_x);
+        }
     }
 
     // Set sign for player orientation
@@ -273,21 +529,26 @@ pub fn generate_world_with_options(
     ground_pb.finish();
 
     // Save world
+    if bench::is_recording() {
+        bench::record_block_count(editor.block_count());
+    }
+    bench::mark_stage("saving");
     editor.save();
 
-    emit_gui_progress_update(99.0, "Finalizing world...");
+    emit_gui_progress_update(99.0, tr("step_finalizing_world"));
 
     // Update player spawn Y coordinate based on terrain height after generation
     #[cfg(feature = "gui")]
     if world_format == WorldFormat::JavaAnvil {
         if let Some(spawn_coords) = &args.spawn_point {
             use crate::gui::update_player_spawn_y_after_generation;
+            let bbox = args.llbbox();
             let bbox_string = format!(
                 "{},{},{},{}",
-                args.bbox.min().lng(),
-                args.bbox.min().lat(),
-                args.bbox.max().lng(),
-                args.bbox.max().lat()
+                bbox.min().lng(),
+                bbox.min().lat(),
+                bbox.max().lng(),
+                bbox.max().lat()
             );
 
             if let Err(e) = update_player_spawn_y_after_generation(
@@ -315,6 +576,312 @@ pub fn generate_world_with_options(
     Ok(output_path)
 }
 
+/// Elements generated after the main pass, once the features they depend on exist
+#[derive(Default)]
+struct DeferredElements<'e> {
+    /// Elevated highways are drawn after everything else, lowest layer first,
+    /// so that their pillars can avoid the roads passing underneath
+    elevated_highways: Vec<&'e ProcessedElement>,
+    /// Bus stops and platforms are placed once the roads they face exist
+    bus_stops: Vec<&'e ProcessedElement>,
+    /// Ramps at accessible entrances need the buildings around them
+    accessible_entrances: Vec<&'e ProcessedNode>,
+    /// Station halls are built around the tracks and platforms running through them
+    station_halls: Vec<&'e ProcessedWay>,
+    /// The ocean is filled once all coastlines are known
+    coastlines: Vec<&'e ProcessedWay>,
+    /// Beaches slope down towards the water next to them, including the ocean
+    beaches: Vec<&'e ProcessedWay>,
+    /// Dams, weirs and culverts are built once the waterways and what they cross exist
+    water_structures: Vec<&'e ProcessedWay>,
+    /// Marinas moor their boats along the piers inside them
+    marinas: Vec<&'e ProcessedWay>,
+}
+
+impl<'e> DeferredElements<'e> {
+    fn append(&mut self, other: DeferredElements<'e>) {
+        self.elevated_highways.extend(other.elevated_highways);
+        self.bus_stops.extend(other.bus_stops);
+        self.accessible_entrances.extend(other.accessible_entrances);
+        self.station_halls.extend(other.station_halls);
+        self.coastlines.extend(other.coastlines);
+        self.beaches.extend(other.beaches);
+        self.water_structures.extend(other.water_structures);
+        self.marinas.extend(other.marinas);
+    }
+}
+
+/// Generates an element in the main pass, or collects it for after the main pass
+fn generate_element<'e>(
+    editor: &mut WorldEditor,
+    element: &'e ProcessedElement,
+    args: &Args,
+    highway_connectivity: &HighwayConnectivityMap,
+    deferred: &mut DeferredElements<'e>,
+) {
+    editor.set_surface_layer(Some(land_water::surface_layer(element)));
+    start_element(editor, block_owner(element));
+
+    match element {
+        ProcessedElement::Way(way) => {
+            if railway_stations::is_station_hall(way) {
+                deferred.station_halls.push(way);
+            } else if way.tags.contains_key("building") || way.tags.contains_key("building:part") {
+                buildings::generate_buildings(editor, way, args, None);
+            } else if railway_stations::is_railway_platform(way) {
+                railway_stations::generate_railway_platform(editor, way, args);
+            } else if bus_stops::is_bus_stop(element) {
+                deferred.bus_stops.push(element);
+            } else if way.tags.contains_key("highway") {
+                if highways::is_elevated_highway(element) {
+                    deferred.elevated_highways.push(element);
+                } else {
+                    highways::generate_highways(editor, element, args, highway_connectivity);
+                }
+            } else if golf::is_golf_feature(way) {
+                golf::generate_golf_feature(editor, way, args);
+            } else if way.tags.contains_key("landuse") {
+                landuse::generate_landuse(editor, way, args);
+            } else if coastline::is_coastline(way) {
+                deferred.coastlines.push(way);
+            } else if natural::is_beach(way) {
+                deferred.beaches.push(way);
+            } else if volcanoes::is_volcanic_feature(element) {
+                volcanoes::generate_volcanic_feature(editor, element, args);
+            } else if way.tags.contains_key("natural") {
+                natural::generate_natural(editor, element, args);
+            } else if way.tags.contains_key("amenity") {
+                amenities::generate_amenities(editor, element, args);
+            } else if leisure::is_marina(way) {
+                deferred.marinas.push(way);
+            } else if way.tags.contains_key("leisure") {
+                leisure::generate_leisure(editor, way, args);
+            } else if man_made::is_retaining_wall(way) {
+                man_made::generate_retaining_wall(editor, way, args);
+            } else if way.tags.contains_key("barrier") {
+                barriers::generate_barriers(editor, element);
+            } else if let Some(val) = way.tags.get("waterway") {
+                if val == "dock" || val == "riverbank" {
+                    // docks and riverbanks count as water areas
+                    water_areas::generate_water_area_from_way(editor, way);
+                } else if waterway_structures::is_waterway_structure(way) {
+                    deferred.water_structures.push(way);
+                } else {
+                    waterways::generate_waterways(editor, way, args);
+                }
+            } else if way.tags.contains_key("railway") {
+                railways::generate_railways(editor, way);
+            } else if way.tags.contains_key("bridge") {
+                //bridges::generate_bridges(editor, way, ground_level); // TODO FIX
+            } else if way.tags.contains_key("roller_coaster") {
+                railways::generate_roller_coaster(editor, way);
+            } else if way.tags.contains_key("aeroway") || way.tags.contains_key("area:aeroway") {
+                highways::generate_aeroway(editor, way, args);
+            } else if way.tags.get("service") == Some(&"siding".to_string()) {
+                highways::generate_siding(editor, way);
+            } else if way.tags.contains_key("man_made") {
+                man_made::generate_man_made(editor, element, args);
+            }
+        }
+        ProcessedElement::Node(node) => {
+            if node.tags.contains_key("door") || node.tags.contains_key("entrance") {
+                doors::generate_doors(editor, node);
+                if doors::is_accessible_entrance(node) {
+                    deferred.accessible_entrances.push(node);
+                }
+            } else if node.tags.contains_key("natural")
+                && node.tags.get("natural") == Some(&"tree".to_string())
+            {
+                natural::generate_natural(editor, element, args);
+            } else if caves::is_cave_entrance(node) {
+                caves::generate_cave_entrance(editor, node, args);
+            } else if peaks::is_peak(node) {
+                peaks::generate_peak(editor, node, args);
+            } else if volcanoes::is_volcanic_feature(element) {
+                volcanoes::generate_volcanic_feature(editor, element, args);
+            } else if node.tags.contains_key("amenity") {
+                amenities::generate_amenities(editor, element, args);
+            } else if node.tags.contains_key("barrier") {
+                barriers::generate_barrier_nodes(editor, node);
+            } else if playgrounds::is_playground_node(node) {
+                playgrounds::generate_playground_node(editor, node);
+            } else if bus_stops::is_bus_stop(element) {
+                deferred.bus_stops.push(element);
+            } else if node.tags.contains_key("highway") {
+                highways::generate_highways(editor, element, args, highway_connectivity);
+            } else if node.tags.contains_key("tourism") {
+                tourisms::generate_tourisms(editor, node);
+            } else if node.tags.contains_key("man_made") {
+                man_made::generate_man_made_nodes(editor, node);
+            } else if waterways::is_waterfall_node(node) {
+                waterways::generate_waterfall_node(editor, node);
+            }
+        }
+        ProcessedElement::Relation(rel) => {
+            // Keep the holes of multipolygons free, water areas leave out their islands themselves
+            if fills_relation_area(&rel.tags) {
+                editor.set_excluded_columns(relation_holes(rel, args));
+            }
+
+            if rel.tags.contains_key("building") || rel.tags.contains_key("building:part") {
+                buildings::generate_building_from_relation(editor, rel, args);
+            } else if is_water_element(&rel.tags) {
+                water_areas::generate_water_areas_from_relation(editor, rel);
+            } else if rel.tags.contains_key("natural") {
+                natural::generate_natural_from_relation(editor, rel, args);
+            } else if rel.tags.contains_key("landuse") {
+                landuse::generate_landuse_from_relation(editor, rel, args);
+            } else if rel.tags.contains_key("leisure") {
+                leisure::generate_leisure_from_relation(editor, rel, args);
+            } else if rel.tags.contains_key("amenity") {
+                for way in rel.outer_ways() {
+                    amenities::generate_amenities(editor, &ProcessedElement::Way(way), args);
+                }
+            } else if rel.tags.contains_key("man_made") {
+                man_made::generate_man_made(editor, &ProcessedElement::Relation(rel.clone()), args);
+            } else if args.trail_markers && is_trail_route(&rel.tags) {
+                trail_markers::generate_trail_markers(editor, rel, highway_connectivity);
+            }
+
+            editor.set_excluded_columns(None);
+        }
+    }
+}
+
+/// Attributes the blocks placed next to the given element and seeds the random choices, which
+/// thus only depend on the element, also when it is generated in several tiles
+fn start_element(editor: &mut WorldEditor, owner: BlockOwner) {
+    element_rng::seed_for_element(owner.kind, owner.id);
+    editor.set_block_owner(Some(owner));
+}
+
+/// Returns the owner recorded for the blocks placed by an element
+fn block_owner(element: &ProcessedElement) -> BlockOwner {
+    BlockOwner {
+        kind: element.kind(),
+        id: element.id(),
+    }
+}
+
+/// Keys grouping the elements of the dry run report, in the order the features are generated
+const PLAN_CATEGORY_KEYS: [&str; 14] = [
+    "building", "highway", "landuse", "natural", "amenity", "leisure", "barrier", "waterway",
+    "water", "railway", "man_made", "tourism", "power", "historic",
+];
+
+/// Most conflicts listed in the dry run report
+const MAX_REPORTED_CONFLICTS: usize = 20;
+
+/// Prints the blocks claimed by each kind of feature and the placements refused because
+/// another feature had already claimed the block
+fn print_plan_report(editor: &WorldEditor, elements: &[ProcessedElement]) {
+    let Some(plan) = editor.plan() else {
+        return;
+    };
+
+    let categories: HashMap<BlockOwner, &'static str> = elements
+        .iter()
+        .map(|element| {
+            let category = PLAN_CATEGORY_KEYS
+                .iter()
+                .copied()
+                .find(|key| element.tags().contains_key(*key))
+                .unwrap_or("other");
+            (block_owner(element), category)
+        })
+        .collect();
+    let category_of = |owner: &BlockOwner| categories.get(owner).copied().unwrap_or("other");
+
+    let mut claimed: HashMap<&str, u64> = HashMap::new();
+    for (owner, count) in plan.claimed_blocks() {
+        *claimed.entry(category_of(&owner)).or_default() += count;
+    }
+    let mut claimed: Vec<(&str, u64)> = claimed.into_iter().collect();
+    claimed.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    let mut conflicts: HashMap<(&str, &str), u64> = HashMap::new();
+    for ((refused, holder), count) in plan.conflicts() {
+        *conflicts
+            .entry((category_of(refused), category_of(holder)))
+            .or_default() += count;
+    }
+    let mut conflicts: Vec<((&str, &str), u64)> = conflicts.into_iter().collect();
+    conflicts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    println!("{}", "Dry run, the world was not written".bold());
+    println!("Blocks placed by feature:");
+    for (category, count) in &claimed {
+        println!("  {category}: {count}");
+    }
+    println!("Blocks refused because another feature placed them first:");
+    if conflicts.is_empty() {
+        println!("  none");
+    }
+    for ((refused, holder), count) in conflicts.iter().take(MAX_REPORTED_CONFLICTS) {
+        println!("  {refused} blocked by {holder}: {count}");
+    }
+}
+
+/// Keys that name what a place is, in order of preference, for the world index
+const INDEX_CATEGORY_KEYS: [&str; 12] = [
+    "highway", "railway", "waterway", "amenity", "shop", "tourism", "leisure", "historic",
+    "building", "place", "natural", "landuse",
+];
+
+/// Adds a named element to the world index, placed at a node in the middle of it
+fn add_index_entry(editor: &mut WorldEditor, element: &ProcessedElement) {
+    let tags = element.tags();
+    let Some(name) = tags.get("name") else {
+        return;
+    };
+    let Some(category) = INDEX_CATEGORY_KEYS
+        .iter()
+        .find_map(|key| tags.get(*key).map(|value| format!("{key}={value}")))
+    else {
+        return;
+    };
+
+    let nodes: Vec<&ProcessedNode> = match element {
+        ProcessedElement::Relation(rel) => rel
+            .members
+            .iter()
+            .flat_map(|member| member.way.nodes.iter())
+            .collect(),
+        _ => element.nodes().collect(),
+    };
+    let Some(node) = nodes.get(nodes.len() / 2) else {
+        return;
+    };
+    editor.add_index_entry(name, category, block_owner(element), node.x, node.z);
+}
+
+/// Returns true for the multipolygons whose outer rings are filled as a whole, in the order
+/// they are dispatched, and whose holes must be kept free while filling them
+fn fills_relation_area(tags: &HashMap<String, String>) -> bool {
+    if tags.contains_key("building") || tags.contains_key("building:part") {
+        return true;
+    }
+    if is_water_element(tags) {
+        return false;
+    }
+    ["natural", "landuse", "leisure"]
+        .iter()
+        .any(|key| tags.contains_key(*key))
+}
+
+/// Returns the columns inside the closed inner rings of a multipolygon, None if it has no holes
+fn relation_holes(rel: &ProcessedRelation, args: &Args) -> Option<HashSet<(i32, i32)>> {
+    let holes: HashSet<(i32, i32)> = rel
+        .inner_ways()
+        .flat_map(|way| {
+            let polygon_coords: Vec<(i32, i32)> = way.nodes.iter().map(|n| (n.x, n.z)).collect();
+            flood_fill_area(&polygon_coords, args.timeout.as_ref())
+        })
+        .collect();
+
+    (!holes.is_empty()).then_some(holes)
+}
+
 /// Information needed to generate a map preview after world generation is complete
 #[derive(Clone)]
 pub struct MapPreviewInfo {
@@ -381,3 +948,226 @@ pub fn start_map_preview_generation(info: MapPreviewInfo) {
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_definitions::{Block, GLOWSTONE, LIGHT, OAK_LOG, SMOOTH_STONE};
+    use crate::osm_parser::{ProcessedMember, ProcessedMemberRole};
+    use crate::test_utilities::{example_args, get_llbbox_arnis, minecraft_tmpdir};
+    use crate::time_of_day::TimeOfDay;
+    use rand::Rng;
+
+    fn ring(id: u64, corners: [(i32, i32); 4]) -> ProcessedWay {
+        let nodes = corners
+            .iter()
+            .chain(&corners[..1])
+            .enumerate()
+            .map(|(i, &(x, z))| ProcessedNode {
+                id: id * 10 + (i as u64 % 4),
+                tags: HashMap::new(),
+                x,
+                z,
+            })
+            .collect();
+        ProcessedWay {
+            id,
+            nodes,
+            tags: HashMap::new(),
+        }
+    }
+
+    fn multipolygon(tags: &[(&str, &str)]) -> ProcessedRelation {
+        ProcessedRelation {
+            id: 1,
+            tags: tags
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            members: vec![
+                ProcessedMember {
+                    role: ProcessedMemberRole::Outer,
+                    way: ring(2, [(0, 0), (40, 0), (40, 40), (0, 40)]),
+                },
+                ProcessedMember {
+                    role: ProcessedMemberRole::Inner,
+                    way: ring(3, [(10, 10), (20, 10), (20, 20), (10, 20)]),
+                },
+            ],
+            stops: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_multipolygon_holes_stay_free() {
+        let world = minecraft_tmpdir();
+        let args = example_args(&world, &[]);
+
+        let rel = multipolygon(&[("type", "multipolygon"), ("landuse", "commercial")]);
+        let holes = relation_holes(&rel, &args).unwrap();
+        assert!(holes.contains(&(15, 15)));
+        assert!(!holes.contains(&(5, 5)));
+        assert!(!holes.contains(&(30, 30)));
+
+        let mut solid = rel.clone();
+        solid.members.pop();
+        assert_eq!(relation_holes(&solid, &args), None);
+    }
+
+    #[test]
+    fn test_holes_are_only_computed_for_filled_areas() {
+        let tags = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        assert!(fills_relation_area(&tags(&[("landuse", "forest")])));
+        assert!(fills_relation_area(&tags(&[
+            ("building", "yes"),
+            ("water", "pond")
+        ])));
+        // Water areas keep their islands free themselves
+        assert!(!fills_relation_area(&tags(&[("natural", "water")])));
+        assert!(!fills_relation_area(&tags(&[("route", "hiking")])));
+    }
+
+    #[test]
+    fn test_incremental_generation_needs_a_java_world() {
+        let world = minecraft_tmpdir();
+        let args = example_args(&world, &["--incremental"]);
+        let xzbbox = XZBBox::rect_from_xz_lengths(100.0, 100.0).unwrap();
+        let options = GenerationOptions {
+            path: world.path().to_path_buf(),
+            format: WorldFormat::BedrockMcWorld,
+            level_name: None,
+            spawn_point: None,
+        };
+
+        let result = generate_world_with_options(
+            Vec::new(),
+            xzbbox,
+            get_llbbox_arnis(),
+            Ground::new_flat(-62),
+            &args,
+            options,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_random_choices_only_depend_on_the_element() {
+        let world = minecraft_tmpdir();
+        let args = example_args(&world, &[]);
+        let xzbbox = XZBBox::rect_from_xz_lengths(100.0, 100.0).unwrap();
+        let bench = ProcessedElement::Node(ProcessedNode {
+            id: 7,
+            tags: HashMap::from([("amenity".to_string(), "bench".to_string())]),
+            x: 50,
+            z: 50,
+        });
+
+        // Whatever was drawn before, as in another tile or thread, the bench faces the same way
+        let orientations: Vec<bool> = (0..8u64)
+            .map(|draws| {
+                element_rng::seed_for_element("node", draws);
+                for _ in 0..draws {
+                    element_rng::element_rng().gen::<u32>();
+                }
+                let mut editor =
+                    WorldEditor::new(world.path().to_path_buf(), &xzbbox, get_llbbox_arnis());
+                generate_element(
+                    &mut editor,
+                    &bench,
+                    &args,
+                    &HighwayConnectivityMap::new(),
+                    &mut DeferredElements::default(),
+                );
+                editor.check_for_block(51, 1, 50, Some(&[OAK_LOG]))
+            })
+            .collect();
+        assert!(orientations
+            .iter()
+            .all(|&along_x| along_x == orientations[0]));
+    }
+
+    #[test]
+    fn test_night_lights_lit_roads_and_shop_windows() {
+        let world = minecraft_tmpdir();
+        let args = example_args(&world, &[]);
+        let xzbbox = XZBBox::rect_from_xz_lengths(100.0, 100.0).unwrap();
+        let tagged = |mut way: ProcessedWay, tags: &[(&str, &str)]| {
+            for (k, v) in tags {
+                way.tags.insert(k.to_string(), v.to_string());
+            }
+            way
+        };
+        let mut road = ring(1, [(10, 80), (90, 80), (90, 80), (90, 80)]);
+        road.nodes.truncate(2);
+        let road =
+            ProcessedElement::Way(tagged(road, &[("highway", "residential"), ("lit", "yes")]));
+        let shop = tagged(
+            ring(2, [(20, 20), (40, 20), (40, 40), (20, 40)]),
+            &[("building", "retail"), ("shop", "bakery")],
+        );
+
+        let count_lights = |time: TimeOfDay| {
+            let mut editor =
+                WorldEditor::new(world.path().to_path_buf(), &xzbbox, get_llbbox_arnis());
+            editor.set_ground(&Ground::new_flat(args.ground_level));
+            editor.set_time_of_day(time);
+            highways::generate_highways(&mut editor, &road, &args, &HashMap::new());
+            buildings::generate_buildings(&mut editor, &shop, &args, None);
+            let count = |block: Block, zs: std::ops::Range<i32>, ys: std::ops::Range<i32>| {
+                (0..100)
+                    .flat_map(|x| zs.clone().map(move |z| (x, z)))
+                    .flat_map(|(x, z)| ys.clone().map(move |y| (x, y, z)))
+                    .filter(|&(x, y, z)| editor.check_for_block(x, y, z, Some(&[block])))
+                    .count()
+            };
+            (count(GLOWSTONE, 60..100, 5..6), count(LIGHT, 20..41, 1..10))
+        };
+
+        let (day_lamps, day_windows) = count_lights(TimeOfDay::Day);
+        assert_eq!((day_lamps, day_windows), (0, 0));
+        // A lamp post every 16 blocks along the road, the shop has its ground floor lit
+        let (night_lamps, night_windows) = count_lights(TimeOfDay::Night);
+        assert_eq!(night_lamps, 6);
+        assert!(night_windows > 0);
+    }
+
+    #[test]
+    fn test_conflicts_are_resolved_by_priority() {
+        let world = minecraft_tmpdir();
+        let xzbbox = XZBBox::rect_from_xz_lengths(100.0, 100.0).unwrap();
+        let way = |id: u64, key: &str| {
+            let mut way = ring(id, [(0, 0), (10, 0), (10, 10), (0, 10)]);
+            way.tags.insert(key.to_string(), "yes".to_string());
+            ProcessedElement::Way(way)
+        };
+        let elements = vec![way(1, "building"), way(2, "highway")];
+
+        // The road is generated first, yet the building has the higher priority
+        let generate = |with_plan: bool| {
+            let mut editor =
+                WorldEditor::new(world.path().to_path_buf(), &xzbbox, get_llbbox_arnis());
+            if with_plan {
+                editor.enable_plan(&elements);
+            }
+            start_element(&mut editor, block_owner(&elements[1]));
+            editor.set_block(SMOOTH_STONE, 5, 1, 5, None, None);
+            editor.set_block(SMOOTH_STONE, 6, 1, 5, None, None);
+            start_element(&mut editor, block_owner(&elements[0]));
+            editor.set_block(OAK_LOG, 5, 1, 5, None, None);
+            start_element(&mut editor, block_owner(&elements[1]));
+            // Without the plan, this would replace whatever it finds
+            editor.set_block(SMOOTH_STONE, 5, 1, 5, None, Some(&[]));
+            editor.set_block_owner(None);
+            let resolved = editor.commit_plan();
+            (resolved, editor.check_for_block(5, 1, 5, Some(&[OAK_LOG])))
+        };
+
+        assert_eq!(generate(false), (0, false));
+        assert_eq!(generate(true), (1, true));
+    }
+}
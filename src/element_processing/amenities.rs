@@ -2,9 +2,14 @@ use crate::args::Args;
 use crate::block_definitions::*;
 use crate::bresenham::bresenham_line;
 use crate::coordinate_system::cartesian::XZPoint;
+use crate::element_processing::landuse;
+use crate::element_processing::water_areas::is_underwater_light_spot;
+use crate::element_rng::element_rng;
 use crate::floodfill::flood_fill_area;
 use crate::osm_parser::ProcessedElement;
 use crate::world_editor::WorldEditor;
+use rand::Rng;
+use std::collections::HashSet;
 
 pub fn generate_amenities(editor: &mut WorldEditor, element: &ProcessedElement, args: &Args) {
     // Skip if 'layer' or 'level' is negative in the tags
@@ -77,11 +82,17 @@ pub fn generate_amenities(editor: &mut WorldEditor, element: &ProcessedElement,
                     editor.set_block(roof_block, *x, 5, *z, None, None);
                 }
             }
+            "clock" => {
+                if let Some(pt) = first_node {
+                    let support = element.tags().get("support").map(|s| s.as_str());
+                    generate_clock(editor, pt, support);
+                }
+            }
             "bench" => {
                 // Place a bench
                 if let Some(pt) = first_node {
                     // 50% chance to 90 degrees rotate the bench using if
-                    if rand::random::<bool>() {
+                    if element_rng().gen_bool(0.5) {
                         editor.set_block(SMOOTH_STONE, pt.x, 1, pt.z, None, None);
                         editor.set_block(OAK_LOG, pt.x + 1, 1, pt.z, None, None);
                         editor.set_block(OAK_LOG, pt.x - 1, 1, pt.z, None, None);
@@ -118,145 +129,316 @@ pub fn generate_amenities(editor: &mut WorldEditor, element: &ProcessedElement,
                     editor.set_block(roof_block, *x, 5, *z, None, None);
                 }
             }
-            "parking" | "fountain" => {
-                // Process parking or fountain areas
-                let mut previous_node: Option<XZPoint> = None;
-                let mut corner_addup: (i32, i32, i32) = (0, 0, 0);
-                let mut current_amenity: Vec<(i32, i32)> = vec![];
-
-                let block_type = match amenity_type.as_str() {
-                    "fountain" => WATER,
-                    "parking" => GRAY_CONCRETE,
-                    _ => GRAY_CONCRETE,
-                };
+            "parking" => generate_parking(editor, element, args),
+            "fountain" => generate_fountain(editor, element, args),
+            "grave_yard" => {
+                if let ProcessedElement::Way(way) = element {
+                    landuse::generate_grave_yard(editor, way, args);
+                }
+            }
+            _ => {}
+        }
+    }
+}
 
-                for node in element.nodes() {
-                    let pt: XZPoint = node.xz();
-
-                    if let Some(prev) = previous_node {
-                        // Create borders for fountain or parking area
-                        let bresenham_points: Vec<(i32, i32, i32)> =
-                            bresenham_line(prev.x, 0, prev.z, pt.x, 0, pt.z);
-                        for (bx, _, bz) in bresenham_points {
-                            editor.set_block(block_type, bx, 0, bz, Some(&[BLACK_CONCRETE]), None);
-
-                            // Decorative border around fountains
-                            if amenity_type == "fountain" {
-                                for dx in [-1, 0, 1].iter() {
-                                    for dz in [-1, 0, 1].iter() {
-                                        if (*dx, *dz) != (0, 0) {
-                                            editor.set_block(
-                                                LIGHT_GRAY_CONCRETE,
-                                                bx + dx,
-                                                0,
-                                                bz + dz,
-                                                None,
-                                                None,
-                                            );
-                                        }
-                                    }
-                                }
-                            }
-
-                            current_amenity.push((node.x, node.z));
-                            corner_addup.0 += node.x;
-                            corner_addup.1 += node.z;
-                            corner_addup.2 += 1;
-                        }
-                    }
-                    previous_node = Some(pt);
+/// Radius of fountains mapped as a single node
+const FOUNTAIN_NODE_RADIUS: i32 = 3;
+/// Footprint from which fountains get a raised inner basin around their column
+const LARGE_FOUNTAIN_AREA: usize = 80;
+
+/// Generates a fountain with a stone brick basin and a central column that water flows down
+/// from, scaled to the size of the fountain area
+fn generate_fountain(editor: &mut WorldEditor, element: &ProcessedElement, args: &Args) {
+    let cells: HashSet<(i32, i32)> = match element {
+        ProcessedElement::Node(node) => {
+            let radius = FOUNTAIN_NODE_RADIUS;
+            (-radius..=radius)
+                .flat_map(|dx| (-radius..=radius).map(move |dz| (dx, dz)))
+                .filter(|(dx, dz)| dx * dx + dz * dz <= radius * radius)
+                .map(|(dx, dz)| (node.x + dx, node.z + dz))
+                .collect()
+        }
+        _ => {
+            let polygon_coords: Vec<(i32, i32)> = element.nodes().map(|n| (n.x, n.z)).collect();
+            let mut cells: HashSet<(i32, i32)> =
+                flood_fill_area(&polygon_coords, args.timeout.as_ref())
+                    .into_iter()
+                    .collect();
+            for pair in polygon_coords.windows(2) {
+                let (a, b) = (pair[0], pair[1]);
+                for (x, _, z) in bresenham_line(a.0, 0, a.1, b.0, 0, b.1) {
+                    cells.insert((x, z));
                 }
+            }
+            cells
+        }
+    };
+    if cells.is_empty() {
+        return;
+    }
 
-                // Flood-fill the interior area for parking or fountains
-                if corner_addup.2 > 0 {
-                    let polygon_coords: Vec<(i32, i32)> = current_amenity.to_vec();
-                    let flood_area: Vec<(i32, i32)> =
-                        flood_fill_area(&polygon_coords, args.timeout.as_ref());
-
-                    for (x, z) in flood_area {
-                        editor.set_block(
-                            block_type,
-                            x,
-                            0,
-                            z,
-                            Some(&[BLACK_CONCRETE, GRAY_CONCRETE]),
-                            None,
-                        );
-
-                        // Enhanced parking space markings
-                        if amenity_type == "parking" {
-                            // Create defined parking spaces with realistic layout
-                            let space_width = 4; // Width of each parking space
-                            let space_length = 6; // Length of each parking space
-                            let lane_width = 5; // Width of driving lanes
-
-                            // Calculate which "zone" this coordinate falls into
-                            let zone_x = x / space_width;
-                            let zone_z = z / (space_length + lane_width);
-                            let local_x = x % space_width;
-                            let local_z = z % (space_length + lane_width);
-
-                            // Create parking space boundaries (only within parking areas, not in driving lanes)
-                            if local_z < space_length {
-                                // We're in a parking space area, not in the driving lane
-                                if local_x == 0 {
-                                    // Vertical parking space lines (only on the left edge)
-                                    editor.set_block(
-                                        LIGHT_GRAY_CONCRETE,
-                                        x,
-                                        0,
-                                        z,
-                                        Some(&[BLACK_CONCRETE, GRAY_CONCRETE]),
-                                        None,
-                                    );
-                                } else if local_z == 0 {
-                                    // Horizontal parking space lines (only on the top edge)
-                                    editor.set_block(
-                                        LIGHT_GRAY_CONCRETE,
-                                        x,
-                                        0,
-                                        z,
-                                        Some(&[BLACK_CONCRETE, GRAY_CONCRETE]),
-                                        None,
-                                    );
-                                }
-                            } else if local_z == space_length {
-                                // Bottom edge of parking spaces (border with driving lane)
-                                editor.set_block(
-                                    LIGHT_GRAY_CONCRETE,
-                                    x,
-                                    0,
-                                    z,
-                                    Some(&[BLACK_CONCRETE, GRAY_CONCRETE]),
-                                    None,
-                                );
-                            } else if local_z > space_length && local_z < space_length + lane_width
-                            {
-                                // Driving lane - use darker concrete
-                                editor.set_block(
-                                    BLACK_CONCRETE,
-                                    x,
-                                    0,
-                                    z,
-                                    Some(&[GRAY_CONCRETE]),
-                                    None,
-                                );
-                            }
-
-                            // Add light posts at parking space outline corners
-                            if local_x == 0 && local_z == 0 && zone_x % 3 == 0 && zone_z % 2 == 0 {
-                                // Light posts at regular intervals on parking space corners
-                                editor.set_block(COBBLESTONE_WALL, x, 1, z, None, None);
-                                for dy in 2..=4 {
-                                    editor.set_block(OAK_FENCE, x, dy, z, None, None);
-                                }
-                                editor.set_block(GLOWSTONE, x, 5, z, None, None);
-                            }
-                        }
-                    }
+    // The basin is sunk into the ground, its rim is the outline of the fountain
+    for &(x, z) in &cells {
+        let is_rim = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+            .iter()
+            .any(|(dx, dz)| !cells.contains(&(x + dx, z + dz)));
+        if is_rim {
+            editor.set_block(STONE_BRICKS, x, 0, z, None, Some(&[]));
+            editor.set_block(STONE_BRICK_SLAB, x, 1, z, None, None);
+        } else {
+            let floor = if is_underwater_light_spot(editor, x, z) {
+                SEA_LANTERN
+            } else {
+                STONE_BRICKS
+            };
+            editor.set_block(WATER, x, 0, z, None, Some(&[]));
+            editor.set_block(floor, x, -1, z, None, Some(&[]));
+        }
+    }
+
+    // The column stands in the middle and grows with the size of the fountain
+    let center_x = cells.iter().map(|(x, _)| *x as i64).sum::<i64>() / cells.len() as i64;
+    let center_z = cells.iter().map(|(_, z)| *z as i64).sum::<i64>() / cells.len() as i64;
+    let (center_x, center_z) = (center_x as i32, center_z as i32);
+    if !cells.contains(&(center_x, center_z)) {
+        return;
+    }
+    let column_height = ((cells.len() as f64).sqrt() / 2.0).round().clamp(2.0, 6.0) as i32;
+
+    if cells.len() >= LARGE_FOUNTAIN_AREA {
+        // Raised inner basin catching the water of the column
+        for dx in -1..=1 {
+            for dz in -1..=1 {
+                let (x, z) = (center_x + dx, center_z + dz);
+                editor.set_block(STONE_BRICKS, x, 1, z, None, Some(&[]));
+                if (dx, dz) != (0, 0) {
+                    editor.set_block(WATER, x, 2, z, None, None);
                 }
             }
-            _ => {}
         }
     }
+
+    for y in 1..=column_height {
+        editor.set_block(STONE_BRICKS, center_x, y, center_z, None, Some(&[]));
+    }
+    editor.set_block(
+        CHISELED_STONE_BRICKS,
+        center_x,
+        column_height + 1,
+        center_z,
+        None,
+        None,
+    );
+    // Water spills from the top of the column and flows down its sides
+    editor.set_block(WATER, center_x, column_height + 2, center_z, None, None);
+}
+
+/// Depth of a parking bay
+const PARKING_BAY_DEPTH: f64 = 5.0;
+/// Width of a parking bay
+const PARKING_BAY_WIDTH: f64 = 3.0;
+/// Width of the driving aisle between two rows of bays
+const PARKING_AISLE_WIDTH: f64 = 6.0;
+/// Share of the bays occupied by a parked car
+const PARKED_CAR_CHANCE: f64 = 0.25;
+
+/// Generates a parking lot with asphalt, painted bays aligned to its longest edge
+/// and cars parked in some of the bays.
+fn generate_parking(editor: &mut WorldEditor, element: &ProcessedElement, args: &Args) {
+    let polygon_coords: Vec<(i32, i32)> = element.nodes().map(|n| (n.x, n.z)).collect();
+
+    // Align the bay rows with the longest edge of the lot
+    let Some(((origin_x, origin_z), (edge_x, edge_z))) = polygon_coords
+        .windows(2)
+        .map(|pair| (pair[0], pair[1]))
+        .filter(|(start, end)| start != end)
+        .max_by_key(|((ax, az), (bx, bz))| (bx - ax).pow(2) + (bz - az).pow(2))
+    else {
+        return;
+    };
+    let length = (((edge_x - origin_x).pow(2) + (edge_z - origin_z).pow(2)) as f64).sqrt();
+    let along = (
+        (edge_x - origin_x) as f64 / length,
+        (edge_z - origin_z) as f64 / length,
+    );
+    let across = (-along.1, along.0);
+
+    // Position of a block along the bay rows and across them
+    let project = |x: i32, z: i32| -> (f64, f64) {
+        let (dx, dz) = ((x - origin_x) as f64, (z - origin_z) as f64);
+        (dx * along.0 + dz * along.1, dx * across.0 + dz * across.1)
+    };
+    // Block at a position given along and across the bay rows
+    let unproject = |a: f64, b: f64| -> (i32, i32) {
+        (
+            (origin_x as f64 + along.0 * a + across.0 * b).floor() as i32,
+            (origin_z as f64 + along.1 * a + across.1 * b).floor() as i32,
+        )
+    };
+
+    let row_period = 2.0 * PARKING_BAY_DEPTH + PARKING_AISLE_WIDTH;
+    let floor_area: Vec<(i32, i32)> = flood_fill_area(&polygon_coords, args.timeout.as_ref());
+
+    for &(x, z) in &floor_area {
+        let (a, b) = project(x, z);
+        let b = b.rem_euclid(row_period);
+
+        // Bays lie on both sides of the aisle, separated by painted lines
+        let in_aisle = (PARKING_BAY_DEPTH..PARKING_BAY_DEPTH + PARKING_AISLE_WIDTH).contains(&b);
+        let offset = a.rem_euclid(PARKING_BAY_WIDTH);
+        let on_line = !(0.5..PARKING_BAY_WIDTH - 0.5).contains(&offset);
+
+        let block = if !in_aisle && on_line {
+            WHITE_CONCRETE
+        } else {
+            BLACK_CONCRETE
+        };
+        editor.set_block(block, x, 0, z, Some(&[GRAY_CONCRETE]), None);
+    }
+
+    // Parked cars only fit on open air lots
+    if element
+        .tags()
+        .get("parking")
+        .is_some_and(|parking| parking != "surface")
+    {
+        return;
+    }
+
+    let floor_set: HashSet<(i32, i32)> = floor_area.into_iter().collect();
+    let (mut min_a, mut max_a, mut min_b, mut max_b) = (f64::MAX, f64::MIN, f64::MAX, f64::MIN);
+    for &(x, z) in &polygon_coords {
+        let (a, b) = project(x, z);
+        min_a = min_a.min(a);
+        max_a = max_a.max(a);
+        min_b = min_b.min(b);
+        max_b = max_b.max(b);
+    }
+
+    // Place cars in random bays where the whole car lies within the lot
+    let mut rng = element_rng();
+    let first_row = (min_b / row_period).floor() as i32;
+    let last_row = (max_b / row_period).floor() as i32;
+    let first_bay = (min_a / PARKING_BAY_WIDTH).floor() as i32;
+    let last_bay = (max_a / PARKING_BAY_WIDTH).floor() as i32;
+
+    for row in first_row..=last_row {
+        for row_side in [0.0, PARKING_BAY_DEPTH + PARKING_AISLE_WIDTH] {
+            let row_start = row as f64 * row_period + row_side;
+            for bay in first_bay..=last_bay {
+                if !rng.gen_bool(PARKED_CAR_CHANCE) {
+                    continue;
+                }
+
+                let bay_center = (bay as f64 + 0.5) * PARKING_BAY_WIDTH;
+                let car_cells: Vec<(i32, i32)> = (1..=3)
+                    .map(|step| unproject(bay_center, row_start + step as f64 + 0.5))
+                    .collect();
+                if car_cells.iter().all(|cell| floor_set.contains(cell)) {
+                    generate_parked_car(editor, &car_cells, &mut rng);
+                }
+            }
+        }
+    }
+}
+
+/// Builds a small car over the given cells, ordered from front to back
+fn generate_parked_car(editor: &mut WorldEditor, cells: &[(i32, i32)], rng: &mut impl Rng) {
+    let car_colors = [
+        WHITE_CONCRETE,
+        BLACK_CONCRETE,
+        GRAY_CONCRETE,
+        LIGHT_GRAY_CONCRETE,
+        RED_CONCRETE,
+        BLUE_CONCRETE,
+    ];
+    let body = car_colors[rng.gen_range(0..car_colors.len())];
+
+    for (i, &(x, z)) in cells.iter().enumerate() {
+        editor.set_block(body, x, 1, z, None, None);
+
+        // Cabin with windows in the middle of the car
+        if i == cells.len() / 2 {
+            editor.set_block(GLASS, x, 2, z, None, None);
+        }
+    }
+}
+
+/// Offsets to the four neighbours of a block and the direction facing away from it
+const CLOCK_SIDES: [(i32, i32, StairFacing); 4] = [
+    (1, 0, StairFacing::East),
+    (0, 1, StairFacing::South),
+    (-1, 0, StairFacing::West),
+    (0, -1, StairFacing::North),
+];
+
+/// Generates a public clock, either on a post or mounted on an adjacent building wall
+fn generate_clock(editor: &mut WorldEditor, pt: XZPoint, support: Option<&str>) {
+    if support == Some("wall_mounted") {
+        if let Some((x, z, facing)) = find_wall_clock_spot(editor, pt) {
+            set_clock_face(editor, x, 3, z, facing);
+            return;
+        }
+    }
+
+    if support == Some("ground") {
+        // Free standing clock pillar
+        for y in 1..=4 {
+            editor.set_block(STONE_BRICKS, pt.x, y, pt.z, None, None);
+        }
+    } else {
+        // Street clock on a post, with the dials around a box at the top
+        editor.set_block(COBBLESTONE_WALL, pt.x, 1, pt.z, None, None);
+        for y in 2..=3 {
+            editor.set_block(OAK_FENCE, pt.x, y, pt.z, None, None);
+        }
+        editor.set_block(DARK_OAK_PLANKS, pt.x, 4, pt.z, None, None);
+    }
+
+    for (dx, dz, facing) in CLOCK_SIDES {
+        if !editor.block_at(pt.x + dx, 4, pt.z + dz) {
+            set_clock_face(editor, pt.x + dx, 4, pt.z + dz, facing);
+        }
+    }
+}
+
+/// Places a wall sign showing a clock dial, hanging on the block behind it
+fn set_clock_face(editor: &mut WorldEditor, x: i32, y: i32, z: i32, facing: StairFacing) {
+    editor.set_wall_sign(
+        [
+            "12".to_string(),
+            "9    ·    3".to_string(),
+            "6".to_string(),
+            String::new(),
+        ],
+        x,
+        y,
+        z,
+        facing,
+    );
+}
+
+/// Finds a free spot on the outside of the building wall next to a wall mounted clock and
+/// the direction facing away from the wall
+fn find_wall_clock_spot(editor: &WorldEditor, pt: XZPoint) -> Option<(i32, i32, StairFacing)> {
+    // The node lies next to the wall, the dial faces away from it
+    if !editor.block_at(pt.x, 3, pt.z) {
+        return CLOCK_SIDES
+            .iter()
+            .find(|(dx, dz, _)| editor.block_at(pt.x - dx, 3, pt.z - dz))
+            .map(|&(_, _, facing)| (pt.x, pt.z, facing));
+    }
+
+    // The node lies on the wall itself, prefer the side without a building floor
+    let free_spots: Vec<(i32, i32, StairFacing)> = CLOCK_SIDES
+        .iter()
+        .map(|&(dx, dz, facing)| (pt.x + dx, pt.z + dz, facing))
+        .filter(|&(x, z, _)| !editor.block_at(x, 3, z))
+        .collect();
+
+    free_spots
+        .iter()
+        .find(|&&(x, z, _)| !editor.block_at(x, 0, z))
+        .or(free_spots.first())
+        .copied()
 }
@@ -4,13 +4,20 @@ use crate::bresenham::bresenham_line;
 use crate::colors::color_text_to_rgb_tuple;
 use crate::coordinate_system::cartesian::XZPoint;
 use crate::element_processing::subprocessor::buildings_interior::generate_building_interior;
+use crate::element_rng::element_rng;
 use crate::floodfill::flood_fill_area;
 use crate::osm_parser::{ProcessedMemberRole, ProcessedRelation, ProcessedWay};
+use crate::time_of_day::TimeOfDay;
 use crate::world_editor::WorldEditor;
 use rand::Rng;
 use std::collections::HashSet;
 use std::time::Duration;
 
+/// Share of the floors with their lights on at night
+const LIT_FLOOR_CHANCE: f64 = 0.4;
+/// Deepest cut of a building into the uphill side of a slope, in blocks
+const MAX_SLOPE_CUT: i32 = 3;
+
 /// Enum representing different roof types
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum RoofType {
@@ -22,6 +29,28 @@ enum RoofType {
     Flat,   // Default flat roof
 }
 
+/// Returns the ground level under every cell of a footprint
+fn footprint_ground_levels(editor: &WorldEditor, cells: &[(i32, i32)]) -> Vec<i32> {
+    let Some(ground) = editor.get_ground() else {
+        return Vec::new();
+    };
+    let (min_x, min_z) = editor.get_min_coords();
+    ground.levels(
+        cells
+            .iter()
+            .map(|&(x, z)| XZPoint::new(x - min_x, z - min_z)),
+    )
+}
+
+/// Returns the level a building stands on over the given ground levels. It is the lowest of
+/// them unless that cuts too deep into the slope, in which case the downhill side stands on a
+/// foundation instead.
+fn bench_level(levels: &[i32]) -> Option<i32> {
+    let min = levels.iter().copied().min()?;
+    let max = levels.iter().copied().max()?;
+    Some(min.max(max - MAX_SLOPE_CUT))
+}
+
 #[inline]
 pub fn generate_buildings(
     editor: &mut WorldEditor,
@@ -49,32 +78,19 @@ pub fn generate_buildings(
         flood_fill_area(&polygon_coords, args.timeout.as_ref());
     let cached_footprint_size = cached_floor_area.len();
 
-    // Use fixed starting Y coordinate based on maximum ground level when terrain is enabled
+    // Ground levels under the footprint, which is benched into slopes when terrain is enabled
+    let footprint_levels: Vec<i32> = if args.terrain {
+        footprint_ground_levels(editor, &cached_floor_area)
+    } else {
+        Vec::new()
+    };
     let start_y_offset = if args.terrain {
-        // Get nodes' XZ points to find maximum elevation
-        let building_points: Vec<XZPoint> = element
-            .nodes
-            .iter()
-            .map(|n| {
-                XZPoint::new(
-                    n.x - editor.get_min_coords().0,
-                    n.z - editor.get_min_coords().1,
-                )
-            })
-            .collect();
-
-        // Calculate maximum and minimum ground level across all nodes
-        let mut max_ground_level = args.ground_level;
-
-        for point in &building_points {
-            if let Some(ground) = editor.get_ground() {
-                let level = ground.level(*point);
-                max_ground_level = max_ground_level.max(level);
-            }
-        }
-
-        // Use the maximum level + min_level offset as the fixed base for the entire building
-        max_ground_level + min_level_offset
+        // Footprints too small to fill stand on the ground at their nodes
+        let node_cells: Vec<(i32, i32)> = element.nodes.iter().map(|n| (n.x, n.z)).collect();
+        let base = bench_level(&footprint_levels)
+            .or_else(|| bench_level(&footprint_ground_levels(editor, &node_cells)))
+            .unwrap_or(args.ground_level);
+        base + min_level_offset
     } else {
         // When terrain is disabled, just use min_level_offset
         min_level_offset
@@ -121,7 +137,7 @@ pub fn generate_buildings(
     let mut processed_points: HashSet<(i32, i32)> = HashSet::new();
     let mut building_height: i32 = ((6.0 * scale_factor) as i32).max(3); // Default building height with scale and minimum
     let mut is_tall_building = false;
-    let mut rng = rand::thread_rng();
+    let mut rng = element_rng();
     let use_vertical_windows = rng.gen_bool(0.7);
     let use_accent_roof_line = rng.gen_bool(0.25);
 
@@ -649,6 +665,39 @@ pub fn generate_buildings(
                 );
             }
         }
+
+        if editor.time_of_day() == TimeOfDay::Night {
+            light_windows(
+                editor,
+                element,
+                floor_area,
+                &current_building,
+                window_block,
+                building_type,
+                start_y_offset + abs_terrain_offset,
+                building_height,
+            );
+        }
+    }
+
+    // Cut the uphill side of the slope away inside the building
+    if args.terrain && min_level == 0 {
+        for (&(x, z), &level) in cached_floor_area.iter().zip(&footprint_levels) {
+            for y in (start_y_offset + 1)..=level {
+                editor.set_block_absolute(CAVE_AIR, x, y, z, None, None);
+            }
+        }
+    }
+
+    // Turn flat roofs into green roofs if tagged as such or enabled as a theme
+    let has_green_roof = is_green_roof(element, args, building_type, cached_footprint_size);
+    if has_green_roof && corner_addup != (0, 0, 0) {
+        generate_green_roof(
+            editor,
+            &cached_floor_area,
+            start_y_offset + building_height + abs_terrain_offset + 1,
+            floor_block,
+        );
     }
 
     // Process roof shapes if specified and roof generation is enabled
@@ -684,10 +733,11 @@ pub fn generate_buildings(
                 .unwrap_or("yes");
 
             // For apartments, give 80% chance to generate a gabled roof only if building footprint is not too large
-            if building_type == "apartments"
-                || building_type == "residential"
-                || building_type == "house"
-                || building_type == "yes"
+            if !has_green_roof
+                && (building_type == "apartments"
+                    || building_type == "residential"
+                    || building_type == "house"
+                    || building_type == "yes")
             {
                 // Use cached footprint area and size instead of recalculating
                 let footprint_size = cached_footprint_size;
@@ -695,7 +745,7 @@ pub fn generate_buildings(
                 // Maximum footprint size threshold for gabled roofs
                 let max_footprint_for_gabled = 800;
 
-                let mut rng = rand::thread_rng();
+                let mut rng = element_rng();
                 if footprint_size <= max_footprint_for_gabled && rng.gen_bool(0.9) {
                     generate_roof(
                         editor,
@@ -719,6 +769,134 @@ pub fn generate_buildings(
     }
 }
 
+/// Places light blocks behind the windows of some floors at night, shops always have their
+/// ground floor lit
+#[allow(clippy::too_many_arguments)]
+fn light_windows(
+    editor: &mut WorldEditor,
+    element: &ProcessedWay,
+    floor_area: &[(i32, i32)],
+    walls: &[(i32, i32)],
+    window_block: Block,
+    building_type: &str,
+    base_y: i32,
+    building_height: i32,
+) {
+    let walls: HashSet<(i32, i32)> = walls.iter().copied().collect();
+    let is_shop = element.tags.contains_key("shop")
+        || matches!(
+            building_type,
+            "retail" | "commercial" | "kiosk" | "supermarket"
+        );
+
+    let mut rng = element_rng();
+    let lit_floors: Vec<bool> = (0..=building_height / 4)
+        .map(|floor| (floor == 0 && is_shop) || rng.gen_bool(LIT_FLOOR_CHANCE))
+        .collect();
+
+    for &(x, z) in floor_area {
+        if walls.contains(&(x, z)) {
+            continue;
+        }
+        for (dx, dz) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let (wall_x, wall_z) = (x + dx, z + dz);
+            if !walls.contains(&(wall_x, wall_z)) {
+                continue;
+            }
+            for h in 2..=building_height {
+                if lit_floors[((h - 1) / 4) as usize]
+                    && editor.check_for_block_absolute(
+                        wall_x,
+                        base_y + h,
+                        wall_z,
+                        Some(&[window_block]),
+                        None,
+                    )
+                {
+                    editor.set_block_absolute(LIGHT, x, base_y + h, z, None, None);
+                }
+            }
+        }
+    }
+}
+
+/// Determines whether a building should get a green roof instead of a plain flat one
+fn is_green_roof(
+    element: &ProcessedWay,
+    args: &Args,
+    building_type: &str,
+    footprint_size: usize,
+) -> bool {
+    let is_flat = element
+        .tags
+        .get("roof:shape")
+        .is_none_or(|shape| shape == "flat");
+    if !is_flat {
+        return false;
+    }
+
+    if element
+        .tags
+        .get("roof:material")
+        .is_some_and(|material| material == "grass" || material == "plants")
+    {
+        return true;
+    }
+
+    if element
+        .tags
+        .get("roof:colour")
+        .is_some_and(|colour| colour == "green")
+    {
+        return true;
+    }
+
+    // Optional theme for big flat commercial roofs
+    args.green_roofs
+        && footprint_size > 400
+        && matches!(
+            building_type,
+            "commercial" | "retail" | "office" | "industrial" | "warehouse" | "supermarket"
+        )
+        && element_rng().gen_bool(0.5)
+}
+
+/// Generates a green roof with grass, shrubs and a gravel border on top of a flat roof
+fn generate_green_roof(
+    editor: &mut WorldEditor,
+    floor_area: &[(i32, i32)],
+    roof_y: i32,
+    roof_block: Block,
+) {
+    let roof_points: HashSet<(i32, i32)> = floor_area.iter().copied().collect();
+    let mut rng = element_rng();
+
+    for &(x, z) in floor_area {
+        let is_border = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+            .iter()
+            .any(|(dx, dz)| !roof_points.contains(&(x + dx, z + dz)));
+
+        if is_border {
+            editor.set_block_absolute(GRAVEL, x, roof_y, z, Some(&[roof_block]), None);
+            continue;
+        }
+
+        editor.set_block_absolute(GRASS_BLOCK, x, roof_y, z, Some(&[roof_block]), None);
+
+        // Scatter shrubs, grass and some flowers
+        let vegetation = match rng.gen_range(0..100) {
+            0..=7 => Some(OAK_LEAVES),
+            8..=37 => Some(GRASS),
+            38..=40 => Some(YELLOW_FLOWER),
+            41..=42 => Some(RED_FLOWER),
+            _ => None,
+        };
+        if let Some(block) = vegetation {
+            editor.set_block_absolute(block, x, roof_y + 1, z, None, None);
+        }
+    }
+}
+
 fn multiply_scale(value: i32, scale_factor: f64) -> i32 {
     // Use bit operations for faster multiplication when possible
     if scale_factor == 1.0 {
@@ -804,7 +982,7 @@ fn generate_roof(
             };
 
             // 50% accent block, otherwise wall block for roof
-            let mut rng = rand::thread_rng();
+            let mut rng = element_rng();
             let roof_block = if rng.gen_bool(0.5) {
                 accent_block
             } else {
@@ -923,7 +1101,7 @@ fn generate_roof(
             let roof_peak_height = base_height + if width.max(length) > 20 { 7 } else { 5 };
 
             // 50% accent block, otherwise wall block for roof
-            let mut rng = rand::thread_rng();
+            let mut rng = element_rng();
             let roof_block = if rng.gen_bool(0.5) {
                 accent_block
             } else {
@@ -1188,7 +1366,7 @@ fn generate_roof(
             let max_roof_height = (building_size / 3).clamp(4, 10);
 
             // 50% accent block, otherwise wall block for roof
-            let mut rng = rand::thread_rng();
+            let mut rng = element_rng();
             let roof_block = if rng.gen_bool(0.5) {
                 accent_block
             } else {
@@ -1269,7 +1447,7 @@ fn generate_roof(
             let peak_height = base_height + (building_size / 3).clamp(3, 8);
 
             // 50% accent block, otherwise wall block for roof
-            let mut rng = rand::thread_rng();
+            let mut rng = element_rng();
             let roof_block = if rng.gen_bool(0.5) {
                 accent_block
             } else {
@@ -1456,7 +1634,7 @@ fn generate_roof(
             let radius = ((max_x - min_x).max(max_z - min_z) / 2) as f64;
 
             // 50% accent block, otherwise wall block for roof
-            let mut rng = rand::thread_rng();
+            let mut rng = element_rng();
             let roof_block = if rng.gen_bool(0.5) {
                 accent_block
             } else {
@@ -1577,3 +1755,17 @@ fn generate_bridge(
         editor.set_block(floor_block, x, bridge_y_offset, z, None, None);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_level_limits_cut_into_slope() {
+        assert_eq!(bench_level(&[]), None);
+        // Gentle slopes are cut down to their lowest point
+        assert_eq!(bench_level(&[10, 11, 12]), Some(10));
+        // Steep ones are cut in a little and stand on a foundation downhill
+        assert_eq!(bench_level(&[10, 14, 20]), Some(20 - MAX_SLOPE_CUT));
+    }
+}
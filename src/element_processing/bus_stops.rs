@@ -0,0 +1,205 @@
+use crate::block_definitions::*;
+use crate::element_processing::street_signs::sign_lines;
+use crate::element_processing::transit_routes::RouteLabel;
+use crate::element_processing::tunnels::way_centerline;
+use crate::osm_parser::{ProcessedElement, ProcessedNode, ProcessedWay};
+use crate::world_editor::WorldEditor;
+use std::collections::HashSet;
+
+/// How far from a bus stop to look for the road it serves
+const ROAD_SEARCH_DISTANCE: i32 = 6;
+/// Road surface blocks a shelter can face
+const ROAD_BLOCKS: [Block; 3] = [BLACK_CONCRETE, WHITE_CONCRETE, GRAY_CONCRETE];
+
+/// Returns true for bus stops and public transport platforms that are not railway platforms
+pub fn is_bus_stop(element: &ProcessedElement) -> bool {
+    let tags = element.tags();
+    if tags.contains_key("railway") {
+        return false;
+    }
+
+    tags.get("highway")
+        .is_some_and(|highway| highway == "bus_stop" || highway == "platform")
+        || tags
+            .get("public_transport")
+            .is_some_and(|public_transport| public_transport == "platform")
+}
+
+/// Generates a bus stop: a shelter with a sign pole for nodes, a raised platform for ways.
+/// The pole shows the numbers of the routes serving the stop, and the pole and platform the
+/// colour of the first of them.
+pub fn generate_bus_stop(
+    editor: &mut WorldEditor,
+    element: &ProcessedElement,
+    routes: &[RouteLabel],
+) {
+    let colour = routes.iter().find_map(|route| route.colour);
+    match element {
+        ProcessedElement::Node(node) => generate_bus_stop_node(editor, node, routes, colour),
+        ProcessedElement::Way(way) => generate_platform_strip(editor, way, colour),
+        ProcessedElement::Relation(_) => {}
+    }
+}
+
+/// Builds a sign pole and, unless tagged otherwise, a shelter facing the nearest road
+fn generate_bus_stop_node(
+    editor: &mut WorldEditor,
+    node: &ProcessedNode,
+    routes: &[RouteLabel],
+    colour: Option<Block>,
+) {
+    let (x, z) = (node.x, node.z);
+    let road = find_road_direction(editor, x, z).unwrap_or((0, 1));
+    // Direction along the road
+    let (side_dx, side_dz) = (-road.1, road.0);
+
+    if node
+        .tags
+        .get("shelter")
+        .is_none_or(|shelter| shelter != "no")
+    {
+        let has_bench = node.tags.get("bench").is_none_or(|bench| bench != "no");
+        generate_shelter(editor, x, z, road, has_bench);
+    }
+
+    // Sign pole next to the shelter
+    let (pole_x, pole_z) = (x + side_dx * 3, z + side_dz * 3);
+    for dy in 1..=3 {
+        editor.set_block(COBBLESTONE_WALL, pole_x, dy, pole_z, None, None);
+    }
+    let flag = colour.unwrap_or(WHITE_WOOL);
+    editor.set_block(flag, pole_x, 4, pole_z, None, None);
+    editor.set_block(flag, pole_x + side_dx, 4, pole_z + side_dz, None, None);
+
+    // Route numbers on the side of the flag facing the road
+    if !routes.is_empty() {
+        let references: Vec<&str> = routes.iter().map(|r| r.reference.as_str()).collect();
+        let facing = match road {
+            (1, 0) => StairFacing::East,
+            (-1, 0) => StairFacing::West,
+            (0, -1) => StairFacing::North,
+            _ => StairFacing::South,
+        };
+        editor.set_wall_sign(
+            sign_lines(&references.join(" ")),
+            pole_x + road.0,
+            4,
+            pole_z + road.1,
+            facing,
+        );
+    }
+}
+
+/// Returns the cardinal direction pointing from the given position to the closest road
+fn find_road_direction(editor: &WorldEditor, x: i32, z: i32) -> Option<(i32, i32)> {
+    let directions = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+    (1..=ROAD_SEARCH_DISTANCE).find_map(|distance| {
+        directions.into_iter().find(|(dx, dz)| {
+            editor.check_for_block(x + dx * distance, 0, z + dz * distance, Some(&ROAD_BLOCKS))
+        })
+    })
+}
+
+/// Builds a shelter with a glass back wall, a slab roof and a bench, open towards the road
+fn generate_shelter(editor: &mut WorldEditor, x: i32, z: i32, road: (i32, i32), has_bench: bool) {
+    let (road_dx, road_dz) = road;
+    let (side_dx, side_dz) = (-road_dz, road_dx);
+    let bench_facing = match road {
+        (1, 0) => StairFacing::West,
+        (-1, 0) => StairFacing::East,
+        (0, -1) => StairFacing::South,
+        _ => StairFacing::North,
+    };
+
+    for side in -2..=2 {
+        let (front_x, front_z) = (x + side_dx * side, z + side_dz * side);
+        let (back_x, back_z) = (front_x - road_dx, front_z - road_dz);
+
+        // Glass back wall with posts at both ends
+        let wall_block = if side.abs() == 2 {
+            STONE_BRICK_WALL
+        } else {
+            GLASS
+        };
+        for dy in 1..=2 {
+            editor.set_block(wall_block, back_x, dy, back_z, None, None);
+        }
+        if side.abs() == 2 {
+            for dy in 1..=2 {
+                editor.set_block(STONE_BRICK_WALL, front_x, dy, front_z, None, None);
+            }
+        }
+
+        // Roof covering the waiting area
+        editor.set_block(STONE_BLOCK_SLAB, back_x, 3, back_z, None, None);
+        editor.set_block(STONE_BLOCK_SLAB, front_x, 3, front_z, None, None);
+
+        // Bench in front of the back wall, facing the road
+        if has_bench && side.abs() <= 1 {
+            let bench_y = editor.get_absolute_y(front_x, 1, front_z);
+            editor.set_block_with_properties_absolute(
+                create_stair_with_properties(OAK_STAIRS, bench_facing, StairShape::Straight),
+                front_x,
+                bench_y,
+                front_z,
+                None,
+                None,
+            );
+        }
+    }
+}
+
+/// Builds a raised platform along a platform way, with a stripe of the route colour sunk into
+/// its middle
+fn generate_platform_strip(editor: &mut WorldEditor, way: &ProcessedWay, colour: Option<Block>) {
+    let centerline = way_centerline(way);
+    let stripe: HashSet<(i32, i32)> = match colour {
+        Some(colour) => {
+            for &(x, z) in &centerline {
+                editor.set_block(colour, x, 0, z, None, None);
+            }
+            centerline.iter().copied().collect()
+        }
+        None => HashSet::new(),
+    };
+    for &(x, z) in &centerline {
+        for dx in -1..=1 {
+            for dz in -1..=1 {
+                editor.set_block(SMOOTH_STONE, x + dx, 0, z + dz, None, None);
+                if !stripe.contains(&(x + dx, z + dz)) {
+                    editor.set_block(STONE_BLOCK_SLAB, x + dx, 1, z + dz, None, None);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn way(tags: &[(&str, &str)]) -> ProcessedElement {
+        ProcessedElement::Way(ProcessedWay {
+            id: 1,
+            nodes: Vec::new(),
+            tags: tags
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<String, String>>(),
+        })
+    }
+
+    #[test]
+    fn test_platform_ways_are_bus_stops() {
+        // Platforms mapped with the public transport scheme alone have no highway tag
+        assert!(is_bus_stop(&way(&[("public_transport", "platform")])));
+        assert!(is_bus_stop(&way(&[("highway", "platform")])));
+        assert!(!is_bus_stop(&way(&[
+            ("public_transport", "platform"),
+            ("railway", "platform"),
+        ])));
+        assert!(!is_bus_stop(&way(&[("highway", "footway")])));
+    }
+}
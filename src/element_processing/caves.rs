@@ -0,0 +1,185 @@
+use crate::args::Args;
+use crate::block_definitions::*;
+use crate::element_rng::{element_rng, ElementRng};
+use crate::osm_parser::ProcessedNode;
+use crate::world_editor::WorldEditor;
+use rand::Rng;
+use std::collections::BTreeSet;
+
+/// Radius of the rock outcrop the cave opens into, in blocks
+const OUTCROP_RADIUS: i32 = 6;
+/// Height of the rock outcrop above the ground at its middle
+const OUTCROP_HEIGHT: f64 = 6.0;
+/// Length of the tunnel behind the opening
+const TUNNEL_LENGTH: i32 = 12;
+/// Blocks into the tunnel after which it starts to descend, one block every two blocks
+const TUNNEL_LEVEL_LENGTH: i32 = 3;
+/// Steps of the random walk carving the cave system behind the tunnel
+const CAVE_STEPS: usize = 32;
+/// Blocks of rock kept between the caves and the ground above them
+const CAVE_COVER: i32 = 3;
+/// Chance of a pointed dripstone hanging from each block of the cave ceiling
+const DRIPSTONE_CHANCE: f64 = 0.08;
+
+pub fn is_cave_entrance(node: &ProcessedNode) -> bool {
+    node.tags.get("natural").map(String::as_str) == Some("cave_entrance")
+}
+
+/// Opens a cave in a rock outcrop with a tunnel leading down into it, and with
+/// `--cave-systems` a winding cave behind the tunnel
+pub fn generate_cave_entrance(editor: &mut WorldEditor, node: &ProcessedNode, args: &Args) {
+    let mut rng: ElementRng = element_rng();
+    // The tunnel leads away from the direction the opening faces
+    let facing = node
+        .tags
+        .get("direction")
+        .and_then(|direction| parse_direction(direction))
+        .unwrap_or_else(|| rng.gen_range(0.0..360.0));
+    let heading = (facing + 180.0).to_radians();
+    let (dx, dz) = (heading.sin(), -heading.cos());
+    let (x, z) = (node.x as f64, node.z as f64);
+
+    // Rock outcrop behind the opening, so that the cave stands out of flat ground
+    let (outcrop_x, outcrop_z) = (
+        (x + dx * OUTCROP_RADIUS as f64).round() as i32,
+        (z + dz * OUTCROP_RADIUS as f64).round() as i32,
+    );
+    for ox in -OUTCROP_RADIUS..=OUTCROP_RADIUS {
+        for oz in -OUTCROP_RADIUS..=OUTCROP_RADIUS {
+            let distance = ((ox * ox + oz * oz) as f64).sqrt() / OUTCROP_RADIUS as f64;
+            let height = ((1.0 - distance) * OUTCROP_HEIGHT).round() as i32;
+            let (cx, cz) = (outcrop_x + ox, outcrop_z + oz);
+            for y in 1..=height {
+                let block = if y == height && rng.gen_bool(0.3) {
+                    MOSSY_COBBLESTONE
+                } else {
+                    STONE
+                };
+                editor.set_block(block, cx, y, cz, None, None);
+            }
+        }
+    }
+
+    // Tunnel of 3 by 3 blocks, level at first and then descending
+    let mouth_y = editor.get_absolute_y(node.x, 1, node.z);
+    let mut carved: BTreeSet<(i32, i32, i32)> = BTreeSet::new();
+    let mut end = (node.x, mouth_y, node.z);
+    for step in 0..TUNNEL_LENGTH {
+        let cx = (x + dx * step as f64).round() as i32;
+        let cz = (z + dz * step as f64).round() as i32;
+        let floor = mouth_y - (step - TUNNEL_LEVEL_LENGTH).max(0) / 2;
+        for (ox, oz) in [(0, 0), (1, 0), (-1, 0), (0, 1), (0, -1)] {
+            for y in floor..floor + 3 {
+                carved.insert((cx + ox, y, cz + oz));
+            }
+        }
+        end = (cx, floor + 1, cz);
+    }
+
+    if args.cave_systems {
+        carve_cave_system(editor, end, heading, &mut rng, &mut carved);
+    }
+
+    for &(cx, y, cz) in &carved {
+        editor.set_block_absolute(CAVE_AIR, cx, y, cz, None, Some(&[BEDROCK]));
+    }
+    line_with_rock(editor, &carved, &mut rng);
+}
+
+/// Carves a winding cave of round chambers from the given start, staying below the ground
+fn carve_cave_system(
+    editor: &WorldEditor,
+    start: (i32, i32, i32),
+    mut heading: f64,
+    rng: &mut ElementRng,
+    carved: &mut BTreeSet<(i32, i32, i32)>,
+) {
+    let (mut x, mut y, mut z) = (start.0 as f64, start.1 as f64, start.2 as f64);
+    for _ in 0..CAVE_STEPS {
+        // Gentle turns keep the cave from winding back into the tunnel
+        heading += rng.gen_range(-0.6..0.6);
+        let radius: i32 = rng.gen_range(2..=4);
+        x += heading.sin() * radius as f64;
+        z -= heading.cos() * radius as f64;
+        y += rng.gen_range(-1.5..1.0);
+
+        // Keep a cover of rock between the cave and the ground above
+        let (cx, cz) = (x.round() as i32, z.round() as i32);
+        let ceiling = editor.get_absolute_y(cx, 0, cz) - CAVE_COVER - radius;
+        y = y.min(ceiling as f64);
+        let cy = y.round() as i32;
+
+        for ox in -radius..=radius {
+            for oy in -radius..=radius {
+                for oz in -radius..=radius {
+                    if ox * ox + oy * oy + oz * oz <= radius * radius {
+                        carved.insert((cx + ox, cy + oy, cz + oz));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Lines the carved space below the ground with stone wherever it is not enclosed yet, and
+/// hangs pointed dripstone from parts of its ceiling
+fn line_with_rock(
+    editor: &mut WorldEditor,
+    carved: &BTreeSet<(i32, i32, i32)>,
+    rng: &mut ElementRng,
+) {
+    for &(x, y, z) in carved {
+        for (ox, oy, oz) in [
+            (1, 0, 0),
+            (-1, 0, 0),
+            (0, 1, 0),
+            (0, -1, 0),
+            (0, 0, 1),
+            (0, 0, -1),
+        ] {
+            // Above the ground the opening stays open to the outside
+            let (nx, ny, nz) = (x + ox, y + oy, z + oz);
+            if !carved.contains(&(nx, ny, nz)) && ny <= editor.get_absolute_y(nx, 0, nz) {
+                editor.set_block_absolute(STONE, nx, ny, nz, None, None);
+            }
+        }
+
+        let is_ceiling = !carved.contains(&(x, y + 1, z))
+            && carved.contains(&(x, y - 1, z))
+            && y < editor.get_absolute_y(x, 0, z);
+        if is_ceiling && rng.gen_bool(DRIPSTONE_CHANCE) {
+            editor.set_block_absolute(DRIPSTONE_BLOCK, x, y + 1, z, Some(&[STONE]), None);
+            editor.set_block_absolute(POINTED_DRIPSTONE, x, y, z, Some(&[CAVE_AIR]), None);
+        }
+    }
+}
+
+/// Parses the direction tag in degrees or as a compass point, such as "NE"
+fn parse_direction(direction: &str) -> Option<f64> {
+    const POINTS: [&str; 16] = [
+        "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW",
+        "NW", "NNW",
+    ];
+    let direction = direction.trim();
+    if let Ok(degrees) = direction.parse::<f64>() {
+        return Some(degrees.rem_euclid(360.0));
+    }
+    POINTS
+        .iter()
+        .position(|point| point.eq_ignore_ascii_case(direction))
+        .map(|index| index as f64 * 22.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_direction() {
+        assert_eq!(parse_direction("90"), Some(90.0));
+        assert_eq!(parse_direction("-90"), Some(270.0));
+        assert_eq!(parse_direction("sw"), Some(225.0));
+        assert_eq!(parse_direction("NNE"), Some(22.5));
+        assert_eq!(parse_direction("uphill"), None);
+    }
+}
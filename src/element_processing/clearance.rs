@@ -0,0 +1,21 @@
+use crate::block_definitions::AIR;
+use crate::world_editor::WorldEditor;
+
+/// Height of the free space kept above roads and plazas, in blocks
+const ROAD_CLEARANCE: i32 = 3;
+
+/// Removes blocks hovering above roads and plazas, such as tree crowns, low building overhangs
+/// and bridge decks, so that players can walk along them. Blocks standing on the road like
+/// lamp posts and rails are kept, as is everything below elevated roads.
+pub fn clear_above_roads(editor: &mut WorldEditor) {
+    for (x, z) in editor.take_walkable_columns() {
+        if editor.block_at(x, 1, z) {
+            continue;
+        }
+        for y in 2..=ROAD_CLEARANCE {
+            if editor.block_at(x, y, z) {
+                editor.set_block(AIR, x, y, z, None, Some(&[]));
+            }
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::VecDeque;
+
+use crate::block_definitions::SAND;
+use crate::bresenham::bresenham_line;
+use crate::element_processing::water_areas::{place_water_with_owners, WaterMask};
+use crate::osm_parser::ProcessedWay;
+use crate::world_editor::{Biome, BlockOwner, WorldEditor};
+
+const UNKNOWN: u8 = 0;
+const COAST: u8 = 1;
+const LAND: u8 = 2;
+const SEA: u8 = 3;
+/// Source of a cell that no coastline reached
+const NO_SOURCE: u32 = u32::MAX;
+
+pub fn is_coastline(way: &ProcessedWay) -> bool {
+    way.tags.get("natural").map(String::as_str) == Some("coastline")
+}
+
+/// Fills the sea side of all coastline ways with ocean up to the edge of the world.
+/// Coastlines are drawn with the land on their left, so the sea is found on their right.
+/// Every block belongs to the coastline the sea spread from to reach it.
+pub fn generate_ocean(editor: &mut WorldEditor, coastlines: &[&ProcessedWay]) {
+    if coastlines.is_empty() {
+        return;
+    }
+
+    let (min_x, min_z) = editor.get_min_coords();
+    let (max_x, max_z) = editor.get_max_coords();
+    let size_x = (max_x - min_x + 1) as usize;
+    let size_z = (max_z - min_z + 1) as usize;
+    let index = |x: i32, z: i32| -> Option<usize> {
+        let (dx, dz) = (x - min_x, z - min_z);
+        (dx >= 0 && dz >= 0 && (dx as usize) < size_x && (dz as usize) < size_z)
+            .then(|| dz as usize * size_x + dx as usize)
+    };
+
+    let mut cells: Vec<u8> = vec![UNKNOWN; size_x * size_z];
+    // Index of the coastline each cell was found from
+    let mut sources: Vec<u32> = vec![NO_SOURCE; size_x * size_z];
+    let mut sea_seeds: Vec<(usize, u32)> = Vec::new();
+
+    for (source, way) in (0u32..).zip(coastlines) {
+        for pair in way.nodes.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+
+            // Clipping joins the points where a coastline leaves and re-enters the world
+            // with a straight segment along the edge, which is no coast
+            let on_edge = |x: i32, z: i32| x == min_x || x == max_x || z == min_z || z == max_z;
+            if on_edge(a.x, a.z) && on_edge(b.x, b.z) {
+                continue;
+            }
+
+            let (dx, dz) = ((b.x - a.x) as f64, (b.z - a.z) as f64);
+            let length = (dx * dx + dz * dz).sqrt();
+            if length == 0.0 {
+                continue;
+            }
+            // Right hand side of the direction of travel, with z pointing south
+            let (right_x, right_z) = ((-dz / length).round() as i32, (dx / length).round() as i32);
+
+            for (x, _, z) in bresenham_line(a.x, 0, a.z, b.x, 0, b.z) {
+                if let Some(i) = index(x, z) {
+                    cells[i] = COAST;
+                    sources[i] = source;
+                }
+                if let Some(i) = index(x + right_x, z + right_z) {
+                    sea_seeds.push((i, source));
+                }
+                if let Some(i) = index(x - right_x, z - right_z) {
+                    if cells[i] == UNKNOWN {
+                        cells[i] = LAND;
+                        sources[i] = source;
+                    }
+                }
+            }
+        }
+    }
+
+    // Spread the sea from the seeds until it hits a coastline or the land next to one
+    let mut queue: VecDeque<usize> = VecDeque::new();
+    for (i, source) in sea_seeds {
+        if cells[i] != COAST {
+            cells[i] = SEA;
+            sources[i] = source;
+            queue.push_back(i);
+        }
+    }
+    while let Some(i) = queue.pop_front() {
+        let (x, z) = (i % size_x, i / size_x);
+        let neighbours = [
+            (x > 0).then(|| i - 1),
+            (x + 1 < size_x).then(|| i + 1),
+            (z > 0).then(|| i - size_x),
+            (z + 1 < size_z).then(|| i + size_x),
+        ];
+        for neighbour in neighbours.into_iter().flatten() {
+            if cells[neighbour] == UNKNOWN {
+                cells[neighbour] = SEA;
+                sources[neighbour] = sources[i];
+                queue.push_back(neighbour);
+            }
+        }
+    }
+
+    let owner_at = |x: i32, z: i32| {
+        let source = sources[index(x, z)?];
+        coastlines.get(source as usize).map(|way| BlockOwner {
+            kind: "way",
+            id: way.id,
+        })
+    };
+
+    let mut mask = WaterMask::new(min_x, min_z, max_x, max_z);
+    for (i, &cell) in cells.iter().enumerate() {
+        let (x, z) = (min_x + (i % size_x) as i32, min_z + (i / size_x) as i32);
+        match cell {
+            SEA => mask.mark(x, z),
+            // A strip of beach along the shore
+            COAST | LAND => {
+                editor.set_block_owner(owner_at(x, z));
+                editor.set_block(SAND, x, 0, z, None, None);
+            }
+            _ => {}
+        }
+    }
+    place_water_with_owners(editor, mask, Biome::Ocean, owner_at);
+    editor.set_block_owner(None);
+}
@@ -23,3 +23,67 @@ pub fn generate_doors(editor: &mut WorldEditor, element: &ProcessedNode) {
         editor.set_block(DARK_OAK_DOOR_UPPER, x, 2, z, None, None);
     }
 }
+
+/// Longest ramp built in front of an accessible entrance
+const MAX_RAMP_LENGTH: i32 = 8;
+
+/// Returns true for entrances tagged as accessible by wheelchair or with a ramp
+pub fn is_accessible_entrance(element: &ProcessedNode) -> bool {
+    let tag_is = |key: &str, values: &[&str]| {
+        element
+            .tags
+            .get(key)
+            .is_some_and(|value| values.contains(&value.as_str()))
+    };
+
+    tag_is("wheelchair", &["yes", "designated", "limited"])
+        || tag_is("ramp", &["yes"])
+        || tag_is("ramp:wheelchair", &["yes"])
+}
+
+/// Builds a slab ramp from an accessible entrance down to the surrounding ground.
+/// Runs after the buildings so the outside of the entrance can be told from the inside.
+pub fn generate_entrance_ramp(editor: &mut WorldEditor, element: &ProcessedNode) {
+    let (x, z) = (element.x, element.z);
+    let is_covered =
+        |editor: &WorldEditor, x: i32, z: i32| (1..=8).any(|y| editor.block_at(x, y, z));
+
+    // The outside is open to the sky, the inside lies under the building
+    let Some((dx, dz)) = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+        .into_iter()
+        .find(|&(dx, dz)| {
+            !is_covered(editor, x + dx, z + dz) && is_covered(editor, x - dx, z - dz)
+        })
+    else {
+        return;
+    };
+
+    // Walking height on top of the doorstep, counted in half blocks
+    let doorstep_top = editor.get_absolute_y(x, 0, z) + 1;
+
+    for distance in 1..=MAX_RAMP_LENGTH {
+        let level = 2 * doorstep_top - distance;
+
+        for side in -1..=1 {
+            let (ramp_x, ramp_z) = (x + dx * distance - dz * side, z + dz * distance + dx * side);
+            let ground_top = editor.get_absolute_y(ramp_x, 0, ramp_z) + 1;
+            if level <= 2 * ground_top {
+                continue;
+            }
+
+            // Support up to the ramp surface, finished with a slab on half block levels
+            let surface_y = level.div_euclid(2);
+            for y in ground_top..surface_y {
+                editor.set_block_absolute(STONE_BRICKS, ramp_x, y, ramp_z, None, None);
+            }
+            if level.rem_euclid(2) == 1 {
+                editor.set_block_absolute(STONE_BRICK_SLAB, ramp_x, surface_y, ramp_z, None, None);
+            }
+        }
+
+        // The ramp ends where it meets the ground in front of the entrance
+        if level <= 2 * (editor.get_absolute_y(x + dx * distance, 0, z + dz * distance) + 1) {
+            break;
+        }
+    }
+}
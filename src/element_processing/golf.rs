@@ -0,0 +1,108 @@
+use crate::args::Args;
+use crate::block_definitions::*;
+use crate::element_processing::water_areas::{place_water, WaterMask};
+use crate::element_rng::{element_rng, ElementRng};
+use crate::floodfill::flood_fill_area;
+use crate::osm_parser::ProcessedWay;
+use crate::world_editor::{Biome, WorldEditor};
+use rand::Rng;
+use std::collections::HashSet;
+
+/// Height of the flag poles on greens, in blocks
+const FLAG_POLE_HEIGHT: i32 = 4;
+
+/// Returns true for the areas of a golf course, which are generated before any landuse or
+/// natural tags they also carry
+pub fn is_golf_feature(way: &ProcessedWay) -> bool {
+    matches!(
+        way.tags.get("golf").map(String::as_str),
+        Some(
+            "fairway"
+                | "green"
+                | "tee"
+                | "rough"
+                | "bunker"
+                | "water_hazard"
+                | "lateral_water_hazard"
+        )
+    )
+}
+
+pub fn generate_golf_feature(editor: &mut WorldEditor, way: &ProcessedWay, args: &Args) {
+    let polygon: Vec<(i32, i32)> = way.nodes.iter().map(|n| (n.x, n.z)).collect();
+    let area: HashSet<(i32, i32)> = flood_fill_area(&polygon, args.timeout.as_ref())
+        .into_iter()
+        .collect();
+    if area.is_empty() {
+        return;
+    }
+    let mut cells: Vec<(i32, i32)> = area.iter().copied().collect();
+    cells.sort_unstable();
+
+    let mut rng: ElementRng = element_rng();
+    match way.tags.get("golf").map(String::as_str) {
+        // Closely mown grass
+        Some(kind @ ("green" | "tee")) => {
+            for &(x, z) in &cells {
+                editor.set_block(MOSS_BLOCK, x, 0, z, Some(&[GRASS_BLOCK]), None);
+            }
+            if kind == "green" {
+                generate_flag(editor, &cells);
+            }
+        }
+        Some("fairway") => {
+            for &(x, z) in &cells {
+                editor.set_block(GRASS_BLOCK, x, 0, z, None, None);
+            }
+        }
+        Some("rough") => {
+            for &(x, z) in &cells {
+                editor.set_block(GRASS_BLOCK, x, 0, z, None, None);
+                if rng.gen_bool(0.3) {
+                    editor.set_block(GRASS, x, 1, z, None, None);
+                }
+            }
+        }
+        // Sand lowered by a block inside its rim
+        Some("bunker") => {
+            for &(x, z) in &cells {
+                let is_rim = [(x + 1, z), (x - 1, z), (x, z + 1), (x, z - 1)]
+                    .iter()
+                    .any(|cell| !area.contains(cell));
+                if is_rim {
+                    editor.set_block(SAND, x, 0, z, Some(&[GRASS_BLOCK]), None);
+                } else {
+                    editor.set_block(SAND, x, -1, z, Some(&[DIRT]), None);
+                    editor.set_block(CAVE_AIR, x, 0, z, Some(&[GRASS_BLOCK, SAND]), None);
+                }
+            }
+        }
+        Some("water_hazard" | "lateral_water_hazard") => {
+            let (min_x, max_x) = (cells[0].0, cells[cells.len() - 1].0);
+            let min_z = cells.iter().map(|cell| cell.1).min().unwrap_or_default();
+            let max_z = cells.iter().map(|cell| cell.1).max().unwrap_or_default();
+            let mut mask = WaterMask::new(min_x, min_z, max_x, max_z);
+            for &(x, z) in &cells {
+                // The grass of the golf course may already cover the pond
+                editor.set_block(AIR, x, 0, z, Some(&[GRASS_BLOCK]), None);
+                mask.mark(x, z);
+            }
+            place_water(editor, mask, Biome::River);
+        }
+        _ => {}
+    }
+}
+
+/// Puts up a flag pole with a red flag in the middle of a green
+fn generate_flag(editor: &mut WorldEditor, cells: &[(i32, i32)]) {
+    let count = cells.len() as i32;
+    let (sum_x, sum_z) = cells
+        .iter()
+        .fold((0, 0), |(sx, sz), &(x, z)| (sx + x, sz + z));
+    let (x, z) = (sum_x / count, sum_z / count);
+
+    for y in 1..=FLAG_POLE_HEIGHT {
+        editor.set_block(IRON_BARS, x, y, z, None, None);
+    }
+    editor.set_block(RED_WOOL, x + 1, FLAG_POLE_HEIGHT, z, None, None);
+}
@@ -0,0 +1,208 @@
+use crate::bresenham::bresenham_line;
+use crate::element_processing::tunnels::way_centerline;
+use crate::osm_parser::ProcessedElement;
+use std::collections::HashMap;
+
+/// Depth of gorges between cliffs without a height tag at scale 1, in blocks
+const DEFAULT_GORGE_DEPTH: f64 = 8.0;
+/// Widest distance between the cliffs of a gorge at scale 1, in blocks
+const MAX_GORGE_WIDTH: f64 = 48.0;
+/// Step along the rays across a waterway looking for the cliffs on either side
+const RAY_STEP: f64 = 0.5;
+
+/// Cell on the line of a cliff
+struct CliffCell {
+    /// Direction away from the cliff towards its top
+    towards_top: (f64, f64),
+    /// Tagged height of the cliff in blocks
+    height: Option<f64>,
+}
+
+/// Finds the stretches of waterways running between two cliffs that face each other across
+/// them, and returns how deep to sink the ground between the cliffs for every column of these
+/// gorges, so that the river flows at their bottom
+pub fn gorge_cuts(elements: &[ProcessedElement], scale: f64) -> HashMap<(i32, i32), i32> {
+    let mut cliffs: HashMap<(i32, i32), CliffCell> = HashMap::new();
+    for element in elements {
+        let ProcessedElement::Way(way) = element else {
+            continue;
+        };
+        if way.tags.get("natural").map(String::as_str) != Some("cliff") {
+            continue;
+        }
+        let height = way
+            .tags
+            .get("height")
+            .and_then(|height| height.trim_end_matches('m').trim().parse::<f64>().ok())
+            .map(|height| height * scale);
+        for pair in way.nodes.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            let (dx, dz) = ((b.x - a.x) as f64, (b.z - a.z) as f64);
+            let length = (dx * dx + dz * dz).sqrt();
+            if length == 0.0 {
+                continue;
+            }
+            // Cliffs are mapped with their top on the left
+            for (x, _, z) in bresenham_line(a.x, 0, a.z, b.x, 0, b.z) {
+                cliffs.insert(
+                    (x, z),
+                    CliffCell {
+                        towards_top: (dz / length, -dx / length),
+                        height,
+                    },
+                );
+            }
+        }
+    }
+
+    let mut cuts: HashMap<(i32, i32), i32> = HashMap::new();
+    if cliffs.is_empty() {
+        return cuts;
+    }
+
+    let max_reach = MAX_GORGE_WIDTH * scale.max(0.5);
+    for element in elements {
+        let ProcessedElement::Way(way) = element else {
+            continue;
+        };
+        if !matches!(
+            way.tags.get("waterway").map(String::as_str),
+            Some("river" | "stream" | "canal" | "brook")
+        ) {
+            continue;
+        }
+
+        let centerline = way_centerline(way);
+        for (i, &(x, z)) in centerline.iter().enumerate() {
+            // Direction of the waterway around this point
+            let (before, after) = (
+                centerline[i.saturating_sub(2)],
+                centerline[(i + 2).min(centerline.len() - 1)],
+            );
+            let (dx, dz) = ((after.0 - before.0) as f64, (after.1 - before.1) as f64);
+            let length = (dx * dx + dz * dz).sqrt();
+            if length == 0.0 {
+                continue;
+            }
+            let (tx, tz) = (dx / length, dz / length);
+            let normal = (tz, -tx);
+
+            let facing_cliff = |sign: f64| {
+                find_facing_cliff(
+                    &cliffs,
+                    (x, z),
+                    (normal.0 * sign, normal.1 * sign),
+                    max_reach,
+                )
+            };
+            let (Some((left, left_height)), Some((right, right_height))) =
+                (facing_cliff(1.0), facing_cliff(-1.0))
+            else {
+                continue;
+            };
+            if left + right > max_reach {
+                continue;
+            }
+
+            let depth = match (left_height, right_height) {
+                (Some(a), Some(b)) => a.min(b),
+                (Some(height), None) | (None, Some(height)) => height,
+                (None, None) => DEFAULT_GORGE_DEPTH * scale,
+            }
+            .round()
+            .max(1.0) as i32;
+
+            // Sink the ground across the gorge up to the foot of both cliffs
+            for (sign, reach) in [(1.0, left), (-1.0, right)] {
+                let mut distance = 0.0;
+                while distance < reach {
+                    for along in [-RAY_STEP, 0.0, RAY_STEP] {
+                        let cell = (
+                            (x as f64 + normal.0 * sign * distance + tx * along).round() as i32,
+                            (z as f64 + normal.1 * sign * distance + tz * along).round() as i32,
+                        );
+                        if !cliffs.contains_key(&cell) {
+                            let cut = cuts.entry(cell).or_insert(0);
+                            *cut = (*cut).max(depth);
+                        }
+                    }
+                    distance += RAY_STEP;
+                }
+            }
+        }
+    }
+    cuts
+}
+
+/// Follows a ray from a point of a waterway and returns the distance to the first cliff it
+/// meets and the height of that cliff, if the cliff faces the waterway with its foot
+fn find_facing_cliff(
+    cliffs: &HashMap<(i32, i32), CliffCell>,
+    (x, z): (i32, i32),
+    (dx, dz): (f64, f64),
+    max_reach: f64,
+) -> Option<(f64, Option<f64>)> {
+    let mut distance = RAY_STEP;
+    while distance <= max_reach {
+        let cell = (
+            (x as f64 + dx * distance).round() as i32,
+            (z as f64 + dz * distance).round() as i32,
+        );
+        if let Some(cliff) = cliffs.get(&cell) {
+            // The top must lie further away along the ray, beyond the face
+            let faces = cliff.towards_top.0 * dx + cliff.towards_top.1 * dz > 0.0;
+            return faces.then_some((distance, cliff.height));
+        }
+        distance += RAY_STEP;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osm_parser::{ProcessedNode, ProcessedWay};
+
+    fn way(id: u64, tags: &[(&str, &str)], points: &[(i32, i32)]) -> ProcessedElement {
+        ProcessedElement::Way(ProcessedWay {
+            id,
+            nodes: points
+                .iter()
+                .map(|&(x, z)| ProcessedNode {
+                    id: 0,
+                    tags: HashMap::new(),
+                    x,
+                    z,
+                })
+                .collect(),
+            tags: tags
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        })
+    }
+
+    #[test]
+    fn test_gorge_between_facing_cliffs() {
+        // A river flowing east between cliffs with their tops to the north and south
+        let river = way(1, &[("waterway", "river")], &[(0, 0), (40, 0)]);
+        let north = way(2, &[("natural", "cliff")], &[(0, -6), (40, -6)]);
+        let south = way(
+            3,
+            &[("natural", "cliff"), ("height", "12")],
+            &[(40, 6), (0, 6)],
+        );
+
+        let cuts = gorge_cuts(&[river.clone(), north.clone(), south], 1.0);
+        // As deep as the one cliff with a height
+        assert_eq!(cuts.get(&(20, 0)), Some(&12));
+        assert_eq!(cuts.get(&(20, -5)), Some(&12));
+        assert_eq!(cuts.get(&(20, 5)), Some(&12));
+        assert_eq!(cuts.get(&(20, 6)), None);
+        assert_eq!(cuts.get(&(20, 10)), None);
+
+        // Cliffs with their tops towards the river do not form a gorge
+        let turned = way(3, &[("natural", "cliff")], &[(0, 6), (40, 6)]);
+        assert!(gorge_cuts(&[river, north, turned], 1.0).is_empty());
+    }
+}
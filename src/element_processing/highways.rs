@@ -2,14 +2,28 @@ use crate::args::Args;
 use crate::block_definitions::*;
 use crate::bresenham::bresenham_line;
 use crate::coordinate_system::cartesian::XZPoint;
+use crate::element_processing::bus_stops;
+use crate::element_processing::tree::Tree;
+use crate::element_processing::tunnels;
+use crate::element_rng::element_rng;
 use crate::floodfill::flood_fill_area;
-use crate::osm_parser::{ProcessedElement, ProcessedWay};
+use crate::osm_parser::{ProcessedElement, ProcessedNode, ProcessedWay};
+use crate::theme::Theme;
+use crate::time_of_day::TimeOfDay;
 use crate::world_editor::WorldEditor;
-use std::collections::HashMap;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
 
 /// Type alias for highway connectivity map
 pub type HighwayConnectivityMap = HashMap<(i32, i32), Vec<i32>>;
 
+/// Distance between lamp posts along lit roads, in blocks
+const ROAD_LAMP_SPACING: i32 = 16;
+/// Blocks on either side of each point of a graded road that its height is averaged over
+const GRADE_SMOOTHING_REACH: usize = 4;
+/// Deepest cut into the terrain and highest fill above it of graded roads, in blocks
+const MAX_GRADE_DEPTH: f64 = 6.0;
+
 /// Generates highways with elevation support based on layer tags and connectivity analysis
 pub fn generate_highways(
     editor: &mut WorldEditor,
@@ -27,11 +41,7 @@ pub fn build_highway_connectivity_map(elements: &[ProcessedElement]) -> HighwayC
     for element in elements {
         if let ProcessedElement::Way(way) = element {
             if way.tags.contains_key("highway") {
-                let layer_value = way
-                    .tags
-                    .get("layer")
-                    .and_then(|layer| layer.parse::<i32>().ok())
-                    .unwrap_or(0);
+                let layer_value = get_highway_layer(element);
 
                 // Treat negative layers as ground level (0) for connectivity
                 let layer_value = if layer_value < 0 { 0 } else { layer_value };
@@ -60,6 +70,103 @@ pub fn build_highway_connectivity_map(elements: &[ProcessedElement]) -> HighwayC
     connectivity_map
 }
 
+/// Joins roundabouts that are mapped as several ways into a single way each, following the
+/// pieces from end node to start node. The joined way takes the place, id and tags of the
+/// first piece, so it can be closed into a ring like a roundabout mapped as one way.
+pub fn join_roundabout_ways(mut elements: Vec<ProcessedElement>) -> Vec<ProcessedElement> {
+    let pieces: Vec<usize> = elements
+        .iter()
+        .enumerate()
+        .filter(|(_, element)| match element {
+            ProcessedElement::Way(way) => {
+                way.nodes.len() >= 2 && way.tags.contains_key("highway") && is_roundabout(&way.tags)
+            }
+            _ => false,
+        })
+        .map(|(index, _)| index)
+        .collect();
+
+    let piece_nodes = |elements: &[ProcessedElement], index: usize| -> Vec<ProcessedNode> {
+        match &elements[index] {
+            ProcessedElement::Way(way) => way.nodes.clone(),
+            _ => Vec::new(),
+        }
+    };
+
+    let mut absorbed: Vec<usize> = Vec::new();
+    for &start in &pieces {
+        if absorbed.contains(&start) {
+            continue;
+        }
+
+        let mut nodes: Vec<ProcessedNode> = piece_nodes(&elements, start);
+        let mut joined_any = false;
+        loop {
+            let first_id = nodes[0].id;
+            let last_id = nodes[nodes.len() - 1].id;
+            let free_pieces: Vec<usize> = pieces
+                .iter()
+                .copied()
+                .filter(|&index| index != start && !absorbed.contains(&index))
+                .collect();
+
+            let mut joined = false;
+            for index in free_pieces {
+                let piece = piece_nodes(&elements, index);
+                if piece[0].id == last_id {
+                    nodes.extend(piece.into_iter().skip(1));
+                } else if piece[piece.len() - 1].id == first_id {
+                    nodes.splice(0..1, piece);
+                } else {
+                    continue;
+                }
+                absorbed.push(index);
+                joined = true;
+                break;
+            }
+
+            if !joined {
+                break;
+            }
+            joined_any = true;
+        }
+
+        if joined_any {
+            if let ProcessedElement::Way(way) = &mut elements[start] {
+                way.nodes = nodes;
+            }
+        }
+    }
+
+    let mut index = 0;
+    elements.retain(|_| {
+        index += 1;
+        !absorbed.contains(&(index - 1))
+    });
+    elements
+}
+
+/// Returns the layer of a highway, placing bridges without a layer tag on layer 1
+pub fn get_highway_layer(element: &ProcessedElement) -> i32 {
+    let tags = element.tags();
+    tags.get("layer")
+        .and_then(|layer| layer.parse::<i32>().ok())
+        .unwrap_or_else(|| {
+            if tags.get("bridge").is_some_and(|bridge| bridge != "no") {
+                1
+            } else {
+                0
+            }
+        })
+}
+
+/// Returns true for highway ways that are drawn above ground level
+pub fn is_elevated_highway(element: &ProcessedElement) -> bool {
+    matches!(element, ProcessedElement::Way(_))
+        && element.tags().contains_key("highway")
+        && get_highway_layer(element) > 0
+}
+
 /// Internal function that generates highways with connectivity context for elevation handling
 fn generate_highways_internal(
     editor: &mut WorldEditor,
@@ -71,13 +178,7 @@ fn generate_highways_internal(
         if highway_type == "street_lamp" {
             // Handle street lamps
             if let ProcessedElement::Node(first_node) = element {
-                let x: i32 = first_node.x;
-                let z: i32 = first_node.z;
-                editor.set_block(COBBLESTONE_WALL, x, 1, z, None, None);
-                for dy in 2..=4 {
-                    editor.set_block(OAK_FENCE, x, dy, z, None, None);
-                }
-                editor.set_block(GLOWSTONE, x, 5, z, None, None);
+                place_lamp_post(editor, first_node.x, first_node.z);
             }
         } else if highway_type == "crossing" {
             // Handle traffic signals for crossings
@@ -97,18 +198,8 @@ fn generate_highways_internal(
                     }
                 }
             }
-        } else if highway_type == "bus_stop" {
-            // Handle bus stops
-            if let ProcessedElement::Node(node) = element {
-                let x = node.x;
-                let z = node.z;
-                for dy in 1..=3 {
-                    editor.set_block(COBBLESTONE_WALL, x, dy, z, None, None);
-                }
-
-                editor.set_block(WHITE_WOOL, x, 4, z, None, None);
-                editor.set_block(WHITE_WOOL, x + 1, 4, z, None, None);
-            }
+        } else if bus_stops::is_bus_stop(element) {
+            bus_stops::generate_bus_stop(editor, element, &[]);
         } else if element
             .tags()
             .get("area")
@@ -146,8 +237,13 @@ fn generate_highways_internal(
             let filled_area: Vec<(i32, i32)> =
                 flood_fill_area(&polygon_coords, args.timeout.as_ref());
 
-            for (x, z) in filled_area {
+            for &(x, z) in &filled_area {
                 editor.set_block(surface_block, x, 0, z, None, None);
+                editor.mark_walkable(x, z);
+            }
+
+            if highway_type == "pedestrian" {
+                smooth_pedestrian_steps(editor, &filled_area.into_iter().collect());
             }
         } else {
             let mut previous_node: Option<(i32, i32)> = None;
@@ -158,11 +254,7 @@ fn generate_highways_internal(
             let scale_factor = args.scale;
 
             // Parse the layer value for elevation calculation
-            let layer_value = element
-                .tags()
-                .get("layer")
-                .and_then(|layer| layer.parse::<i32>().ok())
-                .unwrap_or(0);
+            let layer_value = get_highway_layer(element);
 
             // Treat negative layers as ground level (0)
             let layer_value = if layer_value < 0 { 0 } else { layer_value };
@@ -176,12 +268,29 @@ fn generate_highways_internal(
 
             // Determine block type and range based on highway type
             match highway_type.as_str() {
-                "footway" | "pedestrian" => {
+                "pedestrian" => {
                     block_type = GRAY_CONCRETE;
                     block_range = 1;
                 }
+                "footway" => {
+                    // Sidewalks and crossings stay paved, other footways are gravel unless tagged otherwise
+                    let is_sidewalk = element
+                        .tags()
+                        .get("footway")
+                        .is_some_and(|footway| footway == "sidewalk" || footway == "crossing");
+                    block_type = if is_sidewalk {
+                        GRAY_CONCRETE
+                    } else {
+                        get_path_surface_block(element, GRAVEL)
+                    };
+                    block_range = 1;
+                }
                 "path" => {
-                    block_type = DIRT_PATH;
+                    block_type = get_path_surface_block(element, DIRT_PATH);
+                    block_range = 1;
+                }
+                "cycleway" => {
+                    block_type = RED_TERRACOTTA;
                     block_range = 1;
                 }
                 "motorway" | "primary" | "trunk" => {
@@ -212,11 +321,6 @@ fn generate_highways_internal(
                     block_type = SAND;
                     block_range = 1;
                 }
-                "steps" => {
-                    //TODO: Add correct stairs respecting height, step_count, etc.
-                    block_type = GRAY_CONCRETE;
-                    block_range = 1;
-                }
 
                 _ => {
                     if let Some(lanes) = element.tags().get("lanes") {
@@ -233,14 +337,51 @@ fn generate_highways_internal(
                 }
             }
 
+            // Sun bleached asphalt in arid regions
+            if block_type == BLACK_CONCRETE && editor.theme() == Theme::Arid {
+                block_type = GRAY_CONCRETE;
+            }
+
             let ProcessedElement::Way(way) = element else {
                 return;
             };
 
+            // Roundabouts are drawn as closed rings so the road doesn't leave a gap
+            let is_roundabout = is_roundabout(element.tags());
+            let closed_ring: Option<ProcessedWay> = if is_roundabout {
+                close_roundabout_ring(way)
+            } else {
+                None
+            };
+            let way: &ProcessedWay = closed_ring.as_ref().unwrap_or(way);
+
+            // Shared paths with separate lanes for cyclists and pedestrians
+            let is_segregated = matches!(highway_type.as_str(), "cycleway" | "footway" | "path")
+                && element
+                    .tags()
+                    .get("segregated")
+                    .is_some_and(|segregated| segregated == "yes");
+            if is_segregated {
+                block_range = 2;
+            }
+
             if scale_factor < 1.0 {
                 block_range = ((block_range as f64) * scale_factor).floor() as i32;
             }
 
+            // Steps follow the terrain with actual stairs
+            if highway_type == "steps" {
+                generate_steps(editor, way);
+                return;
+            }
+
+            // Tunnels are carved through the terrain instead of being drawn on the surface
+            if tunnels::is_tunnel(editor, way) {
+                let points = tunnels::way_centerline(way);
+                tunnels::generate_tunnel(editor, &points, block_range, block_type);
+                return;
+            }
+
             // Calculate elevation based on layer
             const LAYER_HEIGHT_STEP: i32 = 6; // Each layer is 6 blocks higher/lower
             let base_elevation = layer_value * LAYER_HEIGHT_STEP;
@@ -271,6 +412,16 @@ fn generate_highways_internal(
 
             let slope_length = (total_way_length as f32 * 0.35).clamp(15.0, 50.0) as usize; // 35% of way length, max 50 blocks, min 15 blocks
 
+            // Roads are graded into hilly terrain with cuts and fills, instead of following
+            // every bump of it
+            let graded: HashMap<(i32, i32), i32> = match max_road_grade(highway_type) {
+                Some(max_grade) if effective_elevation == 0 => {
+                    grade_road(editor, way, block_range + add_outline as i32, max_grade)
+                }
+                _ => HashMap::new(),
+            };
+            let grade_offset = |x: i32, z: i32| graded.get(&(x, z)).copied().unwrap_or(0);
+
             // Iterate over nodes to create the highway
             let mut segment_index = 0;
             let total_segments = way.nodes.len() - 1;
@@ -311,6 +462,7 @@ fn generate_highways_internal(
                             for dz in -block_range..=block_range {
                                 let set_x: i32 = x + dx;
                                 let set_z: i32 = z + dz;
+                                let surface_y = current_y + grade_offset(set_x, set_z);
 
                                 // Zebra crossing logic
                                 if highway_type == "footway"
@@ -323,7 +475,7 @@ fn generate_highways_internal(
                                             editor.set_block(
                                                 WHITE_CONCRETE,
                                                 set_x,
-                                                current_y,
+                                                surface_y,
                                                 set_z,
                                                 Some(&[BLACK_CONCRETE]),
                                                 None,
@@ -332,7 +484,7 @@ fn generate_highways_internal(
                                             editor.set_block(
                                                 BLACK_CONCRETE,
                                                 set_x,
-                                                current_y,
+                                                surface_y,
                                                 set_z,
                                                 None,
                                                 None,
@@ -342,7 +494,7 @@ fn generate_highways_internal(
                                         editor.set_block(
                                             WHITE_CONCRETE,
                                             set_x,
-                                            current_y,
+                                            surface_y,
                                             set_z,
                                             Some(&[BLACK_CONCRETE]),
                                             None,
@@ -351,23 +503,45 @@ fn generate_highways_internal(
                                         editor.set_block(
                                             BLACK_CONCRETE,
                                             set_x,
-                                            current_y,
+                                            surface_y,
                                             set_z,
                                             None,
                                             None,
                                         );
                                     }
+                                } else if is_segregated {
+                                    // Cycle lane on the right hand side, footway on the left
+                                    let side = (x2 - x1) * dz - (z2 - z1) * dx;
+                                    let lane_block = if side >= 0 {
+                                        RED_TERRACOTTA
+                                    } else {
+                                        GRAY_CONCRETE
+                                    };
+                                    editor.set_block(
+                                        lane_block,
+                                        set_x,
+                                        surface_y,
+                                        set_z,
+                                        None,
+                                        Some(&[BLACK_CONCRETE, WHITE_CONCRETE]),
+                                    );
                                 } else {
                                     editor.set_block(
                                         block_type,
                                         set_x,
-                                        current_y,
+                                        surface_y,
                                         set_z,
                                         None,
                                         Some(&[BLACK_CONCRETE, WHITE_CONCRETE]),
                                     );
                                 }
 
+                                if current_y == 0 {
+                                    editor.mark_walkable(set_x, set_z);
+                                } else if effective_elevation != 0 {
+                                    editor.mark_overpass(set_x, set_z);
+                                }
+
                                 // Add stone brick foundation underneath elevated highways for thickness
                                 if effective_elevation > 0 && current_y > 0 {
                                     // Add 1 layer of stone bricks underneath the highway surface
@@ -405,7 +579,7 @@ fn generate_highways_internal(
                                 editor.set_block(
                                     LIGHT_GRAY_CONCRETE,
                                     outline_x,
-                                    current_y,
+                                    current_y + grade_offset(outline_x, outline_z),
                                     outline_z,
                                     None,
                                     None,
@@ -418,7 +592,7 @@ fn generate_highways_internal(
                                 editor.set_block(
                                     LIGHT_GRAY_CONCRETE,
                                     outline_x,
-                                    current_y,
+                                    current_y + grade_offset(outline_x, outline_z),
                                     outline_z,
                                     None,
                                     None,
@@ -434,7 +608,7 @@ fn generate_highways_internal(
                                 editor.set_block(
                                     WHITE_CONCRETE,
                                     stripe_x,
-                                    current_y,
+                                    current_y + grade_offset(stripe_x, stripe_z),
                                     stripe_z,
                                     Some(&[BLACK_CONCRETE]),
                                     None,
@@ -453,10 +627,401 @@ fn generate_highways_internal(
                 }
                 previous_node = Some((node.x, node.z));
             }
+
+            // Landscape the center island of ground level roundabouts
+            if is_roundabout && effective_elevation == 0 && is_closed_ring(&way.nodes) {
+                generate_roundabout_island(editor, way, args);
+            }
+
+            if effective_elevation == 0
+                && matches!(highway_type.as_str(), "pedestrian" | "footway" | "path")
+            {
+                let columns: HashSet<(i32, i32)> = tunnels::way_centerline(way)
+                    .into_iter()
+                    .flat_map(|(x, z)| {
+                        (-block_range..=block_range).flat_map(move |dx| {
+                            (-block_range..=block_range).map(move |dz| (x + dx, z + dz))
+                        })
+                    })
+                    .collect();
+                smooth_pedestrian_steps(editor, &columns);
+            }
+
+            // Lit roads get lamp posts along their side at night
+            if effective_elevation == 0
+                && editor.time_of_day() == TimeOfDay::Night
+                && way.tags.get("lit").is_some_and(|lit| lit == "yes")
+            {
+                generate_road_lamps(editor, way, block_range);
+            }
         }
     }
 }
 
+pub fn place_lamp_post(editor: &mut WorldEditor, x: i32, z: i32) {
+    editor.set_block(COBBLESTONE_WALL, x, 1, z, None, None);
+    for dy in 2..=4 {
+        editor.set_block(OAK_FENCE, x, dy, z, None, None);
+    }
+    editor.set_block(GLOWSTONE, x, 5, z, None, None);
+}
+
+/// Places lamp posts at regular intervals next to a road, alternating between its sides
+fn generate_road_lamps(editor: &mut WorldEditor, way: &ProcessedWay, block_range: i32) {
+    let mut distance: i32 = 0;
+    for pair in way.nodes.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+
+        // Posts stand beside the road, across its main direction
+        let (side_x, side_z) = if (b.x - a.x).abs() >= (b.z - a.z).abs() {
+            (0, block_range + 2)
+        } else {
+            (block_range + 2, 0)
+        };
+
+        for (x, _, z) in bresenham_line(a.x, 0, a.z, b.x, 0, b.z) {
+            if distance % ROAD_LAMP_SPACING == 0 {
+                let side = if (distance / ROAD_LAMP_SPACING) % 2 == 0 {
+                    1
+                } else {
+                    -1
+                };
+                place_lamp_post(editor, x + side * side_x, z + side * side_z);
+            }
+            distance += 1;
+        }
+    }
+}
+
+/// Returns the steepest rise per block along roads of the given type that are graded into the
+/// terrain, None for ways that follow it as they are
+fn max_road_grade(highway_type: &str) -> Option<f64> {
+    match highway_type {
+        "motorway" | "trunk" | "primary" | "motorway_link" | "trunk_link" | "primary_link" => {
+            Some(0.2)
+        }
+        "secondary" | "tertiary" | "secondary_link" | "tertiary_link" => Some(0.3),
+        "residential" | "unclassified" | "living_street" | "service" => Some(0.5),
+        _ => None,
+    }
+}
+
+/// Returns a road profile along the given terrain heights that averages out bumps and rises
+/// no steeper than the given grade. It starts on the terrain to meet other roads and ends as
+/// close to it as the grade allows.
+fn graded_profile(heights: &[f64], max_grade: f64) -> Vec<f64> {
+    let mut profile: Vec<f64> = (0..heights.len())
+        .map(|i| {
+            let window = &heights[i.saturating_sub(GRADE_SMOOTHING_REACH)
+                ..(i + GRADE_SMOOTHING_REACH + 1).min(heights.len())];
+            window.iter().sum::<f64>() / window.len() as f64
+        })
+        .collect();
+    if let (Some(first), Some(height)) = (profile.first_mut(), heights.first()) {
+        *first = *height;
+    }
+    if let (Some(last), Some(height)) = (profile.last_mut(), heights.last()) {
+        *last = *height;
+    }
+
+    // Limit the grade in both directions
+    for i in 1..profile.len() {
+        let previous = profile[i - 1];
+        profile[i] = profile[i].clamp(previous - max_grade, previous + max_grade);
+    }
+    for i in (0..profile.len().saturating_sub(1)).rev() {
+        let next = profile[i + 1];
+        profile[i] = profile[i].clamp(next - max_grade, next + max_grade);
+    }
+
+    profile
+        .iter()
+        .zip(heights)
+        .map(|(&graded, &height)| graded.clamp(height - MAX_GRADE_DEPTH, height + MAX_GRADE_DEPTH))
+        .collect()
+}
+
+/// Grades a road into the terrain, cutting into hillsides and filling depressions beneath it.
+/// Returns the height of the graded road above the terrain for each column it covers.
+fn grade_road(
+    editor: &mut WorldEditor,
+    way: &ProcessedWay,
+    reach: i32,
+    max_grade: f64,
+) -> HashMap<(i32, i32), i32> {
+    let mut offsets: HashMap<(i32, i32), i32> = HashMap::new();
+    if !editor
+        .get_ground()
+        .is_some_and(|ground| ground.elevation_enabled)
+    {
+        return offsets;
+    }
+
+    let centerline = tunnels::way_centerline(way);
+    let heights: Vec<f64> = centerline
+        .iter()
+        .map(|&(x, z)| editor.get_absolute_y(x, 0, z) as f64)
+        .collect();
+    let profile = graded_profile(&heights, max_grade);
+
+    for (&(x, z), &graded) in centerline.iter().zip(&profile) {
+        let road_y = graded.round() as i32;
+        for dx in -reach..=reach {
+            for dz in -reach..=reach {
+                let (cx, cz) = (x + dx, z + dz);
+                if offsets.contains_key(&(cx, cz)) {
+                    continue;
+                }
+                let ground_y = editor.get_absolute_y(cx, 0, cz);
+                offsets.insert((cx, cz), road_y - ground_y);
+
+                // Cut down to the road through the terrain above it
+                for y in road_y + 1..=ground_y {
+                    editor.set_block_absolute(CAVE_AIR, cx, y, cz, None, None);
+                }
+                // Fill up the depression below it
+                for y in ground_y + 1..road_y {
+                    editor.set_block_absolute(DIRT, cx, y, cz, None, None);
+                }
+            }
+        }
+    }
+    offsets
+}
+
+/// Places slabs in front of single block steps of the terrain along pedestrian ways and areas,
+/// so they can be walked without jumping
+fn smooth_pedestrian_steps(editor: &mut WorldEditor, columns: &HashSet<(i32, i32)>) {
+    if !editor
+        .get_ground()
+        .is_some_and(|ground| ground.elevation_enabled)
+    {
+        return;
+    }
+
+    for &(x, z) in columns {
+        let height = editor.get_absolute_y(x, 0, z);
+        let has_step_up = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+            .into_iter()
+            .any(|(dx, dz)| {
+                columns.contains(&(x + dx, z + dz))
+                    && editor.get_absolute_y(x + dx, 0, z + dz) == height + 1
+            });
+
+        if has_step_up {
+            editor.set_block(STONE_BRICK_SLAB, x, 1, z, None, None);
+        }
+    }
+}
+
+/// Checks whether the first and last node of a way coincide
+fn is_closed_ring(nodes: &[ProcessedNode]) -> bool {
+    match (nodes.first(), nodes.last()) {
+        (Some(first), Some(last)) => {
+            nodes.len() > 3 && (first.id == last.id || (first.x == last.x && first.z == last.z))
+        }
+        _ => false,
+    }
+}
+
+/// Checks whether the tags mark a roundabout or another circular junction
+fn is_roundabout(tags: &HashMap<String, String>) -> bool {
+    tags.get("junction")
+        .is_some_and(|junction| junction == "roundabout" || junction == "circular")
+}
+
+/// Closes a nearly closed roundabout way into a ring. Returns None if the way is already
+/// closed or if it is only a partial arc, e.g. of a roundabout cut by the edge of the bbox.
+fn close_roundabout_ring(way: &ProcessedWay) -> Option<ProcessedWay> {
+    if way.nodes.len() < 4 || is_closed_ring(&way.nodes) {
+        return None;
+    }
+
+    let first = &way.nodes[0];
+    let last = &way.nodes[way.nodes.len() - 1];
+    let gap = (((last.x - first.x).pow(2) + (last.z - first.z).pow(2)) as f32).sqrt();
+
+    // Only close the ring if the missing piece is short compared to the whole loop
+    if gap > calculate_way_length(way) as f32 * 0.25 {
+        return None;
+    }
+
+    let mut closed_way = way.clone();
+    closed_way.nodes.push(first.clone());
+    Some(closed_way)
+}
+
+/// Generates the raised, landscaped center island of a roundabout
+fn generate_roundabout_island(editor: &mut WorldEditor, way: &ProcessedWay, args: &Args) {
+    let polygon_coords: Vec<(i32, i32)> = way.nodes.iter().map(|n| (n.x, n.z)).collect();
+    let filled_area: Vec<(i32, i32)> = flood_fill_area(&polygon_coords, args.timeout.as_ref());
+
+    let road_blocks: [Block; 4] = [
+        BLACK_CONCRETE,
+        WHITE_CONCRETE,
+        GRAY_CONCRETE,
+        LIGHT_GRAY_CONCRETE,
+    ];
+
+    // Everything inside the ring that isn't road surface belongs to the island
+    let island: Vec<(i32, i32)> = filled_area
+        .into_iter()
+        .filter(|&(x, z)| !editor.check_for_block(x, 0, z, Some(&road_blocks)))
+        .collect();
+
+    if island.is_empty() {
+        return;
+    }
+
+    for &(x, z) in &island {
+        let touches_road = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+            .iter()
+            .any(|(dx, dz)| editor.check_for_block(x + dx, 0, z + dz, Some(&road_blocks)));
+
+        editor.set_block(DIRT, x, 0, z, None, None);
+        if touches_road {
+            // Curb around the island
+            editor.set_block(STONE_BRICKS, x, 1, z, None, None);
+        } else {
+            editor.set_block(GRASS_BLOCK, x, 1, z, None, None);
+        }
+    }
+
+    // Decorate the center of the island with a tree or a small fountain
+    let count = island.len() as i32;
+    let center_x = island.iter().map(|&(x, _)| x).sum::<i32>() / count;
+    let center_z = island.iter().map(|&(_, z)| z).sum::<i32>() / count;
+
+    let radius = ((count as f32 / std::f32::consts::PI).sqrt()) as i32;
+    if radius < 3 || !island.contains(&(center_x, center_z)) {
+        return;
+    }
+
+    let mut rng = element_rng();
+    if radius >= 5 && rng.gen_bool(0.5) {
+        for dx in -2..=2 {
+            for dz in -2..=2 {
+                let (x, z) = (center_x + dx, center_z + dz);
+                if dx.abs() == 2 || dz.abs() == 2 {
+                    editor.set_block(STONE_BRICK_SLAB, x, 2, z, None, None);
+                } else {
+                    editor.set_block(WATER, x, 1, z, Some(&[GRASS_BLOCK]), None);
+                }
+            }
+        }
+        editor.set_block(STONE_BRICKS, center_x, 1, center_z, Some(&[WATER]), None);
+        editor.set_block(STONE_BRICKS, center_x, 2, center_z, None, None);
+        editor.set_block(WATER, center_x, 3, center_z, None, None);
+    } else {
+        Tree::create(editor, (center_x, 2, center_z));
+    }
+}
+
+/// Generates steps as stair blocks climbing evenly between the ground heights of both ends
+fn generate_steps(editor: &mut WorldEditor, way: &ProcessedWay) {
+    let points = tunnels::way_centerline(way);
+    if points.len() < 2 {
+        return;
+    }
+
+    let has_handrail = way
+        .tags
+        .get("handrail")
+        .is_some_and(|handrail| handrail == "yes");
+
+    let (start_x, start_z) = points[0];
+    let (end_x, end_z) = points[points.len() - 1];
+    let start_y = editor.get_absolute_y(start_x, 0, start_z);
+    let end_y = editor.get_absolute_y(end_x, 0, end_z);
+    let last_index = (points.len() - 1) as f64;
+
+    let heights: Vec<i32> = (0..points.len())
+        .map(|i| start_y + ((end_y - start_y) as f64 * (i as f64 / last_index)).round() as i32)
+        .collect();
+
+    for (i, &(x, z)) in points.iter().enumerate() {
+        let height = heights[i];
+
+        // Direction of travel, used to lay the steps across the way
+        let (next_x, next_z) = points[(i + 1).min(points.len() - 1)];
+        let (prev_x, prev_z) = points[i.saturating_sub(1)];
+        let runs_along_x = (next_x - prev_x).abs() >= (next_z - prev_z).abs();
+
+        // A stair is needed wherever this point is higher than one of its neighbours
+        let uphill_from = if i > 0 && heights[i - 1] < height {
+            Some(points[i - 1])
+        } else if i + 1 < points.len() && heights[i + 1] < height {
+            Some(points[i + 1])
+        } else {
+            None
+        };
+        let stair_facing = uphill_from.map(|(from_x, from_z)| {
+            let (dx, dz) = (x - from_x, z - from_z);
+            if dx.abs() >= dz.abs() {
+                if dx > 0 {
+                    StairFacing::East
+                } else {
+                    StairFacing::West
+                }
+            } else if dz > 0 {
+                StairFacing::South
+            } else {
+                StairFacing::North
+            }
+        });
+
+        for offset in -2..=2 {
+            let (set_x, set_z) = if runs_along_x {
+                (x, z + offset)
+            } else {
+                (x + offset, z)
+            };
+            let ground_y = editor.get_absolute_y(set_x, 0, set_z);
+
+            // Support the steps where they rise above the terrain
+            for y in ground_y..height {
+                editor.set_block_absolute(STONE_BRICKS, set_x, y, set_z, None, None);
+            }
+
+            if offset.abs() == 2 {
+                if has_handrail {
+                    editor.set_block_absolute(STONE_BRICKS, set_x, height, set_z, None, None);
+                    editor.set_block_absolute(OAK_FENCE, set_x, height + 1, set_z, None, None);
+                }
+                continue;
+            }
+
+            // Cut through the terrain where the steps run below the surface
+            for y in (height + 1)..=ground_y {
+                editor.set_block_absolute(CAVE_AIR, set_x, y, set_z, None, None);
+            }
+
+            match stair_facing {
+                Some(facing) => editor.set_block_with_properties_absolute(
+                    create_stair_with_properties(STONE_BRICK_STAIRS, facing, StairShape::Straight),
+                    set_x,
+                    height,
+                    set_z,
+                    None,
+                    None,
+                ),
+                None => editor.set_block_absolute(STONE_BRICKS, set_x, height, set_z, None, None),
+            }
+        }
+    }
+}
+
+/// Picks the surface block of a footway or path from its surface tag
+fn get_path_surface_block(element: &ProcessedElement, default: Block) -> Block {
+    match element.tags().get("surface").map(|s| s.as_str()) {
+        Some("asphalt" | "paved" | "concrete" | "paving_stones" | "sett") => GRAY_CONCRETE,
+        Some("gravel" | "fine_gravel" | "compacted" | "pebblestone") => GRAVEL,
+        Some("dirt" | "ground" | "earth" | "grass" | "unpaved") => DIRT_PATH,
+        Some("wood") => OAK_PLANKS,
+        _ => default,
+    }
+}
+
 /// Helper function to determine if a slope should be added at a specific node
 fn should_add_slope_at_node(
     node: &crate::osm_parser::ProcessedNode,
@@ -566,8 +1131,23 @@ fn add_highway_support_pillar(
     dz: i32,
     _block_range: i32, // Keep for future use
 ) {
-    // Only add pillars at specific intervals and positions
-    if dx == 0 && dz == 0 && (x + z) % 8 == 0 {
+    // Only add pillars at specific intervals and positions, never on top of roads passing underneath
+    if dx == 0
+        && dz == 0
+        && (x + z) % 8 == 0
+        && !editor.check_for_block(
+            x,
+            0,
+            z,
+            Some(&[
+                BLACK_CONCRETE,
+                WHITE_CONCRETE,
+                GRAY_CONCRETE,
+                LIGHT_GRAY_CONCRETE,
+                DIRT_PATH,
+            ]),
+        )
+    {
         // Add pillar from ground to highway level
         for y in 1..highway_y {
             editor.set_block(STONE_BRICKS, x, y, z, None, None);
@@ -638,3 +1218,78 @@ pub fn generate_aeroway(editor: &mut WorldEditor, way: &ProcessedWay, args: &Arg
         previous_node = Some((node.x, node.z));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundabout_piece(id: u64, nodes: &[(u64, i32, i32)]) -> ProcessedElement {
+        ProcessedElement::Way(ProcessedWay {
+            id,
+            nodes: nodes
+                .iter()
+                .map(|&(id, x, z)| ProcessedNode {
+                    id,
+                    tags: HashMap::new(),
+                    x,
+                    z,
+                })
+                .collect(),
+            tags: HashMap::from([
+                ("highway".to_string(), "primary".to_string()),
+                ("junction".to_string(), "roundabout".to_string()),
+            ]),
+        })
+    }
+
+    #[test]
+    fn test_roundabout_of_two_ways_is_joined_into_a_ring() {
+        let ring = [
+            (1, 10, 0),
+            (2, 7, 7),
+            (3, 0, 10),
+            (4, -7, 7),
+            (5, -10, 0),
+            (6, -7, -7),
+            (7, 0, -10),
+            (8, 7, -7),
+        ];
+        let north_arc = roundabout_piece(100, &[ring[4], ring[5], ring[6], ring[7], ring[0]]);
+        let south_arc = roundabout_piece(101, &[ring[0], ring[1], ring[2], ring[3], ring[4]]);
+        let mut road = roundabout_piece(102, &[(9, 10, 0), (10, 30, 0)]);
+        if let ProcessedElement::Way(way) = &mut road {
+            way.tags.remove("junction");
+        }
+
+        let elements = join_roundabout_ways(vec![south_arc, road, north_arc]);
+
+        assert_eq!(elements.len(), 2);
+        let ProcessedElement::Way(roundabout) = &elements[0] else {
+            panic!("expected the joined roundabout way");
+        };
+        assert_eq!(roundabout.id, 101);
+        assert_eq!(roundabout.nodes.len(), 9);
+        assert!(is_closed_ring(&roundabout.nodes));
+        assert!(close_roundabout_ring(roundabout).is_none());
+        assert_eq!(elements[1].id(), 102);
+    }
+
+    #[test]
+    fn test_graded_profile_levels_bumps_and_limits_grade() {
+        // A bump of 3 blocks in the middle of flat terrain, then a hill
+        let heights = [
+            0.0, 0.0, 0.0, 0.0, 3.0, 0.0, 0.0, 0.0, 0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 6.0, 6.0,
+        ];
+        let profile = graded_profile(&heights, 0.5);
+
+        assert_eq!(profile.first(), Some(&0.0));
+        assert!(profile[4] < 1.0);
+        assert!(profile.last().is_some_and(|&end| end > 5.0 && end <= 6.0));
+        for pair in profile.windows(2) {
+            assert!((pair[1] - pair[0]).abs() <= 0.5 + 1e-9);
+        }
+        for (graded, height) in profile.iter().zip(heights) {
+            assert!((graded - height).abs() <= MAX_GRADE_DEPTH);
+        }
+    }
+}
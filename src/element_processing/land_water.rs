@@ -0,0 +1,121 @@
+use crate::block_definitions::*;
+use crate::osm_parser::ProcessedElement;
+use crate::world_editor::WorldEditor;
+
+/// Small plants that would float on the water surface if their ground is replaced by water
+const SURFACE_PLANTS: [Block; 11] = [
+    GRASS,
+    TALL_GRASS_BOTTOM,
+    TALL_GRASS_TOP,
+    DEAD_BUSH,
+    RED_FLOWER,
+    YELLOW_FLOWER,
+    BLUE_FLOWER,
+    WHITE_FLOWER,
+    WHEAT,
+    CARROTS,
+    POTATOES,
+];
+
+/// Decides for every column where land and water features overlap which of them wins.
+/// Features are generated in priority order, so the block already in place wins unless the
+/// competing feature lies on a higher `layer`. Plants left above water are removed. The changes
+/// belong to the competing feature.
+pub fn reconcile_land_and_water(editor: &mut WorldEditor) {
+    for conflict in editor.take_surface_conflicts() {
+        let (x, z) = (conflict.x, conflict.z);
+        let placed_layer = editor.surface_layer_at(x, z);
+        editor.set_block_owner(conflict.owner);
+
+        if conflict.layer > placed_layer {
+            if conflict.block == WATER {
+                editor.set_block(WATER, x, 0, z, None, Some(&[WATER]));
+            } else {
+                editor.set_block(conflict.block, x, 0, z, Some(&[WATER]), None);
+            }
+        }
+
+        // Land that lost against water must not leave its vegetation floating on the surface
+        if editor.check_for_block(x, 0, z, Some(&[WATER])) {
+            for y in 1..=2 {
+                editor.set_block(AIR, x, y, z, Some(&SURFACE_PLANTS), None);
+            }
+        }
+    }
+    editor.set_block_owner(None);
+}
+
+/// Returns the `layer` tag of an element, defaulting to ground level
+pub fn surface_layer(element: &ProcessedElement) -> i32 {
+    element
+        .tags()
+        .get("layer")
+        .and_then(|layer| layer.parse::<i32>().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinate_system::cartesian::XZBBox;
+    use crate::test_utilities::{get_llbbox_arnis, minecraft_tmpdir};
+    use crate::world_editor::BlockOwner;
+
+    /// Places a block at ground level as a feature on the given layer would
+    fn place(editor: &mut WorldEditor, block: Block, layer: i32) {
+        editor.set_surface_layer(Some(layer));
+        editor.set_block(block, 5, 0, 5, None, None);
+    }
+
+    /// Generates land with a flower and water on the given layers in this order, and returns
+    /// whether the column is water and whether the flower is left once the conflict is settled
+    fn reconcile(first: (Block, i32), second: (Block, i32)) -> (bool, bool) {
+        let world = minecraft_tmpdir();
+        let xzbbox = XZBBox::rect_from_xz_lengths(10.0, 10.0).unwrap();
+        let mut editor = WorldEditor::new(world.path().to_path_buf(), &xzbbox, get_llbbox_arnis());
+
+        place(&mut editor, first.0, first.1);
+        if first.0 != WATER {
+            editor.set_block(RED_FLOWER, 5, 1, 5, None, None);
+        }
+        place(&mut editor, second.0, second.1);
+        reconcile_land_and_water(&mut editor);
+
+        (
+            editor.check_for_block(5, 0, 5, Some(&[WATER])),
+            editor.check_for_block(5, 1, 5, Some(&[RED_FLOWER])),
+        )
+    }
+
+    #[test]
+    fn test_first_feature_wins_on_the_same_layer() {
+        assert_eq!(reconcile((GRASS_BLOCK, 0), (WATER, 0)), (false, true));
+        assert_eq!(reconcile((WATER, 0), (GRASS_BLOCK, 0)), (true, false));
+    }
+
+    #[test]
+    fn test_winning_blocks_belong_to_their_feature() {
+        let world = minecraft_tmpdir();
+        let xzbbox = XZBBox::rect_from_xz_lengths(10.0, 10.0).unwrap();
+        let mut editor = WorldEditor::new(world.path().to_path_buf(), &xzbbox, get_llbbox_arnis());
+        editor.enable_ownership_map();
+
+        editor.set_block_owner(Some(BlockOwner { kind: "way", id: 1 }));
+        place(&mut editor, GRASS_BLOCK, 0);
+        editor.set_block_owner(Some(BlockOwner { kind: "way", id: 2 }));
+        place(&mut editor, WATER, 1);
+        editor.set_block_owner(None);
+        reconcile_land_and_water(&mut editor);
+
+        let owner = editor.block_owner_at(5, 0, 5);
+        assert_eq!(owner, Some(BlockOwner { kind: "way", id: 2 }));
+    }
+
+    #[test]
+    fn test_higher_layer_wins() {
+        // The flowers of the land that lost are removed with it
+        assert_eq!(reconcile((GRASS_BLOCK, 0), (WATER, 1)), (true, false));
+        assert_eq!(reconcile((WATER, -1), (GRASS_BLOCK, 0)), (false, false));
+        assert_eq!(reconcile((WATER, 1), (GRASS_BLOCK, 0)), (true, false));
+    }
+}
@@ -1,19 +1,138 @@
 use crate::args::Args;
 use crate::block_definitions::*;
+use crate::bresenham::bresenham_line;
 use crate::element_processing::tree::Tree;
-use crate::floodfill::flood_fill_area;
-use crate::osm_parser::{ProcessedMemberRole, ProcessedRelation, ProcessedWay};
-use crate::world_editor::WorldEditor;
+use crate::element_rng::{element_rng, ElementRng};
+use crate::floodfill::{distances_within, flood_fill_area};
+use crate::ground_cover::value_noise;
+use crate::osm_parser::{ProcessedRelation, ProcessedWay};
+use crate::world_editor::{Biome, WorldEditor};
 use rand::Rng;
+use std::collections::{HashMap, HashSet};
+
+/// Size of the patches of flowers on meadows, in blocks
+const FLOWER_PATCH_SIZE: f64 = 9.0;
+/// Value of the noise above which meadows have patches of flowers
+const FLOWER_PATCH_THRESHOLD: f64 = 0.7;
+/// Height of a single step of terraced farmland
+const TERRACE_STEP: i32 = 2;
+/// Average rise of the ground per block above which farmland is terraced
+const TERRACE_MIN_SLOPE: f64 = 0.12;
+/// Distance over which the terrain is averaged before it is cut into terraces, so that the
+/// noise of the elevation data does not break up their edges
+const TERRACE_SMOOTHING_RADIUS: i32 = 2;
+/// Distance between the water channels of a field, every crop is within hydration range
+const WATER_CHANNEL_SPACING: i32 = 9;
+/// Distance between the rows of fruit trees of an orchard at scale 1
+const ORCHARD_ROW_SPACING: f64 = 6.0;
+/// Distance between the fruit trees within a row at scale 1
+const ORCHARD_TREE_SPACING: f64 = 5.0;
+/// Distance between the rows of a vineyard at scale 1
+const VINEYARD_ROW_SPACING: f64 = 3.0;
+/// Number of blocks of vines between two posts of a vineyard row
+const VINEYARD_POST_SPACING: usize = 4;
+
+/// Width of the benches of a quarry at scale 1, in blocks
+const QUARRY_BENCH_WIDTH: f64 = 4.0;
+/// Height of the walls between the benches of a quarry at scale 1, in blocks
+const QUARRY_BENCH_HEIGHT: f64 = 3.0;
+/// Number of benches a quarry steps down
+const QUARRY_LEVELS: i32 = 4;
+
+/// Width of the main path of a cemetery at scale 1, in blocks
+const CEMETERY_PATH_WIDTH: f64 = 3.0;
+/// Distance between the paths crossing a cemetery at scale 1
+const CEMETERY_CROSS_PATH_SPACING: f64 = 16.0;
+/// Distance between the rows of graves at scale 1
+const GRAVE_ROW_SPACING: f64 = 4.0;
+/// Distance between the graves within a row at scale 1
+const GRAVE_SPACING: f64 = 2.0;
+
+/// Length of an allotment plot at scale 1, in blocks
+const ALLOTMENT_PLOT_LENGTH: f64 = 10.0;
+/// Width of an allotment plot at scale 1, including the path in front of it
+const ALLOTMENT_PLOT_WIDTH: f64 = 8.0;
+/// Width of the paths between the plots of allotments at scale 1
+const ALLOTMENT_PATH_WIDTH: f64 = 2.0;
+
+/// Height of the fence around military areas, in blocks
+const MILITARY_FENCE_HEIGHT: i32 = 3;
+/// Distance between the posts of the fence around military areas, in blocks
+const MILITARY_FENCE_POST_SPACING: usize = 4;
+/// Distance between the warning signs on the fence around military areas at scale 1
+const MILITARY_SIGN_SPACING: f64 = 32.0;
+/// Smallest distance between two watchtowers of a military area at scale 1
+const MILITARY_TOWER_SPACING: f64 = 24.0;
+
+/// Use of a plot of allotments
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum AllotmentPlot {
+    Vegetables,
+    Flowers,
+    /// Lawn with a fruit tree
+    Lawn,
+}
+
+/// Crop grown on farmland
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Crop {
+    Wheat,
+    Carrots,
+    Potatoes,
+    Beetroots,
+    /// Maize, grown as rows of sugar cane between water channels
+    SugarCane,
+    /// Rapeseed, flowering yellow
+    Rapeseed,
+}
+
+impl Crop {
+    /// Returns the crop named by the crop or produce tag of a field
+    fn from_tags(tags: &HashMap<String, String>) -> Option<Crop> {
+        let crop = tags.get("crop").or_else(|| tags.get("produce"))?;
+        // Fields can list several crops, the first one is planted
+        let first = crop
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        match first.as_str() {
+            "wheat" | "barley" | "rye" | "oats" | "oat" | "cereal" | "cereals" | "grain"
+            | "spelt" | "triticale" => Some(Crop::Wheat),
+            "carrot" | "carrots" | "vegetables" | "vegetable" => Some(Crop::Carrots),
+            "potato" | "potatoes" => Some(Crop::Potatoes),
+            "sugar_beet" | "sugar_beets" | "beet" | "beetroot" => Some(Crop::Beetroots),
+            "maize" | "corn" | "sugarcane" | "sugar_cane" => Some(Crop::SugarCane),
+            "rape" | "rapeseed" | "canola" | "oilseed_rape" => Some(Crop::Rapeseed),
+            _ => None,
+        }
+    }
+
+    /// Returns the ground the crop grows on
+    fn ground(self) -> Block {
+        match self {
+            Crop::SugarCane => DIRT,
+            Crop::Rapeseed => GRASS_BLOCK,
+            _ => FARMLAND,
+        }
+    }
+}
 
 pub fn generate_landuse(editor: &mut WorldEditor, element: &ProcessedWay, args: &Args) {
     // Determine block type based on landuse tag
     let binding: String = "".to_string();
     let landuse_tag: &String = element.tags.get("landuse").unwrap_or(&binding);
 
+    let mut rng: ElementRng = element_rng();
+
+    // Untagged fields are planted with one of the common crops
+    let crop = Crop::from_tags(&element.tags)
+        .unwrap_or_else(|| [Crop::Wheat, Crop::Carrots, Crop::Potatoes][rng.gen_range(0..3)]);
+
     let block_type = match landuse_tag.as_str() {
         "greenfield" | "meadow" | "grass" | "orchard" | "forest" => GRASS_BLOCK,
-        "farmland" => FARMLAND,
+        "farmland" => crop.ground(),
         "cemetery" => PODZOL,
         "construction" => COARSE_DIRT,
         "traffic_island" => STONE_BLOCK_SLAB,
@@ -48,53 +167,51 @@ pub fn generate_landuse(editor: &mut WorldEditor, element: &ProcessedWay, args:
     let polygon_coords: Vec<(i32, i32)> = element.nodes.iter().map(|n| (n.x, n.z)).collect();
     let floor_area: Vec<(i32, i32)> = flood_fill_area(&polygon_coords, args.timeout.as_ref());
 
-    let mut rng: rand::prelude::ThreadRng = rand::thread_rng();
+    // Crop rows run along the longer side of the field
+    let span = |coord: fn(&(i32, i32)) -> i32| {
+        let min = floor_area.iter().map(coord).min().unwrap_or_default();
+        let max = floor_area.iter().map(coord).max().unwrap_or_default();
+        max - min
+    };
+    let rows_along_x = span(|p| p.0) >= span(|p| p.1);
+
+    // Fields on steep slopes are built as flat terraces instead of following the terrain
+    let terrace_offsets = if landuse_tag == "farmland" || landuse_tag == "vineyard" {
+        generate_terraces(editor, &floor_area)
+    } else {
+        HashMap::new()
+    };
+
+    // Orchards and vineyards are planted in rows and quarries dug once their ground is in place
+    let shaped_area: HashSet<(i32, i32)> = if matches!(
+        landuse_tag.as_str(),
+        "orchard" | "vineyard" | "quarry" | "military" | "allotments"
+    ) {
+        floor_area.iter().copied().collect()
+    } else {
+        HashSet::new()
+    };
 
     for (x, z) in floor_area {
+        // Height of the surface relative to the natural ground
+        let dy = terrace_offsets.get(&(x, z)).copied().unwrap_or(0);
+
         if landuse_tag == "traffic_island" {
             editor.set_block(block_type, x, 1, z, None, None);
         } else if landuse_tag == "construction" || landuse_tag == "railway" {
             editor.set_block(block_type, x, 0, z, None, Some(&[SPONGE]));
         } else {
-            editor.set_block(block_type, x, 0, z, None, None);
+            editor.set_block(block_type, x, dy, z, None, None);
         }
 
         // Add specific features for different landuse types
         match landuse_tag.as_str() {
-            "cemetery" => {
-                if (x % 3 == 0) && (z % 3 == 0) {
-                    let random_choice: i32 = rng.gen_range(0..100);
-                    if random_choice < 15 {
-                        // Place graves
-                        if editor.check_for_block(x, 0, z, Some(&[PODZOL])) {
-                            if rng.gen_bool(0.5) {
-                                editor.set_block(COBBLESTONE, x - 1, 1, z, None, None);
-                                editor.set_block(STONE_BRICK_SLAB, x - 1, 2, z, None, None);
-                                editor.set_block(STONE_BRICK_SLAB, x, 1, z, None, None);
-                                editor.set_block(STONE_BRICK_SLAB, x + 1, 1, z, None, None);
-                            } else {
-                                editor.set_block(COBBLESTONE, x, 1, z - 1, None, None);
-                                editor.set_block(STONE_BRICK_SLAB, x, 2, z - 1, None, None);
-                                editor.set_block(STONE_BRICK_SLAB, x, 1, z, None, None);
-                                editor.set_block(STONE_BRICK_SLAB, x, 1, z + 1, None, None);
-                            }
-                        }
-                    } else if random_choice < 30 {
-                        if editor.check_for_block(x, 0, z, Some(&[PODZOL])) {
-                            editor.set_block(RED_FLOWER, x, 1, z, None, None);
-                        }
-                    } else if random_choice < 33 {
-                        Tree::create(editor, (x, 1, z));
-                    } else if random_choice < 35 {
-                        editor.set_block(OAK_LEAVES, x, 1, z, None, None);
-                    }
-                }
-            }
             "forest" => {
+                editor.set_biome(x, z, Biome::Forest);
                 if editor.check_for_block(x, 0, z, Some(&[GRASS_BLOCK])) {
                     let random_choice: i32 = rng.gen_range(0..30);
-                    if random_choice == 20 {
-                        Tree::create(editor, (x, 1, z));
+                    if Tree::is_forest_tree_spot(x, z, &mut rng) {
+                        Tree::create_in_forest(editor, (x, 1, z), &element.tags);
                     } else if random_choice == 2 {
                         let flower_block: Block = match rng.gen_range(1..=5) {
                             1 => OAK_LEAVES,
@@ -111,24 +228,9 @@ pub fn generate_landuse(editor: &mut WorldEditor, element: &ProcessedWay, args:
             }
             "farmland" => {
                 // Check if the current block is not water or another undesired block
-                if !editor.check_for_block(x, 0, z, Some(&[WATER])) {
-                    if x % 9 == 0 && z % 9 == 0 {
-                        // Place water in dot pattern
-                        editor.set_block(WATER, x, 0, z, Some(&[FARMLAND]), None);
-                    } else if rng.gen_range(0..76) == 0 {
-                        let special_choice: i32 = rng.gen_range(1..=10);
-                        if special_choice <= 4 {
-                            editor.set_block(HAY_BALE, x, 1, z, None, Some(&[SPONGE]));
-                        } else {
-                            editor.set_block(OAK_LEAVES, x, 1, z, None, Some(&[SPONGE]));
-                        }
-                    } else {
-                        // Set crops only if the block below is farmland
-                        if editor.check_for_block(x, 0, z, Some(&[FARMLAND])) {
-                            let crop_choice = [WHEAT, CARROTS, POTATOES][rng.gen_range(0..3)];
-                            editor.set_block(crop_choice, x, 1, z, None, None);
-                        }
-                    }
+                if !editor.check_for_block(x, dy, z, Some(&[WATER])) {
+                    let row = if rows_along_x { z } else { x };
+                    generate_field_block(editor, (x, dy, z), row, crop, &mut rng);
                 }
             }
             "construction" => {
@@ -205,10 +307,17 @@ pub fn generate_landuse(editor: &mut WorldEditor, element: &ProcessedWay, args:
             }
             "grass" => {
                 if editor.check_for_block(x, 0, z, Some(&[GRASS_BLOCK])) {
-                    match rng.gen_range(0..200) {
-                        0 => editor.set_block(OAK_LEAVES, x, 1, z, None, None),
-                        1..=170 => editor.set_block(GRASS, x, 1, z, None, None),
-                        _ => {}
+                    if rng.gen_range(0..200) == 0 {
+                        editor.set_block(OAK_LEAVES, x, 1, z, None, None);
+                    } else {
+                        generate_meadow_plants(
+                            editor,
+                            x,
+                            z,
+                            "grass",
+                            args.meadow_density,
+                            &mut rng,
+                        );
                     }
                 }
             }
@@ -226,19 +335,22 @@ pub fn generate_landuse(editor: &mut WorldEditor, element: &ProcessedWay, args:
                     let random_choice: i32 = rng.gen_range(0..1001);
                     if random_choice < 5 {
                         Tree::create(editor, (x, 1, z));
-                    } else if random_choice < 6 {
-                        editor.set_block(RED_FLOWER, x, 1, z, None, None);
-                    } else if random_choice < 9 {
+                    } else if random_choice < 8 {
                         editor.set_block(OAK_LEAVES, x, 1, z, None, None);
-                    } else if random_choice < 800 {
-                        editor.set_block(GRASS, x, 1, z, None, None);
+                    } else {
+                        generate_meadow_plants(
+                            editor,
+                            x,
+                            z,
+                            "meadow",
+                            args.meadow_density,
+                            &mut rng,
+                        );
                     }
                 }
             }
             "orchard" => {
-                if x % 18 == 0 && z % 10 == 0 {
-                    Tree::create(editor, (x, 1, z));
-                } else if editor.check_for_block(x, 0, z, Some(&[GRASS_BLOCK])) {
+                if editor.check_for_block(x, 0, z, Some(&[GRASS_BLOCK])) {
                     match rng.gen_range(0..100) {
                         0 => editor.set_block(OAK_LEAVES, x, 1, z, None, None),
                         1..=20 => editor.set_block(GRASS, x, 1, z, None, None),
@@ -246,63 +358,726 @@ pub fn generate_landuse(editor: &mut WorldEditor, element: &ProcessedWay, args:
                     }
                 }
             }
-            "quarry" => {
-                // Add stone layer under it
-                editor.set_block(STONE, x, -1, z, Some(&[STONE]), None);
-                editor.set_block(STONE, x, -2, z, Some(&[STONE]), None);
-                // Generate ore blocks
-                if let Some(resource) = element.tags.get("resource") {
-                    let ore_block = match resource.as_str() {
-                        "iron_ore" => IRON_ORE,
-                        "coal" => COAL_ORE,
-                        "copper" => COPPER_ORE,
-                        "gold" => GOLD_ORE,
-                        "clay" | "kaolinite" => CLAY,
-                        _ => STONE,
-                    };
-                    let random_choice: i32 = rng.gen_range(0..100 + editor.get_absolute_y(x, 0, z)); // The deeper it is the more resources are there
-                    if random_choice < 5 {
-                        editor.set_block(ore_block, x, 0, z, Some(&[STONE]), None);
-                    }
+            _ => {}
+        }
+    }
+
+    match landuse_tag.as_str() {
+        "orchard" => generate_orchard_trees(editor, element, &shaped_area, args.scale),
+        "vineyard" => {
+            generate_vineyard_rows(editor, element, &shaped_area, &terrace_offsets, args.scale)
+        }
+        "quarry" => generate_quarry_pit(editor, element, &shaped_area, args.scale),
+        "cemetery" => generate_cemetery(editor, element, &shaped_area, args.scale),
+        "military" => generate_military_perimeter(editor, element, &shaped_area, args.scale),
+        "allotments" => generate_allotments(editor, element, &shaped_area, args.scale, &mut rng),
+        _ => {}
+    }
+}
+
+/// Divides allotments into rows of small fenced plots along gravel paths, each one with
+/// beds of vegetables, flowers or a lawn with a fruit tree, and some of them with a shed
+fn generate_allotments(
+    editor: &mut WorldEditor,
+    element: &ProcessedWay,
+    area: &HashSet<(i32, i32)>,
+    scale: f64,
+    rng: &mut ElementRng,
+) {
+    let (axis_x, axis_z) = long_axis(element);
+    let along = |&(x, z): &(i32, i32)| x as f64 * axis_x + z as f64 * axis_z;
+    let across = |&(x, z): &(i32, i32)| z as f64 * axis_x - x as f64 * axis_z;
+    let (Some(min_u), Some(min_v)) = (
+        area.iter().map(along).reduce(f64::min),
+        area.iter().map(across).reduce(f64::min),
+    ) else {
+        return;
+    };
+
+    let plot_length = (ALLOTMENT_PLOT_LENGTH * scale).max(6.0);
+    let plot_width = (ALLOTMENT_PLOT_WIDTH * scale).max(6.0);
+    let path_width = (ALLOTMENT_PATH_WIDTH * scale).max(1.0);
+    // Middle of the part of a plot behind its fence
+    let (middle_u, middle_v) = (plot_length / 2.0, (path_width + 1.0 + plot_width) / 2.0);
+
+    let mut cells: Vec<(i32, i32)> = area.iter().copied().collect();
+    cells.sort_unstable();
+    // Use of every plot and whether it has a shed, decided when the plot is first reached
+    let mut plots: HashMap<(i32, i32), (AllotmentPlot, bool)> = HashMap::new();
+    for (x, z) in cells {
+        if !editor.check_for_block(x, 0, z, Some(&[GRASS_BLOCK])) {
+            continue;
+        }
+        let (u, v) = (along(&(x, z)) - min_u, across(&(x, z)) - min_v);
+        let plot = (
+            (u / plot_length).floor() as i32,
+            (v / plot_width).floor() as i32,
+        );
+        let (lu, lv) = (u.rem_euclid(plot_length), v.rem_euclid(plot_width));
+        if lv < path_width {
+            editor.set_block(GRAVEL, x, 0, z, Some(&[GRASS_BLOCK]), None);
+            continue;
+        }
+
+        let (kind, has_shed) = *plots.entry(plot).or_insert_with(|| {
+            let kind = match rng.gen_range(0..5) {
+                0..=2 => AllotmentPlot::Vegetables,
+                3 => AllotmentPlot::Flowers,
+                _ => AllotmentPlot::Lawn,
+            };
+            (kind, rng.gen_bool(0.5))
+        });
+
+        // Low fences between the plots and along the path, with a gate in the middle
+        let is_gate = (lu - middle_u).abs() < 1.0;
+        if lu < 1.0 || (lv < path_width + 1.0 && !is_gate) {
+            editor.set_block(OAK_FENCE, x, 1, z, None, None);
+            continue;
+        }
+        // Sheds stand in the back corner of the plot
+        if has_shed && lu >= plot_length - 3.0 && lv >= plot_width - 2.0 {
+            for y in 1..=2 {
+                editor.set_block(SPRUCE_PLANKS, x, y, z, None, None);
+            }
+            editor.set_block(OAK_SLAB, x, 3, z, None, None);
+            continue;
+        }
+
+        let is_middle = (lu - middle_u).abs() < 0.5 && (lv - middle_v).abs() < 0.5;
+        match kind {
+            AllotmentPlot::Vegetables => {
+                // A water basin keeps the beds around it moist
+                if is_middle {
+                    editor.set_block(WATER, x, 0, z, Some(&[GRASS_BLOCK]), None);
+                    continue;
+                }
+                // Beds of two rows with a walkway after them
+                let row = (lv - path_width - 1.0) as i32;
+                if row % 3 == 2 {
+                    continue;
+                }
+                let crops = [CARROTS, POTATOES, BEETROOTS, WHEAT];
+                let crop = crops[(row / 3 + plot.0 + plot.1).rem_euclid(4) as usize];
+                editor.set_block(FARMLAND, x, 0, z, Some(&[GRASS_BLOCK]), None);
+                editor.set_block(crop, x, 1, z, None, None);
+            }
+            AllotmentPlot::Flowers => {
+                if rng.gen_bool(0.6) {
+                    let flower =
+                        [RED_FLOWER, YELLOW_FLOWER, BLUE_FLOWER, WHITE_FLOWER][rng.gen_range(0..4)];
+                    editor.set_block(flower, x, 1, z, None, None);
+                }
+            }
+            AllotmentPlot::Lawn => {
+                if is_middle {
+                    Tree::create_fruit_tree(editor, (x, 1, z), None);
+                } else if rng.gen_bool(0.3) {
+                    editor.set_block(GRASS, x, 1, z, None, None);
                 }
             }
-            _ => {}
         }
     }
 }
 
-pub fn generate_landuse_from_relation(
+/// Surrounds a military area with a high fence of iron bars carrying warning signs, with
+/// watchtowers at its corners. Roads and paths keep their way through the fence.
+fn generate_military_perimeter(
     editor: &mut WorldEditor,
-    rel: &ProcessedRelation,
-    args: &Args,
+    element: &ProcessedWay,
+    area: &HashSet<(i32, i32)>,
+    scale: f64,
 ) {
-    if rel.tags.contains_key("landuse") {
-        // Generate individual ways with their original tags
-        for member in &rel.members {
-            if member.role == ProcessedMemberRole::Outer {
-                generate_landuse(editor, &member.way.clone(), args);
+    let sign_spacing = (MILITARY_SIGN_SPACING * scale).max(8.0) as usize;
+    let mut fence: HashSet<(i32, i32)> = HashSet::new();
+    let mut step: usize = 0;
+    for pair in element.nodes.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        let (dx, dz) = ((b.x - a.x) as f64, (b.z - a.z) as f64);
+        let length = dx.hypot(dz);
+        if length == 0.0 {
+            continue;
+        }
+        // Normal of the edge pointing out of the area
+        let (mut nx, mut nz) = (dz / length, -dx / length);
+        let (mid_x, mid_z) = ((a.x + b.x) / 2, (a.z + b.z) / 2);
+        let probe = (
+            mid_x + (nx * 2.0).round() as i32,
+            mid_z + (nz * 2.0).round() as i32,
+        );
+        if area.contains(&probe) {
+            (nx, nz) = (-nx, -nz);
+        }
+
+        for (x, _, z) in bresenham_line(a.x, 0, a.z, b.x, 0, b.z) {
+            if !fence.insert((x, z)) || editor.is_walkable(x, z) {
+                continue;
+            }
+            step += 1;
+            let block = if step.is_multiple_of(MILITARY_FENCE_POST_SPACING) {
+                ANDESITE_WALL
+            } else {
+                IRON_BARS
+            };
+            for y in 1..=MILITARY_FENCE_HEIGHT {
+                editor.set_block(block, x, y, z, None, None);
+            }
+
+            // Warning signs facing away from the area
+            if step % sign_spacing == sign_spacing / 2 {
+                let facing = if nx.abs() > nz.abs() {
+                    if nx > 0.0 {
+                        StairFacing::East
+                    } else {
+                        StairFacing::West
+                    }
+                } else if nz > 0.0 {
+                    StairFacing::South
+                } else {
+                    StairFacing::North
+                };
+                let (sign_x, sign_z) = (x + nx.round() as i32, z + nz.round() as i32);
+                editor.set_wall_sign(
+                    [
+                        "WARNING".to_string(),
+                        "Military area".to_string(),
+                        "No entry for".to_string(),
+                        "unauthorised persons".to_string(),
+                    ],
+                    sign_x,
+                    2,
+                    sign_z,
+                    facing,
+                );
             }
         }
+    }
 
-        // Combine all outer ways into one with relation tags
-        let mut combined_nodes = Vec::new();
-        for member in &rel.members {
-            if member.role == ProcessedMemberRole::Outer {
-                combined_nodes.extend(member.way.nodes.clone());
+    // Watchtowers at the corners where the fence turns
+    let tower_spacing = (MILITARY_TOWER_SPACING * scale).max(8.0);
+    let corners: Vec<(i32, i32)> = element.nodes.iter().map(|n| (n.x, n.z)).collect();
+    let count = corners.len().saturating_sub(1);
+    let mut towers: Vec<(i32, i32)> = Vec::new();
+    for i in 0..count {
+        let (px, pz) = corners[(i + count - 1) % count];
+        let (x, z) = corners[i];
+        let (nx, nz) = corners[(i + 1) % count];
+        let (ax, az) = ((x - px) as f64, (z - pz) as f64);
+        let (bx, bz) = ((nx - x) as f64, (nz - z) as f64);
+        let lengths = ax.hypot(az) * bx.hypot(bz);
+        let is_turn = lengths > 0.0 && (ax * bx + az * bz) / lengths < 30f64.to_radians().cos();
+        let is_free = towers
+            .iter()
+            .all(|&(tx, tz)| ((tx - x) as f64).hypot((tz - z) as f64) >= tower_spacing);
+        if is_turn && is_free {
+            towers.push((x, z));
+            generate_watchtower(editor, x, z);
+        }
+    }
+}
+
+/// Builds a wooden watchtower on four legs with a railed platform and a roof
+fn generate_watchtower(editor: &mut WorldEditor, x: i32, z: i32) {
+    // The legs stand in place of the fence where it runs through them
+    for dx in -1..=1 {
+        for dz in -1..=1 {
+            let is_corner = dx != 0 && dz != 0;
+            if is_corner {
+                for y in 1..=5 {
+                    let fence = [IRON_BARS, ANDESITE_WALL];
+                    editor.set_block(SPRUCE_LOG, x + dx, y, z + dz, Some(&fence), None);
+                }
+                for y in 7..=8 {
+                    editor.set_block(OAK_FENCE, x + dx, y, z + dz, None, None);
+                }
+            } else if dx != 0 || dz != 0 {
+                editor.set_block(OAK_FENCE, x + dx, 7, z + dz, None, None);
             }
+            editor.set_block(SPRUCE_PLANKS, x + dx, 6, z + dz, None, None);
+            editor.set_block(STONE_BLOCK_SLAB, x + dx, 9, z + dz, None, None);
         }
+    }
+    // Ladder up one of the legs
+    for y in 1..=7 {
+        editor.set_block(LADDER, x - 1, y, z - 2, None, None);
+    }
+}
 
-        // Only process if we have nodes
-        if !combined_nodes.is_empty() {
-            // Create combined way with relation tags
-            let combined_way = ProcessedWay {
-                id: rel.id,
-                nodes: combined_nodes,
-                tags: rel.tags.clone(),
+/// Generates an amenity=grave_yard area, a cemetery usually next to a church
+pub fn generate_grave_yard(editor: &mut WorldEditor, element: &ProcessedWay, args: &Args) {
+    let polygon_coords: Vec<(i32, i32)> = element.nodes.iter().map(|n| (n.x, n.z)).collect();
+    let area: HashSet<(i32, i32)> = flood_fill_area(&polygon_coords, args.timeout.as_ref())
+        .into_iter()
+        .collect();
+    for &(x, z) in &area {
+        editor.set_block(PODZOL, x, 0, z, None, None);
+    }
+    generate_cemetery(editor, element, &area, args.scale);
+}
+
+/// Lays out a cemetery with a hedge around it, gravel paths along its middle and across it,
+/// and rows of graves in between, whose headstones follow the religion tag
+fn generate_cemetery(
+    editor: &mut WorldEditor,
+    element: &ProcessedWay,
+    area: &HashSet<(i32, i32)>,
+    scale: f64,
+) {
+    let (axis_x, axis_z) = long_axis(element);
+    let along = |&(x, z): &(i32, i32)| x as f64 * axis_x + z as f64 * axis_z;
+    let across = |&(x, z): &(i32, i32)| z as f64 * axis_x - x as f64 * axis_z;
+    let (Some(min_u), Some(min_v), Some(max_v)) = (
+        area.iter().map(along).reduce(f64::min),
+        area.iter().map(across).reduce(f64::min),
+        area.iter().map(across).reduce(f64::max),
+    ) else {
+        return;
+    };
+
+    let path_half_width = (CEMETERY_PATH_WIDTH * scale / 2.0).max(0.5);
+    let cross_path_spacing = (CEMETERY_CROSS_PATH_SPACING * scale).max(4.0);
+    let is_path = |cell: &(i32, i32)| {
+        (across(cell) - (min_v + max_v) / 2.0).abs() < path_half_width
+            || (along(cell) - min_u).rem_euclid(cross_path_spacing) < path_half_width * 2.0
+    };
+    let is_edge = |&(x, z): &(i32, i32)| {
+        [(x + 1, z), (x - 1, z), (x, z + 1), (x, z - 1)]
+            .iter()
+            .any(|cell| !area.contains(cell))
+    };
+
+    let mut cells: Vec<(i32, i32)> = area.iter().copied().collect();
+    cells.sort_unstable();
+    for (x, z) in cells {
+        if is_path(&(x, z)) {
+            editor.set_block(GRAVEL, x, 0, z, Some(&[PODZOL]), None);
+        } else if is_edge(&(x, z)) {
+            // Paths leave gates in the hedge
+            editor.set_block(OAK_LEAVES, x, 1, z, None, None);
+        }
+    }
+
+    // Graves face across the rows, a headstone followed by the plot
+    let religion = element.tags.get("religion").map(String::as_str);
+    let plot_step = (-axis_z.round() as i32, axis_x.round() as i32);
+    let mut rng: ElementRng = element_rng();
+    let rows = planting_rows(
+        area,
+        (axis_x, axis_z),
+        (GRAVE_ROW_SPACING * scale).max(3.0),
+        (GRAVE_SPACING * scale).max(2.0),
+    );
+    for (x, z) in rows.into_iter().flatten() {
+        let plot = [1, 2].map(|i| (x + plot_step.0 * i, z + plot_step.1 * i));
+        let grave = [(x, z), plot[0], plot[1]];
+        if grave
+            .iter()
+            .any(|cell| !area.contains(cell) || is_path(cell) || is_edge(cell))
+        {
+            continue;
+        }
+
+        if rng.gen_range(0..30) == 0 {
+            Tree::create(editor, (x, 1, z));
+            continue;
+        }
+
+        match religion {
+            // A post at the head and the foot of the grave
+            Some("muslim") => {
+                editor.set_block(ANDESITE_WALL, x, 1, z, None, None);
+                editor.set_block(ANDESITE_WALL, plot[1].0, 1, plot[1].1, None, None);
+            }
+            // A headstone with a slab covering the grave
+            Some("jewish") => {
+                editor.set_block(POLISHED_ANDESITE, x, 1, z, None, None);
+                for (plot_x, plot_z) in plot {
+                    editor.set_block(STONE_BRICK_SLAB, plot_x, 1, plot_z, None, None);
+                }
+            }
+            Some("christian") => {
+                editor.set_block(STONE_BRICKS, x, 1, z, None, None);
+                editor.set_block(STONE_BRICK_WALL, x, 2, z, None, None);
+            }
+            _ => {
+                editor.set_block(COBBLESTONE, x, 1, z, None, None);
+                editor.set_block(STONE_BRICK_SLAB, x, 2, z, None, None);
+            }
+        }
+
+        // Flowers on some of the graves without a slab
+        if religion != Some("jewish") && rng.gen_bool(0.3) {
+            let (plot_x, plot_z) = plot[rng.gen_range(0..2)];
+            editor.set_block(RED_FLOWER, plot_x, 1, plot_z, None, None);
+        }
+    }
+}
+
+/// Digs a quarry as a pit of benches that step down from its edge, with a ramp down to the
+/// bottom along its middle. The pit is dug with cave air so the ground is not filled in again.
+fn generate_quarry_pit(
+    editor: &mut WorldEditor,
+    element: &ProcessedWay,
+    area: &HashSet<(i32, i32)>,
+    scale: f64,
+) {
+    let bench_width = ((QUARRY_BENCH_WIDTH * scale).round() as i32).max(2);
+    let bench_height = ((QUARRY_BENCH_HEIGHT * scale).round() as i32).max(1);
+    let max_depth = QUARRY_LEVELS * bench_height;
+
+    let edge = area.iter().copied().filter(|&(x, z)| {
+        [(x + 1, z), (x - 1, z), (x, z + 1), (x, z - 1)]
+            .iter()
+            .any(|cell| !area.contains(cell))
+    });
+    let edge_distance = distances_within(area, edge);
+
+    let ore_block = match element.tags.get("resource").map(String::as_str) {
+        Some("iron_ore") => Some(IRON_ORE),
+        Some("coal") => Some(COAL_ORE),
+        Some("copper") => Some(COPPER_ORE),
+        Some("gold") => Some(GOLD_ORE),
+        Some("clay" | "kaolinite") => Some(CLAY),
+        _ => None,
+    };
+
+    // The ramp runs through the middle of the pit along its longer side
+    let bounds = |coord: fn(&(i32, i32)) -> i32| {
+        let min = area.iter().map(coord).min().unwrap_or_default();
+        let max = area.iter().map(coord).max().unwrap_or_default();
+        (min, max)
+    };
+    let ((min_x, max_x), (min_z, max_z)) = (bounds(|p| p.0), bounds(|p| p.1));
+    let rows_along_x = max_x - min_x >= max_z - min_z;
+    let ramp_row = if rows_along_x {
+        (min_z + max_z) / 2
+    } else {
+        (min_x + max_x) / 2
+    };
+
+    let mut rng: ElementRng = element_rng();
+    let mut cells: Vec<(i32, i32)> = area.iter().copied().collect();
+    cells.sort_unstable();
+    for (x, z) in cells {
+        let distance = edge_distance.get(&(x, z)).copied().unwrap_or(1);
+        let bench_depth = ((distance - 1) / bench_width * bench_height).min(max_depth);
+        let row = if rows_along_x { z } else { x };
+        let is_ramp = (row - ramp_row).abs() <= 1;
+        // The ramp descends by one block per column, which can be walked
+        let depth = if is_ramp {
+            bench_depth.max((distance - 1).min(max_depth))
+        } else {
+            bench_depth
+        };
+
+        for y in (1 - depth)..=0 {
+            editor.set_block(CAVE_AIR, x, y, z, Some(&[STONE]), None);
+        }
+        let floor = if is_ramp || rng.gen_bool(0.3) {
+            GRAVEL
+        } else {
+            STONE
+        };
+        editor.set_block(floor, x, -depth, z, Some(&[STONE]), None);
+        // Solid rock below the floor, which forms the walls of the deeper benches next to it
+        for y in (-max_depth - 2)..-depth {
+            let rock = match ore_block {
+                Some(ore) if rng.gen_range(0..100) < 4 => ore,
+                _ => STONE,
+            };
+            editor.set_block(rock, x, y, z, None, None);
+        }
+    }
+}
+
+/// Plants fruit trees in a regular grid aligned with the long side of an orchard
+fn generate_orchard_trees(
+    editor: &mut WorldEditor,
+    element: &ProcessedWay,
+    area: &HashSet<(i32, i32)>,
+    scale: f64,
+) {
+    let trees = element.tags.get("trees").map(String::as_str);
+    let rows = planting_rows(
+        area,
+        long_axis(element),
+        (ORCHARD_ROW_SPACING * scale).max(3.0),
+        (ORCHARD_TREE_SPACING * scale).max(3.0),
+    );
+    for (x, z) in rows.into_iter().flatten() {
+        if editor.check_for_block(x, 0, z, Some(&[GRASS_BLOCK])) {
+            Tree::create_fruit_tree(editor, (x, 1, z), trees);
+        }
+    }
+}
+
+/// Builds parallel rows of vines along the long side of a vineyard, held up by fence posts
+fn generate_vineyard_rows(
+    editor: &mut WorldEditor,
+    element: &ProcessedWay,
+    area: &HashSet<(i32, i32)>,
+    terrace_offsets: &HashMap<(i32, i32), i32>,
+    scale: f64,
+) {
+    let row_spacing = (VINEYARD_ROW_SPACING * scale).max(2.0);
+    for row in planting_rows(area, long_axis(element), row_spacing, 1.0) {
+        for (i, (x, z)) in row.into_iter().enumerate() {
+            let dy = terrace_offsets.get(&(x, z)).copied().unwrap_or(0);
+            if !editor.check_for_block(x, dy, z, Some(&[GRASS_BLOCK])) {
+                continue;
+            }
+            let block = if i % VINEYARD_POST_SPACING == 0 {
+                OAK_FENCE
+            } else {
+                OAK_LEAVES
             };
+            editor.set_block(block, x, dy + 1, z, None, None);
+            editor.set_block(block, x, dy + 2, z, None, None);
+        }
+    }
+}
+
+/// Scatters grass, tall grass, ferns and flowers over a column of open grassland, with the
+/// flowers growing in patches. The shares of the plants are multiplied by the density.
+pub fn generate_meadow_plants(
+    editor: &mut WorldEditor,
+    x: i32,
+    z: i32,
+    kind: &str,
+    density: f64,
+    rng: &mut ElementRng,
+) {
+    // Shares of the columns with grass, tall grass, ferns and flowers
+    let (grass, tall_grass, fern, flowers) = match kind {
+        "meadow" => (0.6, 0.12, 0.03, 0.04),
+        "grassland" => (0.45, 0.08, 0.02, 0.02),
+        _ => (0.8, 0.02, 0.0, 0.01),
+    };
+    let flowers = if value_noise(x, z, FLOWER_PATCH_SIZE) > FLOWER_PATCH_THRESHOLD {
+        flowers * 4.0
+    } else {
+        flowers
+    };
+
+    let mut roll: f64 = rng.gen::<f64>() / density.max(0.0);
+    if roll < flowers {
+        let flower = [RED_FLOWER, YELLOW_FLOWER, BLUE_FLOWER, WHITE_FLOWER][rng.gen_range(0..4)];
+        editor.set_block(flower, x, 1, z, None, None);
+        return;
+    }
+    roll -= flowers;
+    if roll < tall_grass {
+        editor.set_block(TALL_GRASS_BOTTOM, x, 1, z, None, None);
+        editor.set_block(TALL_GRASS_TOP, x, 2, z, None, None);
+        return;
+    }
+    roll -= tall_grass;
+    if roll < fern {
+        editor.set_block(FERN, x, 1, z, None, None);
+        return;
+    }
+    roll -= fern;
+    if roll < grass {
+        editor.set_block(GRASS, x, 1, z, None, None);
+    }
+}
+
+/// Returns the direction of the longest edge of an outline as a unit vector
+pub fn long_axis(element: &ProcessedWay) -> (f64, f64) {
+    element
+        .nodes
+        .windows(2)
+        .map(|pair| {
+            (
+                (pair[1].x - pair[0].x) as f64,
+                (pair[1].z - pair[0].z) as f64,
+            )
+        })
+        .max_by(|a, b| a.0.hypot(a.1).total_cmp(&b.0.hypot(b.1)))
+        .filter(|&(dx, dz)| dx != 0.0 || dz != 0.0)
+        .map(|(dx, dz)| (dx / dx.hypot(dz), dz / dx.hypot(dz)))
+        .unwrap_or((1.0, 0.0))
+}
 
-            // Generate landuse area from combined way
-            generate_landuse(editor, &combined_way, args);
+/// Returns the blocks of parallel rows through an area running along the given axis, the
+/// rows `row_spacing` apart with a block every `step` along them
+fn planting_rows(
+    area: &HashSet<(i32, i32)>,
+    (axis_x, axis_z): (f64, f64),
+    row_spacing: f64,
+    step: f64,
+) -> Vec<Vec<(i32, i32)>> {
+    // Position of a block along and across the rows
+    let project = |&(x, z): &(i32, i32)| {
+        let (x, z) = (x as f64, z as f64);
+        (x * axis_x + z * axis_z, z * axis_x - x * axis_z)
+    };
+    let projected: Vec<(f64, f64)> = area.iter().map(project).collect();
+    let along = projected.iter().map(|p| p.0);
+    let across = projected.iter().map(|p| p.1);
+    let (Some(min_u), Some(max_u)) = (along.clone().reduce(f64::min), along.reduce(f64::max))
+    else {
+        return Vec::new();
+    };
+    let (Some(min_v), Some(max_v)) = (across.clone().reduce(f64::min), across.reduce(f64::max))
+    else {
+        return Vec::new();
+    };
+
+    // The outermost rows and plants keep half a spacing from the edge
+    let mut rows = Vec::new();
+    let mut v = min_v + row_spacing / 2.0;
+    while v <= max_v {
+        let mut row: Vec<(i32, i32)> = Vec::new();
+        let mut u = min_u + step / 2.0;
+        while u <= max_u {
+            let point = (
+                (u * axis_x - v * axis_z).round() as i32,
+                (u * axis_z + v * axis_x).round() as i32,
+            );
+            if area.contains(&point) && row.last() != Some(&point) {
+                row.push(point);
+            }
+            u += step;
+        }
+        rows.push(row);
+        v += row_spacing;
+    }
+    rows
+}
+
+/// Plants the crop of a field at one column, in rows along the field with water channels
+/// between them
+fn generate_field_block(
+    editor: &mut WorldEditor,
+    (x, y, z): (i32, i32, i32),
+    row: i32,
+    crop: Crop,
+    rng: &mut impl Rng,
+) {
+    // Sugar cane needs water next to every row
+    let channel_spacing = match crop {
+        Crop::SugarCane => 3,
+        _ => WATER_CHANNEL_SPACING,
+    };
+    if crop != Crop::Rapeseed && row.rem_euclid(channel_spacing) == 0 {
+        editor.set_block(WATER, x, y, z, Some(&[crop.ground()]), None);
+        return;
+    }
+
+    if rng.gen_range(0..76) == 0 {
+        if rng.gen_range(1..=10) <= 4 {
+            editor.set_block(HAY_BALE, x, y + 1, z, None, Some(&[SPONGE]));
+        } else {
+            editor.set_block(OAK_LEAVES, x, y + 1, z, None, Some(&[SPONGE]));
+        }
+        return;
+    }
+
+    // Plant only on the ground of the field
+    if !editor.check_for_block(x, y, z, Some(&[crop.ground()])) {
+        return;
+    }
+    match crop {
+        Crop::SugarCane => {
+            for dy in 1..=rng.gen_range(2..=3) {
+                editor.set_block(SUGAR_CANE, x, y + dy, z, None, None);
+            }
+        }
+        Crop::Rapeseed => editor.set_block(YELLOW_FLOWER, x, y + 1, z, None, None),
+        Crop::Wheat => editor.set_block(WHEAT, x, y + 1, z, None, None),
+        Crop::Carrots => editor.set_block(CARROTS, x, y + 1, z, None, None),
+        Crop::Potatoes => editor.set_block(POTATOES, x, y + 1, z, None, None),
+        Crop::Beetroots => editor.set_block(BEETROOTS, x, y + 1, z, None, None),
+    }
+}
+
+/// Builds terraces for fields on steep slopes when terrain is enabled. Every block is raised
+/// or cut down to the terrace level nearest to the smoothed terrain, and blocks above a lower
+/// terrace get a stone retaining edge. Returns the height of the terrace surface relative to
+/// the natural ground for every block.
+fn generate_terraces(
+    editor: &mut WorldEditor,
+    floor_area: &[(i32, i32)],
+) -> HashMap<(i32, i32), i32> {
+    let elevation_enabled = editor
+        .get_ground()
+        .is_some_and(|ground| ground.elevation_enabled);
+    if !elevation_enabled || floor_area.is_empty() {
+        return HashMap::new();
+    }
+
+    let heights: HashMap<(i32, i32), i32> = floor_area
+        .iter()
+        .map(|&(x, z)| ((x, z), editor.get_absolute_y(x, 0, z)))
+        .collect();
+    let min_height = *heights.values().min().unwrap();
+
+    // Gentle slopes keep following the terrain, the rise towards the next block in x and z
+    // direction is averaged over the field so that its shape does not matter
+    let rise = |(x, z): (i32, i32), height: i32| {
+        heights
+            .get(&(x, z))
+            .map_or(0.0, |&next| f64::from(next - height))
+    };
+    let mean_slope = heights
+        .iter()
+        .map(|(&(x, z), &height)| rise((x + 1, z), height).hypot(rise((x, z + 1), height)))
+        .sum::<f64>()
+        / heights.len() as f64;
+    if mean_slope < TERRACE_MIN_SLOPE {
+        return HashMap::new();
+    }
+
+    let levels: HashMap<(i32, i32), i32> = heights
+        .keys()
+        .map(|&(x, z)| {
+            let r = TERRACE_SMOOTHING_RADIUS;
+            let nearby: Vec<i32> = (-r..=r)
+                .flat_map(|dx| (-r..=r).map(move |dz| (x + dx, z + dz)))
+                .filter_map(|pos| heights.get(&pos).copied())
+                .collect();
+            let smoothed = f64::from(nearby.iter().sum::<i32>()) / nearby.len() as f64;
+            let steps = ((smoothed - f64::from(min_height)) / f64::from(TERRACE_STEP)).round();
+            ((x, z), min_height + steps as i32 * TERRACE_STEP)
+        })
+        .collect();
+
+    for (&(x, z), &level) in &levels {
+        let height = heights[&(x, z)];
+        let is_edge = [(1, 0), (-1, 0), (0, 1), (0, -1)].iter().any(|(dx, dz)| {
+            levels
+                .get(&(x + dx, z + dz))
+                .is_some_and(|&neighbor_level| neighbor_level < level)
+        });
+
+        // Retaining edges are solid stone up to the terrace surface
+        let fill_block = if is_edge { STONE_BRICKS } else { DIRT };
+        for y in height..level {
+            editor.set_block_absolute(fill_block, x, y, z, None, None);
+        }
+        // Terrain above the terrace is cut away and kept free of the ground filled in later
+        for y in (level + 1)..=height {
+            editor.set_block_absolute(CAVE_AIR, x, y, z, None, None);
+        }
+        if is_edge {
+            editor.set_block_absolute(STONE_BRICKS, x, level, z, None, None);
+        }
+    }
+
+    levels
+        .into_iter()
+        .map(|(pos, level)| (pos, level - heights[&pos]))
+        .collect()
+}
+
+pub fn generate_landuse_from_relation(
+    editor: &mut WorldEditor,
+    rel: &ProcessedRelation,
+    args: &Args,
+) {
+    if rel.tags.contains_key("landuse") {
+        for way in rel.outer_ways() {
+            generate_landuse(editor, &way, args);
         }
     }
 }
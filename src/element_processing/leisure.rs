@@ -1,42 +1,74 @@
 use crate::args::Args;
 use crate::block_definitions::*;
 use crate::bresenham::bresenham_line;
+use crate::element_processing::highways::place_lamp_post;
+use crate::element_processing::pitches::{generate_pitch, is_marked_pitch};
+use crate::element_processing::playgrounds::{place_equipment, surface_block, Equipment};
 use crate::element_processing::tree::Tree;
+use crate::element_processing::water_areas::{is_underwater_light_spot, place_water, WaterMask};
+use crate::element_rng::{element_rng, ElementRng};
 use crate::floodfill::flood_fill_area;
-use crate::osm_parser::{ProcessedMemberRole, ProcessedRelation, ProcessedWay};
-use crate::world_editor::WorldEditor;
+use crate::ground_cover::value_noise;
+use crate::osm_parser::{ProcessedRelation, ProcessedWay};
+use crate::world_editor::{Biome, WorldEditor};
+use fastnbt::Value;
 use rand::Rng;
+use std::collections::{HashMap, HashSet};
+
+/// Smallest distance between benches and lamp posts along the paths of parks, in blocks
+const PARK_FURNITURE_SPACING: i32 = 8;
+/// Distance that trees in parks keep from paths, in blocks
+const PARK_TREE_PATH_CLEARANCE: i32 = 3;
+/// Size of the flower beds in parks and gardens, in blocks
+const FLOWERBED_SIZE: f64 = 6.0;
+/// Value of the noise above which parks and gardens have flower beds
+const FLOWERBED_THRESHOLD: f64 = 0.78;
+/// Pools smaller than this are shallow paddling pools, one block deep
+const PADDLING_POOL_AREA: usize = 30;
+/// Pools from this size on get a diving board
+const DIVING_BOARD_POOL_AREA: usize = 60;
+/// Chance that a free berth along a pier in a marina has a boat moored at it
+const MOORED_BOAT_CHANCE: f64 = 0.6;
+/// Length of a moored boat, in blocks
+const BOAT_LENGTH: i32 = 5;
 
 pub fn generate_leisure(editor: &mut WorldEditor, element: &ProcessedWay, args: &Args) {
     if let Some(leisure_type) = element.tags.get("leisure") {
+        if leisure_type == "swimming_pool" {
+            generate_swimming_pool(editor, element, args);
+            return;
+        }
+        if leisure_type == "marina" {
+            generate_marina(editor, element, args);
+            return;
+        }
+        if leisure_type == "pitch" && is_marked_pitch(element) {
+            generate_pitch(editor, element, args);
+            return;
+        }
+
         let mut previous_node: Option<(i32, i32)> = None;
         let mut corner_addup: (i32, i32, i32) = (0, 0, 0);
         let mut current_leisure: Vec<(i32, i32)> = vec![];
 
+        // Outdoor competition pools get lane markings and starting blocks
+        let is_competition_pool = element.tags.get("sport") == Some(&"swimming".to_string())
+            && matches!(leisure_type.as_str(), "swimming_pool" | "sports_centre")
+            && !element.tags.contains_key("building");
+
         // Determine block type based on leisure type
         let block_type: Block = match leisure_type.as_str() {
+            _ if is_competition_pool => WATER,
             "park" | "nature_reserve" | "garden" | "disc_golf_course" | "golf_course" => {
                 GRASS_BLOCK
             }
             "schoolyard" => BLACK_CONCRETE,
             "playground" | "recreation_ground" | "pitch" | "beach_resort" | "dog_park" => {
-                if let Some(surface) = element.tags.get("surface") {
-                    match surface.as_str() {
-                        "clay" => TERRACOTTA,
-                        "sand" => SAND,
-                        "tartan" => RED_TERRACOTTA,
-                        "grass" => GRASS_BLOCK,
-                        "dirt" => DIRT,
-                        "pebblestone" | "cobblestone" | "unhewn_cobblestone" => COBBLESTONE,
-                        _ => GREEN_STAINED_HARDENED_CLAY,
-                    }
-                } else {
-                    GREEN_STAINED_HARDENED_CLAY
-                }
+                surface_block(&element.tags)
             }
-            "swimming_pool" | "swimming_area" => WATER, //Swimming area: Area in a larger body of water for swimming
-            "bathing_place" => SMOOTH_SANDSTONE,        // Could be sand or concrete
-            "outdoor_seating" => SMOOTH_STONE,          //Usually stone or stone bricks
+            "swimming_area" => WATER, //Swimming area: Area in a larger body of water for swimming
+            "bathing_place" => SMOOTH_SANDSTONE, // Could be sand or concrete
+            "outdoor_seating" => SMOOTH_STONE, //Usually stone or stone bricks
             "water_park" | "slipway" => LIGHT_GRAY_CONCRETE, // Water park area, not the pool. Usually is concrete
             "ice_rink" => PACKED_ICE, // TODO: Ice for Ice Rink, needs building defined
             _ => GRASS_BLOCK,
@@ -84,87 +116,40 @@ pub fn generate_leisure(editor: &mut WorldEditor, element: &ProcessedWay, args:
             let filled_area: Vec<(i32, i32)> =
                 flood_fill_area(&polygon_coords, args.timeout.as_ref());
 
+            if is_competition_pool {
+                generate_pool_lanes(editor, &filled_area, args);
+            }
+
+            // Benches and lamp posts placed along the paths of a park so far
+            let mut furniture: Vec<(i32, i32)> = Vec::new();
+
             for (x, z) in filled_area {
                 editor.set_block(block_type, x, 0, z, Some(&[GRASS_BLOCK]), None);
+                if leisure_type == "park" {
+                    editor.set_biome(x, z, Biome::Plains);
+                }
+
+                // Pools are lit from below at night
+                if block_type == WATER
+                    && is_underwater_light_spot(editor, x, z)
+                    && editor.check_for_block(x, 0, z, Some(&[WATER]))
+                {
+                    editor.set_block(SEA_LANTERN, x, -1, z, None, None);
+                }
 
                 // Add decorative elements for parks and gardens
                 if matches!(leisure_type.as_str(), "park" | "garden" | "nature_reserve")
                     && editor.check_for_block(x, 0, z, Some(&[GRASS_BLOCK]))
                 {
-                    let mut rng: rand::prelude::ThreadRng = rand::thread_rng();
-                    let random_choice: i32 = rng.gen_range(0..1000);
-
-                    match random_choice {
-                        0..30 => {
-                            // Flowers
-                            let flower_choice = match random_choice {
-                                0..10 => RED_FLOWER,
-                                10..20 => YELLOW_FLOWER,
-                                20..30 => BLUE_FLOWER,
-                                _ => WHITE_FLOWER,
-                            };
-                            editor.set_block(flower_choice, x, 1, z, None, None);
-                        }
-                        30..90 => {
-                            // Grass
-                            editor.set_block(GRASS, x, 1, z, None, None);
-                        }
-                        90..105 => {
-                            // Oak leaves
-                            editor.set_block(OAK_LEAVES, x, 1, z, None, None);
-                        }
-                        105..120 => {
-                            // Tree
-                            Tree::create(editor, (x, 1, z));
-                        }
-                        _ => {}
-                    }
+                    generate_park_block(editor, x, z, leisure_type, &mut furniture);
                 }
 
                 // Add playground or recreation ground features
                 if matches!(leisure_type.as_str(), "playground" | "recreation_ground") {
-                    let mut rng: rand::prelude::ThreadRng = rand::thread_rng();
-                    let random_choice: i32 = rng.gen_range(0..5000);
-
-                    match random_choice {
-                        0..10 => {
-                            // Swing set
-                            for y in 1..=3 {
-                                editor.set_block(OAK_FENCE, x - 1, y, z, None, None);
-                                editor.set_block(OAK_FENCE, x + 1, y, z, None, None);
-                            }
-                            editor.set_block(OAK_PLANKS, x - 1, 4, z, None, None);
-                            editor.set_block(OAK_SLAB, x, 4, z, None, None);
-                            editor.set_block(OAK_PLANKS, x + 1, 4, z, None, None);
-                            editor.set_block(STONE_BLOCK_SLAB, x, 2, z, None, None);
-                        }
-                        10..20 => {
-                            // Slide
-                            editor.set_block(OAK_SLAB, x, 1, z, None, None);
-                            editor.set_block(OAK_SLAB, x + 1, 2, z, None, None);
-                            editor.set_block(OAK_SLAB, x + 2, 3, z, None, None);
-
-                            editor.set_block(OAK_PLANKS, x + 2, 2, z, None, None);
-                            editor.set_block(OAK_PLANKS, x + 2, 1, z, None, None);
-
-                            editor.set_block(LADDER, x + 2, 2, z - 1, None, None);
-                            editor.set_block(LADDER, x + 2, 1, z - 1, None, None);
-                        }
-                        20..30 => {
-                            // Sandpit
-                            editor.fill_blocks(
-                                SAND,
-                                x - 3,
-                                0,
-                                z - 3,
-                                x + 3,
-                                0,
-                                z + 3,
-                                Some(&[GREEN_STAINED_HARDENED_CLAY]),
-                                None,
-                            );
-                        }
-                        _ => {}
+                    let mut rng: ElementRng = element_rng();
+                    let random_choice: usize = rng.gen_range(0..5000);
+                    if let Some(&equipment) = Equipment::ALL.get(random_choice / 10) {
+                        place_equipment(editor, equipment, x, z, block_type);
                     }
                 }
             }
@@ -172,35 +157,417 @@ pub fn generate_leisure(editor: &mut WorldEditor, element: &ProcessedWay, args:
     }
 }
 
-pub fn generate_leisure_from_relation(
+/// Generates lane divider lines on the pool floor and starting blocks at both ends of a competition pool
+/// Digs a swimming pool lined with quartz and a light blue floor, with a ladder and, for
+/// larger pools, a diving board. Outdoor pools are surrounded by a deck.
+fn generate_swimming_pool(editor: &mut WorldEditor, element: &ProcessedWay, args: &Args) {
+    let polygon_coords: Vec<(i32, i32)> = element.nodes.iter().map(|n| (n.x, n.z)).collect();
+    let mut cells: HashSet<(i32, i32)> = flood_fill_area(&polygon_coords, args.timeout.as_ref())
+        .into_iter()
+        .collect();
+    for pair in polygon_coords.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        for (x, _, z) in bresenham_line(a.0, 0, a.1, b.0, 0, b.1) {
+            cells.insert((x, z));
+        }
+    }
+    if cells.is_empty() {
+        return;
+    }
+    let mut pool: Vec<(i32, i32)> = cells.iter().copied().collect();
+    pool.sort_unstable();
+
+    let is_competition_pool = element.tags.get("sport") == Some(&"swimming".to_string())
+        && !element.tags.contains_key("building");
+    let is_indoor = element.tags.get("location") == Some(&"indoor".to_string())
+        || element.tags.get("indoor") == Some(&"yes".to_string());
+    let depth = if pool.len() < PADDLING_POOL_AREA && !is_competition_pool {
+        1
+    } else {
+        2
+    };
+
+    // Lane markings are laid first and take the place of the plain floor
+    if is_competition_pool {
+        generate_pool_lanes(editor, &pool, args);
+    }
+
+    for &(x, z) in &pool {
+        for y in (1 - depth)..=0 {
+            editor.set_block(WATER, x, y, z, Some(&[GRASS_BLOCK]), None);
+        }
+        let floor = if is_underwater_light_spot(editor, x, z) {
+            SEA_LANTERN
+        } else {
+            LIGHT_BLUE_CONCRETE
+        };
+        editor.set_block(floor, x, -depth, z, None, None);
+    }
+
+    // Quartz walls below the edge, and a deck around outdoor pools
+    for &(x, z) in &pool {
+        for dx in -1..=1 {
+            for dz in -1..=1 {
+                let (edge_x, edge_z) = (x + dx, z + dz);
+                if cells.contains(&(edge_x, edge_z)) {
+                    continue;
+                }
+                for y in -depth..0 {
+                    editor.set_block(QUARTZ_BLOCK, edge_x, y, edge_z, None, None);
+                }
+                if !is_indoor {
+                    editor.set_block(SMOOTH_QUARTZ, edge_x, 0, edge_z, Some(&[GRASS_BLOCK]), None);
+                }
+            }
+        }
+    }
+
+    // Cells along the edge of the pool with the direction towards the outside
+    let cells = &cells;
+    let edges: Vec<(i32, i32, i32, i32)> = pool
+        .iter()
+        .flat_map(|&(x, z)| {
+            [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                .into_iter()
+                .filter(move |(dx, dz)| !cells.contains(&(x + dx, z + dz)))
+                .map(move |(dx, dz)| (x, z, dx, dz))
+        })
+        .collect();
+
+    // A ladder at one end and a diving board at the other
+    if let Some(&(x, z, dx, dz)) = edges.first() {
+        generate_pool_ladder(editor, x, z, (dx, dz), depth);
+    }
+    if depth >= 2 && pool.len() >= DIVING_BOARD_POOL_AREA {
+        if let Some(&(x, z, dx, dz)) = edges.last() {
+            if cells.contains(&(x - dx, z - dz)) {
+                for k in -1..=1 {
+                    editor.set_block(STONE_BLOCK_SLAB, x - dx * k, 1, z - dz * k, None, None);
+                }
+            }
+        }
+    }
+}
+
+/// Places a ladder down the wall of a pool, from the water surface to the floor
+fn generate_pool_ladder(editor: &mut WorldEditor, x: i32, z: i32, wall: (i32, i32), depth: i32) {
+    // Ladders face away from the wall they hang on
+    let facing = match wall {
+        (1, 0) => "west",
+        (-1, 0) => "east",
+        (0, 1) => "north",
+        _ => "south",
+    };
+    let mut properties = HashMap::new();
+    properties.insert("facing".to_string(), Value::String(facing.to_string()));
+    properties.insert("waterlogged".to_string(), Value::String("true".to_string()));
+    let ladder = BlockWithProperties::new(LADDER, Some(Value::Compound(properties)));
+
+    for y in (1 - depth)..=0 {
+        let absolute_y = editor.get_absolute_y(x, y, z);
+        editor.set_block_with_properties_absolute(
+            ladder.clone(),
+            x,
+            absolute_y,
+            z,
+            Some(&[WATER]),
+            None,
+        );
+    }
+}
+
+fn generate_pool_lanes(editor: &mut WorldEditor, filled_area: &[(i32, i32)], args: &Args) {
+    if filled_area.is_empty() {
+        return;
+    }
+
+    let min_x = filled_area.iter().map(|&(x, _)| x).min().unwrap();
+    let max_x = filled_area.iter().map(|&(x, _)| x).max().unwrap();
+    let min_z = filled_area.iter().map(|&(_, z)| z).min().unwrap();
+    let max_z = filled_area.iter().map(|&(_, z)| z).max().unwrap();
+
+    // Lanes run along the longer side of the pool, 2.5m wide each
+    let lanes_along_x = (max_x - min_x) >= (max_z - min_z);
+    let lane_width = ((2.5 * args.scale).round() as i32).max(2);
+    let (across_min, across_max) = if lanes_along_x {
+        (min_z, max_z)
+    } else {
+        (min_x, max_x)
+    };
+
+    for &(x, z) in filled_area {
+        let (along, across) = if lanes_along_x { (x, z) } else { (z, x) };
+        let lane_offset = across - across_min;
+
+        // Deepen the pool and tile the floor, with alternating lane lines between lanes
+        let on_lane_line = lane_offset > 0 && across < across_max && lane_offset % lane_width == 0;
+        let floor_block = if on_lane_line {
+            if along.div_euclid(2).rem_euclid(2) == 0 {
+                BLUE_CONCRETE
+            } else {
+                WHITE_CONCRETE
+            }
+        } else if is_underwater_light_spot(editor, x, z) {
+            SEA_LANTERN
+        } else {
+            LIGHT_BLUE_CONCRETE
+        };
+        editor.set_block(WATER, x, -1, z, None, None);
+        editor.set_block(floor_block, x, -2, z, None, None);
+    }
+
+    // Starting blocks in the middle of every lane, on the edge of the pool at both ends
+    let mut lane_ends: HashMap<i32, (i32, i32)> = HashMap::new();
+    for &(x, z) in filled_area {
+        let (along, across) = if lanes_along_x { (x, z) } else { (z, x) };
+        lane_ends
+            .entry(across)
+            .and_modify(|(first, last)| {
+                *first = (*first).min(along);
+                *last = (*last).max(along);
+            })
+            .or_insert((along, along));
+    }
+    let lane_centers = (across_min + lane_width / 2..across_max).step_by(lane_width as usize);
+    for lane_center in lane_centers {
+        let Some(&(first, last)) = lane_ends.get(&lane_center) else {
+            continue;
+        };
+        for end in [first - 1, last + 1] {
+            let (x, z) = if lanes_along_x {
+                (end, lane_center)
+            } else {
+                (lane_center, end)
+            };
+            editor.set_block(SMOOTH_STONE, x, 0, z, Some(&[WATER]), None);
+            editor.set_block(STONE_BLOCK_SLAB, x, 1, z, None, None);
+        }
+    }
+}
+
+/// Returns true for marinas, which are filled once the piers inside them are built
+pub fn is_marina(element: &ProcessedWay) -> bool {
+    element.tags.get("leisure") == Some(&"marina".to_string())
+}
+
+/// Fills the marina basin with water and moors boats at the berths along its piers
+fn generate_marina(editor: &mut WorldEditor, element: &ProcessedWay, args: &Args) {
+    let polygon_coords: Vec<(i32, i32)> = element.nodes.iter().map(|n| (n.x, n.z)).collect();
+    let mut basin: Vec<(i32, i32)> = flood_fill_area(&polygon_coords, args.timeout.as_ref());
+    if basin.is_empty() {
+        return;
+    }
+    basin.sort_unstable();
+
+    let min_x = basin.iter().map(|&(x, _)| x).min().unwrap_or_default();
+    let max_x = basin.iter().map(|&(x, _)| x).max().unwrap_or_default();
+    let min_z = basin.iter().map(|&(_, z)| z).min().unwrap_or_default();
+    let max_z = basin.iter().map(|&(_, z)| z).max().unwrap_or_default();
+    let mut mask = WaterMask::new(min_x, min_z, max_x, max_z);
+    for &(x, z) in &basin {
+        mask.mark(x, z);
+    }
+    place_water(editor, mask, Biome::River);
+
+    // Berths are the water columns next to a pier deck, the boat points away from the pier
+    let mut rng = element_rng();
+    let mut occupied: HashSet<(i32, i32)> = HashSet::new();
+    for &(x, z) in &basin {
+        if !editor.check_for_block(x, 0, z, Some(&[WATER])) {
+            continue;
+        }
+        let pier_side = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+            .into_iter()
+            .find(|&(dx, dz)| editor.check_for_block(x + dx, 1, z + dz, Some(&[OAK_SLAB])));
+        let Some((dx, dz)) = pier_side else {
+            continue;
+        };
+
+        let cells = boat_cells(x, z, (-dx, -dz));
+        let is_free = cells.iter().all(|&(cx, cz)| {
+            editor.check_for_block(cx, 0, cz, Some(&[WATER]))
+                && !editor.block_at(cx, 1, cz)
+                && !(-1..=1).any(|ox| (-1..=1).any(|oz| occupied.contains(&(cx + ox, cz + oz))))
+        });
+        if !is_free {
+            continue;
+        }
+        occupied.extend(cells.iter().copied());
+        if rng.gen_bool(MOORED_BOAT_CHANCE) {
+            generate_moored_boat(editor, &cells, rng.gen_bool(0.5));
+        }
+    }
+}
+
+/// Returns the footprint of a boat starting at x/z, three blocks wide with a narrow bow, the
+/// stern first and the bow last
+fn boat_cells(x: i32, z: i32, (dx, dz): (i32, i32)) -> Vec<(i32, i32)> {
+    let (side_x, side_z) = (-dz, dx);
+    let mut cells = Vec::new();
+    for step in 0..BOAT_LENGTH {
+        for side in -1..=1 {
+            // The corners of the bow stay open for a pointed hull
+            if step == BOAT_LENGTH - 1 && side != 0 {
+                continue;
+            }
+            cells.push((x + dx * step + side_x * side, z + dz * step + side_z * side));
+        }
+    }
+    cells
+}
+
+/// Builds a small boat floating on the water, either a sailing boat with a mast and sail or a
+/// motor boat with a small cabin
+fn generate_moored_boat(editor: &mut WorldEditor, cells: &[(i32, i32)], is_sailing_boat: bool) {
+    // Cells come in rows of three from the stern, followed by the single bow cell. The hull
+    // rises one block above the water around an open deck in the middle row.
+    for (i, &(x, z)) in cells.iter().enumerate() {
+        let is_deck = i % 3 == 1 && i >= 3 && i < cells.len() - 1;
+        if is_deck {
+            editor.set_block(SPRUCE_PLANKS, x, 0, z, Some(&[WATER]), None);
+        } else {
+            editor.set_block(WHITE_CONCRETE, x, 0, z, Some(&[WATER]), None);
+            editor.set_block(WHITE_CONCRETE, x, 1, z, None, None);
+        }
+    }
+
+    // Cells in the middle row, from the stern to the bow
+    let middle: Vec<(i32, i32)> = cells.iter().skip(1).step_by(3).copied().collect();
+    if is_sailing_boat {
+        if let Some(&(x, z)) = middle.get(2) {
+            for y in 1..=6 {
+                editor.set_block(OAK_FENCE, x, y, z, None, None);
+            }
+        }
+        if let Some(&(x, z)) = middle.get(1) {
+            for y in 3..=5 {
+                editor.set_block(WHITE_WOOL, x, y, z, None, None);
+            }
+        }
+    } else if let Some(&(x, z)) = middle.get(2) {
+        editor.set_block(WHITE_STAINED_GLASS, x, 1, z, None, None);
+        editor.set_block(WHITE_CONCRETE, x, 2, z, None, None);
+    }
+}
+
+/// Decorates one column of a park or garden. Benches and lamp posts line the mapped paths,
+/// trees keep away from them, and flowers grow in beds.
+fn generate_park_block(
     editor: &mut WorldEditor,
-    rel: &ProcessedRelation,
-    args: &Args,
+    x: i32,
+    z: i32,
+    leisure_type: &str,
+    furniture: &mut Vec<(i32, i32)>,
 ) {
-    if rel.tags.get("leisure") == Some(&"park".to_string()) {
-        // First generate individual ways with their original tags
-        for member in &rel.members {
-            if member.role == ProcessedMemberRole::Outer {
-                generate_leisure(editor, &member.way, args);
+    let mut rng: ElementRng = element_rng();
+    let is_path_within = |distance: i32| {
+        (-distance..=distance)
+            .any(|dx| (-distance..=distance).any(|dz| editor.is_walkable(x + dx, z + dz)))
+    };
+    let is_planted = leisure_type != "nature_reserve";
+
+    if leisure_type == "park" && is_path_within(1) {
+        let is_free = furniture.iter().all(|&(fx, fz)| {
+            (fx - x).abs() >= PARK_FURNITURE_SPACING || (fz - z).abs() >= PARK_FURNITURE_SPACING
+        });
+        if is_free {
+            let random_choice: i32 = rng.gen_range(0..100);
+            if random_choice < 4 {
+                furniture.push((x, z));
+                place_lamp_post(editor, x, z);
+            } else if random_choice < 10 {
+                furniture.push((x, z));
+                // The bench runs along the path next to it
+                let path_along_x = editor.is_walkable(x, z + 1) || editor.is_walkable(x, z - 1);
+                let (dx, dz) = if path_along_x { (1, 0) } else { (0, 1) };
+                editor.set_block(SMOOTH_STONE, x, 1, z, None, None);
+                editor.set_block(OAK_LOG, x + dx, 1, z + dz, None, None);
+                editor.set_block(OAK_LOG, x - dx, 1, z - dz, None, None);
             }
         }
+        return;
+    }
+
+    // Beds of flowers in a few spots of parks and gardens
+    if is_planted && value_noise(x, z, FLOWERBED_SIZE) > FLOWERBED_THRESHOLD && !is_path_within(1) {
+        if rng.gen_bool(0.6) {
+            let flower =
+                [RED_FLOWER, YELLOW_FLOWER, BLUE_FLOWER, WHITE_FLOWER][rng.gen_range(0..4)];
+            editor.set_block(flower, x, 1, z, None, None);
+        }
+        return;
+    }
 
-        // Then combine all outer ways into one
-        let mut combined_nodes = Vec::new();
-        for member in &rel.members {
-            if member.role == ProcessedMemberRole::Outer {
-                combined_nodes.extend(member.way.nodes.clone());
+    let random_choice: i32 = rng.gen_range(0..1000);
+    match random_choice {
+        0..30 => {
+            // Flowers
+            let flower_choice = match random_choice {
+                0..10 => RED_FLOWER,
+                10..20 => YELLOW_FLOWER,
+                20..30 => BLUE_FLOWER,
+                _ => WHITE_FLOWER,
+            };
+            editor.set_block(flower_choice, x, 1, z, None, None);
+        }
+        30..90 => {
+            // Grass
+            editor.set_block(GRASS, x, 1, z, None, None);
+        }
+        90..105 => {
+            // Bushes, some of them two blocks high
+            editor.set_block(OAK_LEAVES, x, 1, z, None, None);
+            if random_choice < 95 {
+                editor.set_block(OAK_LEAVES, x, 2, z, None, None);
             }
         }
+        // Trees keep their crowns clear of the paths
+        105..120 if !is_path_within(PARK_TREE_PATH_CLEARANCE) => {
+            Tree::create(editor, (x, 1, z));
+        }
+        _ => {}
+    }
+}
 
-        // Create combined way with relation tags
-        let combined_way = ProcessedWay {
-            id: rel.id,
-            nodes: combined_nodes,
-            tags: rel.tags.clone(),
-        };
+pub fn generate_leisure_from_relation(
+    editor: &mut WorldEditor,
+    rel: &ProcessedRelation,
+    args: &Args,
+) {
+    if rel.tags.contains_key("leisure") {
+        for way in rel.outer_ways() {
+            generate_leisure(editor, &way, args);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinate_system::cartesian::XZBBox;
+    use crate::coordinate_system::geographic::LLBBox;
+    use crate::test_utilities::{example_args, minecraft_tmpdir};
+
+    #[test]
+    fn test_starting_blocks_sit_on_the_pool_edge() {
+        let world = minecraft_tmpdir();
+        let args = example_args(&world, &[]);
+        let xzbbox = XZBBox::rect_from_xz_lengths(100.0, 100.0).unwrap();
+        let llbbox = LLBBox::new(54.627053, 9.927928, 54.634902, 9.937563).unwrap();
+        let mut editor = WorldEditor::new(world.path().to_path_buf(), &xzbbox, llbbox);
+
+        // A pool 20 blocks long whose far end narrows into a slope, with lanes along x
+        let pool: Vec<(i32, i32)> = (0..12)
+            .flat_map(|z| (10..30 - z).map(move |x| (x, z)))
+            .collect();
+        generate_pool_lanes(&mut editor, &pool, &args);
 
-        // Generate leisure area from combined way
-        generate_leisure(editor, &combined_way, args);
+        // The first lane is 3 blocks wide and centred one block from the side
+        let lane_center = 1;
+        let row_end = 30 - lane_center;
+        assert!(editor.check_for_block(9, 1, lane_center, Some(&[STONE_BLOCK_SLAB])));
+        assert!(editor.check_for_block(row_end, 1, lane_center, Some(&[STONE_BLOCK_SLAB])));
+        // Not floating beyond the pool at the end of its longest row
+        assert!(!editor.check_for_block(30, 1, lane_center, Some(&[STONE_BLOCK_SLAB])));
     }
 }
@@ -1,10 +1,12 @@
 use crate::args::Args;
 use crate::block_definitions::*;
 use crate::bresenham::bresenham_line;
-use crate::osm_parser::{ProcessedElement, ProcessedNode};
+use crate::osm_parser::{ProcessedElement, ProcessedNode, ProcessedWay};
+use crate::time_of_day::TimeOfDay;
 use crate::world_editor::WorldEditor;
+use std::collections::HashMap;
 
-pub fn generate_man_made(editor: &mut WorldEditor, element: &ProcessedElement, _args: &Args) {
+pub fn generate_man_made(editor: &mut WorldEditor, element: &ProcessedElement, args: &Args) {
     // Skip if 'layer' or 'level' is negative in the tags
     if let Some(layer) = element.tags().get("layer") {
         if layer.parse::<i32>().unwrap_or(0) < 0 {
@@ -26,6 +28,7 @@ pub fn generate_man_made(editor: &mut WorldEditor, element: &ProcessedElement, _
             "water_well" => generate_water_well(editor, element),
             "water_tower" => generate_water_tower(editor, element),
             "mast" => generate_antenna(editor, element),
+            "dyke" | "embankment" => generate_earthwork(editor, element, args),
             _ => {} // Unknown man_made type, ignore
         }
     }
@@ -39,12 +42,15 @@ fn generate_pier(editor: &mut WorldEditor, element: &ProcessedElement) {
             return;
         }
 
+        // Floating pontoons, such as the finger piers of a marina, are narrow and have no piles
+        let is_floating = element.tags().get("floating") == Some(&"yes".to_string());
+
         // Extract pier dimensions from tags
         let pier_width = element
             .tags()
             .get("width")
             .and_then(|w| w.parse::<i32>().ok())
-            .unwrap_or(3); // Default 3 blocks wide
+            .unwrap_or(if is_floating { 1 } else { 3 }); // Default 3 blocks wide
 
         let pier_height = 1; // Pier deck height above ground
         let support_spacing = 4; // Support pillars every 4 blocks
@@ -67,7 +73,7 @@ fn generate_pier(editor: &mut WorldEditor, element: &ProcessedElement) {
                 }
 
                 // Add support pillars every few blocks
-                if index % support_spacing == 0 {
+                if !is_floating && index % support_spacing == 0 {
                     let half_width = pier_width / 2;
 
                     // Place support pillars at the edges of the pier
@@ -79,11 +85,179 @@ fn generate_pier(editor: &mut WorldEditor, element: &ProcessedElement) {
                     for (pillar_x, pillar_z) in support_positions {
                         // Support pillars going down from pier level
                         editor.set_block(OAK_LOG, pillar_x, 0, *pillar_z, None, None);
+
+                        // Lights at the foot of the pillars mark the harbor edge at night
+                        if editor.time_of_day() == TimeOfDay::Night {
+                            editor.set_block(SEA_LANTERN, pillar_x, -1, *pillar_z, None, None);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Width of the ground filled in behind a retaining wall at its full height, in blocks
+const RETAINING_WALL_BACKFILL: f64 = 2.0;
+
+/// Cross section of an earth bank along a way. OSM draws embankments and retaining walls
+/// with their lower side on the right.
+struct BankProfile {
+    /// Height of the crest above the ground at the way, in blocks
+    height: i32,
+    /// Distance from the way over which the crest stays at its full height, left and right
+    crest: (f64, f64),
+    /// Blocks of distance per block of descent beyond the crest, left and right, None for
+    /// sides without any bank
+    run: (Option<f64>, Option<f64>),
+}
+
+impl BankProfile {
+    /// Returns how far the bank has descended below its crest at the given distance on one
+    /// side, None beyond its foot
+    fn drop_at(&self, distance: f64, is_right: bool) -> Option<i32> {
+        let (crest, run) = if is_right {
+            (self.crest.1, self.run.1)
+        } else {
+            (self.crest.0, self.run.0)
+        };
+        if distance <= crest {
+            return Some(0);
+        }
+        let drop = ((distance - crest) / run?).ceil() as i32;
+        (drop < self.height).then_some(drop)
+    }
+
+    /// Distance from the way up to which the bank reaches
+    fn reach(&self) -> i32 {
+        let side =
+            |crest: f64, run: Option<f64>| crest + run.map_or(0.0, |run| run * self.height as f64);
+        side(self.crest.0, self.run.0)
+            .max(side(self.crest.1, self.run.1))
+            .ceil() as i32
+    }
+}
+
+/// Returns the highest absolute top of the bank along the way for every column it covers.
+/// The crest rises the bank's height above the ground at the way, so that the bank follows
+/// the terrain and meets it at its foot.
+fn bank_columns(
+    editor: &WorldEditor,
+    nodes: &[ProcessedNode],
+    profile: &BankProfile,
+) -> HashMap<(i32, i32), i32> {
+    let reach = profile.reach();
+    let mut columns: HashMap<(i32, i32), i32> = HashMap::new();
+    for pair in nodes.windows(2) {
+        let (start, end) = (&pair[0], &pair[1]);
+        let (dir_x, dir_z) = ((end.x - start.x) as f64, (end.z - start.z) as f64);
+
+        for (cx, _, cz) in bresenham_line(start.x, 0, start.z, end.x, 0, end.z) {
+            let crest_y = editor.get_absolute_y(cx, profile.height, cz);
+            for dx in -reach..=reach {
+                for dz in -reach..=reach {
+                    let distance = ((dx * dx + dz * dz) as f64).sqrt();
+                    // Right of the way direction, with z pointing south
+                    let is_right = dx as f64 * -dir_z + dz as f64 * dir_x > 0.0;
+                    if let Some(drop) = profile.drop_at(distance, is_right) {
+                        let entry = columns.entry((cx + dx, cz + dz)).or_insert(i32::MIN);
+                        *entry = (*entry).max(crest_y - drop);
                     }
                 }
             }
         }
     }
+    columns
+}
+
+/// Fills the columns of a bank with earth from the ground up to their top
+fn raise_bank(editor: &mut WorldEditor, columns: &HashMap<(i32, i32), i32>) {
+    let mut columns: Vec<(&(i32, i32), &i32)> = columns.iter().collect();
+    columns.sort_unstable();
+    for (&(x, z), &top) in columns {
+        let ground = editor.get_absolute_y(x, 0, z);
+        if top <= ground {
+            continue;
+        }
+        for y in ground + 1..top {
+            editor.set_block_absolute(DIRT, x, y, z, None, None);
+        }
+        editor.set_block_absolute(GRASS_BLOCK, x, top, z, None, None);
+    }
+}
+
+/// Parses the height tag in meters and scales it to blocks
+fn earthwork_height(tags: &HashMap<String, String>, default_meters: f64, args: &Args) -> i32 {
+    let height_meters = tags
+        .get("height")
+        .and_then(|h| h.trim_end_matches('m').trim().parse::<f64>().ok())
+        .unwrap_or(default_meters);
+    ((height_meters * args.scale).round() as i32).clamp(1, 20)
+}
+
+/// Generate a dyke or embankment as a raised earthwork with a flat crest and gentle slopes
+fn generate_earthwork(editor: &mut WorldEditor, element: &ProcessedElement, args: &Args) {
+    let ProcessedElement::Way(way) = element else {
+        return;
+    };
+    if way.nodes.len() < 2 {
+        return;
+    }
+
+    // Dykes are taller and broader than ordinary embankments and slope down on both sides,
+    // embankments mostly towards their lower side
+    let is_dyke = element.tags().get("man_made") == Some(&"dyke".to_string());
+    let height = earthwork_height(element.tags(), if is_dyke { 5.0 } else { 3.0 }, args);
+    let crest = (if is_dyke { 2.0 } else { 1.0 }) * args.scale;
+    let profile = BankProfile {
+        height,
+        crest: (crest.round(), crest.round()),
+        run: if is_dyke {
+            (Some(2.0), Some(2.0))
+        } else {
+            (Some(1.0), Some(2.0))
+        },
+    };
+
+    let columns = bank_columns(editor, &way.nodes, &profile);
+    raise_bank(editor, &columns);
+}
+
+pub fn is_retaining_wall(way: &ProcessedWay) -> bool {
+    way.tags.get("barrier").map(String::as_str) == Some("retaining_wall")
+}
+
+/// Generate a retaining wall as a vertical wall holding back the raised ground on its left,
+/// with the lower ground on its right
+pub fn generate_retaining_wall(editor: &mut WorldEditor, way: &ProcessedWay, args: &Args) {
+    if way.nodes.len() < 2 {
+        return;
+    }
+    let height = earthwork_height(&way.tags, 2.0, args);
+    let wall_block = match way.tags.get("material").map(String::as_str) {
+        Some("concrete") => SMOOTH_STONE,
+        Some("brick") => BRICK,
+        _ => STONE_BRICKS,
+    };
+
+    let profile = BankProfile {
+        height,
+        crest: (RETAINING_WALL_BACKFILL * args.scale.max(1.0), 0.0),
+        run: (Some(2.0), None),
+    };
+    let columns = bank_columns(editor, &way.nodes, &profile);
+    raise_bank(editor, &columns);
+
+    for pair in way.nodes.windows(2) {
+        let (start, end) = (&pair[0], &pair[1]);
+        for (x, _, z) in bresenham_line(start.x, 0, start.z, end.x, 0, end.z) {
+            let ground = editor.get_absolute_y(x, 0, z);
+            let top = columns.get(&(x, z)).copied().unwrap_or(ground + height);
+            for y in ground + 1..=top {
+                editor.set_block_absolute(wall_block, x, y, z, Some(&[DIRT, GRASS_BLOCK]), None);
+            }
+        }
+    }
 }
 
 /// Generate an antenna/radio tower
@@ -254,3 +428,25 @@ pub fn generate_man_made_nodes(editor: &mut WorldEditor, node: &ProcessedNode) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retaining_wall_profile_drops_only_on_its_high_side() {
+        let profile = BankProfile {
+            height: 3,
+            crest: (2.0, 0.0),
+            run: (Some(2.0), None),
+        };
+        assert_eq!(profile.drop_at(2.0, false), Some(0));
+        assert_eq!(profile.drop_at(3.0, false), Some(1));
+        assert_eq!(profile.drop_at(6.0, false), Some(2));
+        // The foot of the slope meets the ground
+        assert_eq!(profile.drop_at(7.0, false), None);
+        assert_eq!(profile.drop_at(0.0, true), Some(0));
+        assert_eq!(profile.drop_at(1.0, true), None);
+        assert_eq!(profile.reach(), 8);
+    }
+}
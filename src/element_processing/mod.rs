@@ -2,15 +2,32 @@ pub mod amenities;
 pub mod barriers;
 pub mod bridges;
 pub mod buildings;
+pub mod bus_stops;
+pub mod caves;
+pub mod clearance;
+pub mod coastline;
 pub mod doors;
+pub mod golf;
+pub mod gorges;
 pub mod highways;
+pub mod land_water;
 pub mod landuse;
 pub mod leisure;
 pub mod man_made;
 pub mod natural;
+pub mod peaks;
+pub mod pitches;
+pub mod playgrounds;
+pub mod railway_stations;
 pub mod railways;
+pub mod street_signs;
 pub mod subprocessor;
 pub mod tourisms;
+pub mod trail_markers;
+pub mod transit_routes;
 pub mod tree;
+pub mod tunnels;
+pub mod volcanoes;
 pub mod water_areas;
+pub mod waterway_structures;
 pub mod waterways;
@@ -1,11 +1,42 @@
 use crate::args::Args;
 use crate::block_definitions::*;
 use crate::bresenham::bresenham_line;
+use crate::element_processing::landuse::generate_meadow_plants;
 use crate::element_processing::tree::Tree;
-use crate::floodfill::flood_fill_area;
-use crate::osm_parser::{ProcessedElement, ProcessedMemberRole, ProcessedRelation, ProcessedWay};
-use crate::world_editor::WorldEditor;
+use crate::element_rng::{element_rng, ElementRng};
+use crate::floodfill::{distances_within, flood_fill_area};
+use crate::ground_cover::value_noise;
+use crate::osm_parser::{ProcessedElement, ProcessedRelation, ProcessedWay};
+use crate::world_editor::{Biome, WorldEditor};
 use rand::Rng;
+use std::collections::HashSet;
+
+/// Height of cliffs without a height tag on worlds without elevation data, in blocks
+const DEFAULT_CLIFF_HEIGHT: i32 = 8;
+/// Lowest height of a cliff face above the terrain below it, in blocks
+const MIN_CLIFF_HEIGHT: i32 = 3;
+/// Distance from a cliff at which the terrain above and below it is sampled at scale 1
+const CLIFF_SAMPLE_DISTANCE: i32 = 4;
+/// Thickness of the rock of the cliff face at its full height, in blocks
+const CLIFF_THICKNESS: i32 = 2;
+/// Depth of the ice of glaciers below the surface, in blocks
+const GLACIER_DEPTH: i32 = 3;
+/// Distance between the crevasses of glaciers, in blocks
+const CREVASSE_SPACING: f64 = 40.0;
+/// Half the width of the noise band that forms a crevasse
+const CREVASSE_WIDTH: f64 = 0.025;
+/// Size of the snow fields on glaciers, in blocks
+const SNOW_PATCH_SIZE: f64 = 20.0;
+/// Size of the clumps of reeds in reedbeds, in blocks
+const REED_CLUMP_SIZE: f64 = 5.0;
+/// Size of the patches of moss in bogs, in blocks
+const BOG_PATCH_SIZE: f64 = 7.0;
+/// Horizontal distance over which a beach rises by one block at scale 1
+const BEACH_SLOPE: i32 = 4;
+/// Highest a beach rises above the ground level towards its landward edge
+const MAX_BEACH_RISE: i32 = 2;
+/// One in this many sandy beach columns near the water gets a turtle egg
+const TURTLE_EGG_RARITY: i32 = 600;
 
 pub fn generate_natural(editor: &mut WorldEditor, element: &ProcessedElement, args: &Args) {
     if let Some(natural_type) = element.tags().get("natural") {
@@ -14,7 +45,19 @@ pub fn generate_natural(editor: &mut WorldEditor, element: &ProcessedElement, ar
                 let x: i32 = node.x;
                 let z: i32 = node.z;
 
-                Tree::create(editor, (x, 1, z));
+                Tree::create_from_tags(editor, (x, 1, z), &node.tags);
+            }
+        } else if natural_type == "tree_row" {
+            if let ProcessedElement::Way(way) = element {
+                generate_tree_row(editor, way, args);
+            }
+        } else if natural_type == "cliff" {
+            if let ProcessedElement::Way(way) = element {
+                generate_cliff(editor, way, args);
+            }
+        } else if natural_type == "beach" {
+            if let ProcessedElement::Way(way) = element {
+                generate_beach(editor, way, args);
             }
         } else {
             let mut previous_node: Option<(i32, i32)> = None;
@@ -24,15 +67,9 @@ pub fn generate_natural(editor: &mut WorldEditor, element: &ProcessedElement, ar
 
             // Determine block type based on natural tag
             let block_type: Block = match natural_type.as_str() {
-                "scrub" | "grassland" | "wood" | "heath" | "tree_row" => GRASS_BLOCK,
+                "scrub" | "grassland" | "wood" | "heath" => GRASS_BLOCK,
                 "sand" | "dune" => SAND,
-                "beach" | "shoal" => {
-                    let surface = element.tags().get("natural").unwrap_or(&binding);
-                    match surface.as_str() {
-                        "gravel" => GRAVEL,
-                        _ => SAND,
-                    }
-                }
+                "shoal" => beach_surface(element.tags().get("surface").unwrap_or(&binding)),
                 "water" | "reef" => WATER,
                 "bare_rock" => STONE,
                 "blockfield" => COBBLESTONE,
@@ -41,7 +78,6 @@ pub fn generate_natural(editor: &mut WorldEditor, element: &ProcessedElement, ar
                 "mountain_range" => COBBLESTONE,
                 "saddle" | "ridge" => STONE,
                 "shrubbery" | "tundra" | "hill" => GRASS_BLOCK,
-                "cliff" => STONE,
                 _ => GRASS_BLOCK,
             };
 
@@ -79,18 +115,21 @@ pub fn generate_natural(editor: &mut WorldEditor, element: &ProcessedElement, ar
                 let filled_area: Vec<(i32, i32)> =
                     flood_fill_area(&polygon_coords, args.timeout.as_ref());
 
-                let mut rng: rand::prelude::ThreadRng = rand::thread_rng();
+                let mut rng: ElementRng = element_rng();
 
                 for (x, z) in filled_area {
                     editor.set_block(block_type, x, 0, z, None, None);
                     // Generate custom layer instead of dirt, must be stone on the lowest level
                     match natural_type.as_str() {
-                        "beach" | "sand" | "dune" | "shoal" => {
+                        "sand" | "dune" => {
                             editor.set_block(SAND, x, 0, z, None, None);
+                            editor.set_biome(x, z, Biome::Desert);
+                        }
+                        "wood" => {
+                            editor.set_biome(x, z, Biome::Forest);
                         }
                         "glacier" => {
-                            editor.set_block(PACKED_ICE, x, 0, z, None, None);
-                            editor.set_block(STONE, x, -1, z, None, None);
+                            generate_glacier_column(editor, x, z);
                         }
                         "bare_rock" => {
                             editor.set_block(STONE, x, 0, z, None, None);
@@ -107,9 +146,14 @@ pub fn generate_natural(editor: &mut WorldEditor, element: &ProcessedElement, ar
                             if !editor.check_for_block(x, 0, z, Some(&[GRASS_BLOCK])) {
                                 continue;
                             }
-                            if rng.gen_bool(0.6) {
-                                editor.set_block(GRASS, x, 1, z, None, None);
-                            }
+                            generate_meadow_plants(
+                                editor,
+                                x,
+                                z,
+                                "grassland",
+                                args.meadow_density,
+                                &mut rng,
+                            );
                         }
                         "heath" => {
                             if !editor.check_for_block(x, 0, z, Some(&[GRASS_BLOCK])) {
@@ -155,13 +199,13 @@ pub fn generate_natural(editor: &mut WorldEditor, element: &ProcessedElement, ar
                                 }
                             }
                         }
-                        "tree_row" | "wood" => {
+                        "wood" => {
                             if !editor.check_for_block(x, 0, z, Some(&[GRASS_BLOCK])) {
                                 continue;
                             }
                             let random_choice: i32 = rng.gen_range(0..30);
-                            if random_choice == 0 {
-                                Tree::create(editor, (x, 1, z));
+                            if Tree::is_forest_tree_spot(x, z, &mut rng) {
+                                Tree::create_in_forest(editor, (x, 1, z), element.tags());
                             } else if random_choice == 1 {
                                 let flower_block = match rng.gen_range(1..=4) {
                                     1 => RED_FLOWER,
@@ -187,74 +231,8 @@ pub fn generate_natural(editor: &mut WorldEditor, element: &ProcessedElement, ar
                             }
                         }
                         "wetland" => {
-                            if let Some(wetland_type) = element.tags().get("wetland") {
-                                // Wetland without water blocks
-                                if matches!(wetland_type.as_str(), "wet_meadow" | "fen") {
-                                    if rng.gen_bool(0.3) {
-                                        editor.set_block(GRASS_BLOCK, x, 0, z, Some(&[MUD]), None);
-                                    }
-                                    editor.set_block(GRASS, x, 1, z, None, None);
-                                    continue;
-                                }
-                                // All the other types of wetland
-                                if rng.gen_bool(0.3) {
-                                    editor.set_block(
-                                        WATER,
-                                        x,
-                                        0,
-                                        z,
-                                        Some(&[MUD, GRASS_BLOCK]),
-                                        None,
-                                    );
-                                    continue;
-                                }
-                                if !editor.check_for_block(x, 0, z, Some(&[MUD, MOSS_BLOCK])) {
-                                    continue;
-                                }
-                                match wetland_type.as_str() {
-                                    "reedbed" => {
-                                        editor.set_block(TALL_GRASS_BOTTOM, x, 1, z, None, None);
-                                        editor.set_block(TALL_GRASS_TOP, x, 2, z, None, None);
-                                    }
-                                    "swamp" | "mangrove" => {
-                                        // TODO implement mangrove
-                                        let random_choice: i32 = rng.gen_range(0..40);
-                                        if random_choice == 0 {
-                                            Tree::create(editor, (x, 1, z));
-                                        } else if random_choice < 35 {
-                                            editor.set_block(GRASS, x, 1, z, None, None);
-                                        }
-                                    }
-                                    "bog" => {
-                                        if rng.gen_bool(0.2) {
-                                            editor.set_block(
-                                                MOSS_BLOCK,
-                                                x,
-                                                0,
-                                                z,
-                                                Some(&[MUD]),
-                                                None,
-                                            );
-                                        }
-                                        if rng.gen_bool(0.15) {
-                                            editor.set_block(GRASS, x, 1, z, None, None);
-                                        }
-                                    }
-                                    "tidalflat" => {
-                                        continue; // No vegetation here
-                                    }
-                                    _ => {
-                                        editor.set_block(GRASS, x, 1, z, None, None);
-                                    }
-                                }
-                            } else {
-                                // Generic natural=wetland without wetland=... tag
-                                if rng.gen_bool(0.3) {
-                                    editor.set_block(WATER, x, 0, z, Some(&[MUD]), None);
-                                    continue;
-                                }
-                                editor.set_block(GRASS, x, 1, z, None, None);
-                            }
+                            let wetland = element.tags().get("wetland").map(String::as_str);
+                            generate_wetland_column(editor, x, z, wetland, &mut rng);
                         }
                         "mountain_range" => {
                             // Create block clusters instead of random placement
@@ -273,11 +251,11 @@ pub fn generate_natural(editor: &mut WorldEditor, element: &ProcessedElement, ar
                                 };
 
                                 // Generate cluster size (5-10 blocks radius)
-                                let cluster_size = rng.gen_range(5..=10);
+                                let cluster_size: i32 = rng.gen_range(5..=10);
 
                                 // Create cluster around current position
-                                for dx in -(cluster_size as i32)..=(cluster_size as i32) {
-                                    for dz in -(cluster_size as i32)..=(cluster_size as i32) {
+                                for dx in -cluster_size..=cluster_size {
+                                    for dz in -cluster_size..=cluster_size {
                                         let cluster_x = x + dx;
                                         let cluster_z = z + dz;
 
@@ -444,38 +422,274 @@ pub fn generate_natural(editor: &mut WorldEditor, element: &ProcessedElement, ar
     }
 }
 
-pub fn generate_natural_from_relation(
+/// Builds a vertical rock face along a cliff. Following the way, the top of the cliff lies on
+/// its left. With elevation data the face reaches from the terrain below to the terrain above,
+/// on flat worlds it rises by the tagged or default height and the rock falls back behind it.
+fn generate_cliff(editor: &mut WorldEditor, way: &ProcessedWay, args: &Args) {
+    let tagged_height = way
+        .tags
+        .get("height")
+        .and_then(|height| height.trim_end_matches('m').trim().parse::<f64>().ok())
+        .map(|height| ((height * args.scale).round() as i32).max(1));
+    let elevation_enabled = editor
+        .get_ground()
+        .is_some_and(|ground| ground.elevation_enabled);
+    let default_height = ((DEFAULT_CLIFF_HEIGHT as f64 * args.scale).round() as i32).max(2);
+    let sample_distance = (CLIFF_SAMPLE_DISTANCE as f64 * args.scale).round().max(2.0);
+
+    for pair in way.nodes.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        let (dx, dz) = ((b.x - a.x) as f64, (b.z - a.z) as f64);
+        let length = (dx * dx + dz * dz).sqrt();
+        if length == 0.0 {
+            continue;
+        }
+        // Left of the direction of the way, towards the top
+        let (nx, nz) = (dz / length, -dx / length);
+
+        for (x, _, z) in bresenham_line(a.x, 0, a.z, b.x, 0, b.z) {
+            let towards_top = |distance: f64| {
+                (
+                    (x as f64 + nx * distance).round() as i32,
+                    (z as f64 + nz * distance).round() as i32,
+                )
+            };
+            let (low_x, low_z) = towards_top(-sample_distance);
+            let (high_x, high_z) = towards_top(sample_distance);
+            let low = editor.get_absolute_y(low_x, 0, low_z);
+
+            let top = match tagged_height {
+                Some(height) => low + height,
+                None if elevation_enabled => editor
+                    .get_absolute_y(high_x, 0, high_z)
+                    .max(low + MIN_CLIFF_HEIGHT),
+                None => low + default_height,
+            };
+
+            // The face, then rock falling back by one block per column until it meets the
+            // terrain above
+            for distance in 0..=(top - low + CLIFF_THICKNESS) {
+                let column_top = top - (distance - CLIFF_THICKNESS + 1).max(0);
+                let (column_x, column_z) = towards_top(distance as f64);
+                let ground = editor.get_absolute_y(column_x, 0, column_z);
+                if column_top <= ground {
+                    break;
+                }
+                for y in ground..=column_top {
+                    editor.set_block_absolute(STONE, column_x, y, column_z, None, None);
+                }
+                if distance >= CLIFF_THICKNESS {
+                    editor.set_block_absolute(
+                        GRASS_BLOCK,
+                        column_x,
+                        column_top,
+                        column_z,
+                        Some(&[STONE]),
+                        None,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Covers one column of a glacier with snow or packed ice, crossed by streaks of blue ice
+/// where crevasses open
+fn generate_glacier_column(editor: &mut WorldEditor, x: i32, z: i32) {
+    // Thin bands along one contour of the noise wind across the glacier like crevasses
+    let crevasse = (value_noise(x, z, CREVASSE_SPACING) - 0.5).abs() < CREVASSE_WIDTH;
+    let (surface, body) = if crevasse {
+        (BLUE_ICE, BLUE_ICE)
+    } else if value_noise(x + 5000, z - 5000, SNOW_PATCH_SIZE) > 0.45 {
+        (SNOW_BLOCK, PACKED_ICE)
+    } else {
+        (PACKED_ICE, PACKED_ICE)
+    };
+
+    editor.set_block(surface, x, 0, z, Some(&[PACKED_ICE]), None);
+    for y in -GLACIER_DEPTH..0 {
+        editor.set_block(body, x, y, z, None, None);
+    }
+}
+
+/// Share of the columns of a type of wetland that are shallow pools
+fn wetland_water_share(wetland: Option<&str>) -> f64 {
+    match wetland {
+        Some("wet_meadow" | "fen") => 0.0,
+        Some("bog") => 0.1,
+        Some("reedbed" | "mangrove") => 0.4,
+        _ => 0.3,
+    }
+}
+
+/// Covers one column of a wetland with the pools, ground and plants of its type
+fn generate_wetland_column(
     editor: &mut WorldEditor,
-    rel: &ProcessedRelation,
-    args: &Args,
+    x: i32,
+    z: i32,
+    wetland: Option<&str>,
+    rng: &mut ElementRng,
 ) {
-    if rel.tags.contains_key("natural") {
-        // Generate individual ways with their original tags
-        for member in &rel.members {
-            if member.role == ProcessedMemberRole::Outer {
-                generate_natural(editor, &ProcessedElement::Way(member.way.clone()), args);
-            }
+    if rng.gen_bool(wetland_water_share(wetland)) {
+        editor.set_block(WATER, x, 0, z, Some(&[MUD, GRASS_BLOCK]), None);
+        return;
+    }
+
+    // Wetland without water blocks
+    if matches!(wetland, Some("wet_meadow" | "fen")) {
+        if rng.gen_bool(0.3) {
+            editor.set_block(GRASS_BLOCK, x, 0, z, Some(&[MUD]), None);
         }
+        editor.set_block(GRASS, x, 1, z, None, None);
+        return;
+    }
 
-        // Combine all outer ways into one with relation tags
-        let mut combined_nodes = Vec::new();
-        for member in &rel.members {
-            if member.role == ProcessedMemberRole::Outer {
-                combined_nodes.extend(member.way.nodes.clone());
+    if !editor.check_for_block(x, 0, z, Some(&[MUD])) {
+        return;
+    }
+    match wetland {
+        Some("reedbed") => {
+            // Clumps of reeds between the pools, tall grass elsewhere
+            if value_noise(x, z, REED_CLUMP_SIZE) > 0.55 {
+                for y in 1..=rng.gen_range(1..=3) {
+                    editor.set_block(SUGAR_CANE, x, y, z, None, None);
+                }
+            } else if rng.gen_bool(0.3) {
+                editor.set_block(TALL_GRASS_BOTTOM, x, 1, z, None, None);
+                editor.set_block(TALL_GRASS_TOP, x, 2, z, None, None);
             }
         }
-
-        // Only process if we have nodes
-        if !combined_nodes.is_empty() {
-            // Create combined way with relation tags
-            let combined_way = ProcessedWay {
-                id: rel.id,
-                nodes: combined_nodes,
-                tags: rel.tags.clone(),
+        Some("swamp" | "mangrove") => {
+            let random_choice: i32 = rng.gen_range(0..40);
+            if random_choice == 0 {
+                Tree::create_mangrove(editor, (x, 1, z));
+            } else if random_choice < 35 {
+                editor.set_block(GRASS, x, 1, z, None, None);
+            }
+        }
+        Some("bog") => {
+            // Patches of moss on podzol, with little grass
+            let ground = if value_noise(x, z, BOG_PATCH_SIZE) > 0.5 {
+                MOSS_BLOCK
+            } else {
+                PODZOL
             };
+            editor.set_block(ground, x, 0, z, Some(&[MUD]), None);
+            if rng.gen_bool(0.1) {
+                editor.set_block(GRASS, x, 1, z, None, None);
+            }
+        }
+        // No vegetation on tidal flats
+        Some("tidalflat") => {}
+        _ => {
+            editor.set_block(GRASS, x, 1, z, None, None);
+        }
+    }
+}
+
+/// Returns true for beaches, which are generated once the ocean next to them is filled
+pub fn is_beach(way: &ProcessedWay) -> bool {
+    way.tags.get("natural").map(String::as_str) == Some("beach")
+}
+
+/// Returns the block of a beach or shoal with the given surface tag
+fn beach_surface(surface: &str) -> Block {
+    match surface {
+        "gravel" | "fine_gravel" | "pebblestone" | "pebbles" | "shingle" | "rock" => GRAVEL,
+        _ => SAND,
+    }
+}
 
-            // Generate natural area from combined way
-            generate_natural(editor, &ProcessedElement::Way(combined_way), args);
+/// Covers a beach in sand or gravel that rises gently from the water towards its landward
+/// edge, with a few turtle eggs on sandy beaches
+pub fn generate_beach(editor: &mut WorldEditor, way: &ProcessedWay, args: &Args) {
+    let surface = beach_surface(way.tags.get("surface").map_or("", String::as_str));
+
+    let polygon: Vec<(i32, i32)> = way.nodes.iter().map(|n| (n.x, n.z)).collect();
+    let mut area: HashSet<(i32, i32)> = flood_fill_area(&polygon, args.timeout.as_ref())
+        .into_iter()
+        .collect();
+    for pair in polygon.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        area.extend(
+            bresenham_line(a.0, 0, a.1, b.0, 0, b.1)
+                .into_iter()
+                .map(|(x, _, z)| (x, z)),
+        );
+    }
+
+    // Parts of the beach below the water line stay under water
+    let is_water =
+        |editor: &WorldEditor, x: i32, z: i32| editor.check_for_block(x, 0, z, Some(&[WATER]));
+    let land: HashSet<(i32, i32)> = area
+        .iter()
+        .copied()
+        .filter(|&(x, z)| !is_water(editor, x, z))
+        .collect();
+
+    let neighbours = |(x, z): (i32, i32)| [(x + 1, z), (x - 1, z), (x, z + 1), (x, z - 1)];
+    let shore = land.iter().copied().filter(|&cell| {
+        neighbours(cell)
+            .iter()
+            .any(|&(x, z)| is_water(editor, x, z))
+    });
+    let water_distance = distances_within(&land, shore);
+    let edge = land.iter().copied().filter(|&cell| {
+        neighbours(cell)
+            .iter()
+            .any(|&(x, z)| !land.contains(&(x, z)) && !is_water(editor, x, z))
+    });
+    let edge_distance = distances_within(&land, edge);
+
+    let slope = ((BEACH_SLOPE as f64 * args.scale).round() as i32).max(1);
+    let mut rng: ElementRng = element_rng();
+
+    let mut cells: Vec<(i32, i32)> = land.iter().copied().collect();
+    cells.sort_unstable();
+    for (x, z) in cells {
+        let from_water = water_distance.get(&(x, z)).copied();
+        let from_edge = edge_distance.get(&(x, z)).copied().unwrap_or(i32::MAX);
+        // Beaches away from any water stay flat
+        let rise = from_water.map_or(0, |from_water| {
+            (from_water.min(from_edge) / slope).min(MAX_BEACH_RISE)
+        });
+
+        editor.set_block(surface, x, -1, z, Some(&[DIRT, GRASS_BLOCK]), None);
+        editor.set_biome(x, z, Biome::Beach);
+        for y in 0..=rise {
+            editor.set_block(surface, x, y, z, Some(&[SAND, DIRT, GRASS_BLOCK]), None);
+        }
+
+        let near_water = from_water.is_some_and(|d| (2..=slope * 2).contains(&d));
+        if surface == SAND && near_water && rng.gen_range(0..TURTLE_EGG_RARITY) == 0 {
+            editor.set_block(TURTLE_EGG, x, rise + 1, z, None, None);
+        }
+    }
+}
+
+/// Plants the trees of a tree row along its way
+fn generate_tree_row(editor: &mut WorldEditor, way: &ProcessedWay, args: &Args) {
+    let mut line: Vec<(i32, i32)> = Vec::new();
+    for pair in way.nodes.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        for (x, _, z) in bresenham_line(a.x, 0, a.z, b.x, 0, b.z) {
+            // Segments share their end points
+            if line.last() != Some(&(x, z)) {
+                line.push((x, z));
+            }
+        }
+    }
+    Tree::create_row(editor, &line, &way.tags, args.scale);
+}
+
+pub fn generate_natural_from_relation(
+    editor: &mut WorldEditor,
+    rel: &ProcessedRelation,
+    args: &Args,
+) {
+    if rel.tags.contains_key("natural") {
+        for way in rel.outer_ways() {
+            generate_natural(editor, &ProcessedElement::Way(way), args);
         }
     }
 }
@@ -0,0 +1,102 @@
+use crate::args::Args;
+use crate::block_definitions::*;
+use crate::element_rng::{element_rng, ElementRng};
+use crate::ground::parse_ele;
+use crate::osm_parser::ProcessedNode;
+use crate::world_editor::WorldEditor;
+use rand::Rng;
+
+/// Prominence in meters from which a peak counts as a major one
+const MAJOR_PEAK_PROMINENCE: f64 = 300.0;
+/// Elevation in meters from which a peak without a mapped prominence counts as a major one
+const MAJOR_PEAK_ELEVATION: f64 = 3000.0;
+
+pub fn is_peak(node: &ProcessedNode) -> bool {
+    node.tags.get("natural").map(String::as_str) == Some("peak")
+}
+
+/// Marks a summit with a cairn, a sign with its name and elevation, and with `--peak-beacons`
+/// a beacon on major peaks
+pub fn generate_peak(editor: &mut WorldEditor, node: &ProcessedNode, args: &Args) {
+    let mut rng: ElementRng = element_rng();
+    let (x, z) = (node.x, node.z);
+    let ele = node.tags.get("ele").and_then(|ele| parse_ele(ele));
+
+    let mut stone = || {
+        if rng.gen_bool(0.3) {
+            MOSSY_COBBLESTONE
+        } else {
+            COBBLESTONE
+        }
+    };
+
+    if args.peak_beacons && is_major_peak(node, ele) {
+        // The beacon shines from a ring of stones on its iron base
+        for dx in -1..=1 {
+            for dz in -1..=1 {
+                editor.set_block(IRON_BLOCK, x + dx, 0, z + dz, None, None);
+                if (dx, dz) != (0, 0) {
+                    editor.set_block(stone(), x + dx, 1, z + dz, None, None);
+                }
+            }
+        }
+        editor.set_block(BEACON, x, 1, z, None, None);
+    } else {
+        for (dx, dz) in [(0, 0), (1, 0), (-1, 0), (0, 1), (0, -1)] {
+            editor.set_block(stone(), x + dx, 1, z + dz, None, None);
+        }
+        editor.set_block(stone(), x, 2, z, None, None);
+        editor.set_block(COBBLESTONE_WALL, x, 3, z, None, None);
+    }
+
+    let name = node.tags.get("name").cloned().unwrap_or_default();
+    if name.is_empty() && ele.is_none() {
+        return;
+    }
+    let elevation = ele.map_or(String::new(), |ele| format!("{} m", ele.round()));
+    editor.set_block(OAK_FENCE, x + 2, 1, z, None, None);
+    editor.set_sign(
+        name,
+        elevation,
+        String::new(),
+        String::new(),
+        x + 2,
+        2,
+        z,
+        0,
+    );
+}
+
+/// Checks whether a peak stands out enough for a beacon
+fn is_major_peak(node: &ProcessedNode, ele: Option<f64>) -> bool {
+    match node.tags.get("prominence").and_then(|p| parse_ele(p)) {
+        Some(prominence) => prominence >= MAJOR_PEAK_PROMINENCE,
+        None => ele.is_some_and(|ele| ele >= MAJOR_PEAK_ELEVATION),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn peak(tags: &[(&str, &str)]) -> ProcessedNode {
+        ProcessedNode {
+            id: 1,
+            tags: tags
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>(),
+            x: 0,
+            z: 0,
+        }
+    }
+
+    #[test]
+    fn test_major_peaks_by_prominence_or_elevation() {
+        assert!(is_major_peak(&peak(&[("prominence", "450")]), Some(1200.0)));
+        assert!(!is_major_peak(&peak(&[("prominence", "80")]), Some(3500.0)));
+        assert!(is_major_peak(&peak(&[]), Some(3500.0)));
+        assert!(!is_major_peak(&peak(&[]), None));
+    }
+}
@@ -0,0 +1,312 @@
+//! Pitches for soccer, tennis, basketball and athletics, with their painted lines and their
+//! goals, hoops or net. The markings are laid out in the frame of the pitch, which runs along
+//! the longest side of its outline, and are sized by the shares of a standard pitch.
+
+use crate::args::Args;
+use crate::block_definitions::*;
+use crate::bresenham::bresenham_line;
+use crate::element_processing::landuse::long_axis;
+use crate::element_processing::playgrounds::surface_block;
+use crate::floodfill::flood_fill_area;
+use crate::osm_parser::ProcessedWay;
+use crate::world_editor::WorldEditor;
+use std::collections::HashSet;
+
+/// Block of the painted lines
+const LINE: Block = WHITE_CONCRETE;
+/// Width of a lane of running tracks, in blocks
+const LANE_WIDTH: f64 = 2.0;
+/// Number of lanes of running tracks wide enough for them
+const TRACK_LANES: i32 = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Sport {
+    Soccer,
+    Tennis,
+    Basketball,
+    Athletics,
+}
+
+impl Sport {
+    fn from_tags(way: &ProcessedWay) -> Option<Self> {
+        let sports: Vec<&str> = way.tags.get("sport")?.split(';').map(str::trim).collect();
+        // Running tracks enclose the field played on inside them
+        if sports.contains(&"athletics") {
+            return Some(Sport::Athletics);
+        }
+        sports.iter().find_map(|sport| match *sport {
+            "soccer" => Some(Sport::Soccer),
+            "tennis" => Some(Sport::Tennis),
+            "basketball" => Some(Sport::Basketball),
+            _ => None,
+        })
+    }
+
+    /// Ground of the pitch without a surface tag
+    fn default_surface(self) -> Block {
+        match self {
+            Sport::Soccer => GRASS_BLOCK,
+            Sport::Tennis => GREEN_STAINED_HARDENED_CLAY,
+            Sport::Basketball => ORANGE_TERRACOTTA,
+            Sport::Athletics => RED_TERRACOTTA,
+        }
+    }
+}
+
+/// Returns true for pitches of a sport with markings
+pub fn is_marked_pitch(way: &ProcessedWay) -> bool {
+    Sport::from_tags(way).is_some()
+}
+
+/// Position of blocks relative to the middle of a pitch, along and across its length
+struct PitchFrame {
+    axis: (f64, f64),
+    middle: (f64, f64),
+    half_length: f64,
+    half_width: f64,
+}
+
+impl PitchFrame {
+    fn new(cells: &[(i32, i32)], axis: (f64, f64)) -> Self {
+        let frame = Self::fitted(cells, axis);
+        // The pitch runs along its longer extent
+        if frame.half_width > frame.half_length {
+            Self::fitted(cells, (-axis.1, axis.0))
+        } else {
+            frame
+        }
+    }
+
+    fn fitted(cells: &[(i32, i32)], axis: (f64, f64)) -> Self {
+        let projected: Vec<(f64, f64)> = cells
+            .iter()
+            .map(|&(x, z)| project(axis, x as f64, z as f64))
+            .collect();
+        let min_u = projected.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+        let max_u = projected
+            .iter()
+            .map(|p| p.0)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let min_v = projected.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+        let max_v = projected
+            .iter()
+            .map(|p| p.1)
+            .fold(f64::NEG_INFINITY, f64::max);
+        PitchFrame {
+            axis,
+            middle: ((min_u + max_u) / 2.0, (min_v + max_v) / 2.0),
+            half_length: (max_u - min_u) / 2.0,
+            half_width: (max_v - min_v) / 2.0,
+        }
+    }
+
+    /// Returns the position of a block along and across the pitch from its middle
+    fn local(&self, x: i32, z: i32) -> (f64, f64) {
+        let (u, v) = project(self.axis, x as f64, z as f64);
+        (u - self.middle.0, v - self.middle.1)
+    }
+
+    /// Returns the block at the given position along and across the pitch from its middle
+    fn block(&self, du: f64, dv: f64) -> (i32, i32) {
+        let (u, v) = (du + self.middle.0, dv + self.middle.1);
+        let (axis_x, axis_z) = self.axis;
+        (
+            (u * axis_x - v * axis_z).round() as i32,
+            (u * axis_z + v * axis_x).round() as i32,
+        )
+    }
+
+    /// Places a line of blocks across the pitch at the given distance along it
+    fn place_across(
+        &self,
+        editor: &mut WorldEditor,
+        block: Block,
+        du: f64,
+        half_width: f64,
+        heights: std::ops::RangeInclusive<i32>,
+    ) {
+        let (x1, z1) = self.block(du, -half_width);
+        let (x2, z2) = self.block(du, half_width);
+        for (x, _, z) in bresenham_line(x1, 0, z1, x2, 0, z2) {
+            for y in heights.clone() {
+                editor.set_block(block, x, y, z, None, None);
+            }
+        }
+    }
+}
+
+/// Position of a point along and across the given axis
+fn project((axis_x, axis_z): (f64, f64), x: f64, z: f64) -> (f64, f64) {
+    (x * axis_x + z * axis_z, z * axis_x - x * axis_z)
+}
+
+/// Returns true if the value lies on a line of one block at the given distance
+fn on_line(value: f64, line: f64) -> bool {
+    (value - line).abs() < 0.5
+}
+
+/// Returns the ground block at the given position from the middle of a pitch
+fn ground_block(sport: Sport, frame: &PitchFrame, du: f64, dv: f64, surface: Block) -> Block {
+    let (hl, hw) = (frame.half_length, frame.half_width);
+    let (u, v) = (du.abs(), dv.abs());
+    let is_marking = match sport {
+        Sport::Soccer => {
+            // Penalty and goal areas by their depth and half width as shares of the pitch
+            let area_lines = |depth: f64, width: f64| {
+                (on_line(u, hl * (1.0 - depth)) && v <= hw * width)
+                    || (on_line(v, hw * width) && u >= hl * (1.0 - depth))
+            };
+            on_line(u, 0.0)
+                || on_line(du.hypot(dv), 0.27 * hw)
+                || area_lines(0.31, 0.59)
+                || area_lines(0.1, 0.27)
+        }
+        Sport::Tennis => {
+            // The court lies in the middle of its run-off area
+            let (court_length, court_width) = (0.66 * hl, 0.61 * hw);
+            let (service_line, singles_width) = (0.54 * court_length, 0.75 * court_width);
+            (on_line(u, court_length) && v <= court_width)
+                || ((on_line(v, court_width) || on_line(v, singles_width)) && u <= court_length)
+                || (on_line(u, service_line) && v <= singles_width)
+                || (on_line(dv, 0.0) && u <= service_line)
+        }
+        Sport::Basketball => {
+            let basket = 0.89 * hl;
+            on_line(u, 0.0)
+                || on_line(du.hypot(dv), 0.24 * hw)
+                || (on_line(u, 0.59 * hl) && v <= 0.33 * hw)
+                || (on_line(v, 0.33 * hw) && u >= 0.59 * hl)
+                || (u < basket && on_line((basket - u).hypot(v), 0.9 * hw))
+                || (u >= basket && on_line(v, 0.88 * hw))
+        }
+        Sport::Athletics => {
+            // Distance from the middle line of the oval, whose bends are half circles
+            let straight = (hl - hw).max(0.0);
+            let distance = if u <= straight {
+                v
+            } else {
+                (u - straight).hypot(v)
+            };
+            let lanes = TRACK_LANES.min((hw / 3.0 / LANE_WIDTH) as i32).max(1);
+            let inner_edge = hw - lanes as f64 * LANE_WIDTH;
+            if distance < inner_edge - 0.5 || distance > hw + 0.5 {
+                return GRASS_BLOCK;
+            }
+            (0..=lanes).any(|lane| on_line(distance, hw - lane as f64 * LANE_WIDTH))
+                || (dv > 0.0 && on_line(du, straight))
+        }
+    };
+
+    if is_marking {
+        LINE
+    } else {
+        surface
+    }
+}
+
+pub fn generate_pitch(editor: &mut WorldEditor, way: &ProcessedWay, args: &Args) {
+    let Some(sport) = Sport::from_tags(way) else {
+        return;
+    };
+    let polygon: Vec<(i32, i32)> = way.nodes.iter().map(|n| (n.x, n.z)).collect();
+    let mut area: HashSet<(i32, i32)> = flood_fill_area(&polygon, args.timeout.as_ref())
+        .into_iter()
+        .collect();
+    for pair in polygon.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        for (x, _, z) in bresenham_line(a.0, 0, a.1, b.0, 0, b.1) {
+            area.insert((x, z));
+        }
+    }
+    if area.is_empty() {
+        return;
+    }
+    let mut cells: Vec<(i32, i32)> = area.iter().copied().collect();
+    cells.sort_unstable();
+
+    let frame = PitchFrame::new(&cells, long_axis(way));
+    let surface = if way.tags.contains_key("surface") {
+        surface_block(&way.tags)
+    } else {
+        sport.default_surface()
+    };
+    // Soccer and basketball are played up to the lines along the outline
+    let has_boundary_lines = matches!(sport, Sport::Soccer | Sport::Basketball);
+
+    for &(x, z) in &cells {
+        let is_edge = [(x + 1, z), (x - 1, z), (x, z + 1), (x, z - 1)]
+            .iter()
+            .any(|cell| !area.contains(cell));
+        let block = if is_edge && has_boundary_lines {
+            LINE
+        } else {
+            let (du, dv) = frame.local(x, z);
+            ground_block(sport, &frame, du, dv, surface)
+        };
+        editor.set_block(block, x, 0, z, Some(&[GRASS_BLOCK]), None);
+    }
+
+    let (hl, hw) = (frame.half_length, frame.half_width);
+    match sport {
+        Sport::Soccer => {
+            // Goals on the goal lines with a net behind them
+            let goal_width = (0.11 * hw).max(2.0);
+            for end in [-1.0, 1.0] {
+                for post in [-goal_width, goal_width] {
+                    let (x, z) = frame.block(end * hl, post);
+                    for y in 1..=2 {
+                        editor.set_block(WHITE_CONCRETE, x, y, z, None, None);
+                    }
+                }
+                frame.place_across(editor, WHITE_CONCRETE, end * hl, goal_width, 3..=3);
+                frame.place_across(editor, IRON_BARS, end * (hl + 1.0), goal_width, 1..=2);
+            }
+        }
+        Sport::Tennis => {
+            // Net posts just outside the doubles lines
+            let net_width = 0.61 * hw + 1.0;
+            for post in [-net_width, net_width] {
+                let (x, z) = frame.block(0.0, post);
+                for y in 1..=2 {
+                    editor.set_block(OAK_FENCE, x, y, z, None, None);
+                }
+            }
+            frame.place_across(editor, IRON_BARS, 0.0, net_width, 1..=1);
+        }
+        Sport::Basketball => {
+            // Poles behind the end lines holding a backboard and the rim in front of it
+            for end in [-1.0, 1.0] {
+                let (x, z) = frame.block(end * (hl + 1.0), 0.0);
+                for y in 1..=4 {
+                    editor.set_block(IRON_BARS, x, y, z, None, None);
+                }
+                frame.place_across(editor, WHITE_CONCRETE, end * hl, 1.0, 4..=5);
+                let (x, z) = frame.block(end * (hl - 1.0), 0.0);
+                editor.set_block(IRON_BARS, x, 4, z, None, None);
+            }
+        }
+        Sport::Athletics => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_soccer_markings() {
+        let frame = PitchFrame {
+            axis: (1.0, 0.0),
+            middle: (0.0, 0.0),
+            half_length: 50.0,
+            half_width: 32.0,
+        };
+        let block_at = |du: f64, dv: f64| ground_block(Sport::Soccer, &frame, du, dv, GRASS_BLOCK);
+
+        // Halfway line, centre circle and the front of a penalty area
+        assert_eq!(block_at(0.0, 20.0), LINE);
+        assert_eq!(block_at(8.6, 0.0), LINE);
+        assert_eq!(block_at(34.5, 5.0), LINE);
+        assert_eq!(block_at(20.0, 10.0), GRASS_BLOCK);
+    }
+}
@@ -0,0 +1,179 @@
+//! Playground equipment, placed for mapped `playground=*` nodes and scattered over playground
+//! areas. Every piece of equipment stands on a soft surface.
+
+use crate::block_definitions::*;
+use crate::osm_parser::ProcessedNode;
+use crate::world_editor::WorldEditor;
+use std::collections::HashMap;
+
+/// Distance around a mapped piece of equipment covered by the soft surface, in blocks
+const SURFACE_RADIUS: i32 = 3;
+/// Distance between the swing and the slide of playgrounds mapped as a point, in blocks
+const POINT_PLAYGROUND_SPACING: i32 = 6;
+
+/// Pieces of playground equipment with a prefab
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Equipment {
+    Swing,
+    Slide,
+    Sandpit,
+    ClimbingFrame,
+}
+
+impl Equipment {
+    pub const ALL: [Equipment; 4] = [
+        Equipment::Swing,
+        Equipment::Slide,
+        Equipment::Sandpit,
+        Equipment::ClimbingFrame,
+    ];
+
+    /// Returns the equipment for a value of the playground tag
+    fn from_tag(value: &str) -> Option<Self> {
+        match value {
+            "swing" | "basketswing" | "baby_swing" => Some(Equipment::Swing),
+            "slide" => Some(Equipment::Slide),
+            "sandpit" => Some(Equipment::Sandpit),
+            "climbingframe" | "climbing_frame" | "structure" => Some(Equipment::ClimbingFrame),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the ground block of playgrounds, pitches and similar areas by their surface tag
+pub fn surface_block(tags: &HashMap<String, String>) -> Block {
+    match tags.get("surface").map(String::as_str) {
+        Some("clay") => TERRACOTTA,
+        Some("sand") => SAND,
+        Some("tartan") => RED_TERRACOTTA,
+        Some("grass") => GRASS_BLOCK,
+        Some("dirt") => DIRT,
+        Some("pebblestone" | "cobblestone" | "unhewn_cobblestone") => COBBLESTONE,
+        _ => GREEN_STAINED_HARDENED_CLAY,
+    }
+}
+
+/// Returns true for nodes of playground equipment and playgrounds mapped as a point
+pub fn is_playground_node(node: &ProcessedNode) -> bool {
+    node.tags.contains_key("playground")
+        || node.tags.get("leisure").map(String::as_str) == Some("playground")
+}
+
+pub fn generate_playground_node(editor: &mut WorldEditor, node: &ProcessedNode) {
+    let pieces: Vec<(Equipment, i32)> = match node.tags.get("playground") {
+        Some(value) => Equipment::from_tag(value)
+            .into_iter()
+            .map(|equipment| (equipment, 0))
+            .collect(),
+        // A playground mapped as a point gets a swing and a slide
+        None => vec![
+            (Equipment::Swing, -POINT_PLAYGROUND_SPACING / 2),
+            (Equipment::Slide, POINT_PLAYGROUND_SPACING / 2),
+        ],
+    };
+
+    let surface = surface_block(&node.tags);
+    for &(_, offset) in &pieces {
+        for dx in -SURFACE_RADIUS..=SURFACE_RADIUS {
+            for dz in -SURFACE_RADIUS..=SURFACE_RADIUS {
+                let (x, z) = (node.x + dx, node.z + offset + dz);
+                editor.set_block(surface, x, 0, z, Some(&[GRASS_BLOCK]), None);
+            }
+        }
+    }
+    for (equipment, offset) in pieces {
+        place_equipment(editor, equipment, node.x, node.z + offset, surface);
+    }
+}
+
+/// Places a piece of equipment around the given position, lined up along the x axis
+pub fn place_equipment(
+    editor: &mut WorldEditor,
+    equipment: Equipment,
+    x: i32,
+    z: i32,
+    surface: Block,
+) {
+    match equipment {
+        Equipment::Swing => {
+            // Two posts carrying a beam with two seats on chains
+            for y in 1..=3 {
+                editor.set_block(OAK_FENCE, x - 2, y, z, None, None);
+                editor.set_block(OAK_FENCE, x + 2, y, z, None, None);
+            }
+            for dx in -2..=2 {
+                editor.set_block(OAK_PLANKS, x + dx, 4, z, None, None);
+            }
+            for seat_x in [x - 1, x + 1] {
+                editor.set_block(CHAIN, seat_x, 3, z, None, None);
+                editor.set_block(STONE_BLOCK_SLAB, seat_x, 2, z, None, None);
+            }
+        }
+        Equipment::Slide => {
+            // A ladder up a small tower, and stairs sliding down towards +x
+            for y in 1..=2 {
+                editor.set_block(OAK_PLANKS, x, y, z, None, None);
+                editor.set_block(LADDER, x, y, z - 1, None, None);
+            }
+            editor.set_block(OAK_FENCE, x, 3, z + 1, None, None);
+            for (dx, y) in [(1, 2), (2, 1)] {
+                let stair_y = editor.get_absolute_y(x + dx, y, z);
+                editor.set_block_with_properties_absolute(
+                    create_stair_with_properties(
+                        SMOOTH_SANDSTONE_STAIRS,
+                        StairFacing::West,
+                        StairShape::Straight,
+                    ),
+                    x + dx,
+                    stair_y,
+                    z,
+                    None,
+                    None,
+                );
+            }
+        }
+        Equipment::Sandpit => {
+            // Sand framed by a low wooden rim
+            for dx in -3i32..=3 {
+                for dz in -3i32..=3 {
+                    if dx.abs() == 3 || dz.abs() == 3 {
+                        editor.set_block(OAK_SLAB, x + dx, 1, z + dz, None, None);
+                    } else {
+                        editor.set_block(SAND, x + dx, 0, z + dz, Some(&[surface]), None);
+                    }
+                }
+            }
+        }
+        Equipment::ClimbingFrame => {
+            // A lattice of scaffolding to climb around in
+            for dx in -1..=1 {
+                for dz in -1..=1 {
+                    for y in 1..=3 {
+                        if (dx + dz + y) % 2 != 0 {
+                            editor.set_block(SCAFFOLDING, x + dx, y, z + dz, None, None);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playground_tags() {
+        assert_eq!(Equipment::from_tag("swing"), Some(Equipment::Swing));
+        assert_eq!(
+            Equipment::from_tag("climbingframe"),
+            Some(Equipment::ClimbingFrame)
+        );
+        assert_eq!(Equipment::from_tag("bench"), None);
+
+        let tags = HashMap::from([("surface".to_string(), "sand".to_string())]);
+        assert_eq!(surface_block(&tags), SAND);
+        assert_eq!(surface_block(&HashMap::new()), GREEN_STAINED_HARDENED_CLAY);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::args::Args;
+use crate::block_definitions::*;
+use crate::element_processing::tunnels::way_centerline;
+use crate::floodfill::flood_fill_area;
+use crate::osm_parser::ProcessedWay;
+use crate::world_editor::WorldEditor;
+use std::collections::HashSet;
+
+/// Default height of a station hall up to the roof
+const STATION_HALL_HEIGHT: i32 = 12;
+/// Distance between the entrances along the walls of a station hall
+const STATION_ENTRANCE_SPACING: usize = 16;
+/// Free height of the openings where tracks and platforms pass through the walls
+const TRACK_OPENING_HEIGHT: i32 = 5;
+/// Blocks of tracks and platforms that need an opening in the walls of a station hall
+const TRACK_BLOCKS: [Block; 8] = [
+    GRAVEL,
+    SMOOTH_STONE,
+    YELLOW_CONCRETE,
+    RAIL_NORTH_SOUTH,
+    RAIL_EAST_WEST,
+    RAIL_NORTH_EAST,
+    RAIL_NORTH_WEST,
+    RAIL_SOUTH_EAST,
+];
+
+/// Returns true for railway platforms
+pub fn is_railway_platform(way: &ProcessedWay) -> bool {
+    way.tags
+        .get("railway")
+        .is_some_and(|railway| railway == "platform")
+}
+
+/// Returns true for station buildings and station areas that get a station hall
+pub fn is_station_hall(way: &ProcessedWay) -> bool {
+    let is_station_building = way
+        .tags
+        .get("building")
+        .is_some_and(|building| building == "train_station");
+    let is_station_area = way
+        .tags
+        .get("railway")
+        .is_some_and(|railway| railway == "station");
+
+    (is_station_building || is_station_area) && is_closed_way(way)
+}
+
+/// Returns true if a way forms a closed ring
+fn is_closed_way(way: &ProcessedWay) -> bool {
+    way.nodes.len() > 3 && way.nodes.first().map(|n| n.id) == way.nodes.last().map(|n| n.id)
+}
+
+/// Generates a raised smooth stone platform with yellow warning strips along its edges
+pub fn generate_railway_platform(editor: &mut WorldEditor, way: &ProcessedWay, args: &Args) {
+    let platform: HashSet<(i32, i32)> = if is_closed_way(way) {
+        let polygon_coords: Vec<(i32, i32)> = way.nodes.iter().map(|n| (n.x, n.z)).collect();
+        flood_fill_area(&polygon_coords, args.timeout.as_ref())
+            .into_iter()
+            .chain(way_centerline(way))
+            .collect()
+    } else {
+        way_centerline(way)
+            .into_iter()
+            .flat_map(|(x, z)| {
+                (-2..=2).flat_map(move |dx| (-2..=2).map(move |dz| (x + dx, z + dz)))
+            })
+            .collect()
+    };
+
+    for &(x, z) in &platform {
+        let is_edge = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+            .iter()
+            .any(|(dx, dz)| !platform.contains(&(x + dx, z + dz)));
+        let top_block = if is_edge {
+            YELLOW_CONCRETE
+        } else {
+            SMOOTH_STONE
+        };
+
+        editor.set_block(SMOOTH_STONE, x, 0, z, None, None);
+        editor.set_block(top_block, x, 1, z, None, None);
+    }
+}
+
+/// Generates a large station hall with entrances on all sides and a glass roof spanning the
+/// tracks. Runs after the tracks and platforms so the walls can leave openings for them.
+pub fn generate_station_hall(editor: &mut WorldEditor, way: &ProcessedWay, args: &Args) {
+    let polygon_coords: Vec<(i32, i32)> = way.nodes.iter().map(|n| (n.x, n.z)).collect();
+    let floor_area = flood_fill_area(&polygon_coords, args.timeout.as_ref());
+
+    let height = way
+        .tags
+        .get("height")
+        .and_then(|height| height.trim_end_matches('m').trim().parse::<f64>().ok())
+        .map(|height| (height * args.scale) as i32)
+        .unwrap_or(STATION_HALL_HEIGHT)
+        .max(8);
+
+    // Walls with entrances at regular intervals and openings for the tracks
+    for (i, (x, z)) in way_centerline(way).into_iter().enumerate() {
+        let has_track = editor.check_for_block(x, 0, z, Some(&TRACK_BLOCKS))
+            || editor.check_for_block(x, 1, z, Some(&TRACK_BLOCKS));
+        let is_entrance = i % STATION_ENTRANCE_SPACING < 3;
+
+        let opening_height = if has_track {
+            TRACK_OPENING_HEIGHT
+        } else if is_entrance {
+            3
+        } else {
+            0
+        };
+
+        for y in 1..=height {
+            if y <= opening_height {
+                continue;
+            }
+            let wall_block = if y > 4 && y < height - 1 && i % 3 != 0 {
+                GLASS
+            } else {
+                STONE_BRICKS
+            };
+            editor.set_block(wall_block, x, y, z, None, None);
+        }
+    }
+
+    // Floor where no tracks or platforms are, and a glass roof with a stone frame
+    for (x, z) in floor_area {
+        editor.set_block(POLISHED_ANDESITE, x, 0, z, None, None);
+
+        let roof_block = if x % 4 == 0 || z % 4 == 0 {
+            STONE_BRICKS
+        } else {
+            GLASS
+        };
+        editor.set_block(roof_block, x, height + 1, z, None, None);
+    }
+}
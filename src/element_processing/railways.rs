@@ -1,7 +1,26 @@
 use crate::block_definitions::*;
 use crate::bresenham::bresenham_line;
+use crate::element_processing::tunnels;
 use crate::osm_parser::ProcessedWay;
 use crate::world_editor::WorldEditor;
+use fastnbt::Value;
+use std::collections::HashMap;
+
+/// Distance between the wooden sleepers below the rails
+const SLEEPER_SPACING: usize = 3;
+/// Height of a railway bridge deck per layer
+const VIADUCT_LAYER_HEIGHT: i32 = 6;
+/// Distance between the piers of a railway viaduct, spanned by one arch
+const VIADUCT_PIER_SPACING: usize = 8;
+/// Road surface blocks that tram rails can be embedded into
+const ROAD_SURFACE_BLOCKS: [Block; 6] = [
+    BLACK_CONCRETE,
+    GRAY_CONCRETE,
+    LIGHT_GRAY_CONCRETE,
+    WHITE_CONCRETE,
+    STONE_BRICKS,
+    STONE,
+];
 
 pub fn generate_railways(editor: &mut WorldEditor, element: &ProcessedWay) {
     if let Some(railway_type) = element.tags.get("railway") {
@@ -26,46 +45,264 @@ pub fn generate_railways(editor: &mut WorldEditor, element: &ProcessedWay) {
 
         if let Some(tunnel) = element.tags.get("tunnel") {
             if tunnel == "yes" {
+                // Railway tunnels can only be carved when there is terrain around them
+                if tunnels::is_tunnel(editor, element) {
+                    generate_railway_tunnel(editor, element);
+                }
                 return;
             }
         }
 
-        for i in 1..element.nodes.len() {
-            let prev_node = element.nodes[i - 1].xz();
-            let cur_node = element.nodes[i].xz();
+        // Follow the whole way at once so curves across nodes get the right rail shape
+        let points: Vec<(i32, i32, i32)> = tunnels::way_centerline(element)
+            .into_iter()
+            .map(|(x, z)| (x, 0, z))
+            .collect();
+        let track: Vec<(i32, i32)> = smooth_diagonal_rails(&points)
+            .into_iter()
+            .map(|(x, _, z)| (x, z))
+            .collect();
+
+        if element
+            .tags
+            .get("bridge")
+            .is_some_and(|bridge| bridge != "no")
+        {
+            generate_railway_viaduct(editor, element, &track);
+            return;
+        }
 
-            let points = bresenham_line(prev_node.x, 0, prev_node.z, cur_node.x, 0, cur_node.z);
-            let smoothed_points = smooth_diagonal_rails(&points);
+        if is_embedded_track(editor, element, &track) {
+            generate_embedded_track(editor, &track);
+            return;
+        }
 
-            for j in 0..smoothed_points.len() {
-                let (bx, _, bz) = smoothed_points[j];
+        // Gravel ballast bed around the track
+        for &(x, z) in &track {
+            for dx in -1..=1 {
+                for dz in -1..=1 {
+                    editor.set_block(GRAVEL, x + dx, 0, z + dz, None, None);
+                }
+            }
+        }
 
-                editor.set_block(GRAVEL, bx, 0, bz, None, None);
+        for (j, &(x, z)) in track.iter().enumerate() {
+            let prev = if j > 0 { Some(track[j - 1]) } else { None };
+            let next = track.get(j + 1).copied();
 
-                let prev = if j > 0 {
-                    Some(smoothed_points[j - 1])
-                } else {
-                    None
-                };
-                let next = if j < smoothed_points.len() - 1 {
-                    Some(smoothed_points[j + 1])
-                } else {
-                    None
-                };
+            let rail_block = determine_rail_direction((x, z), prev, next);
+            let rail_block = apply_rail_slope(editor, rail_block, (x, z), prev, next);
+            editor.set_block(rail_block, x, 1, z, None, None);
 
-                let rail_block = determine_rail_direction(
-                    (bx, bz),
-                    prev.map(|(x, _, z)| (x, z)),
-                    next.map(|(x, _, z)| (x, z)),
-                );
+            if j % SLEEPER_SPACING == 0 {
+                place_sleeper(editor, x, z, rail_block);
+            }
+        }
+    }
+}
 
-                editor.set_block(rail_block, bx, 1, bz, None, None);
+/// Returns true for tram tracks that run inside a road, either tagged as embedded rails or
+/// laid mostly on top of an already generated road surface
+fn is_embedded_track(editor: &WorldEditor, element: &ProcessedWay, track: &[(i32, i32)]) -> bool {
+    if let Some(embedded_rails) = element.tags.get("embedded_rails") {
+        return embedded_rails != "no";
+    }
+    if element.tags.get("railway").map(|s| s.as_str()) != Some("tram") || track.is_empty() {
+        return false;
+    }
 
-                if bx % 4 == 0 {
-                    editor.set_block(OAK_LOG, bx, 0, bz, None, None);
-                }
+    let on_road = track
+        .iter()
+        .filter(|&&(x, z)| editor.check_for_block(x, 0, z, Some(&ROAD_SURFACE_BLOCKS)))
+        .count();
+    on_road * 2 > track.len()
+}
+
+/// Lays the rails flush into the road surface, without ballast or sleepers,
+/// so the street stays drivable
+fn generate_embedded_track(editor: &mut WorldEditor, track: &[(i32, i32)]) {
+    for (j, &(x, z)) in track.iter().enumerate() {
+        let prev = if j > 0 { Some(track[j - 1]) } else { None };
+        let next = track.get(j + 1).copied();
+
+        let rail_block = determine_rail_direction((x, z), prev, next);
+        let rail_block = apply_rail_slope(editor, rail_block, (x, z), prev, next);
+
+        // Solid bed for the rail in place of the road surface
+        editor.set_block(STONE, x, -1, z, None, Some(&[]));
+        editor.set_block(rail_block, x, 0, z, None, Some(&[]));
+    }
+}
+
+/// Turns a straight rail into an ascending one where the terrain rises by one block
+fn apply_rail_slope(
+    editor: &WorldEditor,
+    rail_block: Block,
+    (x, z): (i32, i32),
+    prev: Option<(i32, i32)>,
+    next: Option<(i32, i32)>,
+) -> Block {
+    if rail_block != RAIL_NORTH_SOUTH && rail_block != RAIL_EAST_WEST {
+        return rail_block;
+    }
+
+    let height = editor.get_absolute_y(x, 0, z);
+    for (nx, nz) in [next, prev].into_iter().flatten() {
+        if editor.get_absolute_y(nx, 0, nz) == height + 1 {
+            return ascending_rail(rail_block, (x, z), (nx, nz));
+        }
+    }
+
+    rail_block
+}
+
+/// Returns the rail ascending from one position towards a neighbouring higher one
+fn ascending_rail(rail_block: Block, (x, z): (i32, i32), (nx, nz): (i32, i32)) -> Block {
+    match (nx - x, nz - z) {
+        (1, 0) => RAIL_ASCENDING_EAST,
+        (-1, 0) => RAIL_ASCENDING_WEST,
+        (0, 1) => RAIL_ASCENDING_SOUTH,
+        (0, -1) => RAIL_ASCENDING_NORTH,
+        _ => rail_block,
+    }
+}
+
+/// Generates a brick viaduct carrying the track, rising from the ends of the bridge to the
+/// deck height and resting on piers with arched openings between them
+fn generate_railway_viaduct(
+    editor: &mut WorldEditor,
+    element: &ProcessedWay,
+    track: &[(i32, i32)],
+) {
+    let layer = element
+        .tags
+        .get("layer")
+        .and_then(|layer| layer.parse::<i32>().ok())
+        .unwrap_or(1)
+        .max(1);
+    let deck_height = layer * VIADUCT_LAYER_HEIGHT;
+
+    // Ramp up from both ends of the bridge by one block per rail
+    let heights: Vec<i32> = (0..track.len())
+        .map(|j| deck_height.min(j as i32 + 1).min((track.len() - j) as i32))
+        .collect();
+
+    for (j, &(x, z)) in track.iter().enumerate() {
+        let height = heights[j];
+        let prev = if j > 0 { Some(track[j - 1]) } else { None };
+        let next = track.get(j + 1).copied();
+
+        // Deck below the rails
+        for dx in -1..=1 {
+            for dz in -1..=1 {
+                editor.set_block(BRICK, x + dx, height, z + dz, None, None);
+            }
+        }
+
+        generate_viaduct_support(editor, x, z, j, height);
+
+        let mut rail_block = determine_rail_direction((x, z), prev, next);
+        if rail_block == RAIL_NORTH_SOUTH || rail_block == RAIL_EAST_WEST {
+            let rises_towards = |i: Option<usize>| {
+                i.filter(|&i| heights.get(i) == Some(&(height + 1)))
+                    .map(|i| track[i])
+            };
+            let rising_neighbour =
+                rises_towards(Some(j + 1)).or_else(|| rises_towards(j.checked_sub(1)));
+            if let Some(neighbour) = rising_neighbour {
+                rail_block = ascending_rail(rail_block, (x, z), neighbour);
             }
         }
+        editor.set_block(rail_block, x, height + 1, z, None, None);
+    }
+}
+
+/// Fills the space below the viaduct deck at one point of the track: solid piers at regular
+/// intervals, round arches between them and a solid embankment where the deck is low
+fn generate_viaduct_support(editor: &mut WorldEditor, x: i32, z: i32, index: usize, height: i32) {
+    let radius = VIADUCT_PIER_SPACING as i32 / 2;
+    let spring_height = height - 1 - radius;
+    let is_pier = index.is_multiple_of(VIADUCT_PIER_SPACING)
+        && !editor.check_for_block(x, 0, z, Some(&ROAD_SURFACE_BLOCKS));
+
+    let lowest_brick = if spring_height < 1 || is_pier {
+        // Piers reach down into rivers and lakes below the bridge
+        for dx in -1..=1 {
+            for dz in -1..=1 {
+                editor.set_block(BRICK, x + dx, 0, z + dz, Some(&[WATER]), None);
+            }
+        }
+        1
+    } else {
+        // Underside of a round arch centered between two piers
+        let offset = (index % VIADUCT_PIER_SPACING) as i32 - radius;
+        let rise = ((radius * radius - offset * offset) as f64).sqrt().ceil() as i32;
+        spring_height + rise
+    };
+
+    for y in lowest_brick..height {
+        for dx in -1..=1 {
+            for dz in -1..=1 {
+                editor.set_block(BRICK, x + dx, y, z + dz, Some(&[WATER]), None);
+            }
+        }
+    }
+}
+
+/// Places a wooden sleeper across the track below a straight rail
+fn place_sleeper(editor: &mut WorldEditor, x: i32, z: i32, rail_block: Block) {
+    let (axis, (dx, dz)) =
+        if [RAIL_NORTH_SOUTH, RAIL_ASCENDING_NORTH, RAIL_ASCENDING_SOUTH].contains(&rail_block) {
+            ("x", (1, 0))
+        } else if [RAIL_EAST_WEST, RAIL_ASCENDING_EAST, RAIL_ASCENDING_WEST].contains(&rail_block) {
+            ("z", (0, 1))
+        } else {
+            // Curves keep their plain ballast
+            return;
+        };
+
+    let mut properties = HashMap::new();
+    properties.insert("axis".to_string(), Value::String(axis.to_string()));
+    let sleeper = BlockWithProperties::new(OAK_LOG, Some(Value::Compound(properties)));
+
+    for offset in -1..=1 {
+        let (sleeper_x, sleeper_z) = (x + dx * offset, z + dz * offset);
+        let sleeper_y = editor.get_absolute_y(sleeper_x, 0, sleeper_z);
+        editor.set_block_with_properties_absolute(
+            sleeper.clone(),
+            sleeper_x,
+            sleeper_y,
+            sleeper_z,
+            Some(&[GRAVEL]),
+            None,
+        );
+    }
+}
+
+/// Generates a railway running through a tunnel bore
+fn generate_railway_tunnel(editor: &mut WorldEditor, element: &ProcessedWay) {
+    let points: Vec<(i32, i32, i32)> = tunnels::way_centerline(element)
+        .into_iter()
+        .map(|(x, z)| (x, 0, z))
+        .collect();
+    let smoothed_points = smooth_diagonal_rails(&points);
+    let centerline: Vec<(i32, i32)> = smoothed_points.iter().map(|&(x, _, z)| (x, z)).collect();
+
+    let floor_heights = tunnels::generate_tunnel(editor, &centerline, 1, GRAVEL);
+
+    for (j, &(bx, bz)) in centerline.iter().enumerate() {
+        let prev = if j > 0 { Some(centerline[j - 1]) } else { None };
+        let next = centerline.get(j + 1).copied();
+
+        let rail_block = determine_rail_direction((bx, bz), prev, next);
+        editor.set_block_absolute(
+            rail_block,
+            bx,
+            floor_heights[j] + 1,
+            bz,
+            Some(&[CAVE_AIR]),
+            None,
+        );
     }
 }
 
@@ -0,0 +1,182 @@
+use crate::block_definitions::*;
+use crate::osm_parser::ProcessedElement;
+use crate::world_editor::{BlockOwner, WorldEditor};
+use std::collections::{BTreeMap, HashMap};
+
+/// Maximum number of characters that fit on one sign line
+const SIGN_LINE_LENGTH: usize = 15;
+/// Maximum distance from the intersection node to search for a free spot for the post
+const MAX_POST_OFFSET: i32 = 6;
+
+/// Street names meeting at an intersection, each with the first way of that name
+type StreetNames = Vec<(String, u64)>;
+
+/// Places signposts showing the street names at intersections of named highways. Every sign
+/// belongs to the first way with its name, and the post to the way of its first sign.
+pub fn generate_street_signs(editor: &mut WorldEditor, elements: &[ProcessedElement]) {
+    // Collect the street names meeting at every node shared by named highways, with the first
+    // way of each name
+    let mut names_at_node: HashMap<u64, (i32, i32, BTreeMap<String, u64>)> = HashMap::new();
+
+    for element in elements {
+        let ProcessedElement::Way(way) = element else {
+            continue;
+        };
+        if !way.tags.contains_key("highway") {
+            continue;
+        }
+        let Some(name) = way.tags.get("name") else {
+            continue;
+        };
+
+        for node in &way.nodes {
+            let way_id = names_at_node
+                .entry(node.id)
+                .or_insert_with(|| (node.x, node.z, BTreeMap::new()))
+                .2
+                .entry(name.clone())
+                .or_insert(way.id);
+            *way_id = (*way_id).min(way.id);
+        }
+    }
+
+    let mut intersections: Vec<(i32, i32, StreetNames)> = names_at_node
+        .into_values()
+        .filter(|(_, _, names)| names.len() >= 2)
+        .map(|(x, z, names)| (x, z, names.into_iter().collect()))
+        .collect();
+    intersections.sort();
+
+    for (x, z, names) in intersections {
+        if let Some((post_x, post_z)) = find_post_spot(editor, x, z) {
+            generate_signpost(editor, post_x, post_z, &names);
+        }
+    }
+    editor.set_block_owner(None);
+}
+
+/// Finds the closest free corner next to an intersection, off the road surface
+pub(crate) fn find_post_spot(editor: &WorldEditor, x: i32, z: i32) -> Option<(i32, i32)> {
+    let corners = [(1, 1), (-1, 1), (1, -1), (-1, -1)];
+
+    for offset in 1..=MAX_POST_OFFSET {
+        for (dx, dz) in corners {
+            let (post_x, post_z) = (x + dx * offset, z + dz * offset);
+            if !editor.block_at(post_x, 0, post_z) && !editor.block_at(post_x, 1, post_z) {
+                return Some((post_x, post_z));
+            }
+        }
+    }
+
+    None
+}
+
+/// Builds a fence post with a wall sign for every street name on its sides
+fn generate_signpost(editor: &mut WorldEditor, x: i32, z: i32, names: &[(String, u64)]) {
+    let owner = |id: u64| Some(BlockOwner { kind: "way", id });
+    editor.set_block_owner(names.first().and_then(|&(_, id)| owner(id)));
+    for y in 1..=3 {
+        editor.set_block(OAK_FENCE, x, y, z, None, None);
+    }
+
+    let sides = [
+        (1, 0, StairFacing::East),
+        (0, 1, StairFacing::South),
+        (-1, 0, StairFacing::West),
+        (0, -1, StairFacing::North),
+    ];
+
+    for ((name, way_id), (dx, dz, facing)) in names.iter().zip(sides) {
+        if editor.block_at(x + dx, 3, z + dz) {
+            continue;
+        }
+        editor.set_block_owner(owner(*way_id));
+        editor.set_wall_sign(sign_lines(name), x + dx, 3, z + dz, facing);
+    }
+}
+
+/// Wraps a street name onto the lines of a sign, breaking at spaces where possible
+pub(crate) fn sign_lines(name: &str) -> [String; 4] {
+    let mut lines: Vec<String> = vec![String::new()];
+
+    // Quotes would break the JSON text component of the sign
+    let name = name.replace('"', "'");
+
+    for word in name.split_whitespace() {
+        let current = lines.last_mut().unwrap();
+        if current.is_empty() {
+            current.push_str(word);
+        } else if current.chars().count() + 1 + word.chars().count() <= SIGN_LINE_LENGTH {
+            current.push(' ');
+            current.push_str(word);
+        } else {
+            lines.push(word.to_string());
+        }
+    }
+
+    // Text beyond the fourth line does not fit on the sign
+    lines.resize(4, String::new());
+    [
+        lines[0].clone(),
+        lines[1].clone(),
+        lines[2].clone(),
+        lines[3].clone(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinate_system::cartesian::XZBBox;
+    use crate::osm_parser::{ProcessedNode, ProcessedWay};
+    use crate::test_utilities::{get_llbbox_arnis, minecraft_tmpdir};
+
+    fn street(id: u64, name: &str, nodes: [(u64, i32, i32); 3]) -> ProcessedElement {
+        ProcessedElement::Way(ProcessedWay {
+            id,
+            nodes: nodes
+                .iter()
+                .map(|&(id, x, z)| ProcessedNode {
+                    id,
+                    tags: HashMap::new(),
+                    x,
+                    z,
+                })
+                .collect(),
+            tags: HashMap::from([
+                ("highway".to_string(), "residential".to_string()),
+                ("name".to_string(), name.to_string()),
+            ]),
+        })
+    }
+
+    #[test]
+    fn test_signs_belong_to_their_streets() {
+        let world = minecraft_tmpdir();
+        let xzbbox = XZBBox::rect_from_xz_lengths(30.0, 30.0).unwrap();
+        let mut editor = WorldEditor::new(world.path().to_path_buf(), &xzbbox, get_llbbox_arnis());
+        editor.enable_ownership_map();
+
+        let elements = [
+            street(8, "Ringstraße", [(1, 10, 20), (100, 10, 10), (2, 10, 0)]),
+            street(5, "Bahnhofstraße", [(3, 0, 10), (100, 10, 10), (4, 20, 10)]),
+            street(3, "Ringstraße", [(5, 10, 0), (100, 10, 10), (6, 10, 20)]),
+        ];
+        generate_street_signs(&mut editor, &elements);
+
+        let owner = |x, y, z| editor.block_owner_at(x, y, z).map(|owner| owner.id);
+        // The post stands at the first free corner, with the names in alphabetical order
+        assert_eq!(owner(11, 1, 11), Some(5));
+        assert_eq!(owner(12, 3, 11), Some(5));
+        assert_eq!(owner(11, 3, 12), Some(3));
+    }
+
+    #[test]
+    fn test_sign_lines_wraps_long_names() {
+        let lines = sign_lines("Kurfürstendamm Nord Allee");
+        assert_eq!(lines[0], "Kurfürstendamm");
+        assert_eq!(lines[1], "Nord Allee");
+        assert_eq!(lines[2], "");
+        assert_eq!(lines[3], "");
+    }
+}
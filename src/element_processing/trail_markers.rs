@@ -0,0 +1,181 @@
+use crate::block_definitions::*;
+use crate::bresenham::bresenham_line;
+use crate::element_processing::highways::HighwayConnectivityMap;
+use crate::element_processing::street_signs::{find_post_spot, sign_lines};
+use crate::osm_parser::ProcessedRelation;
+use crate::world_editor::WorldEditor;
+
+/// Distance between two waymarks along a route, in blocks
+const WAYMARK_SPACING: usize = 48;
+
+/// Places waymarks at regular intervals and at junctions along the ways of a trail route
+pub fn generate_trail_markers(
+    editor: &mut WorldEditor,
+    rel: &ProcessedRelation,
+    highway_connectivity: &HighwayConnectivityMap,
+) {
+    let symbol = route_symbol_block(rel);
+    let label = rel
+        .tags
+        .get("ref")
+        .or_else(|| rel.tags.get("name"))
+        .cloned()
+        .unwrap_or_default();
+
+    for member in &rel.members {
+        let nodes = &member.way.nodes;
+        let mut spots: Vec<(i32, i32)> = Vec::new();
+
+        // Junctions are nodes where other ways end, three way ends at the ends of the way
+        for (i, node) in nodes.iter().enumerate() {
+            let connections = highway_connectivity
+                .get(&(node.x, node.z))
+                .map_or(0, Vec::len);
+            let is_end = i == 0 || i == nodes.len() - 1;
+            if (is_end && connections >= 3) || (!is_end && connections >= 1) {
+                spots.push((node.x, node.z));
+            }
+        }
+
+        let mut distance: usize = 0;
+        for pair in nodes.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            for (x, _, z) in bresenham_line(a.x, 0, a.z, b.x, 0, b.z) {
+                if distance % WAYMARK_SPACING == WAYMARK_SPACING / 2 {
+                    spots.push((x, z));
+                }
+                distance += 1;
+            }
+        }
+
+        for (x, z) in spots {
+            if let Some((post_x, post_z)) = find_post_spot(editor, x, z) {
+                generate_waymark(editor, post_x, post_z, symbol, &label);
+            }
+        }
+    }
+}
+
+/// Builds a short post topped with the route color and a sign with the route reference
+fn generate_waymark(editor: &mut WorldEditor, x: i32, z: i32, symbol: Block, label: &str) {
+    for y in 1..=2 {
+        editor.set_block(OAK_FENCE, x, y, z, None, None);
+    }
+    editor.set_block(symbol, x, 3, z, None, None);
+
+    if !label.is_empty() && !editor.block_at(x, 2, z + 1) {
+        editor.set_wall_sign(sign_lines(label), x, 2, z + 1, StairFacing::South);
+    }
+}
+
+/// Returns the block showing the route color, taken from the colour tag or the waymark symbol
+fn route_symbol_block(rel: &ProcessedRelation) -> Block {
+    // The OSMC symbol starts with the color of the waymark, as in "red:white:red_bar"
+    let color = rel.tags.get("colour").map(String::as_str).or_else(|| {
+        rel.tags
+            .get("osmc:symbol")
+            .and_then(|symbol| symbol.split(':').next())
+    });
+
+    match color.map(|color| color.trim().to_lowercase()).as_deref() {
+        Some("red") => RED_CONCRETE,
+        Some("blue") => BLUE_CONCRETE,
+        Some("yellow") => YELLOW_CONCRETE,
+        Some("green") => LIME_CONCRETE,
+        Some("white") => WHITE_CONCRETE,
+        Some("black") => BLACK_CONCRETE,
+        Some("brown") => BROWN_CONCRETE,
+        Some("orange") => ORANGE_TERRACOTTA,
+        Some("purple") => PURPLE_CONCRETE,
+        _ => match rel.tags.get("route").map(String::as_str) {
+            Some("bicycle" | "mtb") => BLUE_CONCRETE,
+            Some("piste") => MAGENTA_CONCRETE,
+            _ => RED_CONCRETE,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinate_system::cartesian::XZBBox;
+    use crate::osm_parser::{ProcessedMember, ProcessedMemberRole, ProcessedNode, ProcessedWay};
+    use crate::test_utilities::{get_llbbox_arnis, minecraft_tmpdir};
+    use std::collections::HashMap;
+
+    fn route(tags: &[(&str, &str)], points: &[(i32, i32)]) -> ProcessedRelation {
+        let nodes = points
+            .iter()
+            .enumerate()
+            .map(|(i, &(x, z))| ProcessedNode {
+                id: i as u64 + 1,
+                tags: HashMap::new(),
+                x,
+                z,
+            })
+            .collect();
+        ProcessedRelation {
+            id: 1,
+            tags: tags
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            members: vec![ProcessedMember {
+                role: ProcessedMemberRole::Member,
+                way: ProcessedWay {
+                    id: 2,
+                    nodes,
+                    tags: HashMap::new(),
+                },
+            }],
+            stops: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_waymarks_at_intervals_and_junctions() {
+        let world = minecraft_tmpdir();
+        let xzbbox = XZBBox::rect_from_xz_lengths(100.0, 100.0).unwrap();
+        let mut editor = WorldEditor::new(world.path().to_path_buf(), &xzbbox, get_llbbox_arnis());
+        let rel = route(
+            &[
+                ("route", "hiking"),
+                ("ref", "E1"),
+                ("osmc:symbol", "yellow:white:yellow_bar"),
+            ],
+            &[(0, 50), (30, 50), (99, 50)],
+        );
+        // Another path ends at the node in between
+        let connectivity = HighwayConnectivityMap::from([((30, 50), vec![0])]);
+
+        generate_trail_markers(&mut editor, &rel, &connectivity);
+
+        // Halfway into the first and the second interval, and at the junction, each on the
+        // first free corner next to the trail
+        for x in [25, 31, 72] {
+            assert!(editor.check_for_block(x, 1, 51, Some(&[OAK_FENCE])), "{x}");
+            assert!(editor.check_for_block(x, 3, 51, Some(&[YELLOW_CONCRETE])));
+            assert!(editor.block_at(x, 2, 52));
+        }
+        let posts = (0..100)
+            .filter(|&x| editor.check_for_block(x, 3, 51, Some(&[YELLOW_CONCRETE])))
+            .count();
+        assert_eq!(posts, 3);
+    }
+
+    #[test]
+    fn test_route_colour() {
+        let block = |tags: &[(&str, &str)]| route_symbol_block(&route(tags, &[]));
+        assert_eq!(
+            block(&[("colour", "Blue"), ("osmc:symbol", "red")]),
+            BLUE_CONCRETE
+        );
+        assert_eq!(
+            block(&[("osmc:symbol", "green:white:green_dot")]),
+            LIME_CONCRETE
+        );
+        assert_eq!(block(&[("route", "mtb")]), BLUE_CONCRETE);
+        assert_eq!(block(&[("route", "piste")]), MAGENTA_CONCRETE);
+        assert_eq!(block(&[("route", "hiking")]), RED_CONCRETE);
+    }
+}
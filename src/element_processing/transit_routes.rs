@@ -0,0 +1,158 @@
+use crate::block_definitions::*;
+use crate::colors::{color_text_to_rgb_tuple, rgb_distance, RGBTuple};
+use crate::osm_parser::{is_public_transport_route, ProcessedElement, ProcessedMemberRole};
+use std::collections::HashMap;
+
+/// Blocks a route colour is shown with, by their approximate colour
+const ROUTE_COLOUR_BLOCKS: [(RGBTuple, Block); 14] = [
+    ((207, 213, 214), WHITE_CONCRETE),
+    ((125, 125, 115), LIGHT_GRAY_CONCRETE),
+    ((54, 57, 61), GRAY_CONCRETE),
+    ((8, 10, 15), BLACK_CONCRETE),
+    ((142, 33, 33), RED_CONCRETE),
+    ((161, 83, 37), ORANGE_TERRACOTTA),
+    ((241, 175, 21), YELLOW_CONCRETE),
+    ((94, 169, 24), LIME_CONCRETE),
+    ((21, 119, 136), CYAN_CONCRETE),
+    ((36, 137, 199), LIGHT_BLUE_CONCRETE),
+    ((45, 47, 143), BLUE_CONCRETE),
+    ((100, 32, 156), PURPLE_CONCRETE),
+    ((169, 48, 159), MAGENTA_CONCRETE),
+    ((96, 60, 32), BROWN_CONCRETE),
+];
+
+/// Public transport route serving a stop
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteLabel {
+    /// Route number, or the name of routes without one
+    pub reference: String,
+    pub colour: Option<Block>,
+}
+
+/// Routes serving each stop and platform, by the kind and id of its element
+pub type StopRoutes = HashMap<(&'static str, u64), Vec<RouteLabel>>;
+
+/// Collects the routes serving each stop and platform from the public transport route relations
+pub fn stop_routes(elements: &[ProcessedElement]) -> StopRoutes {
+    let mut routes = StopRoutes::new();
+    for element in elements {
+        let ProcessedElement::Relation(rel) = element else {
+            continue;
+        };
+        if !is_public_transport_route(&rel.tags) {
+            continue;
+        }
+        let label = RouteLabel {
+            reference: rel
+                .tags
+                .get("ref")
+                .or_else(|| rel.tags.get("name"))
+                .cloned()
+                .unwrap_or_default(),
+            colour: rel.tags.get("colour").and_then(|c| route_colour_block(c)),
+        };
+
+        let stops = rel.stops.iter().map(|node| ("node", node.id));
+        let platforms = rel
+            .members
+            .iter()
+            .filter(|member| member.role == ProcessedMemberRole::Platform)
+            .map(|member| ("way", member.way.id));
+        for key in stops.chain(platforms) {
+            let labels = routes.entry(key).or_default();
+            // Both directions of a route serve most stops
+            if !labels.iter().any(|l| l.reference == label.reference) {
+                labels.push(label.clone());
+            }
+        }
+    }
+
+    for labels in routes.values_mut() {
+        labels.sort_by(|a, b| compare_references(&a.reference, &b.reference));
+    }
+    routes
+}
+
+/// Returns the block closest to a route colour such as "#e3000f" or "red"
+pub fn route_colour_block(colour: &str) -> Option<Block> {
+    let rgb = color_text_to_rgb_tuple(&colour.trim().to_lowercase())?;
+    ROUTE_COLOUR_BLOCKS
+        .iter()
+        .min_by_key(|(block_rgb, _)| rgb_distance(&rgb, block_rgb))
+        .map(|(_, block)| *block)
+}
+
+/// Orders route numbers by their numeric value first, so that 9 comes before 10
+fn compare_references(a: &str, b: &str) -> std::cmp::Ordering {
+    let number = |reference: &str| -> Option<u32> {
+        let digits: String = reference.chars().take_while(char::is_ascii_digit).collect();
+        digits.parse().ok()
+    };
+    match (number(a), number(b)) {
+        (Some(x), Some(y)) => x.cmp(&y).then_with(|| a.cmp(b)),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osm_parser::{ProcessedMember, ProcessedNode, ProcessedRelation, ProcessedWay};
+
+    fn route(id: u64, reference: &str, colour: &str, stops: &[u64]) -> ProcessedElement {
+        let tags: HashMap<String, String> = [
+            ("type", "route"),
+            ("route", "bus"),
+            ("ref", reference),
+            ("colour", colour),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        ProcessedElement::Relation(ProcessedRelation {
+            id,
+            tags,
+            members: vec![ProcessedMember {
+                role: ProcessedMemberRole::Platform,
+                way: ProcessedWay {
+                    id: 50,
+                    nodes: Vec::new(),
+                    tags: HashMap::new(),
+                },
+            }],
+            stops: stops
+                .iter()
+                .map(|&id| ProcessedNode {
+                    id,
+                    tags: HashMap::new(),
+                    x: 0,
+                    z: 0,
+                })
+                .collect(),
+        })
+    }
+
+    #[test]
+    fn test_stop_routes_are_merged_and_sorted() {
+        let elements = vec![
+            route(1, "12", "#e3000f", &[10, 11]),
+            route(2, "9", "blue", &[11]),
+            // The other direction of route 12
+            route(3, "12", "#e3000f", &[11, 10]),
+        ];
+        let routes = stop_routes(&elements);
+
+        let references: Vec<&str> = routes[&("node", 11)]
+            .iter()
+            .map(|label| label.reference.as_str())
+            .collect();
+        assert_eq!(references, vec!["9", "12"]);
+        assert_eq!(routes[&("node", 10)].len(), 1);
+        assert_eq!(routes[&("node", 10)][0].colour, Some(RED_CONCRETE));
+        assert_eq!(routes[&("way", 50)].len(), 2);
+        assert_eq!(route_colour_block("#0000FF"), Some(BLUE_CONCRETE));
+        assert_eq!(route_colour_block("sparkly"), None);
+    }
+}
@@ -1,9 +1,28 @@
 use crate::block_definitions::*;
+use crate::element_rng::element_rng;
+use crate::ground_cover::value_noise;
+use crate::theme::{Climate, Theme};
 use crate::world_editor::WorldEditor;
 use rand::Rng;
+use std::collections::HashMap;
 
 type Coord = (i32, i32, i32);
 
+/// Most blocks a tree grows taller than its model
+const MAX_EXTRA_TREE_HEIGHT: i32 = 5;
+/// Chance of an oak getting a broader crown
+const BROAD_CROWN_CHANCE: f64 = 0.3;
+/// Average chance of a tree growing on a forest column
+const FOREST_TREE_CHANCE: f64 = 1.0 / 30.0;
+/// Size of the dense groves and clearings of forests, in blocks
+const FOREST_DENSITY_SCALE: f64 = 24.0;
+/// Ground that forest trees may grow next to
+const FOREST_GROUND: [Block; 5] = [GRASS_BLOCK, PODZOL, DIRT, COARSE_DIRT, MOSS_BLOCK];
+/// Distance between the trees of a tree row at scale 1, in blocks
+const TREE_ROW_SPACING: f64 = 6.0;
+/// Height of the roots that mangroves stand on, in blocks
+const MANGROVE_ROOT_HEIGHT: i32 = 2;
+
 // TODO all this data would probably be better suited in a TOML file or something.
 
 /// A circular pattern around a central point.
@@ -90,10 +109,90 @@ fn round(editor: &mut WorldEditor, material: Block, (x, y, z): Coord, block_patt
     }
 }
 
+#[derive(Clone, Copy)]
 pub enum TreeType {
     Oak,
     Spruce,
     Birch,
+    Acacia,
+    Palm,
+}
+
+// Scientific and English names of the trees, matched against the species and genus tags
+const PALM_NAMES: [&str; 6] = [
+    "palm",
+    "phoenix",
+    "washingtonia",
+    "cocos",
+    "trachycarpus",
+    "syagrus",
+];
+const ACACIA_NAMES: [&str; 2] = ["acacia", "vachellia"];
+const BIRCH_NAMES: [&str; 2] = ["betula", "birch"];
+const CONIFER_NAMES: [&str; 14] = [
+    "picea",
+    "spruce",
+    "pinus",
+    "pine",
+    "abies",
+    "fir",
+    "larix",
+    "larch",
+    "cedrus",
+    "cedar",
+    "pseudotsuga",
+    "taxus",
+    "yew",
+    "thuja",
+];
+const OAK_NAMES: [&str; 2] = ["quercus", "oak"];
+/// Fruit trees with dense dark leaves, matched against the trees tag of orchards
+const CITRUS_NAMES: [&str; 5] = ["orange", "lemon", "lime", "citrus", "mandarin"];
+
+/// Returns the height of a tree tagged in meters
+fn tagged_height(tags: &HashMap<String, String>) -> Option<i32> {
+    tags.get("height")
+        .and_then(|height| height.trim_end_matches('m').trim().parse::<f64>().ok())
+        .map(|height| height.round() as i32)
+}
+
+/// Returns the tree type matching the species, genus or leaf type of a tree, None if untagged
+fn tree_type_from_tags(tags: &HashMap<String, String>) -> Option<TreeType> {
+    let names = ["species", "genus", "species:en", "taxon", "genus:en"]
+        .iter()
+        .filter_map(|key| tags.get(*key))
+        .map(|name| name.to_lowercase())
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    let contains_any = |words: &[&str]| words.iter().any(|word| names.contains(word));
+    if contains_any(&PALM_NAMES) {
+        return Some(TreeType::Palm);
+    }
+    if contains_any(&ACACIA_NAMES) {
+        return Some(TreeType::Acacia);
+    }
+    if contains_any(&BIRCH_NAMES) {
+        return Some(TreeType::Birch);
+    }
+    if contains_any(&CONIFER_NAMES) {
+        return Some(TreeType::Spruce);
+    }
+    if contains_any(&OAK_NAMES) {
+        return Some(TreeType::Oak);
+    }
+
+    match tags.get("leaf_type").map(String::as_str) {
+        Some("needleleaved") => Some(TreeType::Spruce),
+        Some("broadleaved") if !names.is_empty() => Some(TreeType::Oak),
+        Some("broadleaved") => Some(if element_rng().gen_bool(0.7) {
+            TreeType::Oak
+        } else {
+            TreeType::Birch
+        }),
+        _ if !names.is_empty() => Some(TreeType::Oak),
+        _ => None,
+    }
 }
 
 // TODO what should be moved in, and what should be referenced?
@@ -107,22 +206,218 @@ pub struct Tree<'a> {
 }
 
 impl Tree<'_> {
-    pub fn create(editor: &mut WorldEditor, (x, y, z): Coord) {
-        let mut blacklist: Vec<Block> = Vec::new();
-        blacklist.extend(Self::get_building_wall_blocks());
-        blacklist.extend(Self::get_building_floor_blocks());
-        blacklist.extend(Self::get_structural_blocks());
-        blacklist.extend(Self::get_functional_blocks());
-        blacklist.push(WATER);
+    pub fn create(editor: &mut WorldEditor, coord: Coord) {
+        Self::create_of_type(editor, coord, None, None);
+    }
 
-        let mut rng = rand::thread_rng();
+    /// Returns true if a forest tree grows at x/z. Forests vary between dense groves and
+    /// sparse clearings.
+    pub fn is_forest_tree_spot(x: i32, z: i32, rng: &mut impl Rng) -> bool {
+        let density = value_noise(x, z, FOREST_DENSITY_SCALE);
+        rng.gen_bool((FOREST_TREE_CHANCE * (0.2 + 1.6 * density)).clamp(0.0, 1.0))
+    }
 
-        let tree = Self::get_tree(match rng.gen_range(1..=3) {
-            1 => TreeType::Oak,
-            2 => TreeType::Spruce,
-            3 => TreeType::Birch,
-            _ => unreachable!(),
+    /// Creates a tree of a forest, mixed from the trees matching its leaf type or climate
+    pub fn create_in_forest(
+        editor: &mut WorldEditor,
+        coord: Coord,
+        tags: &HashMap<String, String>,
+    ) {
+        // Keep the trunks off the edges of roads, paths and water crossing the forest
+        let (x, y, z) = coord;
+        let next_to_other_surface = [(1, 0), (-1, 0), (0, 1), (0, -1)].iter().any(|&(dx, dz)| {
+            editor.block_at(x + dx, 0, z + dz)
+                && !editor.check_for_block(x + dx, 0, z + dz, Some(&FOREST_GROUND))
         });
+        if next_to_other_surface || editor.check_for_block(x, y - 1, z, Some(&[WATER])) {
+            return;
+        }
+
+        let mut rng = element_rng();
+        let kind = match tags.get("leaf_type").map(String::as_str) {
+            Some("needleleaved") => Some(TreeType::Spruce),
+            Some("broadleaved") if rng.gen_bool(0.7) => Some(TreeType::Oak),
+            Some("broadleaved") => Some(TreeType::Birch),
+            Some("mixed") => Some(match rng.gen_range(0..3) {
+                0 => TreeType::Oak,
+                1 => TreeType::Birch,
+                _ => TreeType::Spruce,
+            }),
+            // Cold forests are mostly conifers
+            _ if editor.climate() == Climate::Snowy && rng.gen_bool(0.8) => Some(TreeType::Spruce),
+            _ => None,
+        };
+        Self::create_of_type(editor, coord, kind, None);
+    }
+
+    /// Creates a single tree whose species and height are taken from its tags, falling back to
+    /// a random tree for untagged ones
+    pub fn create_from_tags(
+        editor: &mut WorldEditor,
+        coord: Coord,
+        tags: &HashMap<String, String>,
+    ) {
+        Self::create_of_type(
+            editor,
+            coord,
+            tree_type_from_tags(tags),
+            tagged_height(tags),
+        );
+    }
+
+    /// Plants evenly spaced trees of one species along the line of a tree row, leaving out
+    /// spots on roads and paved ground
+    pub fn create_row(
+        editor: &mut WorldEditor,
+        line: &[(i32, i32)],
+        tags: &HashMap<String, String>,
+        scale: f64,
+    ) {
+        let spacing = ((TREE_ROW_SPACING * scale).round() as usize).max(3);
+        let mut rng = element_rng();
+
+        // Rows are planted with a single species, deserts keep their mix of acacias and shrubs
+        let kind = tree_type_from_tags(tags).or_else(|| {
+            (editor.theme() != Theme::Arid).then(|| {
+                if rng.gen_bool(0.7) {
+                    TreeType::Oak
+                } else {
+                    TreeType::Birch
+                }
+            })
+        });
+        let height = tagged_height(tags);
+
+        for (distance, &(x, z)) in line.iter().enumerate() {
+            if distance % spacing != spacing / 2 {
+                continue;
+            }
+            if editor.block_at(x, 0, z) && !editor.check_for_block(x, 0, z, Some(&FOREST_GROUND)) {
+                continue;
+            }
+            Self::create_of_type(editor, (x, 1, z), kind, height);
+        }
+    }
+
+    /// Creates a small fruit tree of an orchard, its species taken from the trees tag of the
+    /// orchard such as "apple_trees" or "olive_trees"
+    pub fn create_fruit_tree(editor: &mut WorldEditor, coord: Coord, trees: Option<&str>) {
+        let trees = trees.unwrap_or_default().to_lowercase();
+        if trees.contains("palm") {
+            Self::create_of_type(editor, coord, Some(TreeType::Palm), None);
+            return;
+        }
+
+        let (log, leaves) = if CITRUS_NAMES.iter().any(|name| trees.contains(name)) {
+            (JUNGLE_LOG, JUNGLE_LEAVES)
+        } else if trees.contains("olive") {
+            (ACACIA_LOG, ACACIA_LEAVES)
+        } else {
+            (OAK_LOG, OAK_LEAVES)
+        };
+
+        let (x, y, z) = coord;
+        let blacklist = Self::get_blacklist();
+        let crown = y + element_rng().gen_range(1..=2);
+        editor.fill_blocks(log, x, y, z, x, crown, z, None, Some(&blacklist));
+
+        // A low round crown, wider at the bottom
+        for (dy, radius) in [(0, 2i32), (1, 2), (2, 1)] {
+            for dx in -radius..=radius {
+                for dz in -radius..=radius {
+                    if dx.abs() == 2 && dz.abs() == 2 {
+                        continue;
+                    }
+                    editor.set_block(leaves, x + dx, crown + dy, z + dz, None, None);
+                }
+            }
+        }
+    }
+
+    /// Creates a mangrove tree raised on arching roots above mud and shallow water
+    pub fn create_mangrove(editor: &mut WorldEditor, coord: Coord) {
+        let (x, y, z) = coord;
+        let blacklist = Self::get_blacklist();
+        let trunk_base = y + MANGROVE_ROOT_HEIGHT;
+        let crown = trunk_base + element_rng().gen_range(2..=4);
+
+        // Roots reach down from below the trunk to its diagonal neighbours
+        editor.set_block(MANGROVE_ROOTS, x, trunk_base - 1, z, Some(&[WATER]), None);
+        for (dx, dz) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+            for root_y in y..trunk_base {
+                editor.set_block(MANGROVE_ROOTS, x + dx, root_y, z + dz, Some(&[WATER]), None);
+            }
+        }
+        editor.fill_blocks(
+            MANGROVE_LOG,
+            x,
+            trunk_base,
+            z,
+            x,
+            crown,
+            z,
+            None,
+            Some(&blacklist),
+        );
+
+        // A flat, wide crown
+        for (dy, radius) in [(-1, 2i32), (0, 2), (1, 1)] {
+            for dx in -radius..=radius {
+                for dz in -radius..=radius {
+                    if dx.abs() == 2 && dz.abs() == 2 {
+                        continue;
+                    }
+                    editor.set_block(MANGROVE_LEAVES, x + dx, crown + dy, z + dz, None, None);
+                }
+            }
+        }
+    }
+
+    fn create_of_type(
+        editor: &mut WorldEditor,
+        (x, y, z): Coord,
+        kind: Option<TreeType>,
+        height: Option<i32>,
+    ) {
+        let blacklist = Self::get_blacklist();
+        let mut rng = element_rng();
+
+        let kind = match kind {
+            Some(kind) => kind,
+            // Deserts only get sparse acacias and dead bushes
+            None if editor.theme() == Theme::Arid => {
+                if rng.gen_bool(0.5) {
+                    editor.set_block(DEAD_BUSH, x, y, z, None, Some(&blacklist));
+                    return;
+                }
+                TreeType::Acacia
+            }
+            None => match rng.gen_range(1..=3) {
+                1 => TreeType::Oak,
+                2 => TreeType::Spruce,
+                3 => TreeType::Birch,
+                _ => unreachable!(),
+            },
+        };
+
+        let tree = match kind {
+            TreeType::Acacia => {
+                Self::create_acacia(editor, (x, y, z), &blacklist);
+                return;
+            }
+            TreeType::Palm => {
+                let trunk_height = height.map_or(rng.gen_range(6..=9), |h| h - 1).clamp(4, 14);
+                Self::create_palm(editor, (x, y, z), trunk_height, &blacklist);
+                return;
+            }
+            kind => Self::get_tree(kind),
+        };
+
+        // Trees grow a few blocks taller than their model, or as tall as tagged
+        let model_height = tree.log_height + 2;
+        let extra_height = height.map_or(rng.gen_range(0..=2), |h| h - model_height);
+        let extra_height = extra_height.clamp(0, MAX_EXTRA_TREE_HEIGHT);
+        let y_crown = y + extra_height;
 
         // Build the logs
         editor.fill_blocks(
@@ -131,7 +426,7 @@ impl Tree<'_> {
             y,
             z,
             x,
-            y + tree.log_height,
+            y_crown + tree.log_height,
             z,
             None,
             Some(&blacklist),
@@ -142,10 +437,10 @@ impl Tree<'_> {
             editor.fill_blocks(
                 tree.leaves_block,
                 x + i1,
-                y + j1,
+                y_crown + j1,
                 z + k1,
                 x + i2,
-                y + j2,
+                y_crown + j2,
                 z + k2,
                 None,
                 None,
@@ -155,7 +450,105 @@ impl Tree<'_> {
         // Do the three rounds
         for (round_range, round_pattern) in tree.round_ranges.iter().zip(ROUND_PATTERNS) {
             for offset in round_range {
-                round(editor, tree.leaves_block, (x, y + offset, z), round_pattern);
+                round(
+                    editor,
+                    tree.leaves_block,
+                    (x, y_crown + offset, z),
+                    round_pattern,
+                );
+            }
+        }
+
+        // Some oaks spread their crown wider
+        if tree.log_block == OAK_LOG && rng.gen_bool(BROAD_CROWN_CHANCE) {
+            for offset in [4, 7] {
+                round(
+                    editor,
+                    tree.leaves_block,
+                    (x, y_crown + offset, z),
+                    &ROUND3_PATTERN,
+                );
+            }
+        }
+    }
+
+    /// Builds a palm with a slender trunk and fronds spreading from its top, hanging down at
+    /// their tips
+    fn create_palm(
+        editor: &mut WorldEditor,
+        (x, y, z): Coord,
+        trunk_height: i32,
+        blacklist: &[Block],
+    ) {
+        editor.fill_blocks(
+            JUNGLE_LOG,
+            x,
+            y,
+            z,
+            x,
+            y + trunk_height,
+            z,
+            None,
+            Some(blacklist),
+        );
+
+        let top = y + trunk_height + 1;
+        editor.set_block(JUNGLE_LEAVES, x, top, z, None, None);
+        for (dx, dz) in [
+            (1, 0),
+            (-1, 0),
+            (0, 1),
+            (0, -1),
+            (1, 1),
+            (1, -1),
+            (-1, 1),
+            (-1, -1),
+        ] {
+            let length = if dx != 0 && dz != 0 { 2 } else { 3 };
+            for step in 1..=length {
+                editor.set_block(JUNGLE_LEAVES, x + dx * step, top, z + dz * step, None, None);
+            }
+            let tip = length + 1;
+            editor.set_block(
+                JUNGLE_LEAVES,
+                x + dx * tip,
+                top - 1,
+                z + dz * tip,
+                None,
+                None,
+            );
+        }
+    }
+
+    /// Builds an acacia with a leaning trunk and a flat, wide canopy
+    fn create_acacia(editor: &mut WorldEditor, (x, y, z): Coord, blacklist: &[Block]) {
+        let mut rng = element_rng();
+        let (lean_x, lean_z) = [(1, 0), (-1, 0), (0, 1), (0, -1)][rng.gen_range(0..4)];
+
+        // Straight lower trunk, then one block leaning to the side
+        editor.fill_blocks(ACACIA_LOG, x, y, z, x, y + 3, z, None, Some(blacklist));
+        let (top_x, top_z) = (x + lean_x, z + lean_z);
+        editor.fill_blocks(
+            ACACIA_LOG,
+            top_x,
+            y + 4,
+            top_z,
+            top_x,
+            y + 5,
+            top_z,
+            None,
+            Some(blacklist),
+        );
+
+        // Flat canopy with a smaller layer on top
+        for dx in -2i32..=2 {
+            for dz in -2i32..=2 {
+                if dx.abs() + dz.abs() <= 3 {
+                    editor.set_block(ACACIA_LEAVES, top_x + dx, y + 6, top_z + dz, None, None);
+                }
+                if dx.abs() + dz.abs() <= 1 {
+                    editor.set_block(ACACIA_LEAVES, top_x + dx, y + 7, top_z + dz, None, None);
+                }
             }
         }
     }
@@ -179,7 +572,7 @@ impl Tree<'_> {
                 // kind,
                 log_block: SPRUCE_LOG,
                 log_height: 9,
-                leaves_block: BIRCH_LEAVES, // TODO Is this correct?
+                leaves_block: SPRUCE_LEAVES,
                 leaves_fill: &SPRUCE_LEAVES_FILL,
                 // TODO can I omit the third empty vec? May cause issues with iter zip
                 round_ranges: [vec![9, 7, 6, 4, 3], vec![6, 3], vec![]],
@@ -193,9 +586,23 @@ impl Tree<'_> {
                 leaves_fill: &BIRCH_LEAVES_FILL,
                 round_ranges: [(2..=6).rev().collect(), (2..=4).collect(), vec![]],
             },
+
+            // Acacias and palms are built by their own functions
+            TreeType::Acacia | TreeType::Palm => unreachable!(),
         } // match
     } // fn get_tree
 
+    /// Blocks that trees must not replace
+    fn get_blacklist() -> Vec<Block> {
+        let mut blacklist: Vec<Block> = Vec::new();
+        blacklist.extend(Self::get_building_wall_blocks());
+        blacklist.extend(Self::get_building_floor_blocks());
+        blacklist.extend(Self::get_structural_blocks());
+        blacklist.extend(Self::get_functional_blocks());
+        blacklist.push(WATER);
+        blacklist
+    }
+
     /// Get all possible building wall blocks
     fn get_building_wall_blocks() -> Vec<Block> {
         vec![
@@ -340,3 +747,36 @@ impl Tree<'_> {
         ]
     }
 } // impl Tree
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_tree_type_from_tags() {
+        assert!(matches!(
+            tree_type_from_tags(&tags(&[("species", "Pinus sylvestris")])),
+            Some(TreeType::Spruce)
+        ));
+        assert!(matches!(
+            tree_type_from_tags(&tags(&[("genus", "Betula")])),
+            Some(TreeType::Birch)
+        ));
+        assert!(matches!(
+            tree_type_from_tags(&tags(&[("species", "Phoenix canariensis")])),
+            Some(TreeType::Palm)
+        ));
+        assert!(matches!(
+            tree_type_from_tags(&tags(&[("leaf_type", "needleleaved")])),
+            Some(TreeType::Spruce)
+        ));
+        assert!(tree_type_from_tags(&tags(&[])).is_none());
+    }
+}
@@ -0,0 +1,220 @@
+use crate::block_definitions::*;
+use crate::bresenham::bresenham_line;
+use crate::osm_parser::ProcessedWay;
+use crate::world_editor::WorldEditor;
+
+/// Free height inside a tunnel bore
+const TUNNEL_CLEARANCE: i32 = 5;
+/// Distance between the lights in the tunnel ceiling
+const TUNNEL_LIGHT_SPACING: usize = 8;
+
+/// Returns true if a way is a tunnel that should be carved through the terrain
+pub fn is_tunnel(editor: &WorldEditor, way: &ProcessedWay) -> bool {
+    way.tags.get("tunnel").is_some_and(|tunnel| tunnel == "yes")
+        && editor
+            .get_ground()
+            .is_some_and(|ground| ground.elevation_enabled)
+}
+
+/// Returns the centerline of a way as a continuous list of points
+pub fn way_centerline(way: &ProcessedWay) -> Vec<(i32, i32)> {
+    let mut points: Vec<(i32, i32)> = Vec::new();
+
+    for pair in way.nodes.windows(2) {
+        for (x, _, z) in bresenham_line(pair[0].x, 0, pair[0].z, pair[1].x, 0, pair[1].z) {
+            if points.last() != Some(&(x, z)) {
+                points.push((x, z));
+            }
+        }
+    }
+
+    points
+}
+
+/// Carves a tunnel along the given centerline. The floor runs in a straight grade between
+/// the two portals; wherever there is enough terrain above it, an air bore lined with stone
+/// bricks is carved, elsewhere the floor is laid on the surface.
+/// Returns the absolute floor height for every point of the centerline.
+pub fn generate_tunnel(
+    editor: &mut WorldEditor,
+    points: &[(i32, i32)],
+    half_width: i32,
+    floor_block: Block,
+) -> Vec<i32> {
+    if points.is_empty() {
+        return Vec::new();
+    }
+
+    let (start_x, start_z) = points[0];
+    let (end_x, end_z) = points[points.len() - 1];
+    let start_y = editor.get_absolute_y(start_x, 0, start_z);
+    let end_y = editor.get_absolute_y(end_x, 0, end_z);
+    let last_index = (points.len() - 1).max(1) as f64;
+
+    let mut floor_heights: Vec<i32> = Vec::with_capacity(points.len());
+    let mut was_bored = false;
+
+    for (i, &(x, z)) in points.iter().enumerate() {
+        let ground_y = editor.get_absolute_y(x, 0, z);
+        let grade_y = start_y + ((end_y - start_y) as f64 * (i as f64 / last_index)).round() as i32;
+
+        // Only bore where the lining still fits below the surface
+        let is_bored = ground_y - grade_y > TUNNEL_CLEARANCE + 1;
+
+        if is_bored {
+            carve_tunnel_section(editor, x, z, grade_y, half_width, floor_block);
+
+            if i % TUNNEL_LIGHT_SPACING == 0 {
+                editor.set_block_absolute(
+                    GLOWSTONE,
+                    x,
+                    grade_y + TUNNEL_CLEARANCE + 1,
+                    z,
+                    Some(&[STONE_BRICKS]),
+                    None,
+                );
+            }
+            floor_heights.push(grade_y);
+        } else {
+            for dx in -half_width..=half_width {
+                for dz in -half_width..=half_width {
+                    editor.set_block(floor_block, x + dx, 0, z + dz, None, None);
+                }
+            }
+            floor_heights.push(ground_y);
+        }
+
+        // Build a portal wherever the way enters or leaves the mountain
+        if i > 0 && is_bored != was_bored {
+            let (portal_x, portal_z, portal_y) = if is_bored {
+                (x, z, grade_y)
+            } else {
+                let (prev_x, prev_z) = points[i - 1];
+                (prev_x, prev_z, floor_heights[i - 1])
+            };
+            generate_tunnel_portal(editor, portal_x, portal_z, portal_y, half_width);
+        }
+        was_bored = is_bored;
+    }
+
+    floor_heights
+}
+
+/// Carves one cross section of the tunnel bore and lines it with stone bricks
+fn carve_tunnel_section(
+    editor: &mut WorldEditor,
+    x: i32,
+    z: i32,
+    floor_y: i32,
+    half_width: i32,
+    floor_block: Block,
+) {
+    let ceiling_y = floor_y + TUNNEL_CLEARANCE + 1;
+    let lining_range = half_width + 1;
+
+    // Lining first, so the bore of neighbouring sections can replace it
+    for dx in -lining_range..=lining_range {
+        for dz in -lining_range..=lining_range {
+            let is_wall = dx.abs() == lining_range || dz.abs() == lining_range;
+            let (set_x, set_z) = (x + dx, z + dz);
+
+            editor.set_block_absolute(STONE_BRICKS, set_x, floor_y - 1, set_z, None, None);
+            editor.set_block_absolute(STONE_BRICKS, set_x, ceiling_y, set_z, None, None);
+            if is_wall {
+                for y in floor_y..ceiling_y {
+                    editor.set_block_absolute(STONE_BRICKS, set_x, y, set_z, None, None);
+                }
+            }
+        }
+    }
+
+    // Cave air keeps the ground generation from filling the bore again
+    for dx in -half_width..=half_width {
+        for dz in -half_width..=half_width {
+            let (set_x, set_z) = (x + dx, z + dz);
+            editor.set_block_absolute(
+                floor_block,
+                set_x,
+                floor_y,
+                set_z,
+                Some(&[STONE_BRICKS]),
+                None,
+            );
+            for y in (floor_y + 1)..ceiling_y {
+                editor.set_block_absolute(CAVE_AIR, set_x, y, set_z, Some(&[STONE_BRICKS]), None);
+            }
+        }
+    }
+}
+
+/// Builds a stone brick headwall with a keystone above a tunnel opening
+fn generate_tunnel_portal(editor: &mut WorldEditor, x: i32, z: i32, floor_y: i32, half_width: i32) {
+    let lintel_y = floor_y + TUNNEL_CLEARANCE + 1;
+    let portal_range = half_width + 1;
+
+    for dx in -portal_range..=portal_range {
+        for dz in -portal_range..=portal_range {
+            let ground_y = editor.get_absolute_y(x + dx, 0, z + dz);
+            for y in lintel_y..=ground_y.max(lintel_y) {
+                editor.set_block_absolute(STONE_BRICKS, x + dx, y, z + dz, None, None);
+            }
+        }
+    }
+
+    editor.set_block_absolute(
+        CHISELED_STONE_BRICKS,
+        x,
+        lintel_y,
+        z,
+        Some(&[STONE_BRICKS]),
+        None,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinate_system::cartesian::XZBBox;
+    use crate::ground::{ground_from_rows, TerrainInterpolation};
+    use crate::test_utilities::{get_llbbox_arnis, minecraft_tmpdir};
+
+    #[test]
+    fn test_tunnel_is_bored_through_a_hill() {
+        let world = minecraft_tmpdir();
+        let xzbbox = XZBBox::rect_from_xz_lengths(60.0, 20.0).unwrap();
+        let mut editor = WorldEditor::new(world.path().to_path_buf(), &xzbbox, get_llbbox_arnis());
+        // A hill 30 blocks high across the middle of flat land at -60
+        let rows = vec![
+            (0..61)
+                .map(|x| if (15..=45).contains(&x) { -30 } else { -60 })
+                .collect();
+            21
+        ];
+        editor.set_ground(&ground_from_rows(rows, TerrainInterpolation::Nearest));
+
+        let points: Vec<(i32, i32)> = (2..=58).map(|x| (x, 10)).collect();
+        let floors = generate_tunnel(&mut editor, &points, 1, STONE);
+        // Both portals lie at the same height, so the floor stays level through the hill
+        assert_eq!(floors, vec![-60; points.len()]);
+
+        // Inside the hill, a bore of air above the floor, lined with stone bricks
+        assert!(editor.check_for_block_absolute(30, -60, 10, Some(&[STONE]), None));
+        for y in -59..=-55 {
+            assert!(editor.check_for_block_absolute(30, y, 11, Some(&[CAVE_AIR]), None));
+        }
+        assert!(editor.check_for_block_absolute(30, -57, 12, Some(&[STONE_BRICKS]), None));
+        assert!(editor.check_for_block_absolute(30, -61, 10, Some(&[STONE_BRICKS]), None));
+
+        // Outside of it, the floor is laid on the surface
+        assert!(editor.check_for_block_absolute(5, -60, 10, Some(&[STONE]), None));
+        assert!(!editor.block_at_absolute(5, -57, 10));
+
+        // A portal with a keystone where the way enters and leaves the hill
+        let keystones = (0..60)
+            .filter(|&x| {
+                editor.check_for_block_absolute(x, -54, 10, Some(&[CHISELED_STONE_BRICKS]), None)
+            })
+            .count();
+        assert_eq!(keystones, 2);
+    }
+}
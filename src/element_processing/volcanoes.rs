@@ -0,0 +1,193 @@
+use crate::args::Args;
+use crate::block_definitions::*;
+use crate::element_rng::{element_rng, ElementRng};
+use crate::osm_parser::ProcessedElement;
+use crate::world_editor::WorldEditor;
+use rand::Rng;
+
+/// Radius in blocks of volcanoes mapped as a single node
+const VOLCANO_RADIUS: f64 = 40.0;
+/// Radius in blocks of craters mapped as a single node
+const CRATER_RADIUS: f64 = 10.0;
+/// Share of the radius of a volcano taken up by the crater on its summit
+const SUMMIT_CRATER_SHARE: f64 = 0.2;
+
+/// Shape of a cone with a crater, or of a crater alone with a raised rim
+#[derive(Clone, Copy, Debug)]
+struct CraterShape {
+    /// Radius of the foot of the cone, or of the rim of a crater without a cone
+    radius: f64,
+    /// Height of the rim of the crater above the surrounding ground
+    height: f64,
+    /// Radius of the crater
+    crater_radius: f64,
+    /// Depth of the crater below its rim
+    crater_depth: f64,
+}
+
+impl CraterShape {
+    /// Returns the height of the surface above the surrounding ground at the given distance
+    /// from the middle, None beyond the foot
+    fn surface_at(&self, distance: f64) -> Option<f64> {
+        if distance >= self.radius {
+            return None;
+        }
+        if distance < self.crater_radius {
+            // Bowl shaped crater, steepest at its rim
+            let t = distance / self.crater_radius;
+            return Some(self.height - self.crater_depth * (1.0 - t * t));
+        }
+        // Concave flanks, steep below the rim and flattening out towards the foot
+        let t = (distance - self.crater_radius) / (self.radius - self.crater_radius);
+        Some(self.height * (1.0 - t).powf(1.6))
+    }
+}
+
+pub fn is_volcanic_feature(element: &ProcessedElement) -> bool {
+    matches!(
+        element.tags().get("natural").map(String::as_str),
+        Some("volcano" | "crater")
+    )
+}
+
+/// Raises a volcano as a cone with a crater on its summit, or sinks a crater into the ground.
+/// Both rest on the ground around them and only add to it, so that volcanoes already in the
+/// elevation data keep their real shape.
+pub fn generate_volcanic_feature(
+    editor: &mut WorldEditor,
+    element: &ProcessedElement,
+    args: &Args,
+) {
+    let mut rng: ElementRng = element_rng();
+    let is_volcano = element.tags().get("natural").map(String::as_str) == Some("volcano");
+    let Some((center_x, center_z, mapped_radius)) = extent(element) else {
+        return;
+    };
+
+    let shape = if is_volcano {
+        // Shield volcanoes are broad and flat, scoria cones small and steep
+        let (radius, steepness) = match element.tags().get("volcano:type").map(String::as_str) {
+            Some("shield") => (VOLCANO_RADIUS * 1.5, 0.2),
+            Some("scoria") => (VOLCANO_RADIUS * 0.5, 0.5),
+            _ => (VOLCANO_RADIUS, 0.45),
+        };
+        let radius = mapped_radius.unwrap_or(radius * args.scale).max(8.0);
+        CraterShape {
+            radius,
+            height: (radius * steepness).min(120.0),
+            crater_radius: radius * SUMMIT_CRATER_SHARE,
+            crater_depth: radius * SUMMIT_CRATER_SHARE * 0.6,
+        }
+    } else {
+        let radius = mapped_radius.unwrap_or(CRATER_RADIUS * args.scale).max(3.0);
+        CraterShape {
+            radius: radius * 1.3,
+            height: (radius * 0.1).clamp(1.0, 4.0),
+            crater_radius: radius,
+            crater_depth: (radius * 0.4).clamp(2.0, 24.0),
+        }
+    };
+    // Active volcanoes and volcanic craters glow with magma in their crater
+    let has_magma = (is_volcano
+        && element.tags().get("volcano:status").map(String::as_str) != Some("extinct"))
+        || element.tags().get("crater:type").map(String::as_str) == Some("volcanic");
+
+    // The shape rests on the average level of the ground around its foot
+    let rim: Vec<i32> = (0..16)
+        .map(|i| {
+            let angle = i as f64 * std::f64::consts::TAU / 16.0;
+            let x = center_x + (angle.cos() * shape.radius).round() as i32;
+            let z = center_z + (angle.sin() * shape.radius).round() as i32;
+            editor.get_absolute_y(x, 0, z)
+        })
+        .collect();
+    let base = rim.iter().sum::<i32>() as f64 / rim.len() as f64;
+
+    let reach = shape.radius.ceil() as i32;
+    for dx in -reach..=reach {
+        for dz in -reach..=reach {
+            let distance = ((dx * dx + dz * dz) as f64).sqrt();
+            let Some(surface) = shape.surface_at(distance) else {
+                continue;
+            };
+            let (x, z) = (center_x + dx, center_z + dz);
+            let ground = editor.get_absolute_y(x, 0, z);
+            let top = (base + surface).round() as i32;
+            let in_crater = distance < shape.crater_radius;
+
+            // Above the flanks the top of the volcano is bare basalt and blackstone
+            let summit_share = surface / shape.height;
+            let surface_block = if in_crater && has_magma && rng.gen_bool(0.6) {
+                MAGMA_BLOCK
+            } else if in_crater || summit_share > 0.7 {
+                if rng.gen_bool(0.5) {
+                    BASALT
+                } else {
+                    BLACKSTONE
+                }
+            } else if summit_share > 0.35 {
+                if rng.gen_bool(0.7) {
+                    GRAVEL
+                } else {
+                    ANDESITE
+                }
+            } else {
+                GRASS_BLOCK
+            };
+
+            if top > ground {
+                for y in ground + 1..top {
+                    editor.set_block_absolute(STONE, x, y, z, None, None);
+                }
+                editor.set_block_absolute(surface_block, x, top, z, None, None);
+            } else if in_crater {
+                for y in top + 1..=ground {
+                    editor.set_block_absolute(CAVE_AIR, x, y, z, None, Some(&[BEDROCK]));
+                }
+                editor.set_block_absolute(surface_block, x, top, z, None, Some(&[BEDROCK]));
+            }
+        }
+    }
+}
+
+/// Returns the middle of a feature and, for areas, the radius of its outline
+fn extent(element: &ProcessedElement) -> Option<(i32, i32, Option<f64>)> {
+    match element {
+        ProcessedElement::Node(node) => Some((node.x, node.z, None)),
+        ProcessedElement::Way(way) if !way.nodes.is_empty() => {
+            let count = way.nodes.len() as i64;
+            let center_x = (way.nodes.iter().map(|n| n.x as i64).sum::<i64>() / count) as i32;
+            let center_z = (way.nodes.iter().map(|n| n.z as i64).sum::<i64>() / count) as i32;
+            let radius = way
+                .nodes
+                .iter()
+                .map(|n| (((n.x - center_x).pow(2) + (n.z - center_z).pow(2)) as f64).sqrt())
+                .fold(0.0, f64::max);
+            Some((center_x, center_z, Some(radius)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cone_rises_to_the_rim_of_its_crater() {
+        let shape = CraterShape {
+            radius: 40.0,
+            height: 18.0,
+            crater_radius: 8.0,
+            crater_depth: 5.0,
+        };
+        assert_eq!(shape.surface_at(40.0), None);
+        assert_eq!(shape.surface_at(8.0), Some(18.0));
+        assert_eq!(shape.surface_at(0.0), Some(13.0));
+        let (lower, upper) = (
+            shape.surface_at(30.0).unwrap(),
+            shape.surface_at(20.0).unwrap(),
+        );
+        assert!(lower > 0.0 && lower < upper && upper < 18.0);
+    }
+}
@@ -1,20 +1,37 @@
 use geo::orient::{Direction, Orient};
 use geo::{Contains, Intersects, LineString, Point, Polygon, Rect};
+use std::collections::HashMap;
 use std::time::Instant;
 
-use crate::clipping::clip_water_ring_to_bbox;
 use crate::{
-    block_definitions::WATER,
-    coordinate_system::cartesian::{XZBBox, XZPoint},
-    osm_parser::{ProcessedMemberRole, ProcessedNode, ProcessedRelation, ProcessedWay},
-    world_editor::WorldEditor,
+    block_definitions::{GRAVEL, SAND, WATER},
+    coordinate_system::cartesian::XZPoint,
+    osm_parser::{
+        is_water_element, verify_closed_rings, ProcessedMemberRole, ProcessedNode,
+        ProcessedRelation, ProcessedWay,
+    },
+    time_of_day::TimeOfDay,
+    world_editor::{Biome, BlockOwner, WorldEditor},
 };
 
-pub fn generate_water_area_from_way(
-    editor: &mut WorldEditor,
-    element: &ProcessedWay,
-    _xzbbox: &XZBBox,
-) {
+/// Deepest water generated at the center of large water bodies, in blocks
+const MAX_WATER_DEPTH: i32 = 6;
+/// Horizontal distance from the shore per block of additional depth
+const SHORE_SLOPE: i32 = 2;
+/// Depth from which the bed is gravel instead of sand
+const GRAVEL_BED_DEPTH: i32 = 3;
+/// Distance between the lights set into the floor of pools and fountains at night
+const UNDERWATER_LIGHT_SPACING: i32 = 4;
+
+/// Returns true if a light belongs into the floor of a pool or fountain at this column,
+/// only at night like street lamps
+pub fn is_underwater_light_spot(editor: &WorldEditor, x: i32, z: i32) -> bool {
+    editor.time_of_day() == TimeOfDay::Night
+        && x.rem_euclid(UNDERWATER_LIGHT_SPACING) == 0
+        && z.rem_euclid(UNDERWATER_LIGHT_SPACING) == 0
+}
+
+pub fn generate_water_area_from_way(editor: &mut WorldEditor, element: &ProcessedWay) {
     let start_time = Instant::now();
 
     let outers = [element.nodes.clone()];
@@ -23,25 +40,13 @@ pub fn generate_water_area_from_way(
         return;
     }
 
-    generate_water_areas(editor, &outers, &[], start_time);
+    generate_water_areas(editor, &outers, &[], water_biome(&element.tags), start_time);
 }
 
-pub fn generate_water_areas_from_relation(
-    editor: &mut WorldEditor,
-    element: &ProcessedRelation,
-    xzbbox: &XZBBox,
-) {
+pub fn generate_water_areas_from_relation(editor: &mut WorldEditor, element: &ProcessedRelation) {
     let start_time = Instant::now();
 
-    // Check if this is a water relation (either with water tag or natural=water)
-    let is_water = element.tags.contains_key("water")
-        || element
-            .tags
-            .get("natural")
-            .map(|val| val == "water" || val == "bay")
-            .unwrap_or(false);
-
-    if !is_water {
+    if !is_water_element(&element.tags) {
         return;
     }
 
@@ -54,82 +59,45 @@ pub fn generate_water_areas_from_relation(
 
     let mut outers: Vec<Vec<ProcessedNode>> = vec![];
     let mut inners: Vec<Vec<ProcessedNode>> = vec![];
-
     for mem in &element.members {
         match mem.role {
             ProcessedMemberRole::Outer => outers.push(mem.way.nodes.clone()),
             ProcessedMemberRole::Inner => inners.push(mem.way.nodes.clone()),
+            ProcessedMemberRole::Member | ProcessedMemberRole::Platform => {}
         }
     }
 
-    // Preserve OSM-defined outer/inner roles without modification
-    merge_way_segments(&mut outers);
-
-    // Clip assembled rings to bbox (must happen after merging to preserve ring connectivity)
-    outers = outers
-        .into_iter()
-        .filter_map(|ring| clip_water_ring_to_bbox(&ring, xzbbox))
-        .collect();
-    merge_way_segments(&mut inners);
-    inners = inners
-        .into_iter()
-        .filter_map(|ring| clip_water_ring_to_bbox(&ring, xzbbox))
-        .collect();
-
-    if !verify_closed_rings(&outers) {
-        // For clipped multipolygons, some loops may not close perfectly
-        // Instead of force-closing with straight lines (which creates wedges),
-        // filter out unclosed loops and only render the properly closed ones
-
-        // Filter: Keep only loops that are already closed OR can be closed within 1 block
-        outers.retain(|loop_nodes| {
-            if loop_nodes.len() < 3 {
-                return false;
-            }
-            let first = &loop_nodes[0];
-            let last = loop_nodes.last().unwrap();
-            let dx = (first.x - last.x).abs();
-            let dz = (first.z - last.z).abs();
-
-            // Keep if already closed by ID or endpoints are within 1 block
-            first.id == last.id || (dx <= 1 && dz <= 1)
-        });
-
-        // Now close the remaining loops that are within 1 block tolerance
-        for loop_nodes in outers.iter_mut() {
-            let first = loop_nodes[0].clone();
-            let last_idx = loop_nodes.len() - 1;
-            if loop_nodes[0].id != loop_nodes[last_idx].id {
-                // Endpoints are close (within tolerance), close the loop
-                loop_nodes.push(first);
-            }
-        }
-
-        // If no valid outer loops remain, skip the relation
-        if outers.is_empty() {
-            return;
-        }
-
-        // Verify again after filtering and closing
-        if !verify_closed_rings(&outers) {
-            println!("Skipping relation {} due to invalid polygon", element.id);
-            return;
-        }
+    // Rings arrive assembled and clipped from the parser, islands are kept as inner rings
+    if outers.is_empty() {
+        return;
     }
-
-    merge_way_segments(&mut inners);
-    if !verify_closed_rings(&inners) {
+    if !verify_closed_rings(&outers) || !verify_closed_rings(&inners) {
         println!("Skipping relation {} due to invalid polygon", element.id);
         return;
     }
 
-    generate_water_areas(editor, &outers, &inners, start_time);
+    let biome = water_biome(&element.tags);
+    generate_water_areas(editor, &outers, &inners, biome, start_time);
+}
+
+/// Returns the biome of a water body, ocean for parts of the sea and river for inland water
+fn water_biome(tags: &HashMap<String, String>) -> Biome {
+    let is_sea = |key: &str| {
+        tags.get(key)
+            .is_some_and(|value| matches!(value.as_str(), "sea" | "bay" | "strait" | "lagoon"))
+    };
+    if is_sea("natural") || is_sea("water") {
+        Biome::Ocean
+    } else {
+        Biome::River
+    }
 }
 
 fn generate_water_areas(
     editor: &mut WorldEditor,
     outers: &[Vec<ProcessedNode>],
     inners: &[Vec<ProcessedNode>],
+    biome: Biome,
     start_time: Instant,
 ) {
     // Calculate polygon bounding box to limit fill area
@@ -169,131 +137,135 @@ fn generate_water_areas(
         .map(|x| x.iter().map(|y| y.xz()).collect::<Vec<_>>())
         .collect();
 
+    if min_x > max_x || min_z > max_z {
+        return;
+    }
+
+    let mut mask = WaterMask::new(min_x, min_z, max_x, max_z);
     inverse_floodfill(
-        min_x, min_z, max_x, max_z, outers_xz, inners_xz, editor, start_time,
+        min_x, min_z, max_x, max_z, outers_xz, inners_xz, &mut mask, start_time,
     );
+    place_water(editor, mask, biome);
 }
 
-/// Merges way segments that share endpoints into closed rings.
-fn merge_way_segments(rings: &mut Vec<Vec<ProcessedNode>>) {
-    let mut removed: Vec<usize> = vec![];
-    let mut merged: Vec<Vec<ProcessedNode>> = vec![];
+/// Columns covered by a water body, with their distance to the nearest shore
+pub(crate) struct WaterMask {
+    min_x: i32,
+    min_z: i32,
+    size_x: usize,
+    size_z: usize,
+    distances: Vec<u8>,
+}
 
-    // Match nodes by ID or proximity (handles synthetic nodes from bbox clipping)
-    let nodes_match = |a: &ProcessedNode, b: &ProcessedNode| -> bool {
-        if a.id == b.id {
-            return true;
+impl WaterMask {
+    pub(crate) fn new(min_x: i32, min_z: i32, max_x: i32, max_z: i32) -> Self {
+        let size_x = (max_x - min_x + 1) as usize;
+        let size_z = (max_z - min_z + 1) as usize;
+        Self {
+            min_x,
+            min_z,
+            size_x,
+            size_z,
+            distances: vec![0; size_x * size_z],
         }
-        let dx = (a.x - b.x).abs();
-        let dz = (a.z - b.z).abs();
-        dx <= 1 && dz <= 1
-    };
+    }
 
-    for i in 0..rings.len() {
-        for j in 0..rings.len() {
-            if i == j {
-                continue;
-            }
+    pub(crate) fn mark(&mut self, x: i32, z: i32) {
+        let (dx, dz) = (x - self.min_x, z - self.min_z);
+        if dx >= 0 && dz >= 0 && (dx as usize) < self.size_x && (dz as usize) < self.size_z {
+            self.distances[dz as usize * self.size_x + dx as usize] = u8::MAX;
+        }
+    }
 
-            if removed.contains(&i) || removed.contains(&j) {
-                continue;
+    /// Turns the marked columns into their chessboard distance to the nearest unmarked column.
+    /// Columns beyond the mask are the edge of the world, not shore, and are ignored.
+    fn compute_shore_distances(&mut self) {
+        let (size_x, size_z) = (self.size_x, self.size_z);
+        let max_distance = ((MAX_WATER_DEPTH - 1) * SHORE_SLOPE + 1) as u8;
+
+        for pass in 0..2 {
+            for step_z in 0..size_z {
+                for step_x in 0..size_x {
+                    let (x, z) = if pass == 0 {
+                        (step_x, step_z)
+                    } else {
+                        (size_x - 1 - step_x, size_z - 1 - step_z)
+                    };
+                    let index = z * size_x + x;
+                    if self.distances[index] == 0 {
+                        continue;
+                    }
+
+                    // Neighbours already visited in this pass' scan order
+                    let (ox, oz): (isize, isize) = if pass == 0 { (-1, -1) } else { (1, 1) };
+                    let mut distance = self.distances[index].min(max_distance);
+                    for (dx, dz) in [(ox, 0), (-ox, oz), (0, oz), (ox, oz)] {
+                        let (nx, nz) = (x as isize + dx, z as isize + dz);
+                        if nx < 0 || nz < 0 || nx >= size_x as isize || nz >= size_z as isize {
+                            continue;
+                        }
+                        let neighbour = self.distances[nz as usize * size_x + nx as usize];
+                        distance = distance.min(neighbour.saturating_add(1));
+                    }
+                    self.distances[index] = distance;
+                }
             }
+        }
+    }
+}
 
-            let x: &Vec<ProcessedNode> = &rings[i];
-            let y: &Vec<ProcessedNode> = &rings[j];
-
-            // Skip empty rings (can happen after clipping)
-            if x.is_empty() || y.is_empty() {
-                continue;
-            }
+/// Fills the marked columns with water that gets deeper away from the shore, on a sand or
+/// gravel bed
+pub(crate) fn place_water(editor: &mut WorldEditor, mask: WaterMask, biome: Biome) {
+    place_water_with_owners(editor, mask, biome, |_, _| None);
+}
 
-            let x_first = &x[0];
-            let x_last = x.last().unwrap();
-            let y_first = &y[0];
-            let y_last = y.last().unwrap();
+/// Places water like [`place_water`], with the blocks of every column belonging to the element
+/// returned for it. Columns without one belong to the current owner of the editor.
+pub(crate) fn place_water_with_owners(
+    editor: &mut WorldEditor,
+    mut mask: WaterMask,
+    biome: Biome,
+    owner_at: impl Fn(i32, i32) -> Option<BlockOwner>,
+) {
+    mask.compute_shore_distances();
 
-            // Skip already-closed rings
-            if nodes_match(x_first, x_last) {
+    for z in 0..mask.size_z {
+        for x in 0..mask.size_x {
+            let distance = mask.distances[z * mask.size_x + x] as i32;
+            if distance == 0 {
                 continue;
             }
-
-            if nodes_match(y_first, y_last) {
-                continue;
+            let (x, z) = (mask.min_x + x as i32, mask.min_z + z as i32);
+            if let Some(owner) = owner_at(x, z) {
+                editor.set_block_owner(Some(owner));
             }
 
-            if nodes_match(x_first, y_first) {
-                removed.push(i);
-                removed.push(j);
+            editor.set_block(WATER, x, 0, z, None, None);
 
-                let mut x: Vec<ProcessedNode> = x.clone();
-                x.reverse();
-                x.extend(y.iter().skip(1).cloned());
-                merged.push(x);
-            } else if nodes_match(x_last, y_last) {
-                removed.push(i);
-                removed.push(j);
-
-                let mut x: Vec<ProcessedNode> = x.clone();
-                x.extend(y.iter().rev().skip(1).cloned());
-
-                merged.push(x);
-            } else if nodes_match(x_first, y_last) {
-                removed.push(i);
-                removed.push(j);
-
-                let mut y: Vec<ProcessedNode> = y.clone();
-                y.extend(x.iter().skip(1).cloned());
-
-                merged.push(y);
-            } else if nodes_match(x_last, y_first) {
-                removed.push(i);
-                removed.push(j);
-
-                let mut x: Vec<ProcessedNode> = x.clone();
-                x.extend(y.iter().skip(1).cloned());
+            // Only excavate columns where the water surface was actually placed
+            if !editor.check_for_block(x, 0, z, Some(&[WATER])) {
+                continue;
+            }
+            editor.set_biome(x, z, biome);
 
-                merged.push(x);
+            let depth = water_depth(distance);
+            for y in (1 - depth)..0 {
+                editor.set_block(WATER, x, y, z, None, None);
             }
+            let bed = if depth >= GRAVEL_BED_DEPTH {
+                GRAVEL
+            } else {
+                SAND
+            };
+            editor.set_block(bed, x, -depth, z, None, None);
         }
     }
-
-    removed.sort();
-
-    for r in removed.iter().rev() {
-        rings.remove(*r);
-    }
-
-    let merged_len: usize = merged.len();
-    for m in merged {
-        rings.push(m);
-    }
-
-    if merged_len > 0 {
-        merge_way_segments(rings);
-    }
 }
 
-/// Verifies all rings are properly closed (first node matches last).
-fn verify_closed_rings(rings: &[Vec<ProcessedNode>]) -> bool {
-    let mut valid = true;
-    for ring in rings {
-        let first = &ring[0];
-        let last = ring.last().unwrap();
-
-        // Check if ring is closed (by ID or proximity)
-        let is_closed = first.id == last.id || {
-            let dx = (first.x - last.x).abs();
-            let dz = (first.z - last.z).abs();
-            dx <= 1 && dz <= 1
-        };
-
-        if !is_closed {
-            eprintln!("WARN: Disconnected ring");
-            valid = false;
-        }
-    }
-
-    valid
+/// Returns the depth of water at a distance from the shore, one block deep right at the shore
+fn water_depth(shore_distance: i32) -> i32 {
+    (1 + (shore_distance - 1) / SHORE_SLOPE).clamp(1, MAX_WATER_DEPTH)
 }
 
 // Water areas are absolutely huge. We can't easily flood fill the entire thing.
@@ -307,7 +279,7 @@ fn inverse_floodfill(
     max_z: i32,
     outers: Vec<Vec<XZPoint>>,
     inners: Vec<Vec<XZPoint>>,
-    editor: &mut WorldEditor,
+    mask: &mut WaterMask,
     start_time: Instant,
 ) {
     // Convert to geo Polygons with normalized winding order
@@ -346,7 +318,7 @@ fn inverse_floodfill(
         (max_x, max_z),
         &outers,
         &inners,
-        editor,
+        mask,
         start_time,
     );
 }
@@ -356,7 +328,7 @@ fn inverse_floodfill_recursive(
     max: (i32, i32),
     outers: &[Polygon],
     inners: &[Polygon],
-    editor: &mut WorldEditor,
+    mask: &mut WaterMask,
     start_time: Instant,
 ) {
     // Check if we've exceeded 25 seconds
@@ -373,7 +345,7 @@ fn inverse_floodfill_recursive(
     // Multiply as i64 to avoid overflow; in release builds where unchecked math is
     // enabled, this could cause the rest of this code to end up in an infinite loop.
     if ((max.0 - min.0) as i64) * ((max.1 - min.1) as i64) < ITERATIVE_THRES {
-        inverse_floodfill_iterative(min, max, 0, outers, inners, editor);
+        inverse_floodfill_iterative(min, max, outers, inners, mask);
         return;
     }
 
@@ -395,7 +367,7 @@ fn inverse_floodfill_recursive(
         if outers.iter().any(|outer: &Polygon| outer.contains(&rect))
             && !inners.iter().any(|inner: &Polygon| inner.intersects(&rect))
         {
-            rect_fill(min_x, max_x, min_z, max_z, 0, editor);
+            rect_fill(min_x, max_x, min_z, max_z, mask);
             continue;
         }
 
@@ -416,7 +388,7 @@ fn inverse_floodfill_recursive(
                 (max_x, max_z),
                 &outers_intersects,
                 &inners_intersects,
-                editor,
+                mask,
                 start_time,
             );
         }
@@ -427,10 +399,9 @@ fn inverse_floodfill_recursive(
 fn inverse_floodfill_iterative(
     min: (i32, i32),
     max: (i32, i32),
-    ground_level: i32,
     outers: &[Polygon],
     inners: &[Polygon],
-    editor: &mut WorldEditor,
+    mask: &mut WaterMask,
 ) {
     for x in min.0..max.0 {
         for z in min.1..max.1 {
@@ -439,23 +410,39 @@ fn inverse_floodfill_iterative(
             if outers.iter().any(|poly: &Polygon| poly.contains(&p))
                 && inners.iter().all(|poly: &Polygon| !poly.contains(&p))
             {
-                editor.set_block(WATER, x, ground_level, z, None, None);
+                mask.mark(x, z);
             }
         }
     }
 }
 
-fn rect_fill(
-    min_x: i32,
-    max_x: i32,
-    min_z: i32,
-    max_z: i32,
-    ground_level: i32,
-    editor: &mut WorldEditor,
-) {
+fn rect_fill(min_x: i32, max_x: i32, min_z: i32, max_z: i32, mask: &mut WaterMask) {
     for x in min_x..max_x {
         for z in min_z..max_z {
-            editor.set_block(WATER, x, ground_level, z, None, None);
+            mask.mark(x, z);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shore_distances() {
+        let mut mask = WaterMask::new(0, 0, 8, 8);
+        for x in 1..8 {
+            for z in 1..8 {
+                mask.mark(x, z);
+            }
         }
+        mask.compute_shore_distances();
+
+        assert_eq!(mask.distances[0], 0);
+        assert_eq!(mask.distances[9 + 1], 1);
+        assert_eq!(mask.distances[4 * 9 + 4], 4);
+        assert_eq!(water_depth(1), 1);
+        assert_eq!(water_depth(4), 2);
+        assert_eq!(water_depth(100), MAX_WATER_DEPTH);
     }
 }
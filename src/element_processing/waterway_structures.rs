@@ -0,0 +1,227 @@
+use crate::args::Args;
+use crate::block_definitions::*;
+use crate::bresenham::bresenham_line;
+use crate::floodfill::flood_fill_area;
+use crate::osm_parser::ProcessedWay;
+use crate::world_editor::WorldEditor;
+use std::collections::HashSet;
+
+/// Height of dams above the ground without a height tag or elevation data, in blocks
+const DEFAULT_DAM_HEIGHT: i32 = 6;
+/// Height of the dam crest above the higher of the water levels on its two sides
+const DAM_FREEBOARD: i32 = 1;
+/// Distance from the dam at which the terrain on both sides is sampled
+const DAM_LEVEL_SAMPLE_DISTANCE: i32 = 6;
+/// Depth of the dam foundation below the ground
+const DAM_FOUNDATION_DEPTH: i32 = 3;
+
+/// Returns true for dams, weirs and culverts, which are built once the waterways, roads and
+/// buildings they cross exist
+pub fn is_waterway_structure(way: &ProcessedWay) -> bool {
+    is_culvert(way)
+        || matches!(
+            way.tags.get("waterway").map(String::as_str),
+            Some("dam" | "weir")
+        )
+}
+
+fn is_culvert(way: &ProcessedWay) -> bool {
+    way.tags.get("tunnel").map(String::as_str) == Some("culvert")
+}
+
+pub fn generate_waterway_structure(editor: &mut WorldEditor, way: &ProcessedWay, args: &Args) {
+    match way.tags.get("waterway").map(String::as_str) {
+        _ if is_culvert(way) => generate_culvert(editor, way),
+        Some("dam") => generate_dam(editor, way, args),
+        Some("weir") => generate_weir(editor, way, args),
+        _ => {}
+    }
+}
+
+/// Builds a concrete wall rising above the water level held back on its upstream side
+fn generate_dam(editor: &mut WorldEditor, way: &ProcessedWay, args: &Args) {
+    let thickness = ((3.0 * args.scale).round() as i32).max(1);
+    let columns = structure_columns(way, thickness, args);
+    if columns.is_empty() {
+        return;
+    }
+
+    let grounds: Vec<i32> = columns
+        .iter()
+        .map(|&(x, z)| editor.get_absolute_y(x, 0, z))
+        .collect();
+    let highest_ground = grounds.iter().copied().max().unwrap_or_default();
+
+    let elevation_enabled = editor
+        .get_ground()
+        .is_some_and(|ground| ground.elevation_enabled);
+    let tagged_height = way
+        .tags
+        .get("height")
+        .and_then(|height| height.trim_end_matches('m').trim().parse::<f64>().ok())
+        .map(|height| (height * args.scale).round() as i32);
+
+    let crest = if let Some(height) = tagged_height {
+        highest_ground + height.max(1)
+    } else if elevation_enabled {
+        // The terrain model shows the water surfaces, the upstream one is the higher side
+        let d = DAM_LEVEL_SAMPLE_DISTANCE;
+        let water_level = columns
+            .iter()
+            .flat_map(|&(x, z)| [(x + d, z), (x - d, z), (x, z + d), (x, z - d)])
+            .map(|(x, z)| editor.get_absolute_y(x, 0, z))
+            .max()
+            .unwrap_or(highest_ground);
+        (water_level + DAM_FREEBOARD).max(highest_ground + 2)
+    } else {
+        highest_ground + DEFAULT_DAM_HEIGHT
+    };
+
+    for (&(x, z), &ground) in columns.iter().zip(&grounds) {
+        for y in (ground - DAM_FOUNDATION_DEPTH)..=crest {
+            editor.set_block_absolute(LIGHT_GRAY_CONCRETE, x, y, z, None, Some(&[]));
+        }
+        editor.set_block_absolute(SMOOTH_STONE, x, crest + 1, z, None, None);
+    }
+}
+
+/// Builds a low sill across a river that the water flows over
+fn generate_weir(editor: &mut WorldEditor, way: &ProcessedWay, args: &Args) {
+    let thickness = ((2.0 * args.scale).round() as i32).max(1);
+    for (x, z) in structure_columns(way, thickness, args) {
+        if !editor.check_for_block(x, 0, z, Some(&[WATER])) {
+            continue;
+        }
+        editor.set_block(STONE_BRICKS, x, -1, z, Some(&[WATER, DIRT]), None);
+        editor.set_block(STONE_BRICK_SLAB, x, 0, z, Some(&[WATER]), None);
+    }
+}
+
+/// Carves a water channel one block wide and two blocks high below whatever the stream passes
+/// under, lined with stone bricks
+fn generate_culvert(editor: &mut WorldEditor, way: &ProcessedWay) {
+    let mut channel: HashSet<(i32, i32)> = HashSet::new();
+    for pair in way.nodes.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        channel.extend(
+            bresenham_line(a.x, 0, a.z, b.x, 0, b.z)
+                .into_iter()
+                .map(|(x, _, z)| (x, z)),
+        );
+    }
+
+    for &(x, z) in &channel {
+        editor.set_block(STONE_BRICKS, x, -3, z, None, Some(&[]));
+        for y in -2..=-1 {
+            editor.set_block(WATER, x, y, z, None, Some(&[]));
+        }
+        // Roads and floors above the culvert are kept as its ceiling
+        editor.set_block(STONE_BRICKS, x, 0, z, None, None);
+
+        for (dx, dz) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            if channel.contains(&(x + dx, z + dz)) {
+                continue;
+            }
+            for y in -2..=-1 {
+                editor.set_block(STONE_BRICKS, x + dx, y, z + dz, None, Some(&[WATER]));
+            }
+        }
+    }
+}
+
+/// Builds the walls of a lock chamber and its gates at both ends. With elevation data the
+/// chamber is filled up to the higher of the two water levels.
+pub fn generate_lock(editor: &mut WorldEditor, way: &ProcessedWay, width: i32, depth: i32) {
+    let half_width = width / 2;
+    let mut chamber: HashSet<(i32, i32)> = HashSet::new();
+    let mut surroundings: HashSet<(i32, i32)> = HashSet::new();
+    for pair in way.nodes.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        for (x, _, z) in bresenham_line(a.x, 0, a.z, b.x, 0, b.z) {
+            for dx in -(half_width + 1)..=(half_width + 1) {
+                for dz in -(half_width + 1)..=(half_width + 1) {
+                    if dx.abs() <= half_width && dz.abs() <= half_width {
+                        chamber.insert((x + dx, z + dz));
+                    } else {
+                        surroundings.insert((x + dx, z + dz));
+                    }
+                }
+            }
+        }
+    }
+
+    // Without elevation data both sides of the lock are at the same level
+    let elevation_enabled = editor
+        .get_ground()
+        .is_some_and(|ground| ground.elevation_enabled);
+    let water_level = elevation_enabled
+        .then(|| {
+            chamber
+                .iter()
+                .map(|&(x, z)| editor.get_absolute_y(x, 0, z))
+                .max()
+        })
+        .flatten();
+
+    for &(x, z) in surroundings.difference(&chamber) {
+        let ground = editor.get_absolute_y(x, 0, z);
+        let top = water_level.map_or(ground + 1, |level| level.max(ground) + 1);
+        for y in (ground - depth)..=top {
+            editor.set_block_absolute(STONE_BRICKS, x, y, z, None, Some(&[]));
+        }
+    }
+
+    if let Some(water_level) = water_level {
+        for &(x, z) in &chamber {
+            for y in editor.get_absolute_y(x, 1, z)..=water_level {
+                editor.set_block_absolute(WATER, x, y, z, None, None);
+            }
+        }
+    }
+
+    if let (Some(first), Some(last)) = (way.nodes.first(), way.nodes.last()) {
+        generate_lock_gate(editor, first.x, first.z, width, depth);
+        generate_lock_gate(editor, last.x, last.z, width, depth);
+    }
+}
+
+/// Closes the channel with wooden gates reaching from its bed to above the water
+pub fn generate_lock_gate(editor: &mut WorldEditor, x: i32, z: i32, width: i32, depth: i32) {
+    let half_width = width / 2;
+    for dx in -half_width..=half_width {
+        for dz in -half_width..=half_width {
+            for y in (1 - depth)..=1 {
+                editor.set_block(DARK_OAK_PLANKS, x + dx, y, z + dz, None, Some(&[]));
+            }
+        }
+    }
+}
+
+/// Returns the columns covered by a structure, the area of closed ways and a band of the
+/// given thickness along open ones
+fn structure_columns(way: &ProcessedWay, thickness: i32, args: &Args) -> Vec<(i32, i32)> {
+    let outline: Vec<(i32, i32)> = way.nodes.iter().map(|n| (n.x, n.z)).collect();
+    let is_closed =
+        way.nodes.len() >= 4 && way.nodes.first().map(|n| n.id) == way.nodes.last().map(|n| n.id);
+
+    let mut columns: HashSet<(i32, i32)> = HashSet::new();
+    if is_closed {
+        columns.extend(flood_fill_area(&outline, args.timeout.as_ref()));
+    }
+
+    let (low, high) = (-(thickness - 1) / 2, thickness / 2);
+    for pair in outline.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        for (x, _, z) in bresenham_line(a.0, 0, a.1, b.0, 0, b.1) {
+            for dx in low..=high {
+                for dz in low..=high {
+                    columns.insert((x + dx, z + dz));
+                }
+            }
+        }
+    }
+
+    let mut columns: Vec<(i32, i32)> = columns.into_iter().collect();
+    columns.sort_unstable();
+    columns
+}
@@ -1,21 +1,36 @@
+use crate::args::Args;
 use crate::block_definitions::*;
 use crate::bresenham::bresenham_line;
-use crate::osm_parser::ProcessedWay;
-use crate::world_editor::WorldEditor;
+use crate::element_processing::waterway_structures::{generate_lock, generate_lock_gate};
+use crate::element_rng::element_rng;
+use crate::osm_parser::{ProcessedNode, ProcessedWay};
+use crate::world_editor::{Biome, WorldEditor};
+use rand::Rng;
 
-pub fn generate_waterways(editor: &mut WorldEditor, element: &ProcessedWay) {
+/// Widest channel generated from a width tag, in blocks
+const MAX_WATERWAY_WIDTH: i32 = 80;
+/// Drop of the terrain between two neighbouring channel columns that makes a waterfall
+const WATERFALL_MIN_DROP: i32 = 3;
+/// Drop between two neighbouring channel columns that makes rapids
+const RAPIDS_MIN_DROP: i32 = 2;
+/// Share of the channel columns that get a rock on rapids
+const RAPIDS_ROCK_CHANCE: f64 = 0.15;
+
+pub fn generate_waterways(editor: &mut WorldEditor, element: &ProcessedWay, args: &Args) {
     if let Some(waterway_type) = element.tags.get("waterway") {
-        let (mut waterway_width, waterway_depth) = get_waterway_dimensions(waterway_type);
-
-        // Check for custom width in tags
-        if let Some(width_str) = element.tags.get("width") {
-            waterway_width = width_str.parse::<i32>().unwrap_or_else(|_| {
-                width_str
-                    .parse::<f32>()
-                    .map(|f: f32| f as i32)
-                    .unwrap_or(waterway_width)
-            });
-        }
+        let (default_width, default_depth) = get_waterway_dimensions(waterway_type);
+
+        // Widths are tagged in meters, the defaults per waterway class are meters as well
+        let width_meters = element
+            .tags
+            .get("width")
+            .and_then(|width| parse_width_meters(width))
+            .unwrap_or(default_width as f64);
+        let waterway_width =
+            ((width_meters * args.scale).round() as i32).clamp(1, MAX_WATERWAY_WIDTH);
+
+        // Wide rivers get a deeper bed than their class default
+        let waterway_depth = default_depth.max((waterway_width / 6).min(5));
 
         // Skip layers below the ground level
         if matches!(
@@ -25,6 +40,10 @@ pub fn generate_waterways(editor: &mut WorldEditor, element: &ProcessedWay) {
             return;
         }
 
+        let elevation_enabled = editor
+            .get_ground()
+            .is_some_and(|ground| ground.elevation_enabled);
+
         // Process consecutive node pairs to create waterways
         // Use windows(2) to avoid connecting last node back to first
         for nodes_pair in element.nodes.windows(2) {
@@ -41,26 +60,142 @@ pub fn generate_waterways(editor: &mut WorldEditor, element: &ProcessedWay) {
                 current_node.z,
             );
 
+            let mut previous_column: Option<(i32, i32, i32)> = None;
             for (bx, _, bz) in bresenham_points {
                 // Create water channel with proper depth and sloped banks
                 create_water_channel(editor, bx, bz, waterway_width, waterway_depth);
+
+                if !elevation_enabled {
+                    continue;
+                }
+
+                // Steep terrain turns the stepped channel into a waterfall or rapids,
+                // both are placed at the lower of the two columns
+                let surface = editor.get_absolute_y(bx, 0, bz);
+                if let Some((previous_x, previous_z, previous_surface)) = previous_column {
+                    let drop = (previous_surface - surface).abs();
+                    let (x, z) = if previous_surface > surface {
+                        (bx, bz)
+                    } else {
+                        (previous_x, previous_z)
+                    };
+                    if drop >= WATERFALL_MIN_DROP {
+                        generate_waterfall(editor, x, z, drop, waterway_width, waterway_depth);
+                    } else if drop >= RAPIDS_MIN_DROP {
+                        generate_rapids(editor, x, z, waterway_width);
+                    }
+                }
+                previous_column = Some((bx, bz, surface));
             }
         }
+
+        // Tagged waterfalls get a plunge pool even where the terrain shows no drop
+        for node in &element.nodes {
+            if is_waterfall_node(node) {
+                generate_plunge_pool(editor, node.x, node.z, waterway_width, waterway_depth);
+            } else if node.tags.get("waterway").map(String::as_str) == Some("lock_gate") {
+                generate_lock_gate(editor, node.x, node.z, waterway_width, waterway_depth);
+            }
+        }
+
+        if element.tags.get("lock").map(String::as_str) == Some("yes") {
+            generate_lock(editor, element, waterway_width, waterway_depth);
+        }
     }
 }
 
-/// Determines width and depth based on waterway type
+pub fn is_waterfall_node(node: &ProcessedNode) -> bool {
+    node.tags.get("waterway").map(String::as_str) == Some("waterfall")
+}
+
+/// Generates a plunge pool for a waterfall node that is not part of a waterway
+pub fn generate_waterfall_node(editor: &mut WorldEditor, node: &ProcessedNode) {
+    let (width, depth) = get_waterway_dimensions("stream");
+    generate_plunge_pool(editor, node.x, node.z, width, depth);
+}
+
+/// Fills the gap between the upper and the lower channel with a wall of falling water, ending
+/// in a plunge pool at the foot of the fall
+fn generate_waterfall(editor: &mut WorldEditor, x: i32, z: i32, drop: i32, width: i32, depth: i32) {
+    let half_width = width / 2;
+    for dx in -half_width..=half_width {
+        for dz in -half_width..=half_width {
+            for y in 1..=drop {
+                editor.set_block(WATER, x + dx, y, z + dz, None, None);
+            }
+        }
+    }
+    generate_plunge_pool(editor, x, z, width, depth);
+}
+
+/// Digs a round pool, deeper than the channel, where falling water lands
+fn generate_plunge_pool(editor: &mut WorldEditor, x: i32, z: i32, width: i32, depth: i32) {
+    let radius = width / 2 + 2;
+    let pool_depth = depth + 2;
+    for dx in -radius..=radius {
+        for dz in -radius..=radius {
+            if dx * dx + dz * dz > radius * radius {
+                continue;
+            }
+            // Replace the bed and banks of the channel the pool is dug into
+            for y in (1 - pool_depth)..=0 {
+                editor.set_block(WATER, x + dx, y, z + dz, Some(&[DIRT, AIR]), None);
+            }
+            editor.set_block(GRAVEL, x + dx, -pool_depth, z + dz, None, None);
+            editor.set_block(
+                AIR,
+                x + dx,
+                1,
+                z + dz,
+                Some(&[GRASS, WHEAT, CARROTS, POTATOES]),
+                None,
+            );
+        }
+    }
+}
+
+/// Scatters rocks breaking the surface of the channel where it drops by a few blocks
+fn generate_rapids(editor: &mut WorldEditor, x: i32, z: i32, width: i32) {
+    let mut rng = element_rng();
+    let half_width = width / 2;
+    for dx in -half_width..=half_width {
+        for dz in -half_width..=half_width {
+            if rng.gen_bool(RAPIDS_ROCK_CHANCE) {
+                let rock = if rng.gen_bool(0.5) {
+                    COBBLESTONE
+                } else {
+                    MOSSY_COBBLESTONE
+                };
+                editor.set_block(rock, x + dx, 0, z + dz, Some(&[WATER]), None);
+            }
+        }
+    }
+}
+
+/// Parses a width tag in meters, accepting values like "12", "12.5", "12 m" and "12,5"
+fn parse_width_meters(width: &str) -> Option<f64> {
+    width
+        .trim()
+        .trim_end_matches('m')
+        .trim()
+        .replace(',', ".")
+        .parse::<f64>()
+        .ok()
+        .filter(|width| width.is_finite() && *width > 0.0)
+}
+
+/// Determines the default width in meters and depth in blocks based on waterway type
 fn get_waterway_dimensions(waterway_type: &str) -> (i32, i32) {
     match waterway_type {
-        "river" => (8, 3),    // Large rivers: 8 blocks wide, 3 blocks deep
-        "canal" => (6, 2),    // Canals: 6 blocks wide, 2 blocks deep
-        "stream" => (3, 2),   // Streams: 3 blocks wide, 2 blocks deep
-        "fairway" => (12, 3), // Shipping fairways: 12 blocks wide, 3 blocks deep
-        "flowline" => (2, 1), // Water flow lines: 2 blocks wide, 1 block deep
-        "brook" => (2, 1),    // Small brooks: 2 blocks wide, 1 block deep
-        "ditch" => (2, 1),    // Ditches: 2 blocks wide, 1 block deep
-        "drain" => (1, 1),    // Drainage: 1 block wide, 1 block deep
-        _ => (4, 2),          // Default: 4 blocks wide, 2 blocks deep
+        "river" => (8, 3),    // Large rivers: 8 m wide, 3 blocks deep
+        "canal" => (6, 2),    // Canals: 6 m wide, 2 blocks deep
+        "stream" => (3, 2),   // Streams: 3 m wide, 2 blocks deep
+        "fairway" => (12, 3), // Shipping fairways: 12 m wide, 3 blocks deep
+        "flowline" => (2, 1), // Water flow lines: 2 m wide, 1 block deep
+        "brook" => (2, 1),    // Small brooks: 2 m wide, 1 block deep
+        "ditch" => (2, 1),    // Ditches: 2 m wide, 1 block deep
+        "drain" => (1, 1),    // Drainage: 1 m wide, 1 block deep
+        _ => (4, 2),          // Default: 4 m wide, 2 blocks deep
     }
 }
 
@@ -88,6 +223,7 @@ fn create_water_channel(
 
                 // Place one layer of dirt below the water channel
                 editor.set_block(DIRT, x, -depth, z, None, None);
+                editor.set_biome(x, z, Biome::River);
 
                 // Clear vegetation above the water
                 editor.set_block(AIR, x, 1, z, Some(&[GRASS, WHEAT, CARROTS, POTATOES]), None);
@@ -113,3 +249,17 @@ fn create_water_channel(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_width_meters() {
+        assert_eq!(parse_width_meters("12"), Some(12.0));
+        assert_eq!(parse_width_meters("12.5 m"), Some(12.5));
+        assert_eq!(parse_width_meters("7,5m"), Some(7.5));
+        assert_eq!(parse_width_meters("wide"), None);
+        assert_eq!(parse_width_meters("0"), None);
+    }
+}
@@ -0,0 +1,68 @@
+//! Random numbers for the generation of elements.
+//!
+//! Every thread keeps one generator, which is reseeded from the id of each element before it
+//! is generated. An element crossing the border of two tiles thus picks the same materials and
+//! shapes in both tiles, no matter which thread generates them.
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::cell::RefCell;
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Reseeds the generator of the current thread for the element generated next
+pub fn seed_for_element(kind: &str, id: u64) {
+    let kind_seed = kind.bytes().fold(0u64, |seed, byte| {
+        seed.wrapping_mul(31).wrapping_add(byte as u64)
+    });
+    let seed = id.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ kind_seed;
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+/// Returns a handle to the generator of the current thread
+pub fn element_rng() -> ElementRng {
+    ElementRng
+}
+
+/// Handle to the random number generator of the current thread
+#[derive(Clone, Copy, Debug)]
+pub struct ElementRng;
+
+impl RngCore for ElementRng {
+    fn next_u32(&mut self) -> u32 {
+        RNG.with(|rng| rng.borrow_mut().next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        RNG.with(|rng| rng.borrow_mut().next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        RNG.with(|rng| rng.borrow_mut().fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        RNG.with(|rng| rng.borrow_mut().try_fill_bytes(dest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_same_element_draws_same_numbers() {
+        seed_for_element("way", 42);
+        let first: Vec<u32> = (0..4).map(|_| element_rng().gen()).collect();
+        seed_for_element("way", 42);
+        let second: Vec<u32> = (0..4).map(|_| element_rng().gen()).collect();
+        seed_for_element("node", 42);
+        let other: Vec<u32> = (0..4).map(|_| element_rng().gen()).collect();
+
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+}
@@ -1,11 +1,13 @@
-use crate::coordinate_system::{geographic::LLBBox, transformation::geo_distance};
+use crate::coordinate_system::geographic::{LLBBox, LLPoint};
+use crate::coordinate_system::transformation::{CoordTransformer, Projection};
+use crate::height_grid::HeightGrid;
 #[cfg(feature = "gui")]
 use crate::telemetry::{send_log, LogLevel};
 use image::Rgb;
 use std::path::Path;
 
 /// Maximum Y coordinate in Minecraft (build height limit)
-const MAX_Y: i32 = 319;
+pub(crate) const MAX_Y: i32 = 319;
 /// Scale factor for converting real elevation to Minecraft heights
 const BASE_HEIGHT_SCALE: f64 = 0.7;
 /// AWS S3 Terrarium tiles endpoint (no API key required)
@@ -22,11 +24,22 @@ const MAX_ZOOM: u8 = 15;
 #[derive(Clone)]
 pub struct ElevationData {
     /// Height values in Minecraft Y coordinates
-    pub(crate) heights: Vec<Vec<i32>>,
+    pub(crate) heights: HeightGrid,
     /// Width of the elevation grid
     pub(crate) width: usize,
     /// Height of the elevation grid
     pub(crate) height: usize,
+    /// Real altitude of the heights, None for generated terrain
+    pub(crate) altitude: Option<AltitudeScale>,
+}
+
+/// Conversion of Minecraft heights back to the real altitude they were scaled from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AltitudeScale {
+    /// Altitude in meters at the ground level
+    pub(crate) ground_altitude: f64,
+    /// Meters of altitude per block of height
+    pub(crate) meters_per_block: f64,
 }
 
 /// Calculates appropriate zoom level for the given bounding box
@@ -68,24 +81,32 @@ fn download_tile(
     Ok(img.to_rgb8())
 }
 
+/// Returns the width and height of the elevation grid, which match the size of the world
+pub(crate) fn grid_size(bbox: &LLBBox, scale: f64, projection: Projection) -> (usize, usize) {
+    CoordTransformer::llbbox_to_xzbbox(bbox, scale, projection).map_or(
+        (0, 0),
+        |(transformer, _)| {
+            (
+                transformer.scale_factor_x() as usize,
+                transformer.scale_factor_z() as usize,
+            )
+        },
+    )
+}
+
 pub fn fetch_elevation_data(
     bbox: &LLBBox,
     scale: f64,
+    projection: Projection,
     ground_level: i32,
 ) -> Result<ElevationData, Box<dyn std::error::Error>> {
-    let (base_scale_z, base_scale_x) = geo_distance(bbox.min(), bbox.max());
-
-    // Apply same floor() and scale operations as CoordTransformer.llbbox_to_xzbbox()
-    let scale_factor_z: f64 = base_scale_z.floor() * scale;
-    let scale_factor_x: f64 = base_scale_x.floor() * scale;
-
     // Calculate zoom and tiles
     let zoom: u8 = calculate_zoom_level(bbox);
     let tiles: Vec<(u32, u32)> = get_tile_coordinates(bbox, zoom);
 
     // Match grid dimensions with Minecraft world size
-    let grid_width: usize = scale_factor_x as usize;
-    let grid_height: usize = scale_factor_z as usize;
+    let (grid_width, grid_height) = grid_size(bbox, scale, projection);
+    let (transformer, _) = CoordTransformer::llbbox_to_xzbbox(bbox, scale, projection)?;
 
     // Initialize height grid with proper dimensions
     let mut height_grid: Vec<Vec<f64>> = vec![vec![f64::NAN; grid_width]; grid_height];
@@ -197,15 +218,15 @@ pub fn fetch_elevation_data(
                     continue;
                 }
 
-                // Map geographic coordinates to grid coordinates
-                let rel_x = (pixel_lng - bbox.min().lng()) / (bbox.max().lng() - bbox.min().lng());
-                let rel_y =
-                    1.0 - (pixel_lat - bbox.min().lat()) / (bbox.max().lat() - bbox.min().lat());
+                // Map geographic coordinates to grid coordinates the way the OSM data is
+                let position = transformer.transform_point(LLPoint::new(pixel_lat, pixel_lng)?);
+                let (scaled_x, scaled_y) = (position.x as usize, position.z as usize);
 
-                let scaled_x = (rel_x * grid_width as f64).round() as usize;
-                let scaled_y = (rel_y * grid_height as f64).round() as usize;
-
-                if scaled_y >= grid_height || scaled_x >= grid_width {
+                if position.x < 0
+                    || position.z < 0
+                    || scaled_y >= grid_height
+                    || scaled_x >= grid_width
+                {
                     continue;
                 }
 
@@ -239,6 +260,19 @@ pub fn fetch_elevation_data(
         eprintln!("This may indicate corrupted tile data or areas with invalid elevation data");
     }
 
+    Ok(heights_from_meters(height_grid, scale, ground_level))
+}
+
+/// Converts a grid of elevations in meters, NaN where unknown, to Minecraft heights above the
+/// ground level. The grid is smoothed and its height range fitted into the build height.
+pub(crate) fn heights_from_meters(
+    mut height_grid: Vec<Vec<f64>>,
+    scale: f64,
+    ground_level: i32,
+) -> ElevationData {
+    let grid_height: usize = height_grid.len();
+    let grid_width: usize = height_grid.first().map_or(0, Vec::len);
+
     // Fill in any NaN values by interpolating from nearest valid values
     fill_nan_values(&mut height_grid);
 
@@ -275,8 +309,6 @@ pub fn fetch_elevation_data(
     // Continue with the existing blur and conversion to Minecraft heights...
     let blurred_heights: Vec<Vec<f64>> = apply_gaussian_blur(&height_grid, sigma);
 
-    let mut mc_heights: Vec<Vec<i32>> = Vec::with_capacity(blurred_heights.len());
-
     // Find min/max in raw data
     let mut min_height: f64 = f64::MAX;
     let mut max_height: f64 = f64::MIN;
@@ -330,8 +362,10 @@ pub fn fetch_elevation_data(
         eprintln!("Adjusted scaled range: {scaled_range:.1} blocks");
     }
 
-    // Convert to scaled Minecraft Y coordinates
-    for row in blurred_heights {
+    // Convert to scaled Minecraft Y coordinates, row by row into the grid
+    let mut min_block_height: i32 = i32::MAX;
+    let mut max_block_height: i32 = i32::MIN;
+    let mc_heights = blurred_heights.into_iter().map(|row| {
         let mc_row: Vec<i32> = row
             .iter()
             .map(|&h| {
@@ -342,24 +376,24 @@ pub fn fetch_elevation_data(
                 ((ground_level as f64 + scaled_height).round() as i32).clamp(ground_level, MAX_Y)
             })
             .collect();
-        mc_heights.push(mc_row);
-    }
-
-    let mut min_block_height: i32 = i32::MAX;
-    let mut max_block_height: i32 = i32::MIN;
-    for row in &mc_heights {
-        for &height in row {
+        for &height in &mc_row {
             min_block_height = min_block_height.min(height);
             max_block_height = max_block_height.max(height);
         }
-    }
+        mc_row
+    });
+    let heights = HeightGrid::from_rows(grid_width, grid_height, mc_heights);
     eprintln!("Minecraft height data range: {min_block_height} to {max_block_height} blocks");
 
-    Ok(ElevationData {
-        heights: mc_heights,
+    ElevationData {
+        heights,
         width: grid_width,
         height: grid_height,
-    })
+        altitude: Some(AltitudeScale {
+            ground_altitude: min_height,
+            meters_per_block: 1.0 / height_scale,
+        }),
+    }
 }
 
 fn get_tile_coordinates(bbox: &LLBBox, zoom: u8) -> Vec<(u32, u32)> {
@@ -376,7 +410,7 @@ fn get_tile_coordinates(bbox: &LLBBox, zoom: u8) -> Vec<(u32, u32)> {
     tiles
 }
 
-fn apply_gaussian_blur(heights: &[Vec<f64>], sigma: f64) -> Vec<Vec<f64>> {
+pub(crate) fn apply_gaussian_blur(heights: &[Vec<f64>], sigma: f64) -> Vec<Vec<f64>> {
     let kernel_size: usize = (sigma * 3.0).ceil() as usize * 2 + 1;
     let kernel: Vec<f64> = create_gaussian_kernel(kernel_size, sigma);
 
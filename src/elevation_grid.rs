@@ -0,0 +1,232 @@
+//! Elevation grids in meters over WGS84 longitude and latitude, as read from GeoTIFF files and
+//! ESRI ASCII grids. They are the format of local DEM files and of the OpenTopography API.
+
+use crate::coordinate_system::geographic::LLBBox;
+use crate::coordinate_system::transformation::{CoordTransformer, Projection};
+use crate::elevation_data::{grid_size, heights_from_meters, ElevationData};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::Tag;
+
+/// Elevations below this are treated as missing, which covers the usual no-data values
+const MIN_VALID_ELEVATION: f64 = -1000.0;
+
+/// Elevations in meters on a regular grid of longitude and latitude
+pub struct ElevationGrid {
+    /// Rows from north to south
+    values: Vec<f64>,
+    columns: usize,
+    rows: usize,
+    /// Longitude of the west edge and latitude of the north edge of the grid
+    west: f64,
+    north: f64,
+    /// Size of a cell in degrees
+    cell_width: f64,
+    cell_height: f64,
+}
+
+impl ElevationGrid {
+    /// Reads a GeoTIFF or, for the .asc extension, an ESRI ASCII grid
+    pub fn read(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let is_ascii = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("asc"));
+        if is_ascii {
+            Self::parse_ascii(&std::fs::read_to_string(path)?)
+        } else {
+            Self::read_geotiff(path)
+        }
+    }
+
+    /// Parses an ESRI ASCII grid, whose header gives the size, lower left corner and cell
+    /// size of the grid, followed by the values row by row from north to south
+    pub fn parse_ascii(text: &str) -> Result<Self, Box<dyn Error>> {
+        let mut tokens = text.split_whitespace().peekable();
+        let mut header: Vec<(String, f64)> = Vec::new();
+        while let Some(key) = tokens.next_if(|token| token.starts_with(char::is_alphabetic)) {
+            let value = tokens.next().ok_or("Incomplete ASCII grid header")?;
+            header.push((key.to_lowercase(), value.parse()?));
+        }
+        let field = |name: &str| header.iter().find(|(key, _)| key == name).map(|e| e.1);
+
+        let columns = field("ncols").ok_or("ASCII grid without ncols")? as usize;
+        let rows = field("nrows").ok_or("ASCII grid without nrows")? as usize;
+        let cell_size = field("cellsize").ok_or("ASCII grid without cellsize")?;
+        // The lower left point is either the corner or the middle of the lower left cell
+        let west = field("xllcorner")
+            .or_else(|| field("xllcenter").map(|x| x - cell_size / 2.0))
+            .ok_or("ASCII grid without xllcorner")?;
+        let south = field("yllcorner")
+            .or_else(|| field("yllcenter").map(|y| y - cell_size / 2.0))
+            .ok_or("ASCII grid without yllcorner")?;
+        let no_data = field("nodata_value");
+
+        let values = tokens
+            .map(|token| -> Result<f64, Box<dyn Error>> {
+                let value: f64 = token.parse()?;
+                Ok(if Some(value) == no_data {
+                    f64::NAN
+                } else {
+                    value
+                })
+            })
+            .collect::<Result<Vec<f64>, Box<dyn Error>>>()?;
+        Self::new(
+            values,
+            columns,
+            rows,
+            west,
+            south + rows as f64 * cell_size,
+            (cell_size, cell_size),
+        )
+    }
+
+    /// Reads the first image of a GeoTIFF in WGS84 longitude and latitude, placed by its
+    /// tie point and pixel scale
+    fn read_geotiff(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut decoder = Decoder::new(BufReader::new(File::open(path)?))?;
+        let (columns, rows) = decoder.dimensions()?;
+        let pixel_scale = decoder.get_tag_f64_vec(Tag::ModelPixelScaleTag)?;
+        let tie_point = decoder.get_tag_f64_vec(Tag::ModelTiepointTag)?;
+        if pixel_scale.len() < 2 || tie_point.len() < 6 {
+            return Err("GeoTIFF without a valid tie point and pixel scale".into());
+        }
+
+        let values: Vec<f64> = match decoder.read_image()? {
+            DecodingResult::U8(values) => values.into_iter().map(f64::from).collect(),
+            DecodingResult::U16(values) => values.into_iter().map(f64::from).collect(),
+            DecodingResult::I16(values) => values.into_iter().map(f64::from).collect(),
+            DecodingResult::I32(values) => values.into_iter().map(f64::from).collect(),
+            DecodingResult::F32(values) => values.into_iter().map(f64::from).collect(),
+            DecodingResult::F64(values) => values,
+            _ => return Err("Unsupported sample format of the GeoTIFF".into()),
+        };
+
+        // The tie point gives the coordinates of a pixel corner
+        let (cell_width, cell_height) = (pixel_scale[0], pixel_scale[1]);
+        let west = tie_point[3] - tie_point[0] * cell_width;
+        let north = tie_point[4] + tie_point[1] * cell_height;
+        Self::new(
+            values,
+            columns as usize,
+            rows as usize,
+            west,
+            north,
+            (cell_width, cell_height),
+        )
+    }
+
+    fn new(
+        mut values: Vec<f64>,
+        columns: usize,
+        rows: usize,
+        west: f64,
+        north: f64,
+        (cell_width, cell_height): (f64, f64),
+    ) -> Result<Self, Box<dyn Error>> {
+        if columns == 0 || rows == 0 || values.len() < columns * rows {
+            return Err("The elevation grid has fewer values than its size".into());
+        }
+        if cell_width <= 0.0 || cell_height <= 0.0 {
+            return Err("The elevation grid has no valid cell size".into());
+        }
+        values.truncate(columns * rows);
+        for value in &mut values {
+            if !value.is_finite() || *value < MIN_VALID_ELEVATION {
+                *value = f64::NAN;
+            }
+        }
+        Ok(Self {
+            values,
+            columns,
+            rows,
+            west,
+            north,
+            cell_width,
+            cell_height,
+        })
+    }
+
+    /// Returns the elevation at the given position interpolated between the middles of the
+    /// cells around it, NaN outside of the grid or where values are missing
+    pub fn sample(&self, lat: f64, lng: f64) -> f64 {
+        let column = (lng - self.west) / self.cell_width - 0.5;
+        let row = (self.north - lat) / self.cell_height - 0.5;
+        let last_column = (self.columns - 1) as f64;
+        let last_row = (self.rows - 1) as f64;
+        if !(-0.5..=last_column + 0.5).contains(&column) || !(-0.5..=last_row + 0.5).contains(&row)
+        {
+            return f64::NAN;
+        }
+
+        let (column, row) = (column.clamp(0.0, last_column), row.clamp(0.0, last_row));
+        let (left, top) = (column.floor() as usize, row.floor() as usize);
+        let (right, bottom) = (
+            (left + 1).min(self.columns - 1),
+            (top + 1).min(self.rows - 1),
+        );
+        let (tx, tz) = (column - left as f64, row - top as f64);
+
+        // Cells without weight are left out, so that their missing values do not spread
+        [
+            (left, top, (1.0 - tx) * (1.0 - tz)),
+            (right, top, tx * (1.0 - tz)),
+            (left, bottom, (1.0 - tx) * tz),
+            (right, bottom, tx * tz),
+        ]
+        .iter()
+        .filter(|&&(_, _, weight)| weight > 0.0)
+        .map(|&(c, r, weight)| self.values[r * self.columns + c] * weight)
+        .sum()
+    }
+
+    /// Samples the grid at every block of the world and converts it to Minecraft heights
+    pub fn elevation_data(
+        &self,
+        bbox: &LLBBox,
+        scale: f64,
+        projection: Projection,
+        ground_level: i32,
+    ) -> Result<ElevationData, Box<dyn Error>> {
+        let (width, height) = grid_size(bbox, scale, projection);
+        let (transformer, _) = CoordTransformer::llbbox_to_xzbbox(bbox, scale, projection)?;
+        let meters: Vec<Vec<f64>> = (0..height)
+            .map(|z| {
+                (0..width)
+                    .map(|x| {
+                        let (lat, lng) = transformer.xz_to_lat_lng(x as f64 + 0.5, z as f64 + 0.5);
+                        self.sample(lat, lng)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        if meters.iter().flatten().all(|value| value.is_nan()) {
+            return Err("The elevation data does not cover the area".into());
+        }
+        Ok(heights_from_meters(meters, scale, ground_level))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascii_grid_is_sampled_between_cells() {
+        let text = "ncols 2\nnrows 2\nxllcorner 11.0\nyllcorner 48.0\ncellsize 0.5\n\
+                    NODATA_value -9999\n100 200\n300 -9999\n";
+        let grid = ElevationGrid::parse_ascii(text).unwrap();
+
+        // Middles of the north-west and north-east cells and halfway between them
+        assert_eq!(grid.sample(48.75, 11.25), 100.0);
+        assert_eq!(grid.sample(48.75, 11.75), 200.0);
+        assert_eq!(grid.sample(48.75, 11.5), 150.0);
+        assert_eq!(grid.sample(48.25, 11.25), 300.0);
+        assert!(grid.sample(48.25, 11.75).is_nan());
+        assert!(grid.sample(50.0, 11.25).is_nan());
+    }
+}
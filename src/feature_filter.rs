@@ -0,0 +1,190 @@
+//! Filter of the features generated by their tags, applied to the parsed OSM data and read from
+//! a configuration file such as
+//!
+//! ```toml
+//! # Leave out power lines and fences
+//! exclude = ["power=*", "barrier=fence"]
+//! # Generate nothing but buildings and roads
+//! include_only = ["building", "highway"]
+//! ```
+
+use crate::osm_parser::ProcessedElement;
+use std::collections::HashMap;
+use std::fs;
+
+/// Tag an element may have, `key` or `key=*` for any value and `key=value` for a single one
+#[derive(Clone, Debug, PartialEq)]
+struct TagPattern {
+    key: String,
+    value: Option<String>,
+}
+
+impl TagPattern {
+    fn parse(pattern: &str) -> Result<Self, String> {
+        let (key, value) = match pattern.split_once('=') {
+            Some((key, value)) => (key.trim(), Some(value.trim()).filter(|v| *v != "*")),
+            None => (pattern.trim(), None),
+        };
+        if key.is_empty() {
+            return Err(format!("Invalid tag pattern \"{pattern}\""));
+        }
+        Ok(Self {
+            key: key.to_string(),
+            value: value.map(str::to_string),
+        })
+    }
+
+    fn matches(&self, tags: &HashMap<String, String>) -> bool {
+        match (tags.get(&self.key), &self.value) {
+            (Some(value), Some(wanted)) => value == wanted,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+/// Elements to generate by their tags. Excluded tags win over included ones.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FeatureFilter {
+    /// Tags of which an element must have one, if any are given
+    include_only: Vec<TagPattern>,
+    /// Tags no generated element may have
+    exclude: Vec<TagPattern>,
+}
+
+impl FeatureFilter {
+    /// Reads a feature filter configuration file
+    pub fn load(path: &str) -> Result<Self, String> {
+        let config = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read feature filter {path}: {e}"))?;
+        Self::from_config(&config)
+    }
+
+    /// Parses `exclude` and `include_only` lists of tag patterns, with `#` comments
+    fn from_config(config: &str) -> Result<Self, String> {
+        let mut filter = Self::default();
+        let mut rest = skip_blank(config);
+        while !rest.is_empty() {
+            let (key, value) = rest
+                .split_once('=')
+                .ok_or("Expected a setting such as exclude = [...] in the feature filter")?;
+            let key = key.trim();
+            let mut list = skip_blank(value)
+                .strip_prefix('[')
+                .ok_or_else(|| format!("Expected a list for \"{key}\" in the feature filter"))?;
+
+            let mut patterns = Vec::new();
+            loop {
+                list = skip_blank(list);
+                if let Some(after) = list.strip_prefix(']') {
+                    list = after;
+                    break;
+                }
+                let quote = list
+                    .chars()
+                    .next()
+                    .filter(|c| *c == '"' || *c == '\'')
+                    .ok_or_else(|| format!("Expected a quoted tag for \"{key}\""))?;
+                let end = list[1..]
+                    .find(quote)
+                    .ok_or("Unterminated tag in the feature filter")?;
+                patterns.push(TagPattern::parse(&list[1..end + 1])?);
+                list = skip_blank(&list[end + 2..]);
+                list = list.strip_prefix(',').unwrap_or(list);
+            }
+
+            match key {
+                "exclude" => filter.exclude.extend(patterns),
+                "include_only" => filter.include_only.extend(patterns),
+                _ => return Err(format!("Unknown feature filter setting \"{key}\"")),
+            }
+            rest = skip_blank(list);
+        }
+
+        if filter == Self::default() {
+            return Err("Empty feature filter".to_string());
+        }
+        Ok(filter)
+    }
+
+    /// Returns whether an element is generated
+    pub fn keeps(&self, element: &ProcessedElement) -> bool {
+        let tags = element.tags();
+        if self.exclude.iter().any(|pattern| pattern.matches(tags)) {
+            return false;
+        }
+        self.include_only.is_empty()
+            || self
+                .include_only
+                .iter()
+                .any(|pattern| pattern.matches(tags))
+    }
+
+    /// Removes the elements not to generate and returns how many there were
+    pub fn apply(&self, elements: &mut Vec<ProcessedElement>) -> usize {
+        let count = elements.len();
+        elements.retain(|element| self.keeps(element));
+        count - elements.len()
+    }
+}
+
+/// Skips whitespace and comments
+fn skip_blank(mut text: &str) -> &str {
+    loop {
+        text = text.trim_start();
+        match text.strip_prefix('#') {
+            Some(comment) => text = comment.split_once('\n').map_or("", |(_, next)| next),
+            None => return text,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osm_parser::ProcessedNode;
+
+    fn node(tags: &[(&str, &str)]) -> ProcessedElement {
+        ProcessedElement::Node(ProcessedNode {
+            id: 1,
+            tags: tags
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            x: 0,
+            z: 0,
+        })
+    }
+
+    #[test]
+    fn test_feature_filter() {
+        let filter = FeatureFilter::from_config(
+            r#"
+            # Leave out power lines and fences
+            exclude = ["power=*", 'barrier=fence']
+            include_only = [
+                "building",
+                "highway=primary", # main roads only
+                "barrier",
+            ]
+            "#,
+        )
+        .unwrap();
+
+        assert!(filter.keeps(&node(&[("building", "yes")])));
+        assert!(filter.keeps(&node(&[("highway", "primary")])));
+        assert!(filter.keeps(&node(&[("barrier", "wall")])));
+        assert!(!filter.keeps(&node(&[("highway", "residential")])));
+        assert!(!filter.keeps(&node(&[("barrier", "fence")])));
+        assert!(!filter.keeps(&node(&[("building", "yes"), ("power", "plant")])));
+        assert!(!filter.keeps(&node(&[])));
+
+        let mut elements = vec![node(&[("building", "yes")]), node(&[("power", "line")])];
+        assert_eq!(filter.apply(&mut elements), 1);
+        assert_eq!(elements.len(), 1);
+
+        assert!(FeatureFilter::from_config("exclude = [\"power\"").is_err());
+        assert!(FeatureFilter::from_config("colour = [\"red\"]").is_err());
+        assert!(FeatureFilter::from_config("# nothing").is_err());
+    }
+}
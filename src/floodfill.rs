@@ -1,6 +1,6 @@
 use geo::{Contains, LineString, Point, Polygon};
 use itertools::Itertools;
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::time::{Duration, Instant};
 
 /// Main flood fill function with automatic algorithm selection
@@ -38,6 +38,30 @@ pub fn flood_fill_area(
     }
 }
 
+/// Returns the number of steps from the nearest source to every reachable cell of the area,
+/// counting the sources as one step away
+pub fn distances_within(
+    area: &HashSet<(i32, i32)>,
+    sources: impl Iterator<Item = (i32, i32)>,
+) -> HashMap<(i32, i32), i32> {
+    let mut distances: HashMap<(i32, i32), i32> = HashMap::new();
+    let mut queue: VecDeque<(i32, i32)> = VecDeque::new();
+    for source in sources {
+        distances.insert(source, 1);
+        queue.push_back(source);
+    }
+    while let Some((x, z)) = queue.pop_front() {
+        let distance = distances[&(x, z)] + 1;
+        for cell in [(x + 1, z), (x - 1, z), (x, z + 1), (x, z - 1)] {
+            if area.contains(&cell) && !distances.contains_key(&cell) {
+                distances.insert(cell, distance);
+                queue.push_back(cell);
+            }
+        }
+    }
+    distances
+}
+
 /// Optimized flood fill for larger polygons with multi-seed detection for complex shapes like U-shapes
 fn optimized_flood_fill_area(
     polygon_coords: &[(i32, i32)],
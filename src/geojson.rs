@@ -0,0 +1,252 @@
+//! Reader of GeoJSON files of custom features, such as planned buildings or fictional landmarks,
+//! whose properties are taken as OSM tags. The features are converted to the JSON elements the
+//! Overpass API returns and added to the downloaded ones.
+
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Id of the first custom element, wrapped around as negative ids are, and counted down from
+/// there so that the ids stay apart from those of elements not uploaded to OSM yet
+const FIRST_CUSTOM_ID: i64 = -1_000_000_000;
+
+/// Adds the features of a GeoJSON file to OSM data
+pub fn merge_geojson(data: &mut Value, path: &Path) -> Result<usize, Box<dyn Error>> {
+    let geojson: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let elements = geojson_to_elements(&geojson)?;
+    let count = elements.len();
+    match data["elements"].as_array_mut() {
+        Some(existing) => existing.extend(elements),
+        None => data["elements"] = Value::Array(elements),
+    }
+    Ok(count)
+}
+
+/// Converts a feature collection, a single feature or a bare geometry to Overpass elements.
+/// Points become nodes, lines and rings ways, and polygons with holes or several parts
+/// multipolygon relations.
+pub fn geojson_to_elements(geojson: &Value) -> Result<Vec<Value>, Box<dyn Error>> {
+    let mut converter = Converter {
+        elements: Vec::new(),
+        next_id: FIRST_CUSTOM_ID,
+    };
+    match geojson["type"].as_str() {
+        Some("FeatureCollection") => {
+            for feature in geojson["features"].as_array().into_iter().flatten() {
+                converter.feature(feature)?;
+            }
+        }
+        Some("Feature") => converter.feature(geojson)?,
+        Some(_) => converter.geometry(geojson, json!({}))?,
+        None => return Err("GeoJSON without type".into()),
+    }
+    Ok(converter.elements)
+}
+
+struct Converter {
+    elements: Vec<Value>,
+    next_id: i64,
+}
+
+impl Converter {
+    fn id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id -= 1;
+        id as u64
+    }
+
+    fn feature(&mut self, feature: &Value) -> Result<(), Box<dyn Error>> {
+        // Properties that are not strings, such as numbers of levels, are written out
+        let tags: serde_json::Map<String, Value> = feature["properties"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| {
+                let value = match value {
+                    Value::String(text) => text.clone(),
+                    other => other.to_string(),
+                };
+                (key.clone(), Value::String(value))
+            })
+            .collect();
+        if feature["geometry"].is_null() {
+            return Ok(());
+        }
+        self.geometry(&feature["geometry"], Value::Object(tags))
+    }
+
+    fn geometry(&mut self, geometry: &Value, tags: Value) -> Result<(), Box<dyn Error>> {
+        let coordinates = &geometry["coordinates"];
+        match geometry["type"].as_str().unwrap_or_default() {
+            "Point" => {
+                self.node(coordinates, Some(tags))?;
+            }
+            "MultiPoint" => {
+                for point in array(coordinates)? {
+                    self.node(point, Some(tags.clone()))?;
+                }
+            }
+            "LineString" => {
+                self.way(coordinates, Some(tags))?;
+            }
+            "MultiLineString" => {
+                for line in array(coordinates)? {
+                    self.way(line, Some(tags.clone()))?;
+                }
+            }
+            "Polygon" => self.polygons(std::slice::from_ref(coordinates), tags)?,
+            "MultiPolygon" => self.polygons(array(coordinates)?, tags)?,
+            "GeometryCollection" => {
+                for part in geometry["geometries"].as_array().into_iter().flatten() {
+                    self.geometry(part, tags.clone())?;
+                }
+            }
+            other => return Err(format!("Unsupported GeoJSON geometry \"{other}\"").into()),
+        }
+        Ok(())
+    }
+
+    /// Adds a node for a `[lon, lat]` position and returns its id
+    fn node(&mut self, position: &Value, tags: Option<Value>) -> Result<u64, Box<dyn Error>> {
+        let (Some(lon), Some(lat)) = (position[0].as_f64(), position[1].as_f64()) else {
+            return Err("Invalid GeoJSON position".into());
+        };
+        let id = self.id();
+        let mut node = json!({"type": "node", "id": id, "lat": lat, "lon": lon});
+        if let Some(tags) = tags.filter(has_tags) {
+            node["tags"] = tags;
+        }
+        self.elements.push(node);
+        Ok(id)
+    }
+
+    /// Adds a way along the given positions and returns its id. Rings, whose last position
+    /// repeats the first one, end in the node they start with.
+    fn way(&mut self, positions: &Value, tags: Option<Value>) -> Result<u64, Box<dyn Error>> {
+        let positions = array(positions)?;
+        let closed = positions.len() > 2 && positions.first() == positions.last();
+        let open_positions = &positions[..positions.len() - usize::from(closed)];
+        let mut nodes = Vec::with_capacity(positions.len());
+        for position in open_positions {
+            nodes.push(self.node(position, None)?);
+        }
+        if closed {
+            nodes.push(nodes[0]);
+        }
+
+        let id = self.id();
+        let mut way = json!({"type": "way", "id": id, "nodes": nodes});
+        if let Some(tags) = tags.filter(has_tags) {
+            way["tags"] = tags;
+        }
+        self.elements.push(way);
+        Ok(id)
+    }
+
+    /// Adds polygons, each a list of rings of which the first is the outer one. A single polygon
+    /// without holes becomes a tagged closed way, anything else a multipolygon relation.
+    fn polygons(&mut self, polygons: &[Value], tags: Value) -> Result<(), Box<dyn Error>> {
+        if let [polygon] = polygons {
+            if let [outer] = array(polygon)? {
+                self.way(outer, Some(tags))?;
+                return Ok(());
+            }
+        }
+
+        let mut members = Vec::new();
+        for polygon in polygons {
+            for (i, ring) in array(polygon)?.iter().enumerate() {
+                let role = if i == 0 { "outer" } else { "inner" };
+                let way = self.way(ring, None)?;
+                members.push(json!({"type": "way", "ref": way, "role": role}));
+            }
+        }
+        let mut tags = tags;
+        tags["type"] = json!("multipolygon");
+        let id = self.id();
+        self.elements.push(json!({
+            "type": "relation",
+            "id": id,
+            "members": members,
+            "tags": tags,
+        }));
+        Ok(())
+    }
+}
+
+fn array(value: &Value) -> Result<&[Value], Box<dyn Error>> {
+    value
+        .as_array()
+        .map(Vec::as_slice)
+        .ok_or_else(|| "Invalid GeoJSON coordinates".into())
+}
+
+fn has_tags(tags: &Value) -> bool {
+    tags.as_object().is_some_and(|tags| !tags.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geojson_features_become_elements() {
+        let geojson = json!({
+            "type": "FeatureCollection",
+            "features": [
+                {
+                    "type": "Feature",
+                    "properties": {"building": "yes", "building:levels": 12, "note": null},
+                    "geometry": {
+                        "type": "Polygon",
+                        "coordinates": [[[11.0, 48.0], [11.001, 48.0], [11.001, 48.001], [11.0, 48.0]]]
+                    }
+                },
+                {
+                    "type": "Feature",
+                    "properties": {"tourism": "attraction"},
+                    "geometry": {"type": "Point", "coordinates": [11.002, 48.002]}
+                },
+                {
+                    "type": "Feature",
+                    "properties": {"leisure": "park"},
+                    "geometry": {
+                        "type": "Polygon",
+                        "coordinates": [
+                            [[11.0, 48.0], [11.01, 48.0], [11.01, 48.01], [11.0, 48.0]],
+                            [[11.002, 48.001], [11.003, 48.001], [11.003, 48.002], [11.002, 48.001]]
+                        ]
+                    }
+                }
+            ]
+        });
+        let elements = geojson_to_elements(&geojson).unwrap();
+
+        // The building: 3 nodes and a closed way with its properties as tags
+        let building = &elements[3];
+        assert_eq!(building["type"], "way");
+        assert_eq!(building["tags"]["building:levels"], "12");
+        assert!(building["tags"].get("note").is_none());
+        let nodes = building["nodes"].as_array().unwrap();
+        assert_eq!(nodes.len(), 4);
+        assert_eq!(nodes[0], nodes[3]);
+        assert_eq!(elements[0]["lat"], 48.0);
+        assert_eq!(elements[0]["lon"], 11.0);
+        assert_eq!(elements[0]["id"], FIRST_CUSTOM_ID as u64);
+
+        assert_eq!(elements[4]["tags"]["tourism"], "attraction");
+
+        // The park with a hole becomes a multipolygon relation
+        let park = elements.last().unwrap();
+        assert_eq!(park["type"], "relation");
+        assert_eq!(park["tags"]["type"], "multipolygon");
+        assert_eq!(park["members"][1]["role"], "inner");
+
+        let mut ids: Vec<u64> = elements.iter().map(|e| e["id"].as_u64().unwrap()).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), elements.len());
+    }
+}
@@ -1,9 +1,41 @@
 use crate::args::Args;
-use crate::coordinate_system::{cartesian::XZPoint, geographic::LLBBox};
-use crate::elevation_data::{fetch_elevation_data, ElevationData};
+use crate::coordinate_system::{
+    cartesian::{XZBBox, XZPoint},
+    geographic::LLBBox,
+    transformation::Projection,
+};
+use crate::elevation_data::{apply_gaussian_blur, ElevationData, MAX_Y};
+use crate::ground_provider::{
+    DemProvider, ElevationFileProvider, GroundProvider, HeightmapProvider, NoiseProvider,
+    OpenTopographyProvider, TerrainSource,
+};
+use crate::height_grid::HeightGrid;
+use crate::i18n::tr;
+use crate::osm_parser::ProcessedElement;
 use crate::progress::emit_gui_progress_update;
 use colored::Colorize;
 use image::{Rgb, RgbImage};
+use std::collections::HashMap;
+
+/// Radius in blocks over which mapped elevations blend into the terrain at the least
+const CONTROL_POINT_RADIUS: f64 = 12.0;
+/// Largest height difference in blocks that mapped elevations adjust the terrain by
+const MAX_CONTROL_POINT_ADJUSTMENT: f64 = 64.0;
+/// Rows of the elevation grid smoothed at once
+const SMOOTHING_BAND: usize = 256;
+
+/// How the heights between the points of the elevation grid are computed, selected with
+/// `--terrain-interpolation`
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TerrainInterpolation {
+    /// The height of the nearest point
+    Nearest,
+    /// Linear between the four points around
+    #[default]
+    Bilinear,
+    /// Cubic through the sixteen points around, the smoothest slopes
+    Bicubic,
+}
 
 /// Represents terrain data and elevation settings
 #[derive(Clone)]
@@ -11,6 +43,9 @@ pub struct Ground {
     pub elevation_enabled: bool,
     ground_level: i32,
     elevation_data: Option<ElevationData>,
+    interpolation: TerrainInterpolation,
+    /// Blocks by which single columns are sunk below the terrain, such as the floors of gorges
+    sunk_columns: HashMap<(i32, i32), i32>,
 }
 
 impl Ground {
@@ -19,25 +54,151 @@ impl Ground {
             elevation_enabled: false,
             ground_level,
             elevation_data: None,
+            interpolation: TerrainInterpolation::default(),
+            sunk_columns: HashMap::new(),
         }
     }
 
-    pub fn new_enabled(bbox: &LLBBox, scale: f64, ground_level: i32) -> Self {
-        match fetch_elevation_data(bbox, scale, ground_level) {
+    /// Creates the ground from the heights of the given provider, flat if it fails
+    pub fn from_provider(
+        provider: &dyn GroundProvider,
+        bbox: &LLBBox,
+        scale: f64,
+        projection: Projection,
+        ground_level: i32,
+    ) -> Self {
+        match provider.elevation_data(bbox, scale, projection, ground_level) {
             Ok(elevation_data) => Self {
                 elevation_enabled: true,
                 ground_level,
                 elevation_data: Some(elevation_data),
+                interpolation: TerrainInterpolation::default(),
+                sunk_columns: HashMap::new(),
             },
             Err(e) => {
                 eprintln!("Failed to fetch elevation data: {}", e);
-                emit_gui_progress_update(15.0, "Elevation unavailable, using flat ground");
+                emit_gui_progress_update(15.0, tr("elevation_unavailable"));
                 // Graceful fallback: disable elevation and keep provided ground_level
-                Self {
-                    elevation_enabled: false,
-                    ground_level,
-                    elevation_data: None,
+                Self::new_flat(ground_level)
+            }
+        }
+    }
+
+    /// Sinks the given columns in world coordinates by the given number of blocks
+    pub fn sink_columns(&mut self, columns: HashMap<(i32, i32), i32>, xzbbox: &XZBBox) {
+        for ((x, z), depth) in columns {
+            let local = (x - xzbbox.min_x(), z - xzbbox.min_z());
+            let sunk = self.sunk_columns.entry(local).or_insert(0);
+            *sunk = (*sunk).max(depth);
+        }
+    }
+
+    pub fn set_interpolation(&mut self, interpolation: TerrainInterpolation) {
+        self.interpolation = interpolation;
+    }
+
+    /// Blurs the elevation grid with a Gaussian of the given radius in blocks, so that hills
+    /// rise in walkable steps. Does nothing for a radius of 0.
+    pub fn smooth(&mut self, sigma: f64) {
+        let Some(data) = self.elevation_data.as_mut() else {
+            return;
+        };
+        if sigma <= 0.0 || data.heights.is_empty() {
+            return;
+        }
+
+        // Band by band with the rows the blur reaches around each, which blurs them as if the
+        // grid was blurred as a whole
+        let reach = (sigma * 3.0).ceil() as usize;
+        let (width, height) = (data.heights.width(), data.heights.height());
+        let heights = &data.heights;
+        let rows = (0..height).step_by(SMOOTHING_BAND).flat_map(|band_start| {
+            let band_end = (band_start + SMOOTHING_BAND).min(height);
+            let window_start = band_start.saturating_sub(reach);
+            let window: Vec<Vec<f64>> = heights
+                .rows(window_start..(band_end + reach).min(height))
+                .into_iter()
+                .map(|row| row.into_iter().map(|h| h as f64).collect())
+                .collect();
+            apply_gaussian_blur(&window, sigma)
+                .into_iter()
+                .skip(band_start - window_start)
+                .take(band_end - band_start)
+                .map(|row| {
+                    row.into_iter()
+                        .map(|h| h.round() as i32)
+                        .collect::<Vec<i32>>()
+                })
+        });
+        let smoothed = HeightGrid::from_rows(width, height, rows);
+        data.heights = smoothed;
+    }
+
+    /// Raises or lowers the terrain around mapped `ele=` elevations of peaks, saddles, survey
+    /// points and contour lines, so that the ground there reaches them where the elevation data
+    /// is too coarse. Needs real elevation data to turn the altitudes into heights. Returns the
+    /// number of control points applied.
+    pub fn apply_elevation_tags(
+        &mut self,
+        elements: &[ProcessedElement],
+        xzbbox: &XZBBox,
+    ) -> usize {
+        let Some(scale) = self.elevation_data.as_ref().and_then(|data| data.altitude) else {
+            return 0;
+        };
+        if !self.elevation_enabled {
+            return 0;
+        }
+
+        let mut applied = 0;
+        for (x, z, ele) in elevation_control_points(elements) {
+            let (x, z) = ((x - xzbbox.min_x()) as f64, (z - xzbbox.min_z()) as f64);
+            let target =
+                self.ground_level as f64 + (ele - scale.ground_altitude) / scale.meters_per_block;
+            let delta = target.min(MAX_Y as f64) - self.level_f64(x, z);
+            // Far off elevations are mistagged rather than features the data missed
+            if delta.abs() < 1.0 || delta.abs() > MAX_CONTROL_POINT_ADJUSTMENT {
+                continue;
+            }
+            self.adjust_around(x, z, delta);
+            applied += 1;
+        }
+        applied
+    }
+
+    /// Adds the given height difference to the elevation grid at the given coordinates,
+    /// fading out with the distance so that the slopes around stay walkable
+    fn adjust_around(&mut self, x: f64, z: f64, delta: f64) {
+        let Some(data) = self.elevation_data.as_mut() else {
+            return;
+        };
+        let radius = CONTROL_POINT_RADIUS.max(delta.abs() * 2.0);
+        let to_grid_x = (data.width.max(2) - 1) as f64 / data.width as f64;
+        let to_grid_z = (data.height.max(2) - 1) as f64 / data.height as f64;
+        let (grid_x, grid_z) = (x * to_grid_x, z * to_grid_z);
+
+        let (columns, rows) = (
+            data.heights.width() as isize,
+            data.heights.height() as isize,
+        );
+        for row in (grid_z - radius).floor() as isize..=(grid_z + radius).ceil() as isize {
+            if row < 0 || row >= rows {
+                continue;
+            }
+            for column in (grid_x - radius).floor() as isize..=(grid_x + radius).ceil() as isize {
+                if column < 0 || column >= columns {
+                    continue;
                 }
+                let distance =
+                    ((column as f64 - grid_x).powi(2) + (row as f64 - grid_z).powi(2)).sqrt();
+                if distance >= radius {
+                    continue;
+                }
+                // Cosine falloff, flat at the control point and meeting the terrain at the edge
+                let weight = 0.5 * (1.0 + (std::f64::consts::PI * distance / radius).cos());
+                let (column, row) = (column as usize, row as usize);
+                let height = data.heights.get(column, row) + (delta * weight).round() as i32;
+                data.heights.set(column, row, height.min(MAX_Y));
             }
         }
     }
@@ -45,13 +206,60 @@ impl Ground {
     /// Returns the ground level at the given coordinates
     #[inline(always)]
     pub fn level(&self, coord: XZPoint) -> i32 {
-        if !self.elevation_enabled || self.elevation_data.is_none() {
-            return self.ground_level;
+        self.level_f64(coord.x as f64, coord.z as f64).round() as i32
+    }
+
+    /// Returns the interpolated ground level at the given coordinates in blocks, which may
+    /// lie between the blocks, without rounding it to a whole block
+    pub fn level_f64(&self, x: f64, z: f64) -> f64 {
+        let sunk = if self.sunk_columns.is_empty() {
+            0
+        } else {
+            let column = (x.round() as i32, z.round() as i32);
+            self.sunk_columns.get(&column).copied().unwrap_or(0)
+        };
+        let Some(data) = self
+            .elevation_data
+            .as_ref()
+            .filter(|_| self.elevation_enabled)
+        else {
+            return (self.ground_level - sunk) as f64;
+        };
+        let (x_ratio, z_ratio) = self.get_data_coordinates(x, z, data);
+        self.interpolate_height(x_ratio, z_ratio, data) - sunk as f64
+    }
+
+    /// Returns the ground levels at all of the given coordinates
+    pub fn levels<I: IntoIterator<Item = XZPoint>>(&self, coords: I) -> Vec<i32> {
+        coords.into_iter().map(|coord| self.level(coord)).collect()
+    }
+
+    /// Returns the slope of the ground at the given coordinates as the blocks it rises per
+    /// block towards positive x and towards positive z
+    #[allow(unused)]
+    pub fn slope(&self, x: f64, z: f64) -> (f64, f64) {
+        if !self.elevation_enabled {
+            return (0.0, 0.0);
         }
+        let dx = (self.level_f64(x + 1.0, z) - self.level_f64(x - 1.0, z)) / 2.0;
+        let dz = (self.level_f64(x, z + 1.0) - self.level_f64(x, z - 1.0)) / 2.0;
+        (dx, dz)
+    }
+
+    /// Returns the unit normal of the ground surface at the given coordinates as (x, y, z)
+    #[allow(unused)]
+    pub fn normal(&self, x: f64, z: f64) -> (f64, f64, f64) {
+        let (dx, dz) = self.slope(x, z);
+        let length = (dx * dx + 1.0 + dz * dz).sqrt();
+        (-dx / length, 1.0 / length, -dz / length)
+    }
 
-        let data: &ElevationData = self.elevation_data.as_ref().unwrap();
-        let (x_ratio, z_ratio) = self.get_data_coordinates(coord, data);
-        self.interpolate_height(x_ratio, z_ratio, data)
+    /// Returns the real altitude in meters at the given coordinates, None without real
+    /// elevation data
+    pub fn altitude(&self, coord: XZPoint) -> Option<f64> {
+        let scale = self.elevation_data.as_ref()?.altitude?;
+        let blocks = self.level(coord) - self.ground_level;
+        Some(scale.ground_altitude + blocks as f64 * scale.meters_per_block)
     }
 
     #[allow(unused)]
@@ -74,26 +282,59 @@ impl Ground {
 
     /// Converts game coordinates to elevation data coordinates
     #[inline(always)]
-    fn get_data_coordinates(&self, coord: XZPoint, data: &ElevationData) -> (f64, f64) {
-        let x_ratio: f64 = coord.x as f64 / data.width as f64;
-        let z_ratio: f64 = coord.z as f64 / data.height as f64;
+    fn get_data_coordinates(&self, x: f64, z: f64, data: &ElevationData) -> (f64, f64) {
+        let x_ratio: f64 = x / data.width as f64;
+        let z_ratio: f64 = z / data.height as f64;
         (x_ratio.clamp(0.0, 1.0), z_ratio.clamp(0.0, 1.0))
     }
 
     /// Interpolates height value from the elevation grid
     #[inline(always)]
-    fn interpolate_height(&self, x_ratio: f64, z_ratio: f64, data: &ElevationData) -> i32 {
-        let x: usize = ((x_ratio * (data.width - 1) as f64).round() as usize).min(data.width - 1);
-        let z: usize = ((z_ratio * (data.height - 1) as f64).round() as usize).min(data.height - 1);
-        data.heights[z][x]
+    fn interpolate_height(&self, x_ratio: f64, z_ratio: f64, data: &ElevationData) -> f64 {
+        let x: f64 = x_ratio * (data.width - 1) as f64;
+        let z: f64 = z_ratio * (data.height - 1) as f64;
+        let at = |x: isize, z: isize| -> f64 {
+            let x = x.clamp(0, data.width as isize - 1) as usize;
+            let z = z.clamp(0, data.height as isize - 1) as usize;
+            data.heights.get(x, z) as f64
+        };
+        let (left, top) = (x.floor() as isize, z.floor() as isize);
+        let (tx, tz) = (x - left as f64, z - top as f64);
+
+        match self.interpolation {
+            TerrainInterpolation::Nearest => at(x.round() as isize, z.round() as isize),
+            TerrainInterpolation::Bilinear => {
+                let upper = at(left, top) * (1.0 - tx) + at(left + 1, top) * tx;
+                let lower = at(left, top + 1) * (1.0 - tx) + at(left + 1, top + 1) * tx;
+                upper * (1.0 - tz) + lower * tz
+            }
+            TerrainInterpolation::Bicubic => {
+                let rows: [f64; 4] = std::array::from_fn(|i| {
+                    let dz = i as isize - 1;
+                    let row: [f64; 4] =
+                        std::array::from_fn(|j| at(left + j as isize - 1, top + dz));
+                    catmull_rom(row, tx)
+                });
+                // The cubic may overshoot, which must not raise spikes above the points around
+                let around = [
+                    at(left, top),
+                    at(left + 1, top),
+                    at(left, top + 1),
+                    at(left + 1, top + 1),
+                ];
+                let min = around.iter().copied().fold(f64::MAX, f64::min);
+                let max = around.iter().copied().fold(f64::MIN, f64::max);
+                catmull_rom(rows, tz).clamp(min, max)
+            }
+        }
     }
 
     fn save_debug_image(&self, filename: &str) {
-        let heights = &self
+        let data = self
             .elevation_data
             .as_ref()
-            .expect("Elevation data not available")
-            .heights;
+            .expect("Elevation data not available");
+        let heights = data.heights.rows(0..data.heights.height());
         if heights.is_empty() || heights[0].is_empty() {
             return;
         }
@@ -106,7 +347,7 @@ impl Ground {
         let mut min_height: i32 = i32::MAX;
         let mut max_height: i32 = i32::MIN;
 
-        for row in heights {
+        for row in &heights {
             for &h in row {
                 min_height = min_height.min(h);
                 max_height = max_height.max(h);
@@ -138,11 +379,87 @@ impl Ground {
     }
 }
 
+/// Returns the mapped elevations in meters of peaks, saddles, survey points and the nodes of
+/// contour lines at their world coordinates
+fn elevation_control_points(elements: &[ProcessedElement]) -> Vec<(i32, i32, f64)> {
+    let mut points = Vec::new();
+    for element in elements {
+        let tags = element.tags();
+        let Some(ele) = tags.get("ele").and_then(|ele| parse_ele(ele)) else {
+            continue;
+        };
+        match element {
+            ProcessedElement::Node(node) => {
+                let is_control_point = matches!(
+                    tags.get("natural").map(String::as_str),
+                    Some("peak" | "saddle" | "volcano" | "hill")
+                ) || tags.get("man_made").map(String::as_str)
+                    == Some("survey_point");
+                if is_control_point {
+                    points.push((node.x, node.z, ele));
+                }
+            }
+            ProcessedElement::Way(way) if tags.contains_key("contour") => {
+                points.extend(way.nodes.iter().map(|node| (node.x, node.z, ele)));
+            }
+            _ => {}
+        }
+    }
+    points
+}
+
+/// Parses an `ele=` value in meters, such as "1234", "1234.5" or "1234 m"
+pub(crate) fn parse_ele(ele: &str) -> Option<f64> {
+    ele.trim()
+        .trim_end_matches('m')
+        .trim()
+        .replace(',', ".")
+        .parse::<f64>()
+        .ok()
+        .filter(|ele| ele.is_finite())
+}
+
+/// Interpolates between the middle two of four evenly spaced values with a Catmull-Rom spline
+fn catmull_rom([p0, p1, p2, p3]: [f64; 4], t: f64) -> f64 {
+    p1 + 0.5
+        * t
+        * (p2 - p0 + t * (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3 + t * (3.0 * (p1 - p2) + p3 - p0)))
+}
+
 pub fn generate_ground_data(args: &Args) -> Ground {
     if args.terrain {
-        println!("{} Fetching elevation...", "[3/7]".bold());
-        emit_gui_progress_update(15.0, "Fetching elevation...");
-        let ground = Ground::new_enabled(&args.bbox, args.scale, args.ground_level);
+        println!("{} {}", "[3/7]".bold(), tr("step_fetching_elevation"));
+        emit_gui_progress_update(15.0, tr("step_fetching_elevation"));
+        let provider: Box<dyn GroundProvider> = match args.terrain_source {
+            TerrainSource::Dem => Box::new(DemProvider),
+            TerrainSource::OpenTopography => Box::new(OpenTopographyProvider {
+                api_key: args.elevation_api_key.clone(),
+            }),
+            TerrainSource::File => match &args.elevation_file {
+                Some(path) => Box::new(ElevationFileProvider { path: path.clone() }),
+                None => {
+                    eprintln!("--terrain-source file requires --elevation-file, using DEM tiles");
+                    Box::new(DemProvider)
+                }
+            },
+            TerrainSource::Noise => Box::new(NoiseProvider),
+            TerrainSource::Heightmap => match &args.heightmap {
+                Some(path) => Box::new(HeightmapProvider { path: path.clone() }),
+                None => {
+                    eprintln!("--terrain-source heightmap requires --heightmap, using DEM tiles");
+                    Box::new(DemProvider)
+                }
+            },
+        };
+        let mut ground = Ground::from_provider(
+            provider.as_ref(),
+            &args.llbbox(),
+            args.scale,
+            args.projection,
+            args.ground_level,
+        );
+        ground.smooth(args.terrain_smoothing);
+        ground.set_interpolation(args.terrain_interpolation);
         if args.debug {
             ground.save_debug_image("elevation_debug");
         }
@@ -150,3 +467,158 @@ pub fn generate_ground_data(args: &Args) -> Ground {
     }
     Ground::new_flat(args.ground_level)
 }
+
+/// Ground with real elevation from the given rows of heights, one per block
+#[cfg(test)]
+pub(crate) fn ground_from_rows(rows: Vec<Vec<i32>>, interpolation: TerrainInterpolation) -> Ground {
+    Ground {
+        elevation_enabled: true,
+        ground_level: -62,
+        elevation_data: Some(ElevationData {
+            width: rows[0].len(),
+            height: rows.len(),
+            heights: HeightGrid::from_rows(rows[0].len(), rows.len(), rows),
+            altitude: None,
+        }),
+        interpolation,
+        sunk_columns: HashMap::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_follows_elevation_grid() {
+        let ground = ground_from_rows(
+            vec![vec![-60, -55, -50], vec![-40, -35, -30]],
+            TerrainInterpolation::Nearest,
+        );
+
+        assert_eq!(ground.level(XZPoint::new(0, 0)), -60);
+        assert_eq!(ground.level(XZPoint::new(3, 2)), -30);
+        // Points outside of the grid take the height of its edge
+        assert_eq!(ground.level(XZPoint::new(-10, 50)), -40);
+        let corners = [XZPoint::new(0, 0), XZPoint::new(3, 0), XZPoint::new(3, 2)];
+        assert_eq!(ground.min_level(corners.into_iter()), Some(-60));
+        assert_eq!(ground.max_level(corners.into_iter()), Some(-30));
+
+        assert_eq!(Ground::new_flat(-62).level(XZPoint::new(1, 1)), -62);
+
+        let mut sunk = Ground::new_flat(-62);
+        let xzbbox = XZBBox::rect_from_xz_lengths(10.0, 10.0).unwrap();
+        sunk.sink_columns(HashMap::from([((2, 3), 8)]), &xzbbox);
+        assert_eq!(sunk.level(XZPoint::new(2, 3)), -70);
+        assert_eq!(sunk.level(XZPoint::new(3, 3)), -62);
+    }
+
+    #[test]
+    fn test_interpolation_and_smoothing_soften_steps() {
+        let mut ground = ground_from_rows(
+            vec![vec![-60, -60, -40, -40]; 4],
+            TerrainInterpolation::Nearest,
+        );
+        // Halfway across the step of 20 blocks between the second and third column
+        let middle = XZPoint::new(2, 0);
+        assert_eq!(ground.level(middle), -40);
+        ground.set_interpolation(TerrainInterpolation::Bilinear);
+        assert_eq!(ground.level(middle), -50);
+        ground.set_interpolation(TerrainInterpolation::Bicubic);
+        assert_eq!(ground.level(middle), -50);
+        assert_eq!(ground.level(XZPoint::new(0, 0)), -60);
+
+        ground.smooth(1.0);
+        let heights = &ground.elevation_data.as_ref().unwrap().heights;
+        assert!(heights.get(1, 0) > -60 && heights.get(2, 0) < -40);
+    }
+
+    #[test]
+    fn test_smoothing_in_bands_matches_the_whole_grid() {
+        // Taller than a band, with a step across the edge between the first two bands
+        let rows: Vec<Vec<i32>> = (0..SMOOTHING_BAND * 2 + 10)
+            .map(|z| vec![if z < SMOOTHING_BAND + 2 { -60 } else { -20 }; 3])
+            .collect();
+        let whole: Vec<Vec<f64>> = rows
+            .iter()
+            .map(|row| row.iter().map(|&h| h as f64).collect())
+            .collect();
+        let expected: Vec<Vec<i32>> = apply_gaussian_blur(&whole, 2.0)
+            .into_iter()
+            .map(|row| row.into_iter().map(|h| h.round() as i32).collect())
+            .collect();
+
+        let mut ground = ground_from_rows(rows, TerrainInterpolation::Bilinear);
+        ground.smooth(2.0);
+        let heights = &ground.elevation_data.as_ref().unwrap().heights;
+        assert_eq!(heights.rows(0..heights.height()), expected);
+    }
+
+    #[test]
+    fn test_slope_and_normal_of_a_ramp() {
+        let ground = ground_from_rows(
+            vec![vec![-60, -58, -56, -54, -52]; 5],
+            TerrainInterpolation::Bilinear,
+        );
+        // The 5 points of the grid span 5 blocks, so its rise of 2 per point is 1.6 per block
+        assert!((ground.level_f64(2.5, 2.0) - (-56.0)).abs() < 1e-9);
+        assert_eq!(
+            ground.levels([XZPoint::new(0, 0), XZPoint::new(5, 0)]),
+            vec![-60, -52]
+        );
+
+        let (dx, dz) = ground.slope(2.5, 2.0);
+        assert!((dx - 1.6).abs() < 1e-9 && dz.abs() < 1e-9);
+        let (nx, ny, nz) = ground.normal(2.5, 2.0);
+        assert!(nx < 0.0 && ny > 0.0 && nz.abs() < 1e-9);
+        assert!(((nx * nx + ny * ny + nz * nz) - 1.0).abs() < 1e-9);
+
+        assert_eq!(Ground::new_flat(-62).slope(1.0, 1.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_normal_leans_away_from_the_rise() {
+        // Rising by 2 per point towards positive z, which is 1.6 per block
+        let ground = ground_from_rows(
+            (0..5).map(|z| vec![-60 + 2 * z; 5]).collect(),
+            TerrainInterpolation::Bilinear,
+        );
+        let (nx, ny, nz) = ground.normal(2.0, 2.5);
+        assert!(nx.abs() < 1e-9);
+        assert!((nz / ny - (-1.6)).abs() < 1e-9);
+        assert!(((nx * nx + ny * ny + nz * nz) - 1.0).abs() < 1e-9);
+
+        // Flat ground faces straight up
+        assert_eq!(Ground::new_flat(-62).normal(1.0, 1.0), (0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_peak_elevation_raises_the_terrain() {
+        let mut ground = ground_from_rows(vec![vec![-62; 41]; 41], TerrainInterpolation::Bilinear);
+        ground.elevation_data.as_mut().unwrap().altitude =
+            Some(crate::elevation_data::AltitudeScale {
+                ground_altitude: 500.0,
+                meters_per_block: 2.0,
+            });
+        let peak = ProcessedElement::Node(crate::osm_parser::ProcessedNode {
+            id: 1,
+            tags: [("natural", "peak"), ("ele", "520 m")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            x: 20,
+            z: 20,
+        });
+        let xzbbox = XZBBox::rect_from_xz_lengths(40.0, 40.0).unwrap();
+
+        assert_eq!(ground.apply_elevation_tags(&[peak], &xzbbox), 1);
+        // 20 meters above the ground altitude at 2 meters per block
+        assert_eq!(ground.level(XZPoint::new(20, 20)), -52);
+        let flank = ground.level(XZPoint::new(26, 20));
+        assert!(flank > -62 && flank < -52);
+        assert_eq!(ground.level(XZPoint::new(0, 0)), -62);
+
+        assert_eq!(parse_ele("1234,5"), Some(1234.5));
+        assert_eq!(parse_ele("unknown"), None);
+    }
+}
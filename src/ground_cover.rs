@@ -0,0 +1,182 @@
+use crate::block_definitions::*;
+
+/// Size of the patches in the ground cover variation, in blocks
+const NOISE_SCALE: f64 = 12.0;
+/// Range in meters by which the tree line and snow line wander up and down
+const ALPINE_JITTER: f64 = 80.0;
+/// Depth in meters of the zone below the snow line where grass is dusted with snow
+const SNOW_EDGE_DEPTH: f64 = 150.0;
+
+/// Surface used for the ground outside of mapped features
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GroundCover {
+    #[default]
+    Grass,
+    Sand,
+    /// Sand mixed with patches of terracotta
+    Desert,
+    Snow,
+    /// No ground at all outside of mapped features
+    Void,
+}
+
+impl GroundCover {
+    /// Returns the surface and subsurface block of the filler ground at the given column,
+    /// or None if no ground should be generated
+    pub fn blocks_at(&self, x: i32, z: i32) -> Option<(Block, Block)> {
+        let noise = value_noise(x, z, NOISE_SCALE);
+
+        let blocks = match self {
+            GroundCover::Grass if noise > 0.85 => (COARSE_DIRT, DIRT),
+            GroundCover::Grass if noise < 0.1 => (PODZOL, DIRT),
+            GroundCover::Grass => (GRASS_BLOCK, DIRT),
+            GroundCover::Sand if noise > 0.8 => (SANDSTONE, SANDSTONE),
+            GroundCover::Sand => (SAND, SANDSTONE),
+            GroundCover::Desert if noise > 0.75 => (TERRACOTTA, TERRACOTTA),
+            GroundCover::Desert if noise < 0.15 => (ORANGE_TERRACOTTA, TERRACOTTA),
+            GroundCover::Desert => (SAND, SANDSTONE),
+            GroundCover::Snow if noise > 0.9 => (GRAVEL, DIRT),
+            GroundCover::Snow => (SNOW_BLOCK, DIRT),
+            GroundCover::Void => return None,
+        };
+
+        Some(blocks)
+    }
+
+    /// Returns a small plant to scatter on top of the filler ground at the given column
+    pub fn decoration_at(&self, x: i32, z: i32) -> Option<Block> {
+        let noise = value_noise(x, z, NOISE_SCALE);
+        let scatter = hash_to_unit(x, z, 1);
+
+        match self {
+            GroundCover::Grass if noise > 0.4 && noise < 0.85 && scatter < 0.08 => Some(GRASS),
+            GroundCover::Sand | GroundCover::Desert if noise < 0.3 && scatter < 0.01 => {
+                Some(DEAD_BUSH)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Zone of mountain terrain by its altitude, which takes the place of the ground cover
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlpineZone {
+    /// Bare rock and scree above the tree line
+    Rock,
+    /// Grass dusted with snow just below the snow line
+    SnowEdge,
+    /// Snow above the snow line
+    Snow,
+}
+
+impl AlpineZone {
+    /// Returns the zone at the given altitude in meters, None below the tree line. The lines
+    /// wander up and down a little so that the zones do not meet in straight contours.
+    pub fn at(altitude: f64, tree_line: f64, snow_line: f64, x: i32, z: i32) -> Option<Self> {
+        let altitude = altitude + (value_noise(x, z, NOISE_SCALE * 2.0) - 0.5) * ALPINE_JITTER;
+        if altitude >= snow_line {
+            Some(AlpineZone::Snow)
+        } else if altitude >= snow_line - SNOW_EDGE_DEPTH {
+            Some(AlpineZone::SnowEdge)
+        } else if altitude >= tree_line {
+            Some(AlpineZone::Rock)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the surface and subsurface block of the zone at the given column
+    pub fn blocks_at(&self, x: i32, z: i32) -> (Block, Block) {
+        let noise = value_noise(x, z, NOISE_SCALE);
+        match self {
+            AlpineZone::Rock if noise > 0.7 => (GRAVEL, STONE),
+            AlpineZone::Rock if noise < 0.2 => (ANDESITE, STONE),
+            AlpineZone::Rock if noise < 0.3 => (COARSE_DIRT, STONE),
+            AlpineZone::Rock => (STONE, STONE),
+            AlpineZone::SnowEdge if noise > 0.6 => (STONE, STONE),
+            AlpineZone::SnowEdge => (GRASS_BLOCK, DIRT),
+            AlpineZone::Snow => (SNOW_BLOCK, STONE),
+        }
+    }
+
+    /// Returns the block covering the surface of the zone, such as a layer of snow
+    pub fn decoration(&self) -> Option<Block> {
+        match self {
+            AlpineZone::SnowEdge => Some(SNOW_LAYER),
+            _ => None,
+        }
+    }
+}
+
+/// Smooth value noise in the range 0..1, varying over roughly `scale` blocks
+pub fn value_noise(x: i32, z: i32, scale: f64) -> f64 {
+    let fx = x as f64 / scale;
+    let fz = z as f64 / scale;
+    let (cell_x, cell_z) = (fx.floor() as i32, fz.floor() as i32);
+
+    // Smoothstep between the random values at the corners of the cell
+    let smooth = |t: f64| t * t * (3.0 - 2.0 * t);
+    let tx = smooth(fx - cell_x as f64);
+    let tz = smooth(fz - cell_z as f64);
+
+    let top = lerp(
+        hash_to_unit(cell_x, cell_z, 0),
+        hash_to_unit(cell_x + 1, cell_z, 0),
+        tx,
+    );
+    let bottom = lerp(
+        hash_to_unit(cell_x, cell_z + 1, 0),
+        hash_to_unit(cell_x + 1, cell_z + 1, 0),
+        tx,
+    );
+    lerp(top, bottom, tz)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+/// Deterministic pseudo random value in the range 0..1 for a position
+fn hash_to_unit(x: i32, z: i32, seed: u64) -> f64 {
+    let mut hash = (x as i64 as u64)
+        .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        .wrapping_add((z as i64 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F))
+        .wrapping_add(seed.wrapping_mul(0x1656_67B1_9E37_79F9));
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    hash ^= hash >> 33;
+
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_noise_range_and_determinism() {
+        for x in -50..50 {
+            for z in -50..50 {
+                let noise = value_noise(x, z, NOISE_SCALE);
+                assert!((0.0..=1.0).contains(&noise));
+                assert_eq!(noise, value_noise(x, z, NOISE_SCALE));
+            }
+        }
+    }
+
+    #[test]
+    fn test_alpine_zones_follow_altitude() {
+        let zone = |altitude| AlpineZone::at(altitude, 2000.0, 2800.0, 5, 9);
+        assert_eq!(zone(1500.0), None);
+        assert_eq!(zone(2400.0), Some(AlpineZone::Rock));
+        assert_eq!(zone(2750.0), Some(AlpineZone::SnowEdge));
+        assert_eq!(zone(3200.0), Some(AlpineZone::Snow));
+        assert_eq!(AlpineZone::Snow.blocks_at(5, 9).0, SNOW_BLOCK);
+    }
+
+    #[test]
+    fn test_void_has_no_ground() {
+        assert_eq!(GroundCover::Void.blocks_at(3, 7), None);
+        assert_eq!(GroundCover::Void.decoration_at(3, 7), None);
+    }
+}
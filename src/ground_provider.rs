@@ -0,0 +1,240 @@
+//! Sources of the terrain heights used with `--terrain`. Besides the real elevation from DEM
+//! tiles, worlds can get procedural hills or the heights of a grayscale heightmap image, which
+//! work without internet access.
+
+use crate::coordinate_system::geographic::LLBBox;
+use crate::coordinate_system::transformation::Projection;
+use crate::elevation_data::{fetch_elevation_data, grid_size, ElevationData, MAX_Y};
+use crate::elevation_grid::ElevationGrid;
+use crate::ground_cover::value_noise;
+use crate::height_grid::HeightGrid;
+use image::imageops::FilterType;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Height of the highest procedural hills above the ground level at scale 1, in blocks
+const NOISE_HILL_HEIGHT: f64 = 40.0;
+/// Feature sizes of the noise octaves in blocks, with their weights
+const NOISE_OCTAVES: [(f64, f64); 3] = [(160.0, 0.6), (64.0, 0.3), (24.0, 0.1)];
+/// Height of the white parts of a heightmap above the ground level at scale 1, in blocks
+const HEIGHTMAP_HEIGHT: f64 = 96.0;
+/// Global DEM API of OpenTopography
+const OPENTOPOGRAPHY_URL: &str = "https://portal.opentopography.org/API/globaldem";
+/// Dataset requested from OpenTopography, the SRTM elevation at 30 m resolution
+const OPENTOPOGRAPHY_DATASET: &str = "SRTMGL1";
+/// Margin in degrees around the world requested from OpenTopography, so that the heights at
+/// its edges can be interpolated
+const OPENTOPOGRAPHY_MARGIN: f64 = 0.001;
+
+/// Source of the terrain heights selected with `--terrain-source`
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TerrainSource {
+    /// Real elevation from DEM tiles, downloaded from the internet
+    #[default]
+    Dem,
+    /// Real elevation from the OpenTopography API, with the key given by --elevation-api-key
+    #[value(name = "opentopography")]
+    OpenTopography,
+    /// A GeoTIFF or ESRI ASCII grid in WGS84 coordinates given with --elevation-file
+    File,
+    /// Procedural rolling hills
+    Noise,
+    /// A grayscale heightmap image given with --heightmap
+    Heightmap,
+}
+
+/// Provides the grid of terrain heights for a world
+pub trait GroundProvider {
+    fn elevation_data(
+        &self,
+        bbox: &LLBBox,
+        scale: f64,
+        projection: Projection,
+        ground_level: i32,
+    ) -> Result<ElevationData, Box<dyn Error>>;
+}
+
+/// Real elevation from Terrarium DEM tiles
+pub struct DemProvider;
+
+impl GroundProvider for DemProvider {
+    fn elevation_data(
+        &self,
+        bbox: &LLBBox,
+        scale: f64,
+        projection: Projection,
+        ground_level: i32,
+    ) -> Result<ElevationData, Box<dyn Error>> {
+        fetch_elevation_data(bbox, scale, projection, ground_level)
+    }
+}
+
+/// Real elevation from the OpenTopography API, which requires an API key. Responses are cached
+/// next to the DEM tiles.
+pub struct OpenTopographyProvider {
+    pub api_key: Option<String>,
+}
+
+impl GroundProvider for OpenTopographyProvider {
+    fn elevation_data(
+        &self,
+        bbox: &LLBBox,
+        scale: f64,
+        projection: Projection,
+        ground_level: i32,
+    ) -> Result<ElevationData, Box<dyn Error>> {
+        let api_key = self
+            .api_key
+            .as_deref()
+            .ok_or("--terrain-source opentopography requires --elevation-api-key")?;
+        let south = bbox.min().lat() - OPENTOPOGRAPHY_MARGIN;
+        let west = bbox.min().lng() - OPENTOPOGRAPHY_MARGIN;
+        let north = bbox.max().lat() + OPENTOPOGRAPHY_MARGIN;
+        let east = bbox.max().lng() + OPENTOPOGRAPHY_MARGIN;
+
+        let cache_dir = Path::new("./arnis-tile-cache");
+        std::fs::create_dir_all(cache_dir)?;
+        let cache_path = cache_dir.join(format!(
+            "opentopography_{OPENTOPOGRAPHY_DATASET}_{south:.5}_{west:.5}_{north:.5}_{east:.5}.asc"
+        ));
+        if !cache_path.exists() {
+            println!("Fetching {OPENTOPOGRAPHY_DATASET} elevation data from OpenTopography");
+            let response = reqwest::blocking::Client::new()
+                .get(OPENTOPOGRAPHY_URL)
+                .query(&[
+                    ("demtype", OPENTOPOGRAPHY_DATASET.to_string()),
+                    ("south", south.to_string()),
+                    ("north", north.to_string()),
+                    ("west", west.to_string()),
+                    ("east", east.to_string()),
+                    ("outputFormat", "AAIGrid".to_string()),
+                    ("API_Key", api_key.to_string()),
+                ])
+                .send()?;
+            response.error_for_status_ref()?;
+            std::fs::write(&cache_path, response.bytes()?)?;
+        }
+
+        let grid = ElevationGrid::read(&cache_path).inspect_err(|_| {
+            // Do not keep an invalid response for the next run
+            let _ = std::fs::remove_file(&cache_path);
+        })?;
+        grid.elevation_data(bbox, scale, projection, ground_level)
+    }
+}
+
+/// Real elevation from a local GeoTIFF or ESRI ASCII grid
+pub struct ElevationFileProvider {
+    pub path: PathBuf,
+}
+
+impl GroundProvider for ElevationFileProvider {
+    fn elevation_data(
+        &self,
+        bbox: &LLBBox,
+        scale: f64,
+        projection: Projection,
+        ground_level: i32,
+    ) -> Result<ElevationData, Box<dyn Error>> {
+        ElevationGrid::read(&self.path)?.elevation_data(bbox, scale, projection, ground_level)
+    }
+}
+
+/// Rolling hills from several octaves of value noise
+pub struct NoiseProvider;
+
+impl GroundProvider for NoiseProvider {
+    fn elevation_data(
+        &self,
+        bbox: &LLBBox,
+        scale: f64,
+        projection: Projection,
+        ground_level: i32,
+    ) -> Result<ElevationData, Box<dyn Error>> {
+        let (width, height) = grid_size(bbox, scale, projection);
+        let hill_height = NOISE_HILL_HEIGHT * scale.sqrt();
+
+        let heights = HeightGrid::from_fn(width, height, move |x, z| {
+            let (x, z) = (x as i32, z as i32);
+            // Offset the octaves so that their cells do not line up
+            let noise: f64 = NOISE_OCTAVES
+                .iter()
+                .enumerate()
+                .map(|(i, &(size, weight))| {
+                    let offset = i as i32 * 1000;
+                    value_noise(x + offset, z - offset, size * scale) * weight
+                })
+                .sum();
+            to_block_height(noise, hill_height, ground_level)
+        });
+
+        Ok(ElevationData {
+            heights,
+            width,
+            height,
+            altitude: None,
+        })
+    }
+}
+
+/// Heights of a grayscale image stretched over the world, black being the ground level
+pub struct HeightmapProvider {
+    pub path: PathBuf,
+}
+
+impl GroundProvider for HeightmapProvider {
+    fn elevation_data(
+        &self,
+        bbox: &LLBBox,
+        scale: f64,
+        projection: Projection,
+        ground_level: i32,
+    ) -> Result<ElevationData, Box<dyn Error>> {
+        let (width, height) = grid_size(bbox, scale, projection);
+        if width == 0 || height == 0 {
+            return Err("The world is too small for a heightmap".into());
+        }
+
+        let image = image::open(&self.path)?.to_luma16();
+        let image =
+            image::imageops::resize(&image, width as u32, height as u32, FilterType::Triangle);
+        let max_height = HEIGHTMAP_HEIGHT * scale.sqrt();
+
+        let heights = HeightGrid::from_fn(width, height, move |x, z| {
+            let value = image.get_pixel(x as u32, z as u32)[0] as f64 / 65535.0;
+            to_block_height(value, max_height, ground_level)
+        });
+
+        Ok(ElevationData {
+            heights,
+            width,
+            height,
+            altitude: None,
+        })
+    }
+}
+
+/// Converts a relative height in the range 0..1 to a Y coordinate above the ground level
+fn to_block_height(relative: f64, max_height: f64, ground_level: i32) -> i32 {
+    ((ground_level as f64 + relative * max_height).round() as i32).clamp(ground_level, MAX_Y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_noise_heights_stay_above_ground_level() {
+        let bbox = LLBBox::new(48.0, 11.0, 48.002, 11.003).unwrap();
+        let data = NoiseProvider
+            .elevation_data(&bbox, 1.0, Projection::default(), -62)
+            .unwrap();
+
+        assert_eq!(data.heights.height(), data.height);
+        assert_eq!(data.heights.width(), data.width);
+        let rows = data.heights.rows(0..data.height);
+        let all_heights = rows.iter().flatten();
+        assert!(all_heights.clone().all(|&h| (-62..=-62 + 40).contains(&h)));
+        assert!(all_heights.clone().min() < all_heights.max());
+    }
+}
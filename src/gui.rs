@@ -1,14 +1,18 @@
 use crate::args::Args;
 use crate::coordinate_system::cartesian::XZPoint;
 use crate::coordinate_system::geographic::{LLBBox, LLPoint};
-use crate::coordinate_system::transformation::CoordTransformer;
+use crate::coordinate_system::transformation::{CoordTransformer, Projection};
 use crate::data_processing::{self, GenerationOptions};
-use crate::ground::{self, Ground};
+use crate::element_processing::gorges;
+use crate::ground::{self, Ground, TerrainInterpolation};
+use crate::ground_provider::TerrainSource;
 use crate::map_transformation;
 use crate::osm_parser;
 use crate::progress::{self, emit_gui_progress_update};
 use crate::retrieve_data;
 use crate::telemetry::{self, send_log, LogLevel};
+use crate::theme::Theme;
+use crate::time_of_day::TimeOfDay;
 use crate::version_check;
 use crate::world_editor::WorldFormat;
 use colored::Colorize;
@@ -455,8 +459,9 @@ fn update_player_position(
     }
 
     // Convert lat/lng to Minecraft coordinates
-    let (transformer, _) = CoordTransformer::llbbox_to_xzbbox(&llbbox, scale)
-        .map_err(|e| format!("Failed to build transformation on coordinate systems:\n{e}"))?;
+    let (transformer, _) =
+        CoordTransformer::llbbox_to_xzbbox(&llbbox, scale, Projection::default())
+            .map_err(|e| format!("Failed to build transformation on coordinate systems:\n{e}"))?;
 
     let xzpoint = transformer.transform_point(llpoint);
 
@@ -610,7 +615,7 @@ pub fn update_player_spawn_y_after_generation(
         // Parse coordinates for terrain lookup
         let llbbox = LLBBox::from_str(&bbox_text)
             .map_err(|e| format!("Failed to parse bounding box for spawn point:\n{e}"))?;
-        let (_, xzbbox) = CoordTransformer::llbbox_to_xzbbox(&llbbox, scale)
+        let (_, xzbbox) = CoordTransformer::llbbox_to_xzbbox(&llbbox, scale, Projection::default())
             .map_err(|e| format!("Failed to build transformation:\n{e}"))?;
 
         // Calculate relative coordinates for ground system
@@ -672,6 +677,10 @@ fn gui_get_version() -> String {
 
 #[tauri::command]
 fn gui_check_for_updates() -> Result<bool, String> {
+    if version_check::is_update_check_disabled() {
+        return Ok(false);
+    }
+
     match version_check::check_for_updates() {
         Ok(is_newer) => Ok(is_newer),
         Err(e) => Err(format!("Error checking for updates: {e}")),
@@ -904,9 +913,11 @@ fn gui_start_generation(
             // Calculate MC spawn coordinates from lat/lng if spawn point was provided
             let mc_spawn_point: Option<(i32, i32)> = if let Some((lat, lng)) = spawn_point {
                 if let Ok(llpoint) = LLPoint::new(lat, lng) {
-                    if let Ok((transformer, _)) =
-                        CoordTransformer::llbbox_to_xzbbox(&bbox, world_scale)
-                    {
+                    if let Ok((transformer, _)) = CoordTransformer::llbbox_to_xzbbox(
+                        &bbox,
+                        world_scale,
+                        Projection::default(),
+                    ) {
                         let xzpoint = transformer.transform_point(llpoint);
                         Some((xzpoint.x, xzpoint.z))
                     } else {
@@ -930,21 +941,57 @@ fn gui_start_generation(
             // Create an Args instance with the chosen bounding box
             // Note: path is used for Java-specific features like spawn point update
             let args: Args = Args {
-                bbox,
+                bbox: Some(bbox),
+                place: None,
+                yes: false,
                 file: None,
+                geojson: None,
+                streaming: false,
+                feature_filter: None,
+                incremental: false,
                 save_json_file: None,
                 path: if world_format == WorldFormat::JavaAnvil {
                     generation_path
                 } else {
                     world_path
                 },
+                context_margin: 0.0,
                 downloader: "requests".to_string(),
+                overpass_url: None,
+                query_filter: None,
+                no_cache: false,
+                refresh: false,
                 scale: world_scale,
                 ground_level,
                 terrain: terrain_enabled,
+                terrain_source: TerrainSource::Dem,
+                heightmap: None,
+                elevation_api_key: None,
+                elevation_file: None,
+                terrain_smoothing: 0.0,
+                terrain_interpolation: TerrainInterpolation::Bilinear,
+                snow_line: 2800.0,
+                tree_line: 2000.0,
+                cave_systems: false,
+                peak_beacons: false,
                 interior: interior_enabled,
                 roof: roof_enabled,
+                green_roofs: false,
                 fillground: fillground_enabled,
+                theme: Theme::Auto,
+                projection: Projection::TransverseMercator,
+                ground_cover: None,
+                meadow_density: 1.0,
+                time: TimeOfDay::Day,
+                compression_level: None,
+                compact_regions: false,
+                trail_markers: false,
+                ownership_map: false,
+                index: false,
+                dry_run: false,
+                parallel: false,
+                chapters: false,
+                no_update_check: false,
                 debug: false,
                 timeout: Some(std::time::Duration::from_secs(floodfill_timeout)),
                 spawn_point,
@@ -958,13 +1005,13 @@ fn gui_start_generation(
                 // Create empty parsed_elements and xzbbox for terrain-only mode
                 let parsed_elements = Vec::new();
                 let (_coord_transformer, xzbbox) =
-                    CoordTransformer::llbbox_to_xzbbox(&args.bbox, args.scale)
+                    CoordTransformer::llbbox_to_xzbbox(&args.llbbox(), args.scale, args.projection)
                         .map_err(|e| format!("Failed to create coordinate transformer: {}", e))?;
 
                 let _ = data_processing::generate_world_with_options(
                     parsed_elements,
                     xzbbox.clone(),
-                    args.bbox,
+                    args.llbbox(),
                     ground,
                     &args,
                     generation_options.clone(),
@@ -988,10 +1035,24 @@ fn gui_start_generation(
             }
 
             // Run data fetch and world generation (standard mode: objects + terrain, or objects only)
-            match retrieve_data::fetch_data_from_overpass(args.bbox, args.debug, "requests", None) {
+            match retrieve_data::fetch_data_from_overpass(
+                args.llbbox(),
+                args.debug,
+                "requests",
+                None,
+                None,
+                retrieve_data::CacheMode::from_args(&args),
+                args.query_filter.as_ref(),
+            ) {
                 Ok(raw_data) => {
-                    let (mut parsed_elements, mut xzbbox) =
-                        osm_parser::parse_osm_data(raw_data, args.bbox, args.scale, args.debug);
+                    let (mut parsed_elements, mut xzbbox) = osm_parser::parse_osm_data(
+                        raw_data,
+                        args.llbbox(),
+                        args.scale,
+                        args.projection,
+                        args.context_margin,
+                        args.debug,
+                    );
                     parsed_elements.sort_by(|el1, el2| {
                         let (el1_priority, el2_priority) =
                             (osm_parser::get_priority(el1), osm_parser::get_priority(el2));
@@ -1006,6 +1067,8 @@ fn gui_start_generation(
                     });
 
                     let mut ground = ground::generate_ground_data(&args);
+                    ground.apply_elevation_tags(&parsed_elements, &xzbbox);
+                    ground.sink_columns(gorges::gorge_cuts(&parsed_elements, args.scale), &xzbbox);
 
                     // Transform map (parsed_elements). Operations are defined in a json file
                     map_transformation::transform_map(
@@ -1017,7 +1080,7 @@ fn gui_start_generation(
                     let _ = data_processing::generate_world_with_options(
                         parsed_elements,
                         xzbbox.clone(),
-                        args.bbox,
+                        args.llbbox(),
                         ground,
                         &args,
                         generation_options.clone(),
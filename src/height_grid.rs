@@ -0,0 +1,501 @@
+//! Grid of terrain heights. Grids of ordinary areas are kept in memory as a whole. Those of very
+//! large areas are split into square tiles, of which only the most recently used stay in memory.
+//! Tiles are computed when they are first read, and tiles that cannot be computed again are
+//! spilled to a temporary file and read back when they are needed again.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Width and height of the tiles of large grids
+const TILE_SIZE: usize = 256;
+/// Cells up to which a grid is kept in memory as a whole
+const MAX_DENSE_CELLS: usize = 16 * 1024 * 1024;
+/// Tiles of large grids kept in memory at once
+const RESIDENT_TILES: usize = 64;
+/// Tiles each thread keeps at hand, enough for the points around a point at a tile corner
+const THREAD_TILES: usize = 4;
+
+/// Number of spill files created so far, which keeps their names apart
+static SPILL_FILES: AtomicUsize = AtomicUsize::new(0);
+/// Last version given to the tiles of a grid
+static TILE_VERSIONS: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// Tiles last read by this thread by the version of their grid and their index, so that
+    /// reading the points of a tile only locks the cache for the first one
+    static RECENT_TILES: RefCell<Vec<RecentTile>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Tile read by a thread
+struct RecentTile {
+    version: u64,
+    index: usize,
+    tile: Arc<Vec<i32>>,
+}
+
+/// Computes the height of a point of a grid
+type HeightSource = Arc<dyn Fn(usize, usize) -> i32 + Send + Sync>;
+
+/// Heights of the points of an elevation grid
+pub struct HeightGrid {
+    width: usize,
+    height: usize,
+    storage: Storage,
+}
+
+enum Storage {
+    Dense(Vec<i32>),
+    Tiled(Box<Tiles>),
+}
+
+impl HeightGrid {
+    /// Creates a grid of the given size from its rows, which are read one after another so
+    /// that large grids never have to be in memory as a whole
+    pub fn from_rows(
+        width: usize,
+        height: usize,
+        rows: impl IntoIterator<Item = Vec<i32>>,
+    ) -> Self {
+        Self::rows_with_limits(width, height, rows, MAX_DENSE_CELLS, RESIDENT_TILES)
+    }
+
+    /// Creates a grid of the given size whose heights are computed by the given function. The
+    /// tiles of large grids are computed when they are read, again once they left the memory.
+    pub fn from_fn(
+        width: usize,
+        height: usize,
+        source: impl Fn(usize, usize) -> i32 + Send + Sync + 'static,
+    ) -> Self {
+        Self::fn_with_limits(width, height, source, MAX_DENSE_CELLS, RESIDENT_TILES)
+    }
+
+    fn rows_with_limits(
+        width: usize,
+        height: usize,
+        rows: impl IntoIterator<Item = Vec<i32>>,
+        max_dense_cells: usize,
+        resident_tiles: usize,
+    ) -> Self {
+        if width * height <= max_dense_cells {
+            let heights: Vec<i32> = rows.into_iter().flatten().collect();
+            assert_eq!(heights.len(), width * height, "Rows do not fill the grid");
+            return Self {
+                width,
+                height,
+                storage: Storage::Dense(heights),
+            };
+        }
+
+        // The rows are gathered in a band of tiles, which are cached once the band is full
+        let mut cache = TileCache::new(width, height, resident_tiles, None);
+        let mut band: Vec<Vec<i32>> = vec![Vec::new(); cache.tiles_x];
+        for (z, row) in rows.into_iter().enumerate().take(height) {
+            let offset = (z % TILE_SIZE) * TILE_SIZE;
+            for (tile_x, tile) in band.iter_mut().enumerate() {
+                let start = tile_x * TILE_SIZE;
+                let end = (start + TILE_SIZE).min(width);
+                tile.resize(TILE_SIZE * TILE_SIZE, 0);
+                tile[offset..offset + end - start].copy_from_slice(&row[start..end]);
+            }
+            if (z + 1) % TILE_SIZE == 0 || z + 1 == height {
+                let first = (z / TILE_SIZE) * cache.tiles_x;
+                for (tile_x, tile) in band.iter_mut().enumerate() {
+                    cache.insert(first + tile_x, Arc::new(std::mem::take(tile)), true);
+                }
+            }
+        }
+        Self::tiled(width, height, cache)
+    }
+
+    fn fn_with_limits(
+        width: usize,
+        height: usize,
+        source: impl Fn(usize, usize) -> i32 + Send + Sync + 'static,
+        max_dense_cells: usize,
+        resident_tiles: usize,
+    ) -> Self {
+        if width * height <= max_dense_cells {
+            let heights = (0..height)
+                .flat_map(|z| (0..width).map(move |x| (x, z)))
+                .map(|(x, z)| source(x, z))
+                .collect();
+            return Self {
+                width,
+                height,
+                storage: Storage::Dense(heights),
+            };
+        }
+        let cache = TileCache::new(width, height, resident_tiles, Some(Arc::new(source)));
+        Self::tiled(width, height, cache)
+    }
+
+    fn tiled(width: usize, height: usize, cache: TileCache) -> Self {
+        Self {
+            width,
+            height,
+            storage: Storage::Tiled(Box::new(Tiles {
+                version: TILE_VERSIONS.fetch_add(1, Ordering::Relaxed) + 1,
+                tiles_x: cache.tiles_x,
+                cache: Mutex::new(cache),
+            })),
+        }
+    }
+
+    /// Number of points in each row
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of rows
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Returns the height of the given point
+    #[inline(always)]
+    pub fn get(&self, x: usize, z: usize) -> i32 {
+        match &self.storage {
+            Storage::Dense(heights) => heights[z * self.width + x],
+            Storage::Tiled(tiles) => {
+                let (index, offset) = tiles.locate(x, z);
+                RECENT_TILES.with_borrow_mut(|recent| {
+                    let cached = recent
+                        .iter()
+                        .find(|recent| recent.version == tiles.version && recent.index == index);
+                    if let Some(recent) = cached {
+                        return recent.tile[offset];
+                    }
+                    let tile = tiles.cache.lock().unwrap().tile(index);
+                    let height = tile[offset];
+                    if recent.len() >= THREAD_TILES {
+                        recent.remove(0);
+                    }
+                    recent.push(RecentTile {
+                        version: tiles.version,
+                        index,
+                        tile,
+                    });
+                    height
+                })
+            }
+        }
+    }
+
+    /// Sets the height of the given point
+    pub fn set(&mut self, x: usize, z: usize, value: i32) {
+        match &mut self.storage {
+            Storage::Dense(heights) => heights[z * self.width + x] = value,
+            Storage::Tiled(tiles) => {
+                let (index, offset) = tiles.locate(x, z);
+                let cache = tiles.cache.get_mut().unwrap();
+                cache.tile(index);
+                // Copied first if threads still hold the tile as it was
+                Arc::make_mut(cache.resident.get_mut(&index).unwrap())[offset] = value;
+                cache.dirty.insert(index);
+                // The tiles threads read before are out of date now
+                tiles.version = TILE_VERSIONS.fetch_add(1, Ordering::Relaxed) + 1;
+            }
+        }
+    }
+
+    /// Returns the heights of the given rows
+    pub fn rows(&self, range: Range<usize>) -> Vec<Vec<i32>> {
+        let mut rows: Vec<Vec<i32>> = vec![vec![0; self.width]; range.len()];
+        match &self.storage {
+            Storage::Dense(heights) => {
+                for (row, z) in rows.iter_mut().zip(range) {
+                    row.copy_from_slice(&heights[z * self.width..(z + 1) * self.width]);
+                }
+            }
+            Storage::Tiled(tiles) => {
+                // Tile by tile, so that each one is only looked up once
+                for tile_z in range.start / TILE_SIZE..range.end.div_ceil(TILE_SIZE) {
+                    let first_z = (tile_z * TILE_SIZE).max(range.start);
+                    let last_z = ((tile_z + 1) * TILE_SIZE).min(range.end);
+                    for tile_x in 0..tiles.tiles_x {
+                        let tile = tiles
+                            .cache
+                            .lock()
+                            .unwrap()
+                            .tile(tile_z * tiles.tiles_x + tile_x);
+                        let start = tile_x * TILE_SIZE;
+                        let end = (start + TILE_SIZE).min(self.width);
+                        for z in first_z..last_z {
+                            let offset = (z % TILE_SIZE) * TILE_SIZE;
+                            rows[z - range.start][start..end]
+                                .copy_from_slice(&tile[offset..offset + end - start]);
+                        }
+                    }
+                }
+            }
+        }
+        rows
+    }
+}
+
+impl Clone for HeightGrid {
+    fn clone(&self) -> Self {
+        match &self.storage {
+            Storage::Dense(heights) => Self {
+                width: self.width,
+                height: self.height,
+                storage: Storage::Dense(heights.clone()),
+            },
+            Storage::Tiled(tiles) => {
+                let mut cache = tiles.cache.lock().unwrap();
+                let mut copy = TileCache::new(
+                    self.width,
+                    self.height,
+                    cache.capacity,
+                    cache.source.clone(),
+                );
+                // Only the tiles that cannot be computed again are copied, sharing their
+                // heights until either grid changes them
+                let kept: Vec<usize> = cache.dirty.union(&cache.spilled).copied().collect();
+                for index in kept {
+                    let tile = cache.tile(index);
+                    copy.insert(index, tile, true);
+                }
+                Self::tiled(self.width, self.height, copy)
+            }
+        }
+    }
+}
+
+/// Tiles of a large grid shared by the threads reading it
+struct Tiles {
+    /// Changes whenever a tile does, which tells the tiles threads read before apart
+    version: u64,
+    tiles_x: usize,
+    cache: Mutex<TileCache>,
+}
+
+impl Tiles {
+    /// Returns the tile of a point and the offset of the point within it
+    #[inline(always)]
+    fn locate(&self, x: usize, z: usize) -> (usize, usize) {
+        let index = (z / TILE_SIZE) * self.tiles_x + x / TILE_SIZE;
+        (index, (z % TILE_SIZE) * TILE_SIZE + x % TILE_SIZE)
+    }
+}
+
+/// Tiles of a large grid in memory, the least recently used of which are dropped when more
+/// are read
+struct TileCache {
+    width: usize,
+    height: usize,
+    tiles_x: usize,
+    capacity: usize,
+    /// Computes the tiles not read from rows, or `None` if all of them were
+    source: Option<HeightSource>,
+    resident: HashMap<usize, Arc<Vec<i32>>>,
+    /// Resident tiles from the most to the least recently used
+    recent: VecDeque<usize>,
+    /// Resident tiles that differ from their copy in the spill file or from their source
+    dirty: HashSet<usize>,
+    /// Tiles in the spill file
+    spilled: HashSet<usize>,
+    spill: Option<SpillFile>,
+}
+
+impl TileCache {
+    fn new(width: usize, height: usize, capacity: usize, source: Option<HeightSource>) -> Self {
+        Self {
+            width,
+            height,
+            tiles_x: width.div_ceil(TILE_SIZE),
+            capacity: capacity.max(1),
+            source,
+            resident: HashMap::new(),
+            recent: VecDeque::new(),
+            dirty: HashSet::new(),
+            spilled: HashSet::new(),
+            spill: None,
+        }
+    }
+
+    /// Returns a tile, reading it back from the spill file or computing it if it is not in
+    /// memory
+    fn tile(&mut self, index: usize) -> Arc<Vec<i32>> {
+        if let Some(tile) = self.resident.get(&index) {
+            let tile = tile.clone();
+            if self.recent.front() != Some(&index) {
+                self.recent.retain(|&resident| resident != index);
+                self.recent.push_front(index);
+            }
+            return tile;
+        }
+
+        let tile = if self.spilled.contains(&index) {
+            self.spill.as_mut().unwrap().read(index)
+        } else {
+            let source = self
+                .source
+                .as_ref()
+                .expect("Heightmap tile neither in memory nor spilled");
+            let first_x = (index % self.tiles_x) * TILE_SIZE;
+            let first_z = (index / self.tiles_x) * TILE_SIZE;
+            let mut tile = vec![0; TILE_SIZE * TILE_SIZE];
+            for z in first_z..(first_z + TILE_SIZE).min(self.height) {
+                for x in first_x..(first_x + TILE_SIZE).min(self.width) {
+                    tile[(z - first_z) * TILE_SIZE + x - first_x] = source(x, z);
+                }
+            }
+            tile
+        };
+        let tile = Arc::new(tile);
+        self.insert(index, tile.clone(), false);
+        tile
+    }
+
+    /// Adds a tile to the ones in memory, dropping the least recently used one when full.
+    /// Dropped tiles that cannot be computed again are spilled.
+    fn insert(&mut self, index: usize, tile: Arc<Vec<i32>>, dirty: bool) {
+        if self.resident.len() >= self.capacity {
+            if let Some(evicted) = self.recent.pop_back() {
+                let tile = self.resident.remove(&evicted).unwrap();
+                if self.dirty.remove(&evicted) {
+                    self.spill
+                        .get_or_insert_with(SpillFile::create)
+                        .write(evicted, &tile);
+                    self.spilled.insert(evicted);
+                }
+            }
+        }
+        if dirty {
+            self.dirty.insert(index);
+        }
+        self.resident.insert(index, tile);
+        self.recent.push_front(index);
+    }
+}
+
+/// Temporary file holding the tiles of a grid at fixed offsets, removed when dropped
+struct SpillFile {
+    path: PathBuf,
+    file: File,
+}
+
+impl SpillFile {
+    fn create() -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "arnis-heights-{}-{}.bin",
+            std::process::id(),
+            SPILL_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .expect("Failed to create heightmap spill file");
+        Self { path, file }
+    }
+
+    fn offset(index: usize) -> u64 {
+        (index * TILE_SIZE * TILE_SIZE * 4) as u64
+    }
+
+    fn write(&mut self, index: usize, tile: &[i32]) {
+        let bytes: Vec<u8> = tile.iter().flat_map(|h| h.to_le_bytes()).collect();
+        self.file
+            .seek(SeekFrom::Start(Self::offset(index)))
+            .and_then(|_| self.file.write_all(&bytes))
+            .expect("Failed to spill heightmap tile");
+    }
+
+    fn read(&mut self, index: usize) -> Vec<i32> {
+        let mut bytes = vec![0u8; TILE_SIZE * TILE_SIZE * 4];
+        self.file
+            .seek(SeekFrom::Start(Self::offset(index)))
+            .and_then(|_| self.file.read_exact(&mut bytes))
+            .expect("Failed to read spilled heightmap tile");
+        bytes
+            .chunks_exact(4)
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect()
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tiled_grid_spills_and_restores_tiles() {
+        let (width, height) = (TILE_SIZE * 3 + 7, TILE_SIZE * 2 + 1);
+        let rows: Vec<Vec<i32>> = (0..height)
+            .map(|z| (0..width).map(|x| (x * 3 + z * 7) as i32).collect())
+            .collect();
+        // Two of the twelve tiles in memory at once
+        let mut grid = HeightGrid::rows_with_limits(width, height, rows.clone(), 0, 2);
+        assert!(matches!(grid.storage, Storage::Tiled(_)));
+
+        assert_eq!(grid.get(0, 0), 0);
+        assert_eq!(grid.get(width - 1, height - 1), rows[height - 1][width - 1]);
+        grid.set(TILE_SIZE + 1, TILE_SIZE + 2, -5);
+        // Reading the other tiles spills the changed one, which must come back changed
+        assert_eq!(grid.get(10, TILE_SIZE * 2), rows[TILE_SIZE * 2][10]);
+        assert_eq!(grid.get(TILE_SIZE * 3, 0), rows[0][TILE_SIZE * 3]);
+        assert_eq!(grid.get(TILE_SIZE + 1, TILE_SIZE + 2), -5);
+
+        let copy = grid.clone();
+        let mut expected = rows;
+        expected[TILE_SIZE + 2][TILE_SIZE + 1] = -5;
+        assert_eq!(copy.rows(0..height), expected);
+        assert_eq!(
+            copy.rows(TILE_SIZE..TILE_SIZE + 3),
+            expected[TILE_SIZE..TILE_SIZE + 3]
+        );
+        let dense = HeightGrid::from_rows(width, height, expected.clone());
+        assert_eq!(dense.rows(0..height), expected);
+    }
+
+    #[test]
+    fn test_tiles_are_computed_when_read() {
+        let computed = Arc::new(AtomicUsize::new(0));
+        let counter = computed.clone();
+        let (width, height) = (TILE_SIZE * 4, TILE_SIZE * 4);
+        let mut grid = HeightGrid::fn_with_limits(
+            width,
+            height,
+            move |x, z| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                (x + z) as i32
+            },
+            0,
+            2,
+        );
+        assert_eq!(computed.load(Ordering::Relaxed), 0);
+
+        // Only the tile of the point is computed, and only once
+        assert_eq!(grid.get(300, 20), 320);
+        assert_eq!(grid.get(301, 21), 322);
+        assert_eq!(computed.load(Ordering::Relaxed), TILE_SIZE * TILE_SIZE);
+
+        // A changed tile is spilled rather than computed again
+        grid.set(300, 20, -1);
+        for i in 0..4 {
+            assert_eq!(grid.get(i * TILE_SIZE, 3 * TILE_SIZE), (i + 3) as i32 * 256);
+        }
+        assert_eq!(grid.get(300, 20), -1);
+        assert_eq!(grid.get(301, 20), 321);
+    }
+}
@@ -0,0 +1,77 @@
+//! Localization of CLI and progress messages.
+//!
+//! Messages are looked up in the locale files shared with the GUI. The language is taken from
+//! `ARNIS_LANG` or the system locale, falling back to English for missing languages and keys.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const ENGLISH: &str = include_str!("gui/locales/en-US.json");
+const GERMAN: &str = include_str!("gui/locales/de.json");
+
+static ENGLISH_MESSAGES: OnceLock<HashMap<String, String>> = OnceLock::new();
+static LOCALIZED_MESSAGES: OnceLock<Option<HashMap<String, String>>> = OnceLock::new();
+
+/// Returns the message for a key in the user's language
+pub fn tr(key: &str) -> &'static str {
+    let localized = LOCALIZED_MESSAGES.get_or_init(|| bundle_for(&system_language()));
+    let english = ENGLISH_MESSAGES.get_or_init(|| parse_bundle(ENGLISH));
+
+    localized
+        .as_ref()
+        .and_then(|messages| messages.get(key))
+        .or_else(|| english.get(key))
+        .map(String::as_str)
+        .unwrap_or("")
+}
+
+/// Returns the language code of the user, e.g. "de" for "de_DE.UTF-8"
+fn system_language() -> String {
+    ["ARNIS_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .map(|value| language_code(&value))
+        .unwrap_or_default()
+}
+
+fn language_code(locale: &str) -> String {
+    locale
+        .split(['_', '-', '.', '@'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// Returns the messages of a language other than English, None if it has no bundle
+fn bundle_for(language: &str) -> Option<HashMap<String, String>> {
+    match language {
+        "de" => Some(parse_bundle(GERMAN)),
+        _ => None,
+    }
+}
+
+fn parse_bundle(contents: &str) -> HashMap<String, String> {
+    serde_json::from_str(contents).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_code() {
+        assert_eq!(language_code("de_DE.UTF-8"), "de");
+        assert_eq!(language_code("en-US"), "en");
+        assert_eq!(language_code("C"), "c");
+    }
+
+    #[test]
+    fn test_bundles_have_the_same_cli_keys() {
+        let english = parse_bundle(ENGLISH);
+        let german = parse_bundle(GERMAN);
+        for key in english.keys().filter(|key| key.starts_with("step_")) {
+            assert!(german.contains_key(key), "missing German message for {key}");
+        }
+    }
+}
@@ -3,19 +3,33 @@
 mod args;
 #[cfg(feature = "bedrock")]
 mod bedrock_block_map;
+mod bench;
 mod block_definitions;
 mod bresenham;
+mod chapters;
 mod clipping;
 mod colors;
 mod coordinate_system;
 mod data_processing;
 mod element_processing;
+mod element_rng;
 mod elevation_data;
+mod elevation_grid;
+mod feature_filter;
 mod floodfill;
+mod geojson;
 mod ground;
+mod ground_cover;
+mod ground_provider;
+mod height_grid;
+mod i18n;
 mod map_renderer;
 mod map_transformation;
+mod node_index;
 mod osm_parser;
+mod osm_pbf;
+mod osm_xml;
+mod polygon_repair;
 #[cfg(feature = "gui")]
 mod progress;
 mod retrieve_data;
@@ -23,13 +37,19 @@ mod retrieve_data;
 mod telemetry;
 #[cfg(test)]
 mod test_utilities;
+mod theme;
+mod tiles;
+mod time_of_day;
 mod version_check;
 mod world_editor;
 
 use args::Args;
 use clap::Parser;
 use colored::*;
-use std::{env, fs, io::Write};
+use coordinate_system::geographic::LLBBox;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::{env, fs};
 
 #[cfg(feature = "gui")]
 mod gui;
@@ -49,6 +69,32 @@ mod progress {
 use windows::Win32::System::Console::{AttachConsole, FreeConsole, ATTACH_PARENT_PROCESS};
 
 fn run_cli() {
+    match env::args().nth(1).as_deref() {
+        Some("inspect") => {
+            run_inspect(args::InspectArgs::parse_from(env::args().skip(1)));
+            return;
+        }
+        Some("locate") => {
+            run_locate(args::LocateArgs::parse_from(env::args().skip(1)));
+            return;
+        }
+        Some("bench") => {
+            if let Err(e) = bench::run(args::BenchArgs::parse_from(env::args().skip(1))) {
+                eprintln!("{}: {}", "Benchmark failed".red().bold(), e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("self-update") => {
+            if let Err(e) = version_check::self_update() {
+                eprintln!("{}: {}", "Failed to update Arnis".red().bold(), e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        _ => {}
+    }
+
     let version: &str = env!("CARGO_PKG_VERSION");
     let repository: &str = env!("CARGO_PKG_REPOSITORY");
     println!(
@@ -70,35 +116,83 @@ fn run_cli() {
         repository.bright_white().bold()
     );
 
-    // Check for updates
-    if let Err(e) = version_check::check_for_updates() {
-        eprintln!(
-            "{}: {}",
-            "Error checking for version updates".red().bold(),
-            e
-        );
+    // Parse input arguments, looking up the bounding box of a place given by name
+    let mut args: Args = Args::parse();
+    if let Some(place) = &args.place {
+        args.bbox = Some(resolve_place(place, args.yes));
     }
 
-    // Parse input arguments
-    let args: Args = Args::parse();
+    // Only JSON files are read element by element, which is checked before anything is fetched
+    if let (true, Some(file)) = (args.streaming, &args.file) {
+        if let Err(e) = osm_parser::ensure_streamable(Path::new(file)) {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            std::process::exit(1);
+        }
+    }
 
-    // Fetch data
-    let raw_data = match &args.file {
-        Some(file) => retrieve_data::fetch_data_from_file(file),
-        None => retrieve_data::fetch_data_from_overpass(
-            args.bbox,
+    // Check for updates
+    if !args.no_update_check && !version_check::is_update_check_disabled() {
+        if let Err(e) = version_check::check_for_updates() {
+            eprintln!(
+                "{}: {}",
+                "Error checking for version updates".red().bold(),
+                e
+            );
+        }
+    }
+
+    // Fetch data, unless the file is parsed as it is read
+    let mut raw_data = match &args.file {
+        Some(_) if args.streaming => None,
+        Some(file) => Some(retrieve_data::fetch_data_from_file(
+            file,
+            &args.llbbox().expanded(args.context_margin),
+        )),
+        None => Some(retrieve_data::fetch_data_from_overpass(
+            args.llbbox().expanded(args.context_margin),
             args.debug,
             args.downloader.as_str(),
             args.save_json_file.as_deref(),
-        ),
+            args.overpass_url.as_deref(),
+            retrieve_data::CacheMode::from_args(&args),
+            args.query_filter.as_ref(),
+        )),
+    }
+    .map(|data| data.expect("Failed to fetch data"));
+
+    // Custom features are added to the OSM data as if they were mapped
+    if let (Some(geojson_file), Some(raw_data)) = (&args.geojson, raw_data.as_mut()) {
+        let count =
+            geojson::merge_geojson(raw_data, geojson_file).expect("Failed to read GeoJSON file");
+        println!("Added {count} elements from {}", geojson_file.display());
     }
-    .expect("Failed to fetch data");
 
     let mut ground = ground::generate_ground_data(&args);
 
     // Parse raw data
-    let (mut parsed_elements, mut xzbbox) =
-        osm_parser::parse_osm_data(raw_data, args.bbox, args.scale, args.debug);
+    let (mut parsed_elements, mut xzbbox) = match (raw_data, &args.file) {
+        (Some(raw_data), _) => osm_parser::parse_osm_data(
+            raw_data,
+            args.llbbox(),
+            args.scale,
+            args.projection,
+            args.context_margin,
+            args.debug,
+        ),
+        (None, file) => osm_parser::parse_osm_file_streaming(
+            Path::new(file.as_deref().unwrap_or_default()),
+            args.llbbox(),
+            args.scale,
+            args.projection,
+            args.context_margin,
+            args.debug,
+        )
+        .expect("Failed to parse OSM data"),
+    };
+    if let Some(filter) = &args.feature_filter {
+        let removed = filter.apply(&mut parsed_elements);
+        println!("Left out {removed} elements by the feature filter");
+    }
     parsed_elements
         .sort_by_key(|element: &osm_parser::ProcessedElement| osm_parser::get_priority(element));
 
@@ -119,11 +213,99 @@ fn run_cli() {
         }
     }
 
+    ground.apply_elevation_tags(&parsed_elements, &xzbbox);
+    ground.sink_columns(
+        element_processing::gorges::gorge_cuts(&parsed_elements, args.scale),
+        &xzbbox,
+    );
+
     // Transform map (parsed_elements). Operations are defined in a json file
     map_transformation::transform_map(&mut parsed_elements, &mut xzbbox, &mut ground);
 
     // Generate world
-    let _ = data_processing::generate_world(parsed_elements, xzbbox, args.bbox, ground, &args);
+    let _ = data_processing::generate_world(parsed_elements, xzbbox, args.llbbox(), ground, &args);
+}
+
+/// Looks up the bounding box of a place, asking for confirmation of the area found unless told
+/// not to or not run in a terminal
+fn resolve_place(place: &str, yes: bool) -> LLBBox {
+    let (bbox, name) = retrieve_data::geocode_place(place).unwrap_or_else(|e| {
+        eprintln!("{}: {}", "Failed to look up place".red().bold(), e);
+        std::process::exit(1);
+    });
+    println!(
+        "Found {} ({:.1} km²): {:?}",
+        name.bold(),
+        retrieve_data::bbox_area_km2(&bbox),
+        bbox
+    );
+
+    if !yes && io::stdin().is_terminal() {
+        print!("Generate this area? [Y/n] ");
+        let _ = io::stdout().flush();
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).is_ok()
+            && answer.trim().to_lowercase().starts_with('n')
+        {
+            std::process::exit(0);
+        }
+    }
+    bbox
+}
+
+/// Prints the OSM elements that placed the blocks of one column of a generated world
+fn run_inspect(inspect_args: args::InspectArgs) {
+    let (x, z) = (inspect_args.x, inspect_args.z);
+    match world_editor::ownership::inspect(&inspect_args.path, x, z) {
+        Ok(owners) if owners.is_empty() => {
+            println!("No generated blocks at x={x} z={z}");
+        }
+        Ok(owners) => {
+            println!("Blocks at x={x} z={z}:");
+            for (y, kind, id) in owners {
+                println!("  y={y}: {kind} {id} (https://www.openstreetmap.org/{kind}/{id})");
+            }
+        }
+        Err(e) => {
+            eprintln!(
+                "{}: {}\nWas the world generated with --ownership-map?",
+                "Failed to read block ownership map".red().bold(),
+                e
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Prints the coordinates of the named roads and places matching a name
+fn run_locate(locate_args: args::LocateArgs) {
+    match world_editor::index::locate(&locate_args.path, &locate_args.name) {
+        Ok(entries) if entries.is_empty() => {
+            println!("Nothing named \"{}\" found", locate_args.name);
+        }
+        Ok(entries) => {
+            for entry in entries {
+                println!(
+                    "{} ({}, {} {}): /tp @s {} {} {}",
+                    entry.name.bold(),
+                    entry.category,
+                    entry.kind,
+                    entry.id,
+                    entry.x,
+                    entry.y,
+                    entry.z
+                );
+            }
+        }
+        Err(e) => {
+            eprintln!(
+                "{}: {}\nWas the world generated with --index?",
+                "Failed to read world index".red().bold(),
+                e
+            );
+            std::process::exit(1);
+        }
+    }
 }
 
 fn main() {
@@ -1,6 +1,7 @@
 use super::operator::operator_vec_from_json;
 use crate::coordinate_system::cartesian::XZBBox;
 use crate::ground::Ground;
+use crate::i18n::tr;
 use crate::osm_parser::ProcessedElement;
 use crate::progress::emit_gui_progress_update;
 use colored::Colorize;
@@ -10,8 +11,8 @@ pub fn transform_map(
     xzbbox: &mut XZBBox,
     ground: &mut Ground,
 ) {
-    println!("{} Transforming map...", "[4/7]".bold());
-    emit_gui_progress_update(20.0, "Transforming map...");
+    println!("{} {}", "[4/7]".bold(), tr("step_transforming_map"));
+    emit_gui_progress_update(20.0, tr("step_transforming_map"));
 
     let opjson_string = include_str!("../../tests/map_transformation/example_transformations.json");
     let opjson = serde_json::from_str(opjson_string)
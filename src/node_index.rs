@@ -0,0 +1,245 @@
+//! Index of the coordinates of OSM nodes for inputs too large to keep in memory. The nodes are
+//! written to a temporary file in runs sorted by id, and the nodes that ways refer to are looked
+//! up all at once by reading each run a single time.
+
+use crate::coordinate_system::cartesian::XZBBox;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Nodes kept in memory before they are written to the file as a sorted run
+const RUN_LENGTH: usize = 1 << 20;
+/// Bytes of a node in the file: the id and the x and z coordinates
+const RECORD_SIZE: usize = 16;
+
+/// Number of index files created so far, which keeps their names apart
+static INDEX_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Coordinates of nodes by their id
+pub struct NodeIndex {
+    run_length: usize,
+    buffer: Vec<(u64, i32, i32)>,
+    /// Offsets and lengths of the sorted runs in the file
+    runs: Vec<(u64, usize)>,
+    file: Option<IndexFile>,
+}
+
+impl Default for NodeIndex {
+    fn default() -> Self {
+        Self::with_run_length(RUN_LENGTH)
+    }
+}
+
+impl NodeIndex {
+    fn with_run_length(run_length: usize) -> Self {
+        Self {
+            run_length: run_length.max(1),
+            buffer: Vec::new(),
+            runs: Vec::new(),
+            file: None,
+        }
+    }
+
+    /// Number of nodes in the index
+    pub fn node_count(&self) -> usize {
+        self.buffer.len() + self.runs.iter().map(|&(_, len)| len).sum::<usize>()
+    }
+
+    pub fn insert(&mut self, id: u64, x: i32, z: i32) -> io::Result<()> {
+        self.buffer.push((id, x, z));
+        if self.buffer.len() >= self.run_length {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the nodes in memory to the file as a run sorted by id
+    fn spill(&mut self) -> io::Result<()> {
+        self.buffer.sort_unstable_by_key(|&(id, _, _)| id);
+        if self.file.is_none() {
+            self.file = Some(IndexFile::create()?);
+        }
+        let file = self.file.as_mut().unwrap();
+        let offset = file.file.seek(SeekFrom::End(0))?;
+        let mut writer = BufWriter::new(&mut file.file);
+        for &(id, x, z) in &self.buffer {
+            writer.write_all(&id.to_le_bytes())?;
+            writer.write_all(&x.to_le_bytes())?;
+            writer.write_all(&z.to_le_bytes())?;
+        }
+        writer.flush()?;
+        self.runs.push((offset, self.buffer.len()));
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Looks up the coordinates of the given nodes. Nodes not in the index are left out.
+    pub fn resolve(&mut self, mut ids: Vec<u64>) -> io::Result<HashMap<u64, (i32, i32)>> {
+        ids.sort_unstable();
+        ids.dedup();
+        let mut found: HashMap<u64, (i32, i32)> = HashMap::with_capacity(ids.len());
+
+        self.buffer.sort_unstable_by_key(|&(id, _, _)| id);
+        merge_join(&ids, self.buffer.iter().map(|&node| Ok(node)), &mut found)?;
+
+        if let Some(file) = &mut self.file {
+            for &(offset, len) in &self.runs {
+                file.file.seek(SeekFrom::Start(offset))?;
+                let mut reader = BufReader::new(&mut file.file);
+                let records = (0..len).map(|_| {
+                    let mut record = [0u8; RECORD_SIZE];
+                    reader.read_exact(&mut record)?;
+                    Ok((
+                        u64::from_le_bytes(record[0..8].try_into().unwrap()),
+                        i32::from_le_bytes(record[8..12].try_into().unwrap()),
+                        i32::from_le_bytes(record[12..16].try_into().unwrap()),
+                    ))
+                });
+                merge_join(&ids, records, &mut found)?;
+            }
+        }
+        Ok(found)
+    }
+
+    /// Looks up the nodes of the given ways, each with whether it is needed as a whole. Ways
+    /// lying completely outside the bbox are left out with their nodes otherwise, as nothing of
+    /// them is within the world.
+    pub fn resolve_ways(
+        &mut self,
+        ways: &[(&[u64], bool)],
+        bbox: &XZBBox,
+    ) -> io::Result<HashMap<u64, (i32, i32)>> {
+        let ids: Vec<u64> = ways
+            .iter()
+            .flat_map(|(nodes, _)| nodes.iter().copied())
+            .collect();
+        let mut found = self.resolve(ids)?;
+
+        let mut kept: HashSet<u64> = HashSet::new();
+        for &(nodes, whole) in ways {
+            let mut coordinates = nodes.iter().filter_map(|id| found.get(id));
+            let Some(&(x, z)) = coordinates.next() else {
+                continue;
+            };
+            let (min_x, max_x, min_z, max_z) =
+                coordinates.fold((x, x, z, z), |(min_x, max_x, min_z, max_z), &(x, z)| {
+                    (min_x.min(x), max_x.max(x), min_z.min(z), max_z.max(z))
+                });
+            let touches_bbox = min_x <= bbox.max_x()
+                && max_x >= bbox.min_x()
+                && min_z <= bbox.max_z()
+                && max_z >= bbox.min_z();
+            if whole || touches_bbox {
+                kept.extend(nodes);
+            }
+        }
+        found.retain(|id, _| kept.contains(id));
+        Ok(found)
+    }
+}
+
+/// Adds the nodes of a run sorted by id whose ids are among the sorted wanted ones
+fn merge_join(
+    wanted: &[u64],
+    run: impl Iterator<Item = io::Result<(u64, i32, i32)>>,
+    found: &mut HashMap<u64, (i32, i32)>,
+) -> io::Result<()> {
+    let mut position = 0;
+    for node in run {
+        let (id, x, z) = node?;
+        while position < wanted.len() && wanted[position] < id {
+            position += 1;
+        }
+        if position == wanted.len() {
+            break;
+        }
+        if wanted[position] == id {
+            found.insert(id, (x, z));
+        }
+    }
+    Ok(())
+}
+
+/// Temporary file of an index, removed when dropped
+struct IndexFile {
+    path: PathBuf,
+    file: File,
+}
+
+impl IndexFile {
+    fn create() -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "arnis-nodes-{}-{}.bin",
+            std::process::id(),
+            INDEX_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        Ok(Self { path, file })
+    }
+}
+
+impl Drop for IndexFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nodes_are_found_across_runs() {
+        let mut index = NodeIndex::with_run_length(3);
+        // Ids out of order, spread over several runs and the nodes still in memory
+        for id in [7u64, 3, 11, 1, 20, 5, 8] {
+            index.insert(id, id as i32 * 2, -(id as i32)).unwrap();
+        }
+        assert_eq!(index.node_count(), 7);
+        assert_eq!(index.runs.len(), 2);
+
+        let found = index.resolve(vec![8, 1, 20, 4, 1]).unwrap();
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[&1], (2, -1));
+        assert_eq!(found[&8], (16, -8));
+        assert_eq!(found[&20], (40, -20));
+        assert!(!found.contains_key(&4));
+    }
+
+    #[test]
+    fn test_ways_outside_the_bbox_are_left_out() {
+        let mut index = NodeIndex::with_run_length(2);
+        for (id, x, z) in [
+            (1, 10, 10),
+            (2, 50, 10),
+            (3, 200, 200),
+            (4, 250, 200),
+            (5, 120, 10),
+        ] {
+            index.insert(id, x, z).unwrap();
+        }
+        let bbox = XZBBox::rect_from_xz_lengths(100.0, 100.0).unwrap();
+
+        // A way within the bbox, one far outside it and one crossing its edge
+        let found = index
+            .resolve_ways(
+                &[(&[1, 2], false), (&[3, 4], false), (&[2, 5], false)],
+                &bbox,
+            )
+            .unwrap();
+        let mut ids: Vec<u64> = found.into_keys().collect();
+        ids.sort_unstable();
+        assert_eq!(ids, [1, 2, 5]);
+
+        // Ways needed as a whole are kept wherever they are
+        let found = index.resolve_ways(&[(&[3, 4], true)], &bbox).unwrap();
+        assert_eq!(found.len(), 2);
+    }
+}
@@ -1,12 +1,22 @@
-use crate::clipping::clip_way_to_bbox;
+use crate::clipping::{clip_water_ring_to_bbox, clip_way_to_bbox, clip_way_to_bbox_pieces};
 use crate::coordinate_system::cartesian::{XZBBox, XZPoint};
 use crate::coordinate_system::geographic::{LLBBox, LLPoint};
-use crate::coordinate_system::transformation::CoordTransformer;
+use crate::coordinate_system::transformation::{CoordTransformer, Projection};
+use crate::i18n::tr;
+use crate::node_index::NodeIndex;
+use crate::osm_pbf;
+use crate::polygon_repair;
 use crate::progress::emit_gui_progress_update;
 use colored::Colorize;
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
 use serde::Deserialize;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
 
 // Raw data from OSM
 
@@ -107,6 +117,10 @@ pub struct ProcessedWay {
 pub enum ProcessedMemberRole {
     Outer,
     Inner,
+    /// Way of a relation that is not a multipolygon, such as a route
+    Member,
+    /// Platform way of a public transport route
+    Platform,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -120,6 +134,31 @@ pub struct ProcessedRelation {
     pub id: u64,
     pub tags: HashMap<String, String>,
     pub members: Vec<ProcessedMember>,
+    /// Stop and platform nodes of a public transport route, in the order the route serves them
+    pub stops: Vec<ProcessedNode>,
+}
+
+impl ProcessedRelation {
+    /// Returns the assembled outer rings as ways carrying the tags of the relation
+    pub fn outer_ways(&self) -> impl Iterator<Item = ProcessedWay> + '_ {
+        self.members
+            .iter()
+            .filter(|member| member.role == ProcessedMemberRole::Outer)
+            .map(|member| ProcessedWay {
+                id: member.way.id,
+                nodes: member.way.nodes.clone(),
+                tags: self.tags.clone(),
+            })
+    }
+
+    /// Returns the assembled inner rings, the holes of the multipolygon. Rings clipped at the
+    /// edge of the bbox are closed implicitly and may not end in the node they start with.
+    pub fn inner_ways(&self) -> impl Iterator<Item = &ProcessedWay> {
+        self.members
+            .iter()
+            .filter(|member| member.role == ProcessedMemberRole::Inner)
+            .map(|member| &member.way)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -146,7 +185,7 @@ impl ProcessedElement {
         }
     }
 
-    pub fn kind(&self) -> &str {
+    pub fn kind(&self) -> &'static str {
         match self {
             ProcessedElement::Node(_) => "node",
             ProcessedElement::Way(_) => "way",
@@ -167,20 +206,23 @@ pub fn parse_osm_data(
     json_data: Value,
     bbox: LLBBox,
     scale: f64,
+    projection: Projection,
+    context_margin: f64,
     debug: bool,
 ) -> (Vec<ProcessedElement>, XZBBox) {
-    println!("{} Parsing data...", "[2/7]".bold());
+    println!("{} {}", "[2/7]".bold(), tr("step_parsing_data"));
     println!("Bounding box: {bbox:?}");
-    emit_gui_progress_update(5.0, "Parsing data...");
+    emit_gui_progress_update(5.0, tr("step_parsing_data"));
 
     // Deserialize the JSON data into the OSMData structure
     let data = parse_raw_osm_data(json_data).expect("Failed to parse OSM data");
 
-    let (coord_transformer, xzbbox) = CoordTransformer::llbbox_to_xzbbox(&bbox, scale)
+    let (coord_transformer, xzbbox) = CoordTransformer::llbbox_to_xzbbox(&bbox, scale, projection)
         .unwrap_or_else(|e| {
             eprintln!("Error in defining coordinate transformation:\n{e}");
             panic!();
         });
+    let clip_bbox = context_bbox(&xzbbox, context_margin, scale);
 
     if debug {
         println!("Total elements: {}", data.total_count());
@@ -189,7 +231,6 @@ pub fn parse_osm_data(
     }
 
     let mut nodes_map: HashMap<u64, ProcessedNode> = HashMap::new();
-    let mut ways_map: HashMap<u64, ProcessedWay> = HashMap::new();
 
     let mut processed_elements: Vec<ProcessedElement> = Vec::new();
 
@@ -216,111 +257,525 @@ pub fn parse_osm_data(
             // This significantly improves performance by filtering out distant nodes
             if !element.tags.as_ref().map(|t| t.is_empty()).unwrap_or(true) {
                 // Node has tags, check if it's in the bbox (with some margin)
-                if xzbbox.contains(&xzpoint) {
+                if clip_bbox.contains(&xzpoint) {
                     processed_elements.push(ProcessedElement::Node(processed));
                 }
             }
         }
     }
 
-    // Second pass: process ways and clip them to bbox
-    for element in data.ways {
-        let mut nodes: Vec<ProcessedNode> = vec![];
-        if let Some(node_ids) = &element.nodes {
-            for &node_id in node_ids {
-                if let Some(node) = nodes_map.get(&node_id) {
-                    nodes.push(node.clone());
+    process_ways_and_relations(
+        data.ways,
+        data.relations,
+        &nodes_map,
+        &clip_bbox,
+        debug,
+        &mut processed_elements,
+    );
+
+    emit_gui_progress_update(15.0, "");
+
+    (processed_elements, xzbbox)
+}
+
+/// Ways of the second pass whose nodes are looked up together
+const WAY_BATCH_NODES: usize = 1 << 20;
+
+/// Parses a JSON file of OSM data element by element instead of loading it as a whole. The file
+/// is read three times: the nodes are indexed in a temporary file first, then the ways are
+/// assembled in batches and the relations last, so only the ways relations refer to are kept
+/// in memory.
+pub fn parse_osm_file_streaming(
+    path: &Path,
+    bbox: LLBBox,
+    scale: f64,
+    projection: Projection,
+    context_margin: f64,
+    debug: bool,
+) -> Result<(Vec<ProcessedElement>, XZBBox), Box<dyn Error>> {
+    ensure_streamable(path)?;
+    println!("{} {}", "[2/7]".bold(), tr("step_parsing_data"));
+    println!("Bounding box: {bbox:?}");
+    emit_gui_progress_update(5.0, tr("step_parsing_data"));
+
+    let (coord_transformer, xzbbox) = CoordTransformer::llbbox_to_xzbbox(&bbox, scale, projection)?;
+    let clip_bbox = context_bbox(&xzbbox, context_margin, scale);
+
+    // First pass: index the nodes and note the ways and stops relations refer to
+    let mut index = NodeIndex::default();
+    let mut tagged_nodes: HashMap<u64, ProcessedNode> = HashMap::new();
+    let mut relation_ways: HashSet<u64> = HashSet::new();
+    let mut relation_nodes: Vec<u64> = Vec::new();
+    let mut processed_elements: Vec<ProcessedElement> = Vec::new();
+    for_each_element(path, |element| {
+        match element.r#type.as_str() {
+            "node" => {
+                let (Some(lat), Some(lon)) = (element.lat, element.lon) else {
+                    return Ok(());
+                };
+                let xzpoint = coord_transformer.transform_point(LLPoint::new(lat, lon)?);
+                index.insert(element.id, xzpoint.x, xzpoint.z)?;
+                if let Some(tags) = element.tags.filter(|tags| !tags.is_empty()) {
+                    let processed = ProcessedNode {
+                        id: element.id,
+                        tags,
+                        x: xzpoint.x,
+                        z: xzpoint.z,
+                    };
+                    if clip_bbox.contains(&xzpoint) {
+                        processed_elements.push(ProcessedElement::Node(processed.clone()));
+                    }
+                    tagged_nodes.insert(element.id, processed);
                 }
             }
+            "relation" => {
+                for member in &element.members {
+                    match member.r#type.as_str() {
+                        "way" => {
+                            relation_ways.insert(member.r#ref);
+                        }
+                        "node" => relation_nodes.push(member.r#ref),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
         }
+        Ok(())
+    })?;
+    if debug {
+        println!(
+            "Indexed {} nodes, {} ways are relation members",
+            index.node_count(),
+            relation_ways.len()
+        );
+    }
 
-        // Clip the way to bbox to reduce node count dramatically
-        let tags = element.tags.clone().unwrap_or_default();
+    // Second pass: assemble and clip the ways, looking up the nodes of many at once
+    let mut assembler = WayAssembler::new(&clip_bbox, debug, Some(relation_ways));
+    let mut batch: Vec<OsmElement> = Vec::new();
+    let mut batch_nodes: usize = 0;
+    for_each_element(path, |element| {
+        if element.r#type == "way" {
+            batch_nodes += element.nodes.as_ref().map_or(0, Vec::len);
+            batch.push(element);
+            if batch_nodes >= WAY_BATCH_NODES {
+                assemble_way_batch(
+                    &mut batch,
+                    &mut index,
+                    &tagged_nodes,
+                    &mut assembler,
+                    &mut processed_elements,
+                )?;
+                batch_nodes = 0;
+            }
+        }
+        Ok(())
+    })?;
+    assemble_way_batch(
+        &mut batch,
+        &mut index,
+        &tagged_nodes,
+        &mut assembler,
+        &mut processed_elements,
+    )?;
+    assembler.report_repairs();
 
-        // Store unclipped way for relation assembly (clipping happens after ring merging)
-        ways_map.insert(
-            element.id,
-            ProcessedWay {
-                id: element.id,
-                tags: tags.clone(),
-                nodes: nodes.clone(),
-            },
-        );
+    // Third pass: assemble the relations from the kept ways and the stops within the bbox
+    let stops: HashMap<u64, ProcessedNode> = index
+        .resolve(relation_nodes)?
+        .into_iter()
+        .filter(|&(_, (x, z))| clip_bbox.contains(&XZPoint::new(x, z)))
+        .map(|(id, (x, z))| (id, node_at(id, x, z, &tagged_nodes)))
+        .collect();
+    drop(index);
+    drop(tagged_nodes);
+    for_each_element(path, |element| {
+        if element.r#type == "relation" {
+            assembler.add_relation(&element, &stops, &mut processed_elements);
+        }
+        Ok(())
+    })?;
 
-        // Clip way nodes for standalone way processing (not relations)
-        let clipped_nodes = clip_way_to_bbox(&nodes, &xzbbox);
+    emit_gui_progress_update(15.0, "");
 
-        // Skip ways that are completely outside the bbox (empty after clipping)
-        if clipped_nodes.is_empty() {
-            continue;
+    Ok((processed_elements, xzbbox))
+}
+
+/// Fails for files that are not OSM JSON. PBF extracts and OSM XML files are converted as a
+/// whole, so they cannot be read element by element.
+pub fn ensure_streamable(path: &Path) -> Result<(), Box<dyn Error>> {
+    let is_json = BufReader::new(File::open(path)?)
+        .bytes()
+        .find(|byte| !byte.as_ref().is_ok_and(u8::is_ascii_whitespace))
+        .transpose()?
+        == Some(b'{');
+    if !is_json || osm_pbf::is_pbf(&path.to_string_lossy()) {
+        return Err(format!(
+            "{} is not an OSM JSON file, only JSON files can be parsed with --streaming",
+            path.display()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Calls the callback with each element of a JSON file of OSM data as soon as it is read
+fn for_each_element(
+    path: &Path,
+    callback: impl FnMut(OsmElement) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(File::open(path)?));
+    serde::Deserializer::deserialize_map(&mut deserializer, ElementStream(callback))?;
+    deserializer.end()?;
+    Ok(())
+}
+
+/// Looks up the nodes of a batch of ways at once and assembles the ways. The nodes of ways
+/// lying completely outside the bbox are not looked up, unless a relation needs the way.
+fn assemble_way_batch(
+    batch: &mut Vec<OsmElement>,
+    index: &mut NodeIndex,
+    tagged_nodes: &HashMap<u64, ProcessedNode>,
+    assembler: &mut WayAssembler,
+    processed_elements: &mut Vec<ProcessedElement>,
+) -> Result<(), Box<dyn Error>> {
+    let ways: Vec<(&[u64], bool)> = batch
+        .iter()
+        .map(|way| {
+            let nodes = way.nodes.as_deref().unwrap_or_default();
+            (nodes, assembler.is_relation_way(way.id))
+        })
+        .collect();
+    let nodes_map: HashMap<u64, ProcessedNode> = index
+        .resolve_ways(&ways, assembler.xzbbox)?
+        .into_iter()
+        .map(|(id, (x, z))| (id, node_at(id, x, z, tagged_nodes)))
+        .collect();
+    for way in batch.drain(..) {
+        assembler.add_way(way, &nodes_map, processed_elements);
+    }
+    Ok(())
+}
+
+/// Returns the node with the given id and coordinates, with its tags if it has any
+fn node_at(id: u64, x: i32, z: i32, tagged_nodes: &HashMap<u64, ProcessedNode>) -> ProcessedNode {
+    tagged_nodes.get(&id).cloned().unwrap_or(ProcessedNode {
+        id,
+        tags: HashMap::new(),
+        x,
+        z,
+    })
+}
+
+/// Visits the elements of OSM JSON data one at a time, skipping its other fields
+struct ElementStream<F>(F);
+
+impl<'de, F> Visitor<'de> for ElementStream<F>
+where
+    F: FnMut(OsmElement) -> Result<(), Box<dyn Error>>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("OSM JSON data")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "elements" {
+                map.next_value_seed(ElementSeq(&mut self.0))?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
         }
+        Ok(())
+    }
+}
 
-        let processed: ProcessedWay = ProcessedWay {
-            id: element.id,
-            tags: tags.clone(),
-            nodes: clipped_nodes.clone(),
-        };
+/// Elements array of OSM JSON data, each handed to the callback as soon as it is read
+struct ElementSeq<'a, F>(&'a mut F);
+
+impl<'de, F> DeserializeSeed<'de> for ElementSeq<'_, F>
+where
+    F: FnMut(OsmElement) -> Result<(), Box<dyn Error>>,
+{
+    type Value = ();
+
+    fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
 
-        processed_elements.push(ProcessedElement::Way(processed));
+impl<'de, F> Visitor<'de> for ElementSeq<'_, F>
+where
+    F: FnMut(OsmElement) -> Result<(), Box<dyn Error>>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of OSM elements")
     }
 
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(element) = seq.next_element::<OsmElement>()? {
+            (self.0)(element).map_err(de::Error::custom)?;
+        }
+        Ok(())
+    }
+}
+
+/// Assembles and clips the ways and relations once the nodes they refer to are known
+fn process_ways_and_relations(
+    ways: Vec<OsmElement>,
+    relations: Vec<OsmElement>,
+    nodes_map: &HashMap<u64, ProcessedNode>,
+    xzbbox: &XZBBox,
+    debug: bool,
+    processed_elements: &mut Vec<ProcessedElement>,
+) {
+    let mut assembler = WayAssembler::new(xzbbox, debug, None);
+
+    // Second pass: process ways and clip them to bbox
+    for element in ways {
+        assembler.add_way(element, nodes_map, processed_elements);
+    }
+    assembler.report_repairs();
+
     // Third pass: process relations and clip member ways
-    for element in data.relations {
+    for element in &relations {
+        assembler.add_relation(element, nodes_map, processed_elements);
+    }
+}
+
+/// Clips ways to the bbox as they are added, keeping the unclipped ways relations are made of
+/// until the relations are assembled
+struct WayAssembler<'a> {
+    xzbbox: &'a XZBBox,
+    debug: bool,
+    /// Ways that relations refer to, or `None` to keep all of them
+    relation_ways: Option<HashSet<u64>>,
+    ways_map: HashMap<u64, ProcessedWay>,
+    repaired_ways: usize,
+}
+
+impl<'a> WayAssembler<'a> {
+    fn new(xzbbox: &'a XZBBox, debug: bool, relation_ways: Option<HashSet<u64>>) -> Self {
+        Self {
+            xzbbox,
+            debug,
+            relation_ways,
+            ways_map: HashMap::new(),
+            repaired_ways: 0,
+        }
+    }
+
+    fn is_relation_way(&self, id: u64) -> bool {
+        self.relation_ways
+            .as_ref()
+            .is_none_or(|relation_ways| relation_ways.contains(&id))
+    }
+
+    fn add_way(
+        &mut self,
+        element: OsmElement,
+        nodes_map: &HashMap<u64, ProcessedNode>,
+        processed_elements: &mut Vec<ProcessedElement>,
+    ) {
+        let nodes: Vec<ProcessedNode> = element
+            .nodes
+            .iter()
+            .flatten()
+            .filter_map(|node_id| nodes_map.get(node_id).cloned())
+            .collect();
+
+        // Clip the way to bbox to reduce node count dramatically
+        let tags = element.tags.unwrap_or_default();
+
+        // Store unclipped way for relation assembly (clipping happens after ring merging)
+        if self.is_relation_way(element.id) {
+            self.ways_map.insert(
+                element.id,
+                ProcessedWay {
+                    id: element.id,
+                    tags: tags.clone(),
+                    nodes: nodes.clone(),
+                },
+            );
+        }
+
+        // Repair broken area outlines before the area generators flood fill them
+        let rings = if polygon_repair::is_area(&tags) {
+            let (rings, repairs) = polygon_repair::repair_ring(nodes);
+            if !repairs.is_empty() {
+                self.repaired_ways += 1;
+                if self.debug {
+                    println!("Repaired way {}: {}", element.id, repairs.join(", "));
+                }
+            }
+            rings
+        } else {
+            vec![nodes]
+        };
+
+        for ring in rings {
+            // Clip way nodes for standalone way processing (not relations). Ways completely
+            // outside the bbox have no pieces, ways leaving it and coming back several.
+            for clipped_nodes in clip_way_to_bbox_pieces(&ring, self.xzbbox) {
+                let processed: ProcessedWay = ProcessedWay {
+                    id: element.id,
+                    tags: tags.clone(),
+                    nodes: clipped_nodes,
+                };
+
+                processed_elements.push(ProcessedElement::Way(processed));
+            }
+        }
+    }
+
+    fn report_repairs(&self) {
+        if self.repaired_ways > 0 {
+            println!("Repaired {} broken polygon outlines", self.repaired_ways);
+        }
+    }
+
+    /// Assembles a relation from the ways added before, and from the nodes of its stops
+    fn add_relation(
+        &self,
+        element: &OsmElement,
+        nodes_map: &HashMap<u64, ProcessedNode>,
+        processed_elements: &mut Vec<ProcessedElement>,
+    ) {
+        let xzbbox = self.xzbbox;
+        let ways_map = &self.ways_map;
         let Some(tags) = &element.tags else {
-            continue;
+            return;
         };
 
+        // Trail routes keep their member ways as lines
+        if is_trail_route(tags) {
+            let members: Vec<ProcessedMember> = element
+                .members
+                .iter()
+                .filter(|mem| mem.r#type == "way")
+                .filter_map(|mem| ways_map.get(&mem.r#ref))
+                .flat_map(|way| {
+                    clip_way_to_bbox_pieces(&way.nodes, xzbbox).into_iter().map(
+                        move |clipped_nodes| ProcessedMember {
+                            role: ProcessedMemberRole::Member,
+                            way: ProcessedWay {
+                                nodes: clipped_nodes,
+                                ..way.clone()
+                            },
+                        },
+                    )
+                })
+                .collect();
+            if !members.is_empty() {
+                processed_elements.push(ProcessedElement::Relation(ProcessedRelation {
+                    id: element.id,
+                    members,
+                    tags: tags.clone(),
+                    stops: Vec::new(),
+                }));
+            }
+            return;
+        }
+
+        // Public transport routes keep the order of their ways and stops
+        if is_public_transport_route(tags) {
+            let mut members: Vec<ProcessedMember> = Vec::new();
+            let mut stops: Vec<ProcessedNode> = Vec::new();
+            for mem in &element.members {
+                let is_platform = mem.role.starts_with("platform");
+                if mem.r#type == "node" && (is_platform || mem.role.starts_with("stop")) {
+                    if let Some(node) = nodes_map.get(&mem.r#ref) {
+                        if xzbbox.contains(&node.xz()) {
+                            stops.push(node.clone());
+                        }
+                    }
+                } else if mem.r#type == "way" {
+                    let Some(way) = ways_map.get(&mem.r#ref) else {
+                        continue;
+                    };
+                    for clipped_nodes in clip_way_to_bbox_pieces(&way.nodes, xzbbox) {
+                        members.push(ProcessedMember {
+                            role: if is_platform {
+                                ProcessedMemberRole::Platform
+                            } else {
+                                ProcessedMemberRole::Member
+                            },
+                            way: ProcessedWay {
+                                nodes: clipped_nodes,
+                                ..way.clone()
+                            },
+                        });
+                    }
+                }
+            }
+            if !members.is_empty() || !stops.is_empty() {
+                processed_elements.push(ProcessedElement::Relation(ProcessedRelation {
+                    id: element.id,
+                    members,
+                    tags: tags.clone(),
+                    stops,
+                }));
+            }
+            return;
+        }
+
         // Only process multipolygons for now
         if tags.get("type").map(|x: &String| x.as_str()) != Some("multipolygon") {
-            continue;
+            return;
         };
 
-        // Water relations require unclipped ways for ring merging in water_areas.rs
+        // Water rings are clipped with the simpler water clipping
         let is_water_relation = is_water_element(tags);
 
-        let members: Vec<ProcessedMember> = element
-            .members
-            .iter()
-            .filter_map(|mem: &OsmMember| {
-                if mem.r#type != "way" {
-                    eprintln!("WARN: Unknown relation member type \"{}\"", mem.r#type);
-                    return None;
-                }
+        let mut outer_ways: Vec<ProcessedWay> = Vec::new();
+        let mut inner_ways: Vec<ProcessedWay> = Vec::new();
+        for mem in &element.members {
+            if mem.r#type != "way" {
+                eprintln!("WARN: Unknown relation member type \"{}\"", mem.r#type);
+                continue;
+            }
 
-                let role = match mem.role.as_str() {
-                    "outer" => ProcessedMemberRole::Outer,
-                    "inner" => ProcessedMemberRole::Inner,
-                    _ => return None,
-                };
+            // Way was likely filtered out because it was completely outside the bbox
+            let Some(way) = ways_map.get(&mem.r#ref) else {
+                continue;
+            };
 
-                // Check if the way exists in ways_map
-                let way: ProcessedWay = match ways_map.get(&mem.r#ref) {
-                    Some(w) => w.clone(),
-                    None => {
-                        // Way was likely filtered out because it was completely outside the bbox
-                        return None;
-                    }
-                };
+            match mem.role.as_str() {
+                "outer" => outer_ways.push(way.clone()),
+                "inner" => inner_ways.push(way.clone()),
+                _ => {}
+            }
+        }
 
-                // Water relations: keep unclipped for ring merging
-                // Non-water relations: clip member ways now
-                let final_way = if is_water_relation {
-                    way
+        // Assemble closed rings first, clipping afterwards keeps the rings connected
+        let members: Vec<ProcessedMember> = assemble_rings(outer_ways, element.id)
+            .into_iter()
+            .map(|way| (ProcessedMemberRole::Outer, way))
+            .chain(
+                assemble_rings(inner_ways, element.id)
+                    .into_iter()
+                    .map(|way| (ProcessedMemberRole::Inner, way)),
+            )
+            .filter_map(|(role, way)| {
+                let clipped_nodes = if is_water_relation {
+                    clip_water_ring_to_bbox(&way.nodes, xzbbox)?
                 } else {
-                    let clipped_nodes = clip_way_to_bbox(&way.nodes, &xzbbox);
-                    if clipped_nodes.is_empty() {
-                        return None;
-                    }
-                    ProcessedWay {
-                        id: way.id,
-                        tags: way.tags,
-                        nodes: clipped_nodes,
-                    }
+                    clip_way_to_bbox(&way.nodes, xzbbox)
                 };
-
+                if clipped_nodes.is_empty() {
+                    return None;
+                }
                 Some(ProcessedMember {
                     role,
-                    way: final_way,
+                    way: ProcessedWay {
+                        nodes: clipped_nodes,
+                        ..way
+                    },
                 })
             })
             .collect();
@@ -330,17 +785,191 @@ pub fn parse_osm_data(
                 id: element.id,
                 members,
                 tags: tags.clone(),
+                stops: Vec::new(),
             }));
         }
     }
+}
 
-    emit_gui_progress_update(15.0, "");
+/// Returns the area elements are kept in, which is the world grown by the context margin in
+/// meters. Blocks are only placed within the world itself.
+fn context_bbox(xzbbox: &XZBBox, context_margin: f64, scale: f64) -> XZBBox {
+    xzbbox.expanded((context_margin * scale).ceil() as i32)
+}
 
-    (processed_elements, xzbbox)
+/// Assembles the member ways of one role of a multipolygon into closed rings.
+/// Closed ways are kept as they are, open segments are joined at shared endpoints
+/// and become rings carrying the relation id and no tags of their own.
+fn assemble_rings(ways: Vec<ProcessedWay>, relation_id: u64) -> Vec<ProcessedWay> {
+    let (mut rings, segments): (Vec<ProcessedWay>, Vec<ProcessedWay>) = ways
+        .into_iter()
+        .filter(|way| way.nodes.len() > 1)
+        .partition(|way| way.nodes.first().map(|n| n.id) == way.nodes.last().map(|n| n.id));
+
+    let mut merged: Vec<Vec<ProcessedNode>> = segments.into_iter().map(|way| way.nodes).collect();
+    merge_way_segments(&mut merged);
+
+    for mut nodes in merged {
+        if !verify_closed_rings(std::slice::from_ref(&nodes)) {
+            continue;
+        }
+
+        // Segments joined by proximity may still need their closing node
+        if nodes[0].id != nodes[nodes.len() - 1].id {
+            nodes.push(nodes[0].clone());
+        }
+        rings.push(ProcessedWay {
+            id: relation_id,
+            nodes,
+            tags: HashMap::new(),
+        });
+    }
+
+    rings
+}
+
+/// Merges way segments that share endpoints into closed rings.
+pub fn merge_way_segments(rings: &mut Vec<Vec<ProcessedNode>>) {
+    let mut removed: Vec<usize> = vec![];
+    let mut merged: Vec<Vec<ProcessedNode>> = vec![];
+
+    // Match nodes by ID or proximity (handles synthetic nodes from bbox clipping)
+    let nodes_match = |a: &ProcessedNode, b: &ProcessedNode| -> bool {
+        if a.id == b.id {
+            return true;
+        }
+        let dx = (a.x - b.x).abs();
+        let dz = (a.z - b.z).abs();
+        dx <= 1 && dz <= 1
+    };
+
+    for i in 0..rings.len() {
+        for j in 0..rings.len() {
+            if i == j {
+                continue;
+            }
+
+            if removed.contains(&i) || removed.contains(&j) {
+                continue;
+            }
+
+            let x: &Vec<ProcessedNode> = &rings[i];
+            let y: &Vec<ProcessedNode> = &rings[j];
+
+            // Skip empty rings (can happen after clipping)
+            if x.is_empty() || y.is_empty() {
+                continue;
+            }
+
+            let x_first = &x[0];
+            let x_last = x.last().unwrap();
+            let y_first = &y[0];
+            let y_last = y.last().unwrap();
+
+            // Skip already-closed rings
+            if nodes_match(x_first, x_last) {
+                continue;
+            }
+
+            if nodes_match(y_first, y_last) {
+                continue;
+            }
+
+            if nodes_match(x_first, y_first) {
+                removed.push(i);
+                removed.push(j);
+
+                let mut x: Vec<ProcessedNode> = x.clone();
+                x.reverse();
+                x.extend(y.iter().skip(1).cloned());
+                merged.push(x);
+            } else if nodes_match(x_last, y_last) {
+                removed.push(i);
+                removed.push(j);
+
+                let mut x: Vec<ProcessedNode> = x.clone();
+                x.extend(y.iter().rev().skip(1).cloned());
+
+                merged.push(x);
+            } else if nodes_match(x_first, y_last) {
+                removed.push(i);
+                removed.push(j);
+
+                let mut y: Vec<ProcessedNode> = y.clone();
+                y.extend(x.iter().skip(1).cloned());
+
+                merged.push(y);
+            } else if nodes_match(x_last, y_first) {
+                removed.push(i);
+                removed.push(j);
+
+                let mut x: Vec<ProcessedNode> = x.clone();
+                x.extend(y.iter().skip(1).cloned());
+
+                merged.push(x);
+            }
+        }
+    }
+
+    removed.sort();
+
+    for r in removed.iter().rev() {
+        rings.remove(*r);
+    }
+
+    let merged_len: usize = merged.len();
+    for m in merged {
+        rings.push(m);
+    }
+
+    if merged_len > 0 {
+        merge_way_segments(rings);
+    }
+}
+
+/// Verifies all rings are properly closed (first node matches last).
+pub fn verify_closed_rings(rings: &[Vec<ProcessedNode>]) -> bool {
+    let mut valid = true;
+    for ring in rings {
+        let first = &ring[0];
+        let last = ring.last().unwrap();
+
+        // Check if ring is closed (by ID or proximity)
+        let is_closed = first.id == last.id || {
+            let dx = (first.x - last.x).abs();
+            let dz = (first.z - last.z).abs();
+            dx <= 1 && dz <= 1
+        };
+
+        if !is_closed {
+            eprintln!("WARN: Disconnected ring");
+            valid = false;
+        }
+    }
+
+    valid
 }
 
 /// Returns true if tags indicate a water element handled by water_areas.rs.
-fn is_water_element(tags: &HashMap<String, String>) -> bool {
+/// Returns true for route relations of hiking, cycling and skiing trails
+pub fn is_trail_route(tags: &HashMap<String, String>) -> bool {
+    tags.get("type").map(String::as_str) == Some("route")
+        && matches!(
+            tags.get("route").map(String::as_str),
+            Some("hiking" | "foot" | "walking" | "bicycle" | "mtb" | "piste")
+        )
+}
+
+/// Returns true for route relations of buses, trams and metros
+pub fn is_public_transport_route(tags: &HashMap<String, String>) -> bool {
+    tags.get("type").map(String::as_str) == Some("route")
+        && matches!(
+            tags.get("route").map(String::as_str),
+            Some("bus" | "trolleybus" | "tram" | "subway" | "light_rail")
+        )
+}
+
+pub fn is_water_element(tags: &HashMap<String, String>) -> bool {
     // Check for explicit water tag
     if tags.contains_key("water") {
         return true;
@@ -353,9 +982,9 @@ fn is_water_element(tags: &HashMap<String, String>) -> bool {
         }
     }
 
-    // Check for waterway=dock (also handled as water area)
+    // Check for waterway=dock and the older waterway=riverbank (also handled as water areas)
     if let Some(waterway_val) = tags.get("waterway") {
-        if waterway_val == "dock" {
+        if waterway_val == "dock" || waterway_val == "riverbank" {
             return true;
         }
     }
@@ -378,3 +1007,216 @@ pub fn get_priority(element: &ProcessedElement) -> usize {
     // Return a default priority if none of the tags match
     PRIORITY_ORDER.len()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn way(id: u64, nodes: &[u64], tags: Option<HashMap<String, String>>) -> OsmElement {
+        OsmElement {
+            r#type: "way".to_string(),
+            id,
+            lat: None,
+            lon: None,
+            nodes: Some(nodes.to_vec()),
+            tags,
+            members: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_multipolygon_holes_are_assembled_and_clipped() {
+        let xzbbox = XZBBox::rect_from_xz_lengths(100.0, 100.0).unwrap();
+        let nodes_map: HashMap<u64, ProcessedNode> = [
+            (1, 10, 10),
+            (2, 90, 10),
+            (3, 90, 90),
+            (4, 10, 90),
+            // The pond reaches past the edge of the bbox
+            (5, 80, 20),
+            (6, 120, 20),
+            (7, 120, 60),
+            (8, 80, 60),
+        ]
+        .into_iter()
+        .map(|(id, x, z)| {
+            let node = ProcessedNode {
+                id,
+                tags: HashMap::new(),
+                x,
+                z,
+            };
+            (id, node)
+        })
+        .collect();
+
+        let member = |r#ref: u64, role: &str| OsmMember {
+            r#type: "way".to_string(),
+            r#ref,
+            role: role.to_string(),
+        };
+        let relation = OsmElement {
+            r#type: "relation".to_string(),
+            id: 100,
+            lat: None,
+            lon: None,
+            nodes: None,
+            tags: Some(HashMap::from([
+                ("type".to_string(), "multipolygon".to_string()),
+                ("leisure".to_string(), "park".to_string()),
+            ])),
+            members: vec![
+                member(10, "outer"),
+                member(11, "inner"),
+                member(12, "inner"),
+            ],
+        };
+        // The inner ring is split into two open ways
+        let ways = vec![
+            way(10, &[1, 2, 3, 4, 1], None),
+            way(11, &[5, 6, 7], None),
+            way(12, &[7, 8, 5], None),
+        ];
+
+        let mut elements = Vec::new();
+        process_ways_and_relations(
+            ways,
+            vec![relation],
+            &nodes_map,
+            &xzbbox,
+            false,
+            &mut elements,
+        );
+
+        let Some(ProcessedElement::Relation(park)) = elements
+            .iter()
+            .find(|element| matches!(element, ProcessedElement::Relation(_)))
+        else {
+            panic!("The multipolygon was not processed");
+        };
+        assert_eq!(park.outer_ways().count(), 1);
+        assert!(park.outer_ways().all(|way| way.tags["leisure"] == "park"));
+
+        let holes: Vec<&ProcessedWay> = park.inner_ways().collect();
+        assert_eq!(holes.len(), 1);
+        assert!(holes[0].nodes.len() >= 3);
+        assert!(holes[0]
+            .nodes
+            .iter()
+            .all(|node| xzbbox.contains(&XZPoint::new(node.x, node.z))));
+    }
+
+    #[test]
+    fn test_context_margin_keeps_features_around_the_world() {
+        let bbox = LLBBox::new(54.627053, 9.927928, 54.634902, 9.937563).unwrap();
+        // A bench and the end of a path about 50 m north of the bbox
+        let data = serde_json::json!({
+            "elements": [
+                {"type": "node", "id": 1, "lat": 54.630, "lon": 9.930},
+                {"type": "node", "id": 2, "lat": 54.6354, "lon": 9.930},
+                {"type": "node", "id": 3, "lat": 54.6354, "lon": 9.931,
+                    "tags": {"amenity": "bench"}},
+                {"type": "way", "id": 10, "nodes": [1, 2],
+                    "tags": {"highway": "footway"}},
+            ]
+        });
+        let parse = |margin: f64| {
+            parse_osm_data(
+                data.clone(),
+                bbox,
+                1.0,
+                Projection::default(),
+                margin,
+                false,
+            )
+        };
+
+        let (elements, xzbbox) = parse(0.0);
+        assert_eq!(elements.len(), 1);
+        let ProcessedElement::Way(path) = &elements[0] else {
+            panic!("The path was not kept");
+        };
+        assert!(path.nodes.iter().all(|node| node.z >= 0));
+
+        // The world stays the same, but the features beyond its edge are complete
+        let (elements, context_xzbbox) = parse(100.0);
+        assert_eq!(context_xzbbox.max_z(), xzbbox.max_z());
+        assert_eq!(elements.len(), 2);
+        assert!(elements
+            .iter()
+            .any(|element| matches!(element, ProcessedElement::Node(node) if node.z < 0)));
+        let path = elements
+            .iter()
+            .find_map(|element| match element {
+                ProcessedElement::Way(way) => Some(way),
+                _ => None,
+            })
+            .unwrap();
+        assert!(path.nodes.iter().any(|node| node.z < -30));
+    }
+
+    #[test]
+    fn test_streaming_matches_parsing_in_memory() {
+        let bbox = LLBBox::new(54.627053, 9.927928, 54.634902, 9.937563).unwrap();
+        // A bench, a path, a wood of two ways reaching past the edge of the bbox and a road far
+        // away from it
+        let data = serde_json::json!({
+            "elements": [
+                {"type": "node", "id": 1, "lat": 54.630, "lon": 9.930},
+                {"type": "node", "id": 2, "lat": 54.631, "lon": 9.930},
+                {"type": "node", "id": 3, "lat": 54.631, "lon": 9.931,
+                    "tags": {"amenity": "bench"}},
+                {"type": "node", "id": 4, "lat": 54.633, "lon": 9.933},
+                {"type": "node", "id": 5, "lat": 54.633, "lon": 9.940},
+                {"type": "node", "id": 6, "lat": 54.634, "lon": 9.940},
+                {"type": "node", "id": 7, "lat": 54.634, "lon": 9.933},
+                {"type": "node", "id": 8, "lat": 54.700, "lon": 9.990},
+                {"type": "node", "id": 9, "lat": 54.701, "lon": 9.990},
+                {"type": "way", "id": 10, "nodes": [1, 2, 3],
+                    "tags": {"highway": "footway"}},
+                {"type": "way", "id": 11, "nodes": [4, 5, 6]},
+                {"type": "way", "id": 12, "nodes": [6, 7, 4]},
+                {"type": "way", "id": 13, "nodes": [8, 9],
+                    "tags": {"highway": "primary"}},
+                {"type": "relation", "id": 20,
+                    "tags": {"type": "multipolygon", "natural": "wood"},
+                    "members": [
+                        {"type": "way", "ref": 11, "role": "outer"},
+                        {"type": "way", "ref": 12, "role": "outer"},
+                    ]},
+            ]
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.json");
+        std::fs::write(&path, data.to_string()).unwrap();
+
+        let (mut in_memory, _) = parse_osm_data(data, bbox, 1.0, Projection::default(), 0.0, false);
+        let (mut streamed, _) =
+            parse_osm_file_streaming(&path, bbox, 1.0, Projection::default(), 0.0, false).unwrap();
+        let summary = |elements: &mut Vec<ProcessedElement>| {
+            let mut summary: Vec<(u64, usize)> = elements
+                .iter()
+                .map(|element| match element {
+                    ProcessedElement::Node(node) => (node.id, 1),
+                    ProcessedElement::Way(way) => (way.id, way.nodes.len()),
+                    ProcessedElement::Relation(relation) => (
+                        relation.id,
+                        relation.members.iter().map(|m| m.way.nodes.len()).sum(),
+                    ),
+                })
+                .collect();
+            summary.sort_unstable();
+            summary
+        };
+        let streamed = summary(&mut streamed);
+        assert_eq!(streamed, summary(&mut in_memory));
+        let ids: Vec<u64> = streamed.iter().map(|&(id, _)| id).collect();
+        assert_eq!(ids, [3, 10, 11, 12, 20]);
+
+        // OSM XML is only converted as a whole
+        let xml = dir.path().join("data.osm");
+        std::fs::write(&xml, "<?xml version=\"1.0\"?>\n<osm></osm>").unwrap();
+        assert!(ensure_streamable(&xml).is_err());
+        assert!(ensure_streamable(&path).is_ok());
+    }
+}
@@ -0,0 +1,638 @@
+//! Reader of OSM PBF extracts, such as the country extracts of Geofabrik, into the same JSON
+//! structure the Overpass API returns. Only the elements within the bounding box are kept,
+//! together with the nodes outside of it that the kept ways need, so that the memory used
+//! follows the size of the area rather than the size of the extract.
+
+use crate::coordinate_system::geographic::{LLBBox, LLPoint};
+use flate2::read::ZlibDecoder;
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read};
+use std::path::Path;
+
+/// Largest size of a blob header allowed by the format
+const MAX_BLOB_HEADER_SIZE: usize = 64 * 1024;
+/// Largest size of an uncompressed blob allowed by the format
+const MAX_BLOB_SIZE: usize = 32 * 1024 * 1024;
+
+/// Checks whether a file is an OSM PBF extract by its extension
+pub fn is_pbf(path: &str) -> bool {
+    path.to_ascii_lowercase().ends_with(".pbf")
+}
+
+/// Reads the elements of an extract within the given bounding box. Extracts must be sorted
+/// with the nodes before the ways and the ways before the relations, as extracts are.
+pub fn read_pbf(path: &Path, bbox: &LLBBox) -> Result<Value, Box<dyn Error>> {
+    let mut nodes: HashMap<i64, Value> = HashMap::new();
+    let mut ways: HashMap<i64, Value> = HashMap::new();
+    let mut relations: Vec<Value> = Vec::new();
+    let mut missing: HashSet<i64> = HashSet::new();
+
+    for_each_block(path, |block| {
+        for group in &block.groups {
+            for entity in block.entities(group)? {
+                match entity {
+                    Entity::Node { id, lat, lon, tags } => {
+                        let inside = LLPoint::new(lat, lon).is_ok_and(|p| bbox.contains(&p));
+                        if inside {
+                            nodes.insert(id, node_json(id, lat, lon, tags));
+                        }
+                    }
+                    Entity::Way { id, refs, tags } => {
+                        if refs.iter().any(|node| nodes.contains_key(node)) {
+                            missing.extend(refs.iter().filter(|node| !nodes.contains_key(node)));
+                            let refs: Vec<u64> = refs.into_iter().map(|node| node as u64).collect();
+                            ways.insert(id, element_json("way", id, tags, "nodes", json!(refs)));
+                        }
+                    }
+                    Entity::Relation { id, members, tags } => {
+                        let kept = members.iter().any(|(kind, member, _)| match kind.as_str() {
+                            "node" => nodes.contains_key(member),
+                            "way" => ways.contains_key(member),
+                            _ => false,
+                        });
+                        if kept {
+                            let members: Vec<Value> = members
+                                .into_iter()
+                                .map(|(kind, member, role)| {
+                                    json!({"type": kind, "ref": member as u64, "role": role})
+                                })
+                                .collect();
+                            relations.push(element_json(
+                                "relation",
+                                id,
+                                tags,
+                                "members",
+                                json!(members),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    })?;
+
+    // A second pass for the nodes outside of the bounding box that kept ways lead to
+    if !missing.is_empty() {
+        for_each_block(path, |block| {
+            for group in &block.groups {
+                if !group.has_nodes {
+                    continue;
+                }
+                for entity in block.entities(group)? {
+                    if let Entity::Node { id, lat, lon, .. } = entity {
+                        if missing.remove(&id) {
+                            nodes.insert(id, node_json(id, lat, lon, Vec::new()));
+                        }
+                    }
+                }
+            }
+            Ok(())
+        })?;
+    }
+
+    let elements: Vec<Value> = nodes
+        .into_values()
+        .chain(ways.into_values())
+        .chain(relations)
+        .collect();
+    Ok(json!({ "elements": elements }))
+}
+
+fn node_json(id: i64, lat: f64, lon: f64, tags: Vec<(String, String)>) -> Value {
+    let mut element = element_json("node", id, tags, "lat", json!(lat));
+    if let Value::Object(object) = &mut element {
+        object.insert("lon".to_string(), json!(lon));
+    }
+    element
+}
+
+fn element_json(
+    kind: &str,
+    id: i64,
+    tags: Vec<(String, String)>,
+    key: &str,
+    value: Value,
+) -> Value {
+    let mut element = Map::new();
+    element.insert("type".to_string(), json!(kind));
+    // Negative ids wrap around as in OSM XML files
+    element.insert("id".to_string(), json!(id as u64));
+    element.insert(key.to_string(), value);
+    if !tags.is_empty() {
+        element.insert(
+            "tags".to_string(),
+            Value::Object(tags.into_iter().map(|(k, v)| (k, json!(v))).collect()),
+        );
+    }
+    Value::Object(element)
+}
+
+/// Calls the given function with every block of OSM data in the file, one at a time
+fn for_each_block(
+    path: &Path,
+    mut visit: impl FnMut(&PrimitiveBlock) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    loop {
+        let mut size = [0u8; 4];
+        match reader.read_exact(&mut size) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+        let header_size = u32::from_be_bytes(size) as usize;
+        if header_size > MAX_BLOB_HEADER_SIZE {
+            return Err("Invalid blob header in PBF file".into());
+        }
+        let mut header = vec![0u8; header_size];
+        reader.read_exact(&mut header)?;
+
+        let mut kind = String::new();
+        let mut data_size = 0;
+        for field in Fields::new(&header) {
+            match field? {
+                (1, Field::Bytes(bytes)) => kind = String::from_utf8_lossy(bytes).into_owned(),
+                (3, Field::Varint(value)) => data_size = value as usize,
+                _ => {}
+            }
+        }
+        if data_size > MAX_BLOB_SIZE {
+            return Err("Invalid blob in PBF file".into());
+        }
+        let mut blob = vec![0u8; data_size];
+        reader.read_exact(&mut blob)?;
+
+        if kind == "OSMData" {
+            let data = blob_data(&blob)?;
+            visit(&PrimitiveBlock::parse(&data)?)?;
+        }
+    }
+}
+
+/// Returns the uncompressed contents of a blob
+fn blob_data(blob: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    for field in Fields::new(blob) {
+        match field? {
+            (1, Field::Bytes(raw)) => return Ok(raw.to_vec()),
+            (3, Field::Bytes(zlib)) => {
+                let mut data = Vec::new();
+                ZlibDecoder::new(zlib)
+                    .take(MAX_BLOB_SIZE as u64)
+                    .read_to_end(&mut data)?;
+                return Ok(data);
+            }
+            (4..=7, Field::Bytes(_)) => {
+                return Err("Unsupported compression in PBF file, only zlib is supported".into())
+            }
+            _ => {}
+        }
+    }
+    Err("Empty blob in PBF file".into())
+}
+
+/// Block of OSM data with the strings its elements refer to
+struct PrimitiveBlock<'a> {
+    strings: Vec<&'a [u8]>,
+    groups: Vec<Group<'a>>,
+    granularity: i64,
+    lat_offset: i64,
+    lon_offset: i64,
+}
+
+struct Group<'a> {
+    data: &'a [u8],
+    has_nodes: bool,
+}
+
+enum Entity {
+    Node {
+        id: i64,
+        lat: f64,
+        lon: f64,
+        tags: Vec<(String, String)>,
+    },
+    Way {
+        id: i64,
+        refs: Vec<i64>,
+        tags: Vec<(String, String)>,
+    },
+    Relation {
+        id: i64,
+        members: Vec<(String, i64, String)>,
+        tags: Vec<(String, String)>,
+    },
+}
+
+impl<'a> PrimitiveBlock<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self, Box<dyn Error>> {
+        let mut block = Self {
+            strings: Vec::new(),
+            groups: Vec::new(),
+            granularity: 100,
+            lat_offset: 0,
+            lon_offset: 0,
+        };
+        for field in Fields::new(data) {
+            match field? {
+                (1, Field::Bytes(table)) => {
+                    for string in Fields::new(table) {
+                        if let (1, Field::Bytes(string)) = string? {
+                            block.strings.push(string);
+                        }
+                    }
+                }
+                (2, Field::Bytes(group)) => {
+                    let has_nodes = Fields::new(group)
+                        .any(|field| matches!(field, Ok((1 | 2, Field::Bytes(_)))));
+                    block.groups.push(Group {
+                        data: group,
+                        has_nodes,
+                    });
+                }
+                (17, Field::Varint(value)) => block.granularity = value as i64,
+                (19, Field::Varint(value)) => block.lat_offset = value as i64,
+                (20, Field::Varint(value)) => block.lon_offset = value as i64,
+                _ => {}
+            }
+        }
+        Ok(block)
+    }
+
+    fn string(&self, index: u64) -> String {
+        self.strings
+            .get(index as usize)
+            .map(|s| String::from_utf8_lossy(s).into_owned())
+            .unwrap_or_default()
+    }
+
+    fn tags(&self, keys: &[u64], values: &[u64]) -> Vec<(String, String)> {
+        keys.iter()
+            .zip(values)
+            .map(|(&k, &v)| (self.string(k), self.string(v)))
+            .collect()
+    }
+
+    fn coordinate(&self, offset: i64, value: i64) -> f64 {
+        (offset + self.granularity * value) as f64 * 1e-9
+    }
+
+    /// Decodes the elements of a group
+    fn entities(&self, group: &Group) -> Result<Vec<Entity>, Box<dyn Error>> {
+        let mut entities = Vec::new();
+        for field in Fields::new(group.data) {
+            match field? {
+                (1, Field::Bytes(node)) => {
+                    let (mut id, mut lat, mut lon) = (0, 0, 0);
+                    let (mut keys, mut values) = (Vec::new(), Vec::new());
+                    for field in Fields::new(node) {
+                        match field? {
+                            (1, Field::Varint(value)) => id = zigzag(value),
+                            (2, Field::Bytes(packed)) => keys = varints(packed)?,
+                            (3, Field::Bytes(packed)) => values = varints(packed)?,
+                            (8, Field::Varint(value)) => lat = zigzag(value),
+                            (9, Field::Varint(value)) => lon = zigzag(value),
+                            _ => {}
+                        }
+                    }
+                    entities.push(Entity::Node {
+                        id,
+                        lat: self.coordinate(self.lat_offset, lat),
+                        lon: self.coordinate(self.lon_offset, lon),
+                        tags: self.tags(&keys, &values),
+                    });
+                }
+                (2, Field::Bytes(dense)) => self.dense_nodes(dense, &mut entities)?,
+                (3, Field::Bytes(way)) => {
+                    let (mut id, mut refs) = (0, Vec::new());
+                    let (mut keys, mut values) = (Vec::new(), Vec::new());
+                    for field in Fields::new(way) {
+                        match field? {
+                            (1, Field::Varint(value)) => id = value as i64,
+                            (2, Field::Bytes(packed)) => keys = varints(packed)?,
+                            (3, Field::Bytes(packed)) => values = varints(packed)?,
+                            (8, Field::Bytes(packed)) => refs = deltas(packed)?,
+                            _ => {}
+                        }
+                    }
+                    let tags = self.tags(&keys, &values);
+                    entities.push(Entity::Way { id, refs, tags });
+                }
+                (4, Field::Bytes(relation)) => {
+                    let (mut id, mut roles, mut ids, mut types) =
+                        (0, Vec::new(), Vec::new(), Vec::new());
+                    let (mut keys, mut values) = (Vec::new(), Vec::new());
+                    for field in Fields::new(relation) {
+                        match field? {
+                            (1, Field::Varint(value)) => id = value as i64,
+                            (2, Field::Bytes(packed)) => keys = varints(packed)?,
+                            (3, Field::Bytes(packed)) => values = varints(packed)?,
+                            (8, Field::Bytes(packed)) => roles = varints(packed)?,
+                            (9, Field::Bytes(packed)) => ids = deltas(packed)?,
+                            (10, Field::Bytes(packed)) => types = varints(packed)?,
+                            _ => {}
+                        }
+                    }
+                    let members = ids
+                        .into_iter()
+                        .zip(types)
+                        .zip(roles)
+                        .map(|((member, kind), role)| {
+                            let kind = match kind {
+                                0 => "node",
+                                1 => "way",
+                                _ => "relation",
+                            };
+                            (kind.to_string(), member, self.string(role))
+                        })
+                        .collect();
+                    let tags = self.tags(&keys, &values);
+                    entities.push(Entity::Relation { id, members, tags });
+                }
+                _ => {}
+            }
+        }
+        Ok(entities)
+    }
+
+    /// Decodes densely packed nodes, whose ids and coordinates are stored as differences
+    fn dense_nodes(&self, dense: &[u8], entities: &mut Vec<Entity>) -> Result<(), Box<dyn Error>> {
+        let (mut ids, mut lats, mut lons, mut keys_values) =
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for field in Fields::new(dense) {
+            match field? {
+                (1, Field::Bytes(packed)) => ids = deltas(packed)?,
+                (8, Field::Bytes(packed)) => lats = deltas(packed)?,
+                (9, Field::Bytes(packed)) => lons = deltas(packed)?,
+                (10, Field::Bytes(packed)) => keys_values = varints(packed)?,
+                _ => {}
+            }
+        }
+
+        // The tags of all nodes in a row, those of each node ended by a 0
+        let mut keys_values = keys_values.into_iter();
+        for ((id, lat), lon) in ids.into_iter().zip(lats).zip(lons) {
+            let mut tags = Vec::new();
+            while let Some(key) = keys_values.next() {
+                if key == 0 {
+                    break;
+                }
+                let value = keys_values.next().unwrap_or(0);
+                tags.push((self.string(key), self.string(value)));
+            }
+            entities.push(Entity::Node {
+                id,
+                lat: self.coordinate(self.lat_offset, lat),
+                lon: self.coordinate(self.lon_offset, lon),
+                tags,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Value of a protobuf field
+enum Field<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// Iterator over the fields of a protobuf message as numbers and values
+struct Fields<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn varint(&mut self) -> Result<u64, Box<dyn Error>> {
+        let (value, length) = read_varint(&self.data[self.position..])?;
+        self.position += length;
+        Ok(value)
+    }
+
+    fn field(&mut self) -> Result<(u32, Field<'a>), Box<dyn Error>> {
+        let key = self.varint()?;
+        let number = (key >> 3) as u32;
+        let value = match key & 7 {
+            0 => Field::Varint(self.varint()?),
+            2 => {
+                let length = self.varint()? as usize;
+                let data: &'a [u8] = self.data;
+                let bytes = data
+                    .get(self.position..self.position + length)
+                    .ok_or("Truncated field in PBF file")?;
+                self.position += length;
+                Field::Bytes(bytes)
+            }
+            1 => {
+                self.position += 8;
+                Field::Fixed
+            }
+            5 => {
+                self.position += 4;
+                Field::Fixed
+            }
+            _ => return Err("Unsupported field in PBF file".into()),
+        };
+        Ok((number, value))
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<(u32, Field<'a>), Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.data.len() {
+            return None;
+        }
+        let field = self.field();
+        if field.is_err() {
+            // Stop after the first error
+            self.position = self.data.len();
+        }
+        Some(field)
+    }
+}
+
+/// Reads a varint, returning its value and length in bytes
+fn read_varint(data: &[u8]) -> Result<(u64, usize), Box<dyn Error>> {
+    let mut value = 0u64;
+    for (i, &byte) in data.iter().enumerate().take(10) {
+        value |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    Err("Invalid varint in PBF file".into())
+}
+
+/// Decodes a signed value stored with the zigzag encoding
+fn zigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+/// Decodes packed varints
+fn varints(mut data: &[u8]) -> Result<Vec<u64>, Box<dyn Error>> {
+    let mut values = Vec::new();
+    while !data.is_empty() {
+        let (value, length) = read_varint(data)?;
+        values.push(value);
+        data = &data[length..];
+    }
+    Ok(values)
+}
+
+/// Decodes packed signed values, each stored as the difference to the one before
+fn deltas(data: &[u8]) -> Result<Vec<i64>, Box<dyn Error>> {
+    let mut current = 0i64;
+    Ok(varints(data)?
+        .into_iter()
+        .map(|value| {
+            current += zigzag(value);
+            current
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn varint(mut value: u64, out: &mut Vec<u8>) {
+        while value >= 0x80 {
+            out.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn varint_field(number: u32, value: u64, out: &mut Vec<u8>) {
+        varint((number as u64) << 3, out);
+        varint(value, out);
+    }
+
+    fn bytes_field(number: u32, bytes: &[u8], out: &mut Vec<u8>) {
+        varint(((number as u64) << 3) | 2, out);
+        varint(bytes.len() as u64, out);
+        out.extend_from_slice(bytes);
+    }
+
+    fn packed(values: &[u64]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for &value in values {
+            varint(value, &mut out);
+        }
+        out
+    }
+
+    fn zigzag_encode(value: i64) -> u64 {
+        ((value << 1) ^ (value >> 63)) as u64
+    }
+
+    fn packed_deltas(values: &[i64]) -> Vec<u8> {
+        let mut previous = 0;
+        let deltas: Vec<u64> = values
+            .iter()
+            .map(|&value| {
+                let delta = zigzag_encode(value - previous);
+                previous = value;
+                delta
+            })
+            .collect();
+        packed(&deltas)
+    }
+
+    fn blob(kind: &str, data: &[u8], out: &mut Vec<u8>) {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        let mut blob = Vec::new();
+        varint_field(2, data.len() as u64, &mut blob);
+        bytes_field(3, &encoder.finish().unwrap(), &mut blob);
+
+        let mut header = Vec::new();
+        bytes_field(1, kind.as_bytes(), &mut header);
+        varint_field(3, blob.len() as u64, &mut header);
+        out.extend_from_slice(&(header.len() as u32).to_be_bytes());
+        out.extend_from_slice(&header);
+        out.extend_from_slice(&blob);
+    }
+
+    #[test]
+    fn test_pbf_keeps_elements_within_bbox() {
+        // Strings: "", "highway", "residential", "name", "Inside"
+        let mut strings = Vec::new();
+        for string in ["", "highway", "residential", "name", "Inside"] {
+            bytes_field(1, string.as_bytes(), &mut strings);
+        }
+
+        // Node 1 inside the bbox with a name, nodes 2 and 3 outside of it
+        let mut dense = Vec::new();
+        bytes_field(1, &packed_deltas(&[1, 2, 3]), &mut dense);
+        bytes_field(
+            8,
+            &packed_deltas(&[480_050_000, 490_000_000, 470_000_000]),
+            &mut dense,
+        );
+        bytes_field(
+            9,
+            &packed_deltas(&[110_050_000, 110_050_000, 110_050_000]),
+            &mut dense,
+        );
+        bytes_field(10, &packed(&[3, 4, 0, 0, 0]), &mut dense);
+        let mut nodes_group = Vec::new();
+        bytes_field(2, &dense, &mut nodes_group);
+
+        // Way 10 leads from inside to outside, way 11 lies outside
+        let mut ways_group = Vec::new();
+        for (id, refs) in [(10, vec![1, 2]), (11, vec![2, 3])] {
+            let mut way = Vec::new();
+            varint_field(1, id, &mut way);
+            bytes_field(2, &packed(&[1]), &mut way);
+            bytes_field(3, &packed(&[2]), &mut way);
+            bytes_field(8, &packed_deltas(&refs), &mut way);
+            bytes_field(3, &way, &mut ways_group);
+        }
+
+        let mut block = Vec::new();
+        bytes_field(1, &strings, &mut block);
+        bytes_field(2, &nodes_group, &mut block);
+        bytes_field(2, &ways_group, &mut block);
+
+        let mut file = Vec::new();
+        blob("OSMHeader", &[], &mut file);
+        blob("OSMData", &block, &mut file);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("extract.osm.pbf");
+        std::fs::write(&path, file).unwrap();
+
+        let bbox = LLBBox::new(48.0, 11.0, 48.1, 11.1).unwrap();
+        let data = read_pbf(&path, &bbox).unwrap();
+        let elements = data["elements"].as_array().unwrap();
+        let find = |kind: &str, id: u64| {
+            elements
+                .iter()
+                .find(|element| element["type"] == kind && element["id"] == id)
+        };
+
+        assert_eq!(elements.len(), 3);
+        let inside = find("node", 1).unwrap();
+        assert!((inside["lat"].as_f64().unwrap() - 48.005).abs() < 1e-9);
+        assert_eq!(inside["tags"]["name"], "Inside");
+        assert!(find("node", 2).unwrap().get("tags").is_none());
+        assert_eq!(find("way", 10).unwrap()["nodes"], json!([1, 2]));
+        assert_eq!(find("way", 10).unwrap()["tags"]["highway"], "residential");
+        assert!(find("way", 11).is_none());
+        assert!(is_pbf("germany-latest.osm.pbf"));
+    }
+}
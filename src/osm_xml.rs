@@ -0,0 +1,248 @@
+//! Reader of OSM XML files, as exported from openstreetmap.org or saved by JOSM, into the same
+//! JSON structure the Overpass API returns, so that they can be generated offline.
+
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Converts the contents of an OSM XML file to Overpass JSON. Deleted and invisible elements
+/// are left out. The negative ids of elements not uploaded yet are kept apart from the
+/// positive ones by wrapping them around, consistently for all references.
+pub fn osm_xml_to_json(xml: &str) -> Result<Value, Box<dyn Error>> {
+    let mut elements: Vec<Value> = Vec::new();
+    let mut current: Option<Map<String, Value>> = None;
+    let mut skip_current = false;
+    let mut rest = xml;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        // Comments, declarations and processing instructions
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment
+                .find("-->")
+                .ok_or("Unterminated comment in OSM XML")?;
+            rest = &comment[end + 3..];
+            continue;
+        }
+        let end = tag_end(rest).ok_or("Unterminated tag in OSM XML")?;
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+
+        if let Some(name) = tag.strip_prefix('/') {
+            if matches!(name.trim(), "node" | "way" | "relation") {
+                if let Some(element) = current.take() {
+                    if !skip_current {
+                        elements.push(Value::Object(element));
+                    }
+                }
+            }
+            continue;
+        }
+
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let (name, attributes) = match tag.find(char::is_whitespace) {
+            Some(split) => (&tag[..split], parse_attributes(&tag[split..])?),
+            None => (tag, HashMap::new()),
+        };
+        let attribute = |key: &str| {
+            attributes
+                .get(key)
+                .ok_or_else(|| format!("<{name}> without {key} in OSM XML"))
+        };
+
+        match name {
+            "node" | "way" | "relation" => {
+                let mut element = Map::new();
+                element.insert("type".to_string(), json!(name));
+                element.insert("id".to_string(), json!(parse_id(attribute("id")?)?));
+                if name == "node" {
+                    element.insert("lat".to_string(), json!(attribute("lat")?.parse::<f64>()?));
+                    element.insert("lon".to_string(), json!(attribute("lon")?.parse::<f64>()?));
+                }
+                skip_current = attributes.get("action").map(String::as_str) == Some("delete")
+                    || attributes.get("visible").map(String::as_str) == Some("false");
+                if self_closing {
+                    if !skip_current {
+                        elements.push(Value::Object(element));
+                    }
+                } else {
+                    current = Some(element);
+                }
+            }
+            "tag" => {
+                if let Some(element) = current.as_mut() {
+                    let tags = element
+                        .entry("tags")
+                        .or_insert_with(|| Value::Object(Map::new()));
+                    tags[attribute("k")?.as_str()] = json!(attribute("v")?);
+                }
+            }
+            "nd" => {
+                if let Some(element) = current.as_mut() {
+                    let id = parse_id(attribute("ref")?)?;
+                    if let Value::Array(nodes) = element.entry("nodes").or_insert_with(|| json!([]))
+                    {
+                        nodes.push(json!(id));
+                    }
+                }
+            }
+            "member" => {
+                if let Some(element) = current.as_mut() {
+                    let member = json!({
+                        "type": attribute("type")?,
+                        "ref": parse_id(attribute("ref")?)?,
+                        "role": attributes.get("role").cloned().unwrap_or_default(),
+                    });
+                    if let Value::Array(members) =
+                        element.entry("members").or_insert_with(|| json!([]))
+                    {
+                        members.push(member);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(json!({ "elements": elements }))
+}
+
+/// Checks whether file contents look like XML rather than JSON
+pub fn is_xml(contents: &str) -> bool {
+    contents.trim_start().starts_with('<')
+}
+
+/// Returns the position of the `>` closing the tag at the start of the text, skipping the ones
+/// within quoted attribute values
+fn tag_end(text: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parses an element id, wrapping negative ids of new elements around
+fn parse_id(id: &str) -> Result<u64, Box<dyn Error>> {
+    Ok(id.parse::<i64>()? as u64)
+}
+
+/// Parses the attributes of a tag, such as `id="1" k='name'`
+fn parse_attributes(mut text: &str) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let mut attributes = HashMap::new();
+    loop {
+        text = text.trim_start();
+        if text.is_empty() {
+            return Ok(attributes);
+        }
+        let equals = text.find('=').ok_or("Attribute without value in OSM XML")?;
+        let key = text[..equals].trim().to_string();
+        text = text[equals + 1..].trim_start();
+        let quote = text
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+            .ok_or("Unquoted attribute value in OSM XML")?;
+        let end = text[1..]
+            .find(quote)
+            .ok_or("Unterminated attribute value in OSM XML")?;
+        attributes.insert(key, decode_entities(&text[1..end + 1]));
+        text = &text[end + 2..];
+    }
+}
+
+/// Replaces the XML entities and character references in an attribute value
+fn decode_entities(value: &str) -> String {
+    if !value.contains('&') {
+        return value.to_string();
+    }
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let character = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match character {
+            Some(character) => {
+                decoded.push(character);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osm_xml_to_overpass_json() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6" generator="JOSM">
+  <bounds minlat="48.0" minlon="11.0" maxlat="48.1" maxlon="11.1"/>
+  <!-- a <comment> -->
+  <node id="1" lat="48.01" lon="11.02" version="3"/>
+  <node id="-2" lat='48.02' lon='11.03'>
+    <tag k="name" v="Caf&#233; &amp; Bar"/>
+  </node>
+  <node id="3" action="delete" lat="48.03" lon="11.04"/>
+  <way id="10">
+    <nd ref="1"/>
+    <nd ref="-2"/>
+    <tag k="highway" v="residential"/>
+    <tag k="note" v="width > 5 m"/>
+  </way>
+  <relation id="20">
+    <member type="way" ref="10" role="outer"/>
+    <tag k="type" v="multipolygon"/>
+  </relation>
+</osm>"#;
+        assert!(is_xml(xml));
+        let data = osm_xml_to_json(xml).unwrap();
+        let elements = data["elements"].as_array().unwrap();
+
+        assert_eq!(elements.len(), 4);
+        assert_eq!(
+            elements[0],
+            json!({"type": "node", "id": 1, "lat": 48.01, "lon": 11.02})
+        );
+        assert_eq!(elements[1]["tags"]["name"], "Café & Bar");
+        assert_eq!(elements[2]["nodes"], json!([1, -2i64 as u64]));
+        assert_eq!(elements[2]["tags"]["highway"], "residential");
+        assert_eq!(elements[2]["tags"]["note"], "width > 5 m");
+        assert_eq!(
+            elements[3]["members"],
+            json!([{"type": "way", "ref": 10, "role": "outer"}])
+        );
+    }
+}
@@ -0,0 +1,265 @@
+//! Validation and repair of area outlines before they are flood filled.
+//!
+//! Unclosed or self-intersecting outlines can make the flood fill run away or fill nothing,
+//! so broken rings are closed, degenerate ones dropped and bowties split into simple rings.
+
+use crate::osm_parser::ProcessedNode;
+use std::collections::HashMap;
+
+/// Largest gap, relative to the length of the outline, that is closed automatically
+const MAX_CLOSING_GAP: f64 = 0.25;
+/// Maximum number of self-intersections split per outline
+const MAX_SPLITS: usize = 16;
+/// Outlines with more nodes are not checked for self-intersections
+const MAX_INTERSECTION_CHECK_NODES: usize = 2000;
+/// Id given to nodes created at self-intersections
+const INTERSECTION_NODE_ID: u64 = u64::MAX;
+
+/// Returns true if the tags describe an area that is flood filled
+pub fn is_area(tags: &HashMap<String, String>) -> bool {
+    if tags.get("area").is_some_and(|area| area == "no") {
+        return false;
+    }
+    if tags.get("area").is_some_and(|area| area == "yes") {
+        return true;
+    }
+
+    let linear_natural = ["tree_row", "coastline", "cliff", "ridge", "arete", "valley"];
+    let is_area_natural = tags
+        .get("natural")
+        .is_some_and(|natural| !linear_natural.contains(&natural.as_str()));
+
+    is_area_natural
+        || [
+            "building",
+            "building:part",
+            "landuse",
+            "leisure",
+            "amenity",
+            "water",
+        ]
+        .iter()
+        .any(|key| tags.contains_key(*key))
+}
+
+/// Repairs an area outline. Returns the resulting simple rings, which are empty if the
+/// outline is degenerate, together with a description of every repair that was applied.
+pub fn repair_ring(nodes: Vec<ProcessedNode>) -> (Vec<Vec<ProcessedNode>>, Vec<&'static str>) {
+    let mut repairs: Vec<&'static str> = Vec::new();
+
+    // Consecutive nodes at the same position add zero length edges
+    let mut ring: Vec<ProcessedNode> = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        if ring
+            .last()
+            .is_some_and(|last: &ProcessedNode| last.x == node.x && last.z == node.z)
+        {
+            continue;
+        }
+        ring.push(node);
+    }
+    let first_matches_last = ring.len() > 1 && {
+        let (first, last) = (&ring[0], &ring[ring.len() - 1]);
+        first.x == last.x && first.z == last.z
+    };
+    if ring.len() > 1 && !first_matches_last {
+        let gap = distance(&ring[0], &ring[ring.len() - 1]);
+        let length: f64 = ring
+            .windows(2)
+            .map(|pair| distance(&pair[0], &pair[1]))
+            .sum();
+        if gap > length * MAX_CLOSING_GAP {
+            // Too far apart to be a broken ring, leave the outline as it is
+            return (vec![ring], repairs);
+        }
+        ring.push(ring[0].clone());
+        repairs.push("closed ring");
+    }
+
+    if is_degenerate(&ring) {
+        repairs.push("dropped degenerate polygon");
+        return (Vec::new(), repairs);
+    }
+
+    if ring.len() > MAX_INTERSECTION_CHECK_NODES {
+        return (vec![ring], repairs);
+    }
+
+    let mut finished: Vec<Vec<ProcessedNode>> = Vec::new();
+    let mut pending: Vec<Vec<ProcessedNode>> = vec![ring];
+    let mut splits = 0;
+
+    while let Some(ring) = pending.pop() {
+        match find_self_intersection(&ring) {
+            Some((i, j, point)) if splits < MAX_SPLITS => {
+                let (first, second) = split_ring(&ring, i, j, point);
+                splits += 1;
+                pending.extend([first, second].into_iter().filter(|r| !is_degenerate(r)));
+            }
+            _ => finished.push(ring),
+        }
+    }
+    if splits > 0 {
+        repairs.push("split self-intersecting polygon");
+    }
+
+    (finished, repairs)
+}
+
+fn distance(a: &ProcessedNode, b: &ProcessedNode) -> f64 {
+    (((a.x - b.x) as f64).powi(2) + ((a.z - b.z) as f64).powi(2)).sqrt()
+}
+
+/// Returns true if a closed ring has fewer than three distinct points or all of them lie on
+/// one line. The area is not checked, as the lobes of a bowtie cancel each other out.
+fn is_degenerate(ring: &[ProcessedNode]) -> bool {
+    if ring.len() < 4 {
+        return true;
+    }
+
+    let origin = &ring[0];
+    let Some(direction) = ring
+        .iter()
+        .find(|node| node.x != origin.x || node.z != origin.z)
+    else {
+        return true;
+    };
+    let (dx, dz) = (
+        (direction.x - origin.x) as i64,
+        (direction.z - origin.z) as i64,
+    );
+    ring.iter()
+        .all(|node| dx * (node.z - origin.z) as i64 == dz * (node.x - origin.x) as i64)
+}
+
+/// Finds the first pair of non-adjacent edges of a closed ring that cross each other.
+/// Returns the indices of both edges and the crossing point.
+fn find_self_intersection(ring: &[ProcessedNode]) -> Option<(usize, usize, (i32, i32))> {
+    let edge_count = ring.len() - 1;
+
+    for i in 0..edge_count {
+        for j in (i + 2)..edge_count {
+            // The first and the last edge share the closing node
+            if i == 0 && j == edge_count - 1 {
+                continue;
+            }
+            if let Some(point) =
+                segment_intersection(&ring[i], &ring[i + 1], &ring[j], &ring[j + 1])
+            {
+                return Some((i, j, point));
+            }
+        }
+    }
+
+    None
+}
+
+/// Returns the point where two segments properly cross, ignoring touching endpoints
+fn segment_intersection(
+    a1: &ProcessedNode,
+    a2: &ProcessedNode,
+    b1: &ProcessedNode,
+    b2: &ProcessedNode,
+) -> Option<(i32, i32)> {
+    let (ax, az) = ((a2.x - a1.x) as f64, (a2.z - a1.z) as f64);
+    let (bx, bz) = ((b2.x - b1.x) as f64, (b2.z - b1.z) as f64);
+
+    let denominator = ax * bz - az * bx;
+    if denominator == 0.0 {
+        return None;
+    }
+
+    let (dx, dz) = ((b1.x - a1.x) as f64, (b1.z - a1.z) as f64);
+    let t = (dx * bz - dz * bx) / denominator;
+    let u = (dx * az - dz * ax) / denominator;
+
+    if t > 0.0 && t < 1.0 && u > 0.0 && u < 1.0 {
+        Some((
+            (a1.x as f64 + t * ax).round() as i32,
+            (a1.z as f64 + t * az).round() as i32,
+        ))
+    } else {
+        None
+    }
+}
+
+/// Splits a closed ring at the crossing of edges `i` and `j` into two closed rings
+fn split_ring(
+    ring: &[ProcessedNode],
+    i: usize,
+    j: usize,
+    (x, z): (i32, i32),
+) -> (Vec<ProcessedNode>, Vec<ProcessedNode>) {
+    let crossing = ProcessedNode {
+        id: INTERSECTION_NODE_ID,
+        tags: HashMap::new(),
+        x,
+        z,
+    };
+
+    // The loop between both edges
+    let mut first = vec![crossing.clone()];
+    first.extend_from_slice(&ring[i + 1..=j]);
+    first.push(crossing.clone());
+
+    // The rest of the ring, going around the closing node
+    let mut second = vec![crossing.clone()];
+    second.extend_from_slice(&ring[j + 1..]);
+    second.extend_from_slice(&ring[1..=i]);
+    second.push(crossing);
+
+    (first, second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ring(points: &[(i32, i32)]) -> Vec<ProcessedNode> {
+        points
+            .iter()
+            .enumerate()
+            .map(|(i, &(x, z))| ProcessedNode {
+                id: i as u64,
+                tags: HashMap::new(),
+                x,
+                z,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_closes_nearly_closed_ring() {
+        let (rings, repairs) = repair_ring(ring(&[(0, 0), (10, 0), (10, 10), (0, 10), (0, 1)]));
+        assert_eq!(rings.len(), 1);
+        assert_eq!(repairs, vec!["closed ring"]);
+        let repaired = &rings[0];
+        assert_eq!(repaired.first().unwrap().id, repaired.last().unwrap().id);
+    }
+
+    #[test]
+    fn test_drops_degenerate_ring() {
+        let (rings, repairs) = repair_ring(ring(&[(0, 0), (5, 5), (10, 10), (0, 0)]));
+        assert!(rings.is_empty());
+        assert_eq!(repairs, vec!["dropped degenerate polygon"]);
+    }
+
+    #[test]
+    fn test_splits_bowtie() {
+        let (rings, repairs) = repair_ring(ring(&[(0, 0), (10, 10), (10, 0), (0, 10), (0, 0)]));
+        assert_eq!(rings.len(), 2);
+        assert_eq!(repairs, vec!["split self-intersecting polygon"]);
+        for repaired in &rings {
+            assert_eq!(repaired.len(), 4);
+            assert!(find_self_intersection(repaired).is_none());
+        }
+    }
+
+    #[test]
+    fn test_keeps_simple_ring() {
+        let square = ring(&[(0, 0), (10, 0), (10, 10), (0, 10), (0, 0)]);
+        let (rings, repairs) = repair_ring(square.clone());
+        assert_eq!(rings, vec![square]);
+        assert!(repairs.is_empty());
+    }
+}
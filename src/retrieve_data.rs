@@ -1,15 +1,143 @@
+use crate::args::Args;
 use crate::coordinate_system::geographic::LLBBox;
+use crate::i18n::tr;
+use crate::osm_pbf;
+use crate::osm_xml;
 use crate::progress::{emit_gui_error, emit_gui_progress_update, is_running_with_gui};
 use colored::Colorize;
+use fnv::FnvHasher;
 use rand::seq::SliceRandom;
 use reqwest::blocking::Client;
 use reqwest::blocking::ClientBuilder;
-use serde_json::Value;
-use std::fs::File;
-use std::io::{self, BufReader, Write};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::thread;
 use std::time::Duration;
 
+/// Public Overpass API servers, tried in random order
+const PUBLIC_API_SERVERS: [&str; 3] = [
+    "https://overpass-api.de/api/interpreter",
+    "https://lz4.overpass-api.de/api/interpreter",
+    "https://z.overpass-api.de/api/interpreter",
+    //"https://overpass.kumi.systems/api/interpreter", // This server is not reliable anymore
+    //"https://overpass.private.coffee/api/interpreter", // This server is not reliable anymore
+];
+/// Servers tried after the public ones
+const FALLBACK_API_SERVERS: [&str; 1] = ["https://maps.mail.ru/osm/tools/overpass/api/interpreter"];
+/// Retries of a server that is busy or rate limits the requests before moving to the next one
+const MAX_BUSY_RETRIES: u32 = 3;
+/// Wait before the first retry of a busy server, doubled on every further retry
+const BASE_BACKOFF: Duration = Duration::from_secs(5);
+/// Longest wait before retrying a busy server
+const MAX_BACKOFF: Duration = Duration::from_secs(120);
+
+/// Keys of the elements downloaded unless a query filter says otherwise
+const QUERY_KEYS: [&str; 15] = [
+    "building",
+    "highway",
+    "landuse",
+    "natural",
+    "leisure",
+    "water",
+    "waterway",
+    "amenity",
+    "tourism",
+    "bridge",
+    "railway",
+    "public_transport",
+    "barrier",
+    "entrance",
+    "door",
+];
+/// Largest width and height in degrees of an area downloaded with a single query
+const MAX_TILE_SPAN: f64 = 0.1;
+/// Directory of the cached responses of the Overpass API
+const OVERPASS_CACHE_DIR: &str = "./arnis-osm-cache";
+
+/// Use of the cache of Overpass API responses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Responses are read from the cache and downloaded ones are added to it
+    Use,
+    /// Responses are downloaded again and replace the cached ones
+    Refresh,
+    /// The cache is neither read nor written
+    Off,
+}
+
+impl CacheMode {
+    pub fn from_args(args: &Args) -> Self {
+        if args.no_cache {
+            CacheMode::Off
+        } else if args.refresh {
+            CacheMode::Refresh
+        } else {
+            CacheMode::Use
+        }
+    }
+}
+
+/// Changes to the elements downloaded, such as `building,highway` to download nothing but
+/// buildings and roads, or `-landuse` to leave out land use
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QueryFilter {
+    /// Overpass tag selectors such as `["highway"="primary"]`, of which one must match
+    include: Vec<String>,
+    /// Keys no downloaded element may have
+    exclude: Vec<String>,
+}
+
+impl QueryFilter {
+    /// Parses comma-separated keys or key=value pairs to download, and keys prefixed with `-`
+    /// to leave out
+    pub fn from_str(filter: &str) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        for entry in filter.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            if entry.contains(['"', '[', ']', ';']) {
+                return Err(format!("Invalid query filter \"{entry}\""));
+            }
+            if let Some(key) = entry.strip_prefix('-') {
+                parsed.exclude.push(key.trim().to_string());
+            } else if let Some((key, value)) = entry.split_once('=') {
+                parsed
+                    .include
+                    .push(format!("[\"{}\"=\"{}\"]", key.trim(), value.trim()));
+            } else {
+                parsed.include.push(format!("[\"{entry}\"]"));
+            }
+        }
+        if parsed == Self::default() {
+            return Err("Empty query filter".to_string());
+        }
+        Ok(parsed)
+    }
+}
+
+/// Response of a server that is busy (504) or rate limits the requests (429)
+#[derive(Debug)]
+struct ServerBusy {
+    status: u16,
+    retry_after: Option<Duration>,
+}
+
+impl fmt::Display for ServerBusy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.status == 429 {
+            write!(f, "Too many requests to the Overpass API (HTTP 429)")
+        } else {
+            write!(f, "The Overpass API server is busy (HTTP {})", self.status)
+        }
+    }
+}
+
+impl std::error::Error for ServerBusy {}
+
 /// Function to download data using reqwest
 fn download_with_reqwest(url: &str, query: &str) -> Result<String, Box<dyn std::error::Error>> {
     let client: Client = ClientBuilder::new()
@@ -21,7 +149,21 @@ fn download_with_reqwest(url: &str, query: &str) -> Result<String, Box<dyn std::
 
     match response {
         Ok(resp) => {
-            emit_gui_progress_update(3.0, "Downloading data...");
+            emit_gui_progress_update(3.0, tr("step_downloading_data"));
+            let status = resp.status().as_u16();
+            if status == 429 || status == 504 {
+                // Servers may say how long to wait in seconds
+                let retry_after = resp
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.trim().parse().ok())
+                    .map(Duration::from_secs);
+                return Err(Box::new(ServerBusy {
+                    status,
+                    retry_after,
+                }));
+            }
             if resp.status().is_success() {
                 let text = resp.text()?;
                 if text.is_empty() {
@@ -79,13 +221,24 @@ fn download_with_wget(url: &str, query: &str) -> io::Result<String> {
     }
 }
 
-pub fn fetch_data_from_file(file: &str) -> Result<Value, Box<dyn std::error::Error>> {
-    println!("{} Loading data from file...", "[1/7]".bold());
-    emit_gui_progress_update(1.0, "Loading data from file...");
+pub fn fetch_data_from_file(
+    file: &str,
+    bbox: &LLBBox,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    println!("{} {}", "[1/7]".bold(), tr("step_loading_file"));
+    emit_gui_progress_update(1.0, tr("step_loading_file"));
+
+    // PBF extracts are read within the bounding box only, as they may cover whole countries
+    if osm_pbf::is_pbf(file) {
+        return osm_pbf::read_pbf(Path::new(file), bbox);
+    }
 
-    let file: File = File::open(file)?;
-    let reader: BufReader<File> = BufReader::new(file);
-    let data: Value = serde_json::from_reader(reader)?;
+    // OSM XML files are converted to the JSON the Overpass API returns
+    let contents: String = fs::read_to_string(file)?;
+    if osm_xml::is_xml(&contents) {
+        return osm_xml::osm_xml_to_json(&contents);
+    }
+    let data: Value = serde_json::from_str(&contents)?;
     Ok(data)
 }
 
@@ -95,41 +248,145 @@ pub fn fetch_data_from_overpass(
     debug: bool,
     download_method: &str,
     save_file: Option<&str>,
+    overpass_url: Option<&str>,
+    cache: CacheMode,
+    filter: Option<&QueryFilter>,
 ) -> Result<Value, Box<dyn std::error::Error>> {
-    println!("{} Fetching data...", "[1/7]".bold());
-    emit_gui_progress_update(1.0, "Fetching data...");
-
-    // List of Overpass API servers
-    let api_servers: Vec<&str> = vec![
-        "https://overpass-api.de/api/interpreter",
-        "https://lz4.overpass-api.de/api/interpreter",
-        "https://z.overpass-api.de/api/interpreter",
-        //"https://overpass.kumi.systems/api/interpreter", // This server is not reliable anymore
-        //"https://overpass.private.coffee/api/interpreter", // This server is not reliable anymore
-    ];
-    let fallback_api_servers: Vec<&str> =
-        vec!["https://maps.mail.ru/osm/tools/overpass/api/interpreter"];
-    let mut url: &&str = api_servers.choose(&mut rand::thread_rng()).unwrap();
-
-    // Generate Overpass API query for bounding box
-    let query: String = format!(
+    println!("{} {}", "[1/7]".bold(), tr("step_fetching_data"));
+    emit_gui_progress_update(1.0, tr("step_fetching_data"));
+
+    // Large areas are downloaded in tiles, keeping each query within the limits of the servers
+    let tiles = split_bbox(&bbox, MAX_TILE_SPAN);
+    if tiles.len() > 1 {
+        println!("Downloading the area in {} tiles", tiles.len());
+    }
+
+    let mut elements: Vec<Value> = Vec::new();
+    let mut seen: HashSet<(String, u64)> = HashSet::new();
+    let mut remark: Option<String> = None;
+    for (i, tile) in tiles.iter().enumerate() {
+        if tiles.len() > 1 {
+            println!("Tile {}/{}", i + 1, tiles.len());
+            emit_gui_progress_update(1.0 + 4.0 * i as f64 / tiles.len() as f64, "");
+        }
+        let mut tile_data = fetch_tile(tile, download_method, overpass_url, cache, filter)?;
+        if let Some(tile_remark) = tile_data["remark"].as_str() {
+            remark.get_or_insert_with(|| tile_remark.to_string());
+        }
+        // Elements crossing the border of two tiles come with both of them
+        if let Value::Array(tile_elements) = tile_data["elements"].take() {
+            for element in tile_elements {
+                let key = (
+                    element["type"].as_str().unwrap_or_default().to_string(),
+                    element["id"].as_u64().unwrap_or_default(),
+                );
+                if seen.insert(key) {
+                    elements.push(element);
+                }
+            }
+        }
+    }
+
+    let mut data: Value = json!({ "elements": elements });
+    if let Some(remark) = remark {
+        data["remark"] = json!(remark);
+    }
+
+    if let Some(save_file) = save_file {
+        let mut file: File = File::create(save_file)?;
+        serde_json::to_writer(&mut file, &data)?;
+        file.flush()?;
+        println!("API response saved to: {save_file}");
+    }
+
+    if data["elements"]
+        .as_array()
+        .map_or(0, |elements: &Vec<Value>| elements.len())
+        == 0
+    {
+        if let Some(remark) = data["remark"].as_str() {
+            // Check if the remark mentions memory or other runtime errors
+            if remark.contains("runtime error") && remark.contains("out of memory") {
+                eprintln!("{}", "Error! The query ran out of memory on the Overpass API server. Try using a smaller area.".red().bold());
+                emit_gui_error("Try using a smaller area.");
+            } else {
+                // Handle other Overpass API errors if present in the remark field
+                eprintln!("{}", format!("Error! API returned: {remark}").red().bold());
+                emit_gui_error(&format!("API returned: {remark}"));
+            }
+        } else {
+            // General case for when there are no elements and no specific remark
+            eprintln!(
+                "{}",
+                "Error! API returned no data. Please try again!"
+                    .red()
+                    .bold()
+            );
+            emit_gui_error("API returned no data. Please try again!");
+        }
+
+        if debug {
+            println!("Additional debug information: {data}");
+        }
+
+        if !is_running_with_gui() {
+            std::process::exit(1);
+        } else {
+            return Err("Data fetch failed".into());
+        }
+    }
+
+    emit_gui_progress_update(5.0, "");
+
+    Ok(data)
+}
+
+/// Fetches the data of one tile from the cache or the Overpass API
+fn fetch_tile(
+    bbox: &LLBBox,
+    download_method: &str,
+    overpass_url: Option<&str>,
+    cache: CacheMode,
+    filter: Option<&QueryFilter>,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    let query: String = overpass_query(bbox, filter);
+
+    let cache_path = overpass_cache_path(bbox, &query);
+    let cached: Option<String> = match cache {
+        CacheMode::Use => fs::read_to_string(&cache_path).ok(),
+        CacheMode::Refresh | CacheMode::Off => None,
+    };
+    let from_cache = cached.is_some();
+    let response: String = match cached {
+        Some(response) => {
+            println!("Using cached OSM data from {}", cache_path.display());
+            response
+        }
+        None => download_from_servers(&api_servers(overpass_url), &query, download_method)?,
+    };
+    let data: Value = serde_json::from_str(&response)?;
+
+    // Only responses with data are cached, so that failed downloads are retried
+    let has_elements = data["elements"]
+        .as_array()
+        .is_some_and(|elements| !elements.is_empty());
+    if !from_cache && cache != CacheMode::Off && has_elements {
+        if let Err(e) = fs::create_dir_all(OVERPASS_CACHE_DIR)
+            .and_then(|_| fs::write(&cache_path, response.as_bytes()))
+        {
+            eprintln!("Warning: Failed to cache OSM data: {e}");
+        }
+    }
+
+    Ok(data)
+}
+
+/// Generates the Overpass API query for a bounding box
+fn overpass_query(bbox: &LLBBox, filter: Option<&QueryFilter>) -> String {
+    format!(
         r#"[out:json][timeout:360][bbox:{},{},{},{}];
     (
-        nwr["building"];
-        nwr["highway"];
-        nwr["landuse"];
-        nwr["natural"];
-        nwr["leisure"];
-        nwr["water"];
-        nwr["waterway"];
-        nwr["amenity"];
-        nwr["tourism"];
-        nwr["bridge"];
-        nwr["railway"];
-        nwr["barrier"];
-        nwr["entrance"];
-        nwr["door"];
-        way;
+        {}
     )->.relsinbbox;
     (
         way(r.relsinbbox);
@@ -145,86 +402,160 @@ pub fn fetch_data_from_overpass(
         bbox.min().lng(),
         bbox.max().lat(),
         bbox.max().lng(),
-    );
-
-    {
-        // Fetch data from Overpass API
-        let mut attempt = 0;
-        let max_attempts = 1;
-        let response: String = loop {
-            println!("Downloading from {url} with method {download_method}...");
-            let result = match download_method {
-                "requests" => download_with_reqwest(url, &query),
-                "curl" => download_with_curl(url, &query).map_err(|e| e.into()),
-                "wget" => download_with_wget(url, &query).map_err(|e| e.into()),
-                _ => download_with_reqwest(url, &query), // Default to requests
-            };
+        query_statements(filter).join("\n        "),
+    )
+}
 
-            match result {
-                Ok(response) => break response,
-                Err(error) => {
-                    if attempt >= max_attempts {
-                        return Err(error);
-                    }
+/// Returns the statements selecting the elements to download
+fn query_statements(filter: Option<&QueryFilter>) -> Vec<String> {
+    let exclude: &[String] = filter.map_or(&[], |filter| &filter.exclude);
+    let excluded = |key: &str| exclude.iter().any(|excluded| excluded == key);
+    let conditions: String = exclude.iter().map(|key| format!("[!\"{key}\"]")).collect();
 
-                    println!("Request failed. Switching to fallback url...");
-                    url = fallback_api_servers
-                        .choose(&mut rand::thread_rng())
-                        .unwrap();
-                    attempt += 1;
-                }
-            }
-        };
+    if let Some(filter) = filter.filter(|filter| !filter.include.is_empty()) {
+        return filter
+            .include
+            .iter()
+            .map(|selector| format!("nwr{selector}{conditions};"))
+            .collect();
+    }
 
-        if let Some(save_file) = save_file {
-            let mut file: File = File::create(save_file)?;
-            file.write_all(response.as_bytes())?;
-            println!("API response saved to: {save_file}");
-        }
+    let mut statements: Vec<String> = QUERY_KEYS
+        .iter()
+        .filter(|key| !excluded(key))
+        .map(|key| format!("nwr[\"{key}\"]{conditions};"))
+        .collect();
+    if !excluded("place") {
+        statements.push(format!("rel[\"place\"]{conditions};"));
+    }
+    if !excluded("route") {
+        statements.push(format!(
+            "rel[\"route\"~\"^(hiking|foot|walking|bicycle|mtb|piste|bus|trolleybus|tram|subway|light_rail)$\"]{conditions};"
+        ));
+    }
+    statements.push(format!("way{conditions};"));
+    statements
+}
 
-        let data: Value = serde_json::from_str(&response)?;
+/// Splits a bounding box into a grid of tiles no wider or taller than the given span in degrees
+fn split_bbox(bbox: &LLBBox, max_span: f64) -> Vec<LLBBox> {
+    let (min, max) = (bbox.min(), bbox.max());
+    let rows = ((max.lat() - min.lat()) / max_span).ceil().max(1.0) as usize;
+    let columns = ((max.lng() - min.lng()) / max_span).ceil().max(1.0) as usize;
+    let lat_step = (max.lat() - min.lat()) / rows as f64;
+    let lng_step = (max.lng() - min.lng()) / columns as f64;
 
-        if data["elements"]
-            .as_array()
-            .map_or(0, |elements: &Vec<Value>| elements.len())
-            == 0
-        {
-            if let Some(remark) = data["remark"].as_str() {
-                // Check if the remark mentions memory or other runtime errors
-                if remark.contains("runtime error") && remark.contains("out of memory") {
-                    eprintln!("{}", "Error! The query ran out of memory on the Overpass API server. Try using a smaller area.".red().bold());
-                    emit_gui_error("Try using a smaller area.");
-                } else {
-                    // Handle other Overpass API errors if present in the remark field
-                    eprintln!("{}", format!("Error! API returned: {remark}").red().bold());
-                    emit_gui_error(&format!("API returned: {remark}"));
-                }
+    let mut tiles = Vec::with_capacity(rows * columns);
+    for row in 0..rows {
+        for column in 0..columns {
+            // The outer edges are taken as they are, so that rounding does not shrink the area
+            let south = min.lat() + lat_step * row as f64;
+            let north = if row + 1 == rows {
+                max.lat()
             } else {
-                // General case for when there are no elements and no specific remark
-                eprintln!(
-                    "{}",
-                    "Error! API returned no data. Please try again!"
-                        .red()
-                        .bold()
-                );
-                emit_gui_error("API returned no data. Please try again!");
+                south + lat_step
+            };
+            let west = min.lng() + lng_step * column as f64;
+            let east = if column + 1 == columns {
+                max.lng()
+            } else {
+                west + lng_step
+            };
+            if let Ok(tile) = LLBBox::new(south, west, north, east) {
+                tiles.push(tile);
             }
+        }
+    }
+    tiles
+}
 
-            if debug {
-                println!("Additional debug information: {data}");
-            }
+/// Returns the cache file of a query, named after its bounding box and a hash of the query
+fn overpass_cache_path(bbox: &LLBBox, query: &str) -> PathBuf {
+    let mut hasher = FnvHasher::default();
+    hasher.write(query.as_bytes());
+    Path::new(OVERPASS_CACHE_DIR).join(format!(
+        "overpass_{:.5}_{:.5}_{:.5}_{:.5}_{:016x}.json",
+        bbox.min().lat(),
+        bbox.min().lng(),
+        bbox.max().lat(),
+        bbox.max().lng(),
+        hasher.finish()
+    ))
+}
 
-            if !is_running_with_gui() {
-                std::process::exit(1);
-            } else {
-                return Err("Data fetch failed".into());
+/// Returns the servers to download from in the order to try them: the given one first, then
+/// the public servers in random order and then the fallback servers
+fn api_servers(overpass_url: Option<&str>) -> Vec<&str> {
+    let mut public: Vec<&str> = PUBLIC_API_SERVERS.to_vec();
+    public.shuffle(&mut rand::thread_rng());
+    overpass_url
+        .into_iter()
+        .chain(public)
+        .chain(FALLBACK_API_SERVERS)
+        .collect()
+}
+
+/// Returns how long to wait before the given retry of a busy server
+fn backoff_delay(retry: u32, retry_after: Option<Duration>) -> Duration {
+    retry_after
+        .unwrap_or_else(|| BASE_BACKOFF.saturating_mul(1 << retry.min(16)))
+        .min(MAX_BACKOFF)
+}
+
+/// Downloads the query from the first server that answers, retrying busy servers with growing
+/// waits before moving on to the next one
+fn download_from_servers(
+    servers: &[&str],
+    query: &str,
+    download_method: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut last_error: Option<Box<dyn std::error::Error>> = None;
+    for url in servers {
+        for retry in 0..=MAX_BUSY_RETRIES {
+            println!("Downloading from {url} with method {download_method}...");
+            let result = match download_method {
+                "requests" => download_with_reqwest(url, query),
+                "curl" => download_with_curl(url, query).map_err(|e| e.into()),
+                "wget" => download_with_wget(url, query).map_err(|e| e.into()),
+                _ => download_with_reqwest(url, query), // Default to requests
+            };
+
+            match result {
+                Ok(response) => return Ok(response),
+                Err(error) => {
+                    let busy = error.downcast_ref::<ServerBusy>();
+                    let retry_after = busy.map(|busy| busy.retry_after);
+                    last_error = Some(error);
+                    match retry_after {
+                        Some(retry_after) if retry < MAX_BUSY_RETRIES => {
+                            let delay = backoff_delay(retry, retry_after);
+                            println!(
+                                "{}",
+                                format!(
+                                    "Server busy or rate limited, retrying in {} seconds...",
+                                    delay.as_secs()
+                                )
+                                .yellow()
+                            );
+                            thread::sleep(delay);
+                        }
+                        _ => break,
+                    }
+                }
             }
         }
+        println!("Request failed. Switching to next server...");
+    }
 
-        emit_gui_progress_update(5.0, "");
-
-        Ok(data)
+    let error = last_error.unwrap_or_else(|| "No Overpass API server to download from".into());
+    if let Some(busy) = error.downcast_ref::<ServerBusy>() {
+        let message = format!(
+            "{busy}. The servers are overloaded or limit requests from your address, wait a few minutes before trying again or use --overpass-url with another server."
+        );
+        eprintln!("{}", format!("Error! {message}").red().bold());
+        emit_gui_error(&message);
     }
+    Err(error)
 }
 
 /// Fetches a short area name using Nominatim for the given lat/lon
@@ -259,3 +590,154 @@ pub fn fetch_area_name(lat: f64, lon: f64) -> Result<Option<String>, Box<dyn std
 
     Ok(None)
 }
+
+/// Looks up a place by name with Nominatim, returning its bounding box and full name
+pub fn geocode_place(place: &str) -> Result<(LLBBox, String), Box<dyn std::error::Error>> {
+    let client = Client::builder().timeout(Duration::from_secs(20)).build()?;
+    let resp = client
+        .get("https://nominatim.openstreetmap.org/search")
+        .query(&[("format", "jsonv2"), ("limit", "1"), ("q", place)])
+        .header("User-Agent", "arnis-rust")
+        .send()?;
+    if !resp.status().is_success() {
+        return Err(format!("Nominatim returned {}", resp.status()).into());
+    }
+    parse_search_result(&resp.json()?)
+        .ok_or_else(|| format!("No place found for \"{place}\"").into())
+}
+
+/// Reads the bounding box and name of the first result of a Nominatim search, whose bounding
+/// box is given as south, north, west and east
+fn parse_search_result(json: &Value) -> Option<(LLBBox, String)> {
+    let result = json.as_array()?.first()?;
+    let edges: Vec<f64> = result["boundingbox"]
+        .as_array()?
+        .iter()
+        .filter_map(|edge| edge.as_str()?.parse().ok())
+        .collect();
+    let [south, north, west, east] = edges[..] else {
+        return None;
+    };
+    let bbox = LLBBox::new(south, west, north, east).ok()?;
+    let name = result["display_name"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    Some((bbox, name))
+}
+
+/// Returns the approximate area of a bounding box in square kilometers
+pub fn bbox_area_km2(bbox: &LLBBox) -> f64 {
+    const KM_PER_DEGREE: f64 = 111.32;
+    let center_lat = (bbox.min().lat() + bbox.max().lat()) / 2.0;
+    let height = (bbox.max().lat() - bbox.min().lat()) * KM_PER_DEGREE;
+    let width =
+        (bbox.max().lng() - bbox.min().lng()) * KM_PER_DEGREE * center_lat.to_radians().cos();
+    height * width
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_search_result() {
+        let json = json!([{
+            "display_name": "Bamberg, Bayern, Deutschland",
+            "boundingbox": ["49.8383", "49.9367", "10.8082", "10.9650"]
+        }]);
+        let (bbox, name) = parse_search_result(&json).unwrap();
+        assert_eq!(name, "Bamberg, Bayern, Deutschland");
+        assert_eq!(
+            bbox,
+            LLBBox::new(49.8383, 10.8082, 49.9367, 10.9650).unwrap()
+        );
+        // About 11 by 11 kilometers
+        let area = bbox_area_km2(&bbox);
+        assert!((100.0..140.0).contains(&area), "{area}");
+
+        assert!(parse_search_result(&json!([])).is_none());
+    }
+
+    #[test]
+    fn test_servers_and_backoff() {
+        let servers = api_servers(Some("http://localhost:12345/api/interpreter"));
+        assert_eq!(servers[0], "http://localhost:12345/api/interpreter");
+        assert_eq!(
+            servers.len(),
+            1 + PUBLIC_API_SERVERS.len() + FALLBACK_API_SERVERS.len()
+        );
+        assert_eq!(servers.last(), FALLBACK_API_SERVERS.last());
+        assert_eq!(api_servers(None).len(), servers.len() - 1);
+
+        assert_eq!(backoff_delay(0, None), BASE_BACKOFF);
+        assert_eq!(backoff_delay(2, None), BASE_BACKOFF * 4);
+        assert_eq!(backoff_delay(30, None), MAX_BACKOFF);
+        // The wait the server asks for takes precedence, within the limit
+        assert_eq!(
+            backoff_delay(0, Some(Duration::from_secs(30))),
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            backoff_delay(0, Some(Duration::from_secs(3600))),
+            MAX_BACKOFF
+        );
+    }
+
+    #[test]
+    fn test_query_filter_selects_statements() {
+        let bbox = LLBBox::new(54.627053, 9.927928, 54.634902, 9.937563).unwrap();
+        let query = overpass_query(&bbox, None);
+        assert!(query.contains("        nwr[\"building\"];\n        nwr[\"highway\"];"));
+        assert!(query.contains("        way;\n    )->.relsinbbox;"));
+
+        let only = QueryFilter::from_str("building, highway=primary").unwrap();
+        assert_eq!(
+            query_statements(Some(&only)),
+            vec!["nwr[\"building\"];", "nwr[\"highway\"=\"primary\"];"]
+        );
+
+        let without = QueryFilter::from_str("-landuse").unwrap();
+        let statements = query_statements(Some(&without));
+        assert!(!statements.iter().any(|s| s.starts_with("nwr[\"landuse\"]")));
+        assert!(statements.contains(&"nwr[\"building\"][!\"landuse\"];".to_string()));
+        assert_eq!(statements.last().unwrap(), "way[!\"landuse\"];");
+
+        assert!(QueryFilter::from_str("building\"];out;").is_err());
+        assert!(QueryFilter::from_str(" , ").is_err());
+    }
+
+    #[test]
+    fn test_split_bbox_covers_area() {
+        let bbox = LLBBox::new(48.0, 11.0, 48.25, 11.1).unwrap();
+        let tiles = split_bbox(&bbox, MAX_TILE_SPAN);
+        assert_eq!(tiles.len(), 3);
+        assert_eq!(tiles[0].min(), bbox.min());
+        assert_eq!(tiles[2].max(), bbox.max());
+        // Neighbouring tiles share their edges
+        assert_eq!(tiles[0].max().lat(), tiles[1].min().lat());
+        assert!(tiles
+            .iter()
+            .all(|tile| tile.max().lat() - tile.min().lat() <= MAX_TILE_SPAN));
+
+        let small = LLBBox::new(54.627053, 9.927928, 54.634902, 9.937563).unwrap();
+        assert_eq!(split_bbox(&small, MAX_TILE_SPAN), vec![small]);
+    }
+
+    #[test]
+    fn test_overpass_cache_path_follows_bbox_and_query() {
+        let bbox = LLBBox::new(54.627053, 9.927928, 54.634902, 9.937563).unwrap();
+        let other = LLBBox::new(54.627053, 9.927928, 54.634902, 9.94).unwrap();
+        let path = overpass_cache_path(&bbox, "nwr[\"building\"];");
+
+        assert_eq!(path, overpass_cache_path(&bbox, "nwr[\"building\"];"));
+        assert_ne!(path, overpass_cache_path(&bbox, "nwr[\"highway\"];"));
+        assert_ne!(path, overpass_cache_path(&other, "nwr[\"building\"];"));
+        assert!(path.starts_with(OVERPASS_CACHE_DIR));
+        assert!(path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("overpass_54.62705_9.92793_54.63490_9.93756_"));
+    }
+}
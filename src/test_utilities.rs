@@ -1,18 +1,29 @@
+use crate::args::Args;
 use crate::coordinate_system::cartesian::XZBBox;
 use crate::coordinate_system::geographic::LLBBox;
+use crate::coordinate_system::transformation::Projection;
 use crate::osm_parser;
 use crate::osm_parser::ProcessedElement;
 use crate::retrieve_data;
+use clap::Parser;
 
 // this is copied from main.rs
 pub fn generate_example(llbbox: LLBBox) -> (XZBBox, Vec<ProcessedElement>) {
     // Fetch data
-    let raw_data: serde_json::Value =
-        retrieve_data::fetch_data_from_overpass(llbbox, false, "requests", None)
-            .expect("Failed to fetch data");
+    let raw_data: serde_json::Value = retrieve_data::fetch_data_from_overpass(
+        llbbox,
+        false,
+        "requests",
+        None,
+        None,
+        retrieve_data::CacheMode::Off,
+        None,
+    )
+    .expect("Failed to fetch data");
 
     // Parse raw data
-    let (mut parsed_elements, xzbbox) = osm_parser::parse_osm_data(raw_data, llbbox, 1.0, false);
+    let (mut parsed_elements, xzbbox) =
+        osm_parser::parse_osm_data(raw_data, llbbox, 1.0, Projection::default(), 0.0, false);
     parsed_elements
         .sort_by_key(|element: &osm_parser::ProcessedElement| osm_parser::get_priority(element));
 
@@ -27,3 +38,19 @@ pub fn generate_default_example() -> (XZBBox, Vec<ProcessedElement>) {
 pub fn get_llbbox_arnis() -> LLBBox {
     LLBBox::new(54.627053, 9.927928, 54.634902, 9.937563).unwrap()
 }
+
+/// Creates an empty Minecraft world in a temporary directory
+pub fn minecraft_tmpdir() -> tempfile::TempDir {
+    let tmpdir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(tmpdir.path().join("region")).unwrap();
+    tmpdir
+}
+
+/// Parses the arguments of generating the Arnis example into the given world, followed by
+/// further options
+pub fn example_args(world: &tempfile::TempDir, options: &[&str]) -> Args {
+    let path = world.path().to_str().unwrap();
+    let bbox = "54.627053,9.927928,54.634902,9.937563";
+    let argv = ["arnis", "--path", path, "--bbox", bbox];
+    Args::parse_from(argv.iter().chain(options))
+}
@@ -0,0 +1,136 @@
+use crate::coordinate_system::geographic::LLBBox;
+use crate::ground_cover::GroundCover;
+
+/// Arid regions as (min_lat, min_lng, max_lat, max_lng), roughly following the major deserts
+const ARID_REGIONS: [(f64, f64, f64, f64); 9] = [
+    // Sahara
+    (15.0, -17.0, 32.0, 33.0),
+    // Arabian Peninsula and the Levant deserts
+    (12.0, 34.0, 32.0, 60.0),
+    // Iranian Plateau
+    (25.0, 50.0, 36.0, 62.0),
+    // Thar
+    (24.0, 68.0, 30.0, 74.0),
+    // Taklamakan and Gobi
+    (36.0, 75.0, 45.0, 110.0),
+    // Sonoran, Mojave and Chihuahuan
+    (26.0, -118.0, 37.0, -103.0),
+    // Atacama
+    (-30.0, -72.0, -17.0, -68.0),
+    // Namib and Kalahari
+    (-28.0, 12.0, -17.0, 25.0),
+    // Australian Outback
+    (-30.0, 118.0, -19.0, 145.0),
+];
+
+/// Overall look of the generated world
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Theme {
+    /// Detect the theme from the location of the bounding box
+    #[default]
+    Auto,
+    Temperate,
+    /// Sandy ground, lighter asphalt and sparse desert vegetation
+    Arid,
+}
+
+impl Theme {
+    /// Resolves `Auto` to a concrete theme based on the climate at the bounding box
+    pub fn resolve(self, bbox: &LLBBox) -> Theme {
+        match self {
+            Theme::Auto if is_arid_region(bbox) => Theme::Arid,
+            Theme::Auto => Theme::Temperate,
+            theme => theme,
+        }
+    }
+}
+
+/// Climate of the generated world, setting its biome and weather to match the theme
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Climate {
+    #[default]
+    Temperate,
+    /// Desert biome without rain
+    Arid,
+    /// Snowy biome where it keeps snowing
+    Snowy,
+}
+
+impl Climate {
+    /// Returns the climate of a resolved theme, snow covered ground makes it snowy
+    pub fn new(theme: Theme, ground_cover: Option<GroundCover>) -> Climate {
+        match (theme, ground_cover) {
+            (_, Some(GroundCover::Snow)) => Climate::Snowy,
+            (Theme::Arid, _) => Climate::Arid,
+            _ => Climate::Temperate,
+        }
+    }
+
+    /// Java Edition biome of the whole world
+    pub fn java_biome(self) -> &'static str {
+        match self {
+            Climate::Temperate => "minecraft:plains",
+            Climate::Arid => "minecraft:desert",
+            Climate::Snowy => "minecraft:snowy_plains",
+        }
+    }
+
+    /// Bedrock Edition biome id of the whole world
+    #[allow(dead_code)]
+    pub fn bedrock_biome_id(self) -> i32 {
+        match self {
+            Climate::Temperate => 1,
+            Climate::Arid => 2,
+            Climate::Snowy => 12,
+        }
+    }
+
+    /// Returns whether it always rains (or snows), None keeps the normal weather cycle
+    pub fn fixed_rain(self) -> Option<bool> {
+        match self {
+            Climate::Temperate => None,
+            Climate::Arid => Some(false),
+            Climate::Snowy => Some(true),
+        }
+    }
+}
+
+/// Returns true if the center of the bounding box lies in a known arid region
+fn is_arid_region(bbox: &LLBBox) -> bool {
+    let lat = (bbox.min().lat() + bbox.max().lat()) / 2.0;
+    let lng = (bbox.min().lng() + bbox.max().lng()) / 2.0;
+
+    ARID_REGIONS
+        .iter()
+        .any(|&(min_lat, min_lng, max_lat, max_lng)| {
+            (min_lat..=max_lat).contains(&lat) && (min_lng..=max_lng).contains(&lng)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_theme() {
+        // Riyadh
+        let riyadh = LLBBox::new(24.70, 46.67, 24.72, 46.69).unwrap();
+        assert_eq!(Theme::Auto.resolve(&riyadh), Theme::Arid);
+        assert_eq!(Theme::Temperate.resolve(&riyadh), Theme::Temperate);
+
+        // Berlin
+        let berlin = LLBBox::new(52.51, 13.39, 52.52, 13.40).unwrap();
+        assert_eq!(Theme::Auto.resolve(&berlin), Theme::Temperate);
+        assert_eq!(Theme::Arid.resolve(&berlin), Theme::Arid);
+    }
+
+    #[test]
+    fn test_climate() {
+        assert_eq!(Climate::new(Theme::Temperate, None), Climate::Temperate);
+        assert_eq!(Climate::new(Theme::Arid, None), Climate::Arid);
+        assert_eq!(
+            Climate::new(Theme::Arid, Some(GroundCover::Snow)),
+            Climate::Snowy
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Chunk-aligned tiles of the world, which are generated in parallel with `--parallel`.
+//!
+//! Every tile is generated by its own WorldEditor, which only accepts blocks inside the tile.
+//! Elements crossing a tile border are generated once for every tile they touch, each time
+//! placing the blocks inside that tile. Every block thus belongs to exactly one tile and the
+//! world does not depend on the order in which the tiles finish.
+
+use crate::osm_parser::ProcessedElement;
+
+/// Width of a tile in chunks
+pub const TILE_CHUNKS: i32 = 16;
+/// Distance beyond their nodes up to which elements place blocks at scale 1, such as the
+/// width of roads or the crowns of trees
+pub const ELEMENT_MARGIN: f64 = 16.0;
+
+/// A square of whole chunks, with inclusive block coordinates
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tile {
+    pub min_x: i32,
+    pub min_z: i32,
+    pub max_x: i32,
+    pub max_z: i32,
+}
+
+impl Tile {
+    #[inline]
+    pub fn contains(&self, x: i32, z: i32) -> bool {
+        x >= self.min_x && x <= self.max_x && z >= self.min_z && z <= self.max_z
+    }
+
+    /// Returns true if the chunk at the given chunk coordinates lies inside the tile
+    pub fn contains_chunk(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        self.contains(chunk_x << 4, chunk_z << 4)
+    }
+
+    fn intersects(&self, (min_x, min_z, max_x, max_z): (i32, i32, i32, i32)) -> bool {
+        min_x <= self.max_x && max_x >= self.min_x && min_z <= self.max_z && max_z >= self.min_z
+    }
+}
+
+/// Splits the area between the given corners into tiles aligned to chunk borders, row by row
+pub fn partition(min: (i32, i32), max: (i32, i32), tile_chunks: i32) -> Vec<Tile> {
+    let size = tile_chunks * 16;
+    let mut tiles = Vec::new();
+    for tile_z in min.1.div_euclid(size)..=max.1.div_euclid(size) {
+        for tile_x in min.0.div_euclid(size)..=max.0.div_euclid(size) {
+            tiles.push(Tile {
+                min_x: tile_x * size,
+                min_z: tile_z * size,
+                max_x: tile_x * size + size - 1,
+                max_z: tile_z * size + size - 1,
+            });
+        }
+    }
+    tiles
+}
+
+/// Returns the x/z bounds of the nodes of an element, None if it has no nodes
+fn element_bounds(element: &ProcessedElement) -> Option<(i32, i32, i32, i32)> {
+    let points: Vec<(i32, i32)> = match element {
+        ProcessedElement::Node(node) => vec![(node.x, node.z)],
+        ProcessedElement::Way(way) => way.nodes.iter().map(|n| (n.x, n.z)).collect(),
+        ProcessedElement::Relation(rel) => rel
+            .members
+            .iter()
+            .flat_map(|member| member.way.nodes.iter().map(|n| (n.x, n.z)))
+            .collect(),
+    };
+
+    let min_x = points.iter().map(|p| p.0).min()?;
+    let min_z = points.iter().map(|p| p.1).min()?;
+    let max_x = points.iter().map(|p| p.0).max()?;
+    let max_z = points.iter().map(|p| p.1).max()?;
+    Some((min_x, min_z, max_x, max_z))
+}
+
+/// Returns the indices of the elements reaching into each tile, keeping the order of the
+/// elements. Elements without nodes are given to every tile.
+pub fn assign_elements(
+    elements: &[ProcessedElement],
+    tiles: &[Tile],
+    margin: i32,
+) -> Vec<Vec<usize>> {
+    let mut assignments: Vec<Vec<usize>> = vec![Vec::new(); tiles.len()];
+    for (i, element) in elements.iter().enumerate() {
+        let bounds = element_bounds(element).map(|(min_x, min_z, max_x, max_z)| {
+            (
+                min_x - margin,
+                min_z - margin,
+                max_x + margin,
+                max_z + margin,
+            )
+        });
+        for (tile, assigned) in tiles.iter().zip(assignments.iter_mut()) {
+            if bounds.is_none_or(|bounds| tile.intersects(bounds)) {
+                assigned.push(i);
+            }
+        }
+    }
+    assignments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osm_parser::ProcessedNode;
+    use std::collections::HashMap;
+
+    fn node(id: u64, x: i32, z: i32) -> ProcessedElement {
+        ProcessedElement::Node(ProcessedNode {
+            id,
+            tags: HashMap::new(),
+            x,
+            z,
+        })
+    }
+
+    #[test]
+    fn test_elements_are_assigned_to_touching_tiles() {
+        let tiles = partition((0, 0), (300, 100), 16);
+        assert_eq!(tiles.len(), 2);
+        assert_eq!(tiles[1].min_x, 256);
+
+        let elements = vec![node(1, 10, 10), node(2, 250, 10), node(3, 290, 10)];
+        let assignments = assign_elements(&elements, &tiles, 16);
+
+        assert_eq!(assignments[0], vec![0, 1]);
+        assert_eq!(assignments[1], vec![1, 2]);
+    }
+}
@@ -0,0 +1,19 @@
+/// Time of day the generated world is set to
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimeOfDay {
+    /// Keep the time and daylight cycle of the world
+    #[default]
+    Day,
+    /// Midnight with the daylight cycle stopped, with lit windows, shop fronts and lamp posts
+    Night,
+}
+
+impl TimeOfDay {
+    /// Returns the world time in ticks the world is fixed at, None if the time is left alone
+    pub fn fixed_world_time(self) -> Option<i64> {
+        match self {
+            TimeOfDay::Day => None,
+            TimeOfDay::Night => Some(18000),
+        }
+    }
+}
@@ -2,12 +2,27 @@ use colored::Colorize;
 use reqwest::blocking::Client;
 use reqwest::{Error as ReqwestError, StatusCode};
 use semver::Version;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
 use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 /// URL to the remote Cargo.toml file to check for the latest version
 const REMOTE_CARGO_TOML_URL: &str =
     "https://raw.githubusercontent.com/louis-e/arnis/main/Cargo.toml";
 
+/// URL of the GitHub API describing the latest release
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/louis-e/arnis/releases/latest";
+
+/// Endings of release assets that are archives, installers or signatures rather than binaries
+const NON_EXECUTABLE_ASSETS: [&str; 11] = [
+    ".zip", ".tar.gz", ".tgz", ".tar.xz", ".dmg", ".pkg", ".msi", ".deb", ".rpm", ".sha256", ".sig",
+];
+
+/// Environment variable that disables the version check at startup
+pub const NO_UPDATE_CHECK_ENV: &str = "ARNIS_NO_UPDATE_CHECK";
+
 /// Fetches the latest version from the remote Cargo.toml file and compares it with the local version.
 /// Returns `true` if a newer version is available, `false` otherwise.
 pub fn check_for_updates() -> Result<bool, Box<dyn Error>> {
@@ -40,6 +55,7 @@ pub fn check_for_updates() -> Result<bool, Box<dyn Error>> {
                     local_version,
                     remote_version
                 );
+                println!("Run `arnis self-update` to download it.");
                 return Ok(true); // Newer version is available
             }
 
@@ -52,6 +68,172 @@ pub fn check_for_updates() -> Result<bool, Box<dyn Error>> {
     }
 }
 
+/// Returns true if the version check at startup was disabled through the environment
+pub fn is_update_check_disabled() -> bool {
+    std::env::var(NO_UPDATE_CHECK_ENV).is_ok_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Downloads the binary of the latest release for this platform and replaces the running
+/// executable with it once its SHA-256 checksum matches the published one. The previous binary
+/// is kept next to it with an `.old` extension.
+pub fn self_update() -> Result<(), Box<dyn Error>> {
+    let client: Client = Client::new();
+
+    let release: Value = serde_json::from_str(
+        &client
+            .get(LATEST_RELEASE_URL)
+            .header("User-Agent", "arnis-client")
+            .send()?
+            .error_for_status()?
+            .text()?,
+    )?;
+
+    let tag = release["tag_name"].as_str().ok_or("Release has no tag")?;
+    let latest_version: Version = Version::parse(tag.trim_start_matches('v'))?;
+    let local_version: Version = Version::parse(env!("CARGO_PKG_VERSION"))?;
+    if latest_version <= local_version {
+        println!("Arnis {local_version} is already the latest version.");
+        return Ok(());
+    }
+
+    let (os, arch) = (std::env::consts::OS, std::env::consts::ARCH);
+    let asset = platform_asset(&release, os, arch)
+        .ok_or_else(|| format!("No release binary available for {os}"))?;
+    let asset_url = asset["browser_download_url"]
+        .as_str()
+        .ok_or("Release binary has no download URL")?;
+    let expected_checksum = published_sha256(&client, &release, asset)?;
+
+    println!("Downloading Arnis {latest_version}...");
+    let binary = client
+        .get(asset_url)
+        .header("User-Agent", "arnis-client")
+        .send()?
+        .error_for_status()?
+        .bytes()?;
+
+    let checksum = sha256_hex(&binary);
+    if checksum != expected_checksum {
+        return Err(format!(
+            "Checksum of the downloaded binary does not match: expected {expected_checksum}, got {checksum}"
+        )
+        .into());
+    }
+
+    replace_executable(&std::env::current_exe()?, &binary)?;
+    println!(
+        "{} {} -> {}",
+        "Updated Arnis:".green().bold(),
+        local_version,
+        latest_version
+    );
+
+    Ok(())
+}
+
+/// Returns the asset of a release holding the binary for the given platform
+fn platform_asset<'r>(release: &'r Value, os: &str, arch: &str) -> Option<&'r Value> {
+    release["assets"].as_array()?.iter().find(|asset| {
+        asset["name"]
+            .as_str()
+            .is_some_and(|name| is_asset_for_platform(&name.to_lowercase(), os, arch))
+    })
+}
+
+/// Returns true if a lowercase release asset name is the executable for the given platform
+fn is_asset_for_platform(name: &str, os: &str, arch: &str) -> bool {
+    let is_executable = match os {
+        "windows" => name.ends_with(".exe"),
+        _ => !NON_EXECUTABLE_ASSETS
+            .iter()
+            .any(|ending| name.ends_with(ending)),
+    };
+    let os_matches = match os {
+        "windows" => name.contains("windows") || name.ends_with(".exe"),
+        "macos" => name.contains("mac") || name.contains("darwin"),
+        os => name.contains(os),
+    };
+    let arch_matches = match arch {
+        "aarch64" => !name.contains("intel") && !name.contains("x86"),
+        _ => !name.contains("arm") && !name.contains("aarch64"),
+    };
+
+    is_executable && os_matches && arch_matches
+}
+
+/// Returns the SHA-256 checksum published for a release asset, either the digest GitHub lists
+/// for it or the contents of a `.sha256` file released next to it
+fn published_sha256(
+    client: &Client,
+    release: &Value,
+    asset: &Value,
+) -> Result<String, Box<dyn Error>> {
+    if let Some(checksum) = asset["digest"].as_str().and_then(parse_sha256) {
+        return Ok(checksum);
+    }
+
+    let name = asset["name"].as_str().unwrap_or_default();
+    let checksum_url = release["assets"]
+        .as_array()
+        .and_then(|assets| {
+            assets
+                .iter()
+                .find(|other| other["name"].as_str() == Some(&format!("{name}.sha256")))
+        })
+        .and_then(|checksum_asset| checksum_asset["browser_download_url"].as_str())
+        .ok_or_else(|| format!("No checksum published for {name}, not updating"))?;
+    let checksum_file = client
+        .get(checksum_url)
+        .header("User-Agent", "arnis-client")
+        .send()?
+        .error_for_status()?
+        .text()?;
+    parse_sha256(&checksum_file).ok_or_else(|| format!("Invalid checksum file for {name}").into())
+}
+
+/// Reads a SHA-256 checksum in hex from a digest such as `sha256:<hex>` or from the first
+/// word of a checksum file, returning it in lowercase
+fn parse_sha256(text: &str) -> Option<String> {
+    let text = text.trim();
+    let hex = text
+        .strip_prefix("sha256:")
+        .unwrap_or(text)
+        .split_whitespace()
+        .next()?;
+    (hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit())).then(|| hex.to_lowercase())
+}
+
+/// Returns the SHA-256 checksum of the given bytes in lowercase hex
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Swaps the executable at the given path for the downloaded binary
+fn replace_executable(current: &Path, binary: &[u8]) -> Result<(), Box<dyn Error>> {
+    let downloaded: PathBuf = current.with_extension("new");
+    let previous: PathBuf = current.with_extension("old");
+
+    fs::write(&downloaded, binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&downloaded, fs::Permissions::from_mode(0o755))?;
+    }
+
+    // A running executable can be renamed but not overwritten on all platforms
+    let _ = fs::remove_file(&previous);
+    fs::rename(current, &previous)?;
+    if let Err(e) = fs::rename(&downloaded, current) {
+        fs::rename(&previous, current)?;
+        return Err(e.into());
+    }
+
+    Ok(())
+}
+
 /// Extracts the version from the contents of a Cargo.toml file.
 fn extract_version_from_cargo_toml(cargo_toml_contents: &str) -> Result<Version, Box<dyn Error>> {
     for line in cargo_toml_contents.lines() {
@@ -80,3 +262,68 @@ fn handle_request_error(err: ReqwestError) {
         handle_http_error(status);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_platform_asset_is_a_raw_executable() {
+        let release = json!({
+            "assets": [
+                {"name": "arnis-linux-x86_64.tar.gz"},
+                {"name": "arnis-linux-x86_64"},
+                {"name": "arnis-linux-x86_64.sha256"},
+                {"name": "arnis-macos-x86_64.dmg"},
+                {"name": "arnis-macos-aarch64"},
+                {"name": "Arnis-Windows.zip"},
+                {"name": "Arnis-Windows.exe"},
+            ]
+        });
+        let name = |os: &str, arch: &str| {
+            platform_asset(&release, os, arch).and_then(|asset| asset["name"].as_str())
+        };
+
+        assert_eq!(name("linux", "x86_64"), Some("arnis-linux-x86_64"));
+        assert_eq!(name("macos", "aarch64"), Some("arnis-macos-aarch64"));
+        assert_eq!(name("windows", "x86_64"), Some("Arnis-Windows.exe"));
+        // Only an installer image is released for Intel Macs
+        assert_eq!(name("macos", "x86_64"), None);
+        assert!(!is_asset_for_platform("arnis-linux.zip", "linux", "x86_64"));
+    }
+
+    #[test]
+    fn test_checksums() {
+        let abc = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert_eq!(sha256_hex(b"abc"), abc);
+
+        assert_eq!(parse_sha256(&format!("sha256:{abc}")).as_deref(), Some(abc));
+        let checksum_file = format!("{}  arnis-linux\n", abc.to_uppercase());
+        assert_eq!(parse_sha256(&checksum_file).as_deref(), Some(abc));
+        assert_eq!(parse_sha256("sha256:1234"), None);
+        assert_eq!(parse_sha256(""), None);
+    }
+
+    #[test]
+    fn test_replace_executable_keeps_the_previous_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let current = dir.path().join("arnis");
+        fs::write(&current, b"old binary").unwrap();
+
+        replace_executable(&current, b"new binary").unwrap();
+
+        assert_eq!(fs::read(&current).unwrap(), b"new binary");
+        assert_eq!(
+            fs::read(current.with_extension("old")).unwrap(),
+            b"old binary"
+        );
+        assert!(!current.with_extension("new").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&current).unwrap().permissions().mode();
+            assert_eq!(mode & 0o111, 0o111);
+        }
+    }
+}
@@ -3,7 +3,8 @@
 //! This module handles saving worlds in the Bedrock Edition format,
 //! producing .mcworld files that can be imported into Minecraft Bedrock.
 
-use super::common::{ChunkToModify, SectionToModify, WorldToModify};
+use super::biomes::BiomeMap;
+use super::common::{SectionToModify, WorldToModify};
 use super::WorldMetadata;
 use crate::bedrock_block_map::{
     to_bedrock_block_with_properties, BedrockBlock, BedrockBlockStateValue,
@@ -12,6 +13,7 @@ use crate::coordinate_system::cartesian::XZBBox;
 use crate::coordinate_system::geographic::LLBBox;
 use crate::ground::Ground;
 use crate::progress::emit_gui_progress_update;
+use crate::theme::Climate;
 
 use bedrockrs_level::level::db_interface::bedrock_key::ChunkKey;
 use bedrockrs_level::level::db_interface::rusty::RustyDBInterface;
@@ -123,6 +125,12 @@ pub struct BedrockWriter {
     level_name: String,
     spawn_point: Option<(i32, i32)>,
     ground: Option<Box<Ground>>,
+    /// World time the daylight cycle is stopped at, None to keep the cycle running
+    fixed_time: Option<i64>,
+    /// Climate setting the biome and weather of the world
+    climate: Climate,
+    /// Biomes of the columns matching the features on them
+    biomes: BiomeMap,
 }
 
 impl BedrockWriter {
@@ -146,9 +154,30 @@ impl BedrockWriter {
             level_name,
             spawn_point,
             ground,
+            fixed_time: None,
+            climate: Climate::Temperate,
+            biomes: BiomeMap::default(),
         }
     }
 
+    /// Stops the daylight cycle at the given world time
+    pub fn with_fixed_time(mut self, fixed_time: Option<i64>) -> Self {
+        self.fixed_time = fixed_time;
+        self
+    }
+
+    /// Sets the biome and weather of the world
+    pub fn with_climate(mut self, climate: Climate) -> Self {
+        self.climate = climate;
+        self
+    }
+
+    /// Sets the biomes of single columns
+    pub fn with_biomes(mut self, biomes: BiomeMap) -> Self {
+        self.biomes = biomes;
+        self
+    }
+
     /// Writes the world to disk
     pub fn write_world(
         &mut self,
@@ -258,7 +287,7 @@ impl BedrockWriter {
 
             // Time
             last_played: now,
-            time: 0,
+            time: self.fixed_time.unwrap_or(0),
             current_tick: 0,
 
             // Cheats and commands
@@ -277,8 +306,8 @@ impl BedrockWriter {
             platform: 2,
 
             // Game rules
-            do_daylight_cycle: true,
-            do_weather_cycle: true,
+            do_daylight_cycle: self.fixed_time.is_none(),
+            do_weather_cycle: self.climate.fixed_rain().is_none(),
             do_mob_spawning: false,
             do_mob_loot: true,
             do_tile_drops: true,
@@ -302,7 +331,11 @@ impl BedrockWriter {
             freeze_damage: true,
 
             // Weather
-            rain_level: 0.0,
+            rain_level: if self.climate.fixed_rain() == Some(true) {
+                1.0
+            } else {
+                0.0
+            },
             rain_time: 100000,
             lightning_level: 0.0,
             lightning_time: 100000,
@@ -437,7 +470,7 @@ impl BedrockWriter {
 
                 // Write Data3D (heightmap + biomes) - required for chunk to be valid
                 let data3d_key = ChunkKey::data3d(chunk_pos, Dimension::Overworld);
-                let data3d = self.create_data3d(chunk);
+                let data3d = self.create_data3d(abs_chunk_x, abs_chunk_z);
                 db.set_subchunk_raw(data3d_key, &data3d, &mut state)
                     .map_err(|e| BedrockSaveError::Database(format!("{:?}", e)))?;
 
@@ -475,8 +508,8 @@ impl BedrockWriter {
 
     /// Creates a Data3D record containing heightmap and biome data.
     ///
-    /// Format: 512 bytes heightmap (256 x i16 LE) + 28 bytes minimal biome data
-    fn create_data3d(&self, _chunk: &ChunkToModify) -> Vec<u8> {
+    /// Format: 512 bytes heightmap (256 x i16 LE) + the biomes of the first subchunk
+    fn create_data3d(&self, chunk_x: i32, chunk_z: i32) -> Vec<u8> {
         let mut buffer = Vec::with_capacity(540);
 
         // Heightmap: 256 entries (16x16) as i16 LE, fixed height of 4 for flat world
@@ -484,8 +517,12 @@ impl BedrockWriter {
             buffer.extend_from_slice(&4i16.to_le_bytes());
         }
 
-        // Minimal biome data padding (biomes will be regenerated by the game)
-        buffer.extend_from_slice(&[0u8; 28]);
+        // Biomes of the first subchunk, the 23 subchunks above repeat the one below them
+        buffer.extend(
+            self.biomes
+                .bedrock_subchunk_biomes(chunk_x, chunk_z, self.climate),
+        );
+        buffer.extend_from_slice(&[0xFF; 23]);
 
         buffer
     }
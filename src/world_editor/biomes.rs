@@ -0,0 +1,229 @@
+//! Biomes of single columns, set by the features whose surface they match so that the colour
+//! of grass and leaves and the ambient mobs fit the generated world.
+//!
+//! Columns without a biome of their own take the biome of the climate. The climate also turns
+//! the biomes of the features into their snowy variants.
+
+use crate::theme::Climate;
+use fastnbt::{LongArray, Value};
+use std::collections::HashMap;
+
+/// Number of Bedrock biome entries of a subchunk, one for every block
+const BEDROCK_SUBCHUNK_ENTRIES: usize = 4096;
+/// Bits per entry supported by the Bedrock palette storage
+const BEDROCK_BITS_PER_ENTRY: [u32; 8] = [1, 2, 3, 4, 5, 6, 8, 16];
+
+/// Biome matching the surface of a feature
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Plains,
+    Forest,
+    River,
+    Ocean,
+    Beach,
+    Desert,
+}
+
+impl Biome {
+    /// Java Edition biome in the given climate
+    pub fn java_name(self, climate: Climate) -> &'static str {
+        let snowy = climate == Climate::Snowy;
+        match self {
+            Biome::Plains if snowy => "minecraft:snowy_plains",
+            Biome::Plains => "minecraft:plains",
+            Biome::Forest if snowy => "minecraft:snowy_taiga",
+            Biome::Forest => "minecraft:forest",
+            Biome::River if snowy => "minecraft:frozen_river",
+            Biome::River => "minecraft:river",
+            Biome::Ocean if snowy => "minecraft:frozen_ocean",
+            Biome::Ocean => "minecraft:ocean",
+            Biome::Beach if snowy => "minecraft:snowy_beach",
+            Biome::Beach => "minecraft:beach",
+            Biome::Desert => "minecraft:desert",
+        }
+    }
+
+    /// Bedrock Edition biome id in the given climate
+    #[allow(dead_code)]
+    pub fn bedrock_id(self, climate: Climate) -> i32 {
+        let snowy = climate == Climate::Snowy;
+        match self {
+            Biome::Plains if snowy => 12,
+            Biome::Plains => 1,
+            Biome::Forest if snowy => 30,
+            Biome::Forest => 4,
+            Biome::River if snowy => 11,
+            Biome::River => 7,
+            Biome::Ocean if snowy => 10,
+            Biome::Ocean => 0,
+            Biome::Beach if snowy => 26,
+            Biome::Beach => 16,
+            Biome::Desert => 2,
+        }
+    }
+}
+
+/// Biomes set on columns of the world
+#[derive(Clone, Default)]
+pub struct BiomeMap {
+    columns: HashMap<(i32, i32), Biome>,
+}
+
+impl BiomeMap {
+    pub fn set(&mut self, x: i32, z: i32, biome: Biome) {
+        self.columns.insert((x, z), biome);
+    }
+
+    pub fn get(&self, x: i32, z: i32) -> Option<Biome> {
+        self.columns.get(&(x, z)).copied()
+    }
+
+    pub fn extend(&mut self, other: BiomeMap) {
+        self.columns.extend(other.columns);
+    }
+
+    /// Returns the biomes compound of the sections of a Java chunk. Java biomes cover cells of
+    /// 4 by 4 columns, which take the biome of their middle column.
+    pub fn java_section_biomes(&self, chunk_x: i32, chunk_z: i32, climate: Climate) -> Value {
+        let mut palette: Vec<&'static str> = Vec::new();
+        let mut cells: Vec<usize> = Vec::with_capacity(16);
+        for cell_z in 0..4 {
+            for cell_x in 0..4 {
+                let x = chunk_x * 16 + cell_x * 4 + 2;
+                let z = chunk_z * 16 + cell_z * 4 + 2;
+                let name = self
+                    .get(x, z)
+                    .map_or(climate.java_biome(), |biome| biome.java_name(climate));
+                cells.push(palette_index(&mut palette, name));
+            }
+        }
+
+        let mut biomes = HashMap::from([(
+            "palette".to_string(),
+            Value::List(
+                palette
+                    .iter()
+                    .map(|name| Value::String(name.to_string()))
+                    .collect(),
+            ),
+        )]);
+        if palette.len() > 1 {
+            // The same cells repeat in each of the 4 layers of the section
+            let entries: Vec<usize> = cells.iter().copied().cycle().take(64).collect();
+            let bits = usize::BITS - (palette.len() - 1).leading_zeros();
+            biomes.insert(
+                "data".to_string(),
+                Value::LongArray(LongArray::new(pack_longs(&entries, bits))),
+            );
+        }
+        Value::Compound(biomes)
+    }
+
+    /// Returns the biome storage of a Bedrock subchunk with the biome of every column
+    #[allow(dead_code)]
+    pub fn bedrock_subchunk_biomes(&self, chunk_x: i32, chunk_z: i32, climate: Climate) -> Vec<u8> {
+        let mut palette: Vec<i32> = Vec::new();
+        // Bedrock orders the entries by x, then z, then y
+        let mut columns: Vec<usize> = Vec::with_capacity(256);
+        for local_x in 0..16 {
+            for local_z in 0..16 {
+                let id = self
+                    .get(chunk_x * 16 + local_x, chunk_z * 16 + local_z)
+                    .map_or(climate.bedrock_biome_id(), |biome| {
+                        biome.bedrock_id(climate)
+                    });
+                columns.push(palette_index(&mut palette, id));
+            }
+        }
+
+        let mut buffer = Vec::new();
+        if palette.len() == 1 {
+            buffer.push(1);
+            buffer.extend_from_slice(&palette[0].to_le_bytes());
+            return buffer;
+        }
+
+        let needed = usize::BITS - (palette.len() - 1).leading_zeros();
+        let bits = BEDROCK_BITS_PER_ENTRY
+            .into_iter()
+            .find(|&bits| bits >= needed)
+            .unwrap_or(16);
+        let per_word = (32 / bits) as usize;
+        let mut words = vec![0u32; BEDROCK_SUBCHUNK_ENTRIES.div_ceil(per_word)];
+        for (i, word) in words.iter_mut().enumerate() {
+            for slot in 0..per_word {
+                let entry = i * per_word + slot;
+                if entry < BEDROCK_SUBCHUNK_ENTRIES {
+                    *word |= (columns[entry / 16] as u32) << (slot as u32 * bits);
+                }
+            }
+        }
+
+        buffer.push(((bits << 1) | 1) as u8);
+        for word in words {
+            buffer.extend_from_slice(&word.to_le_bytes());
+        }
+        buffer.extend_from_slice(&(palette.len() as i32).to_le_bytes());
+        for id in palette {
+            buffer.extend_from_slice(&id.to_le_bytes());
+        }
+        buffer
+    }
+}
+
+/// Returns the index of a value in a palette, adding it if it is new
+fn palette_index<T: PartialEq>(palette: &mut Vec<T>, value: T) -> usize {
+    match palette.iter().position(|entry| *entry == value) {
+        Some(index) => index,
+        None => {
+            palette.push(value);
+            palette.len() - 1
+        }
+    }
+}
+
+/// Packs palette indices into longs as Java stores them, without entries spanning two longs
+fn pack_longs(entries: &[usize], bits: u32) -> Vec<i64> {
+    let per_long = (64 / bits) as usize;
+    let mut longs = vec![0u64; entries.len().div_ceil(per_long)];
+    for (i, &entry) in entries.iter().enumerate() {
+        longs[i / per_long] |= (entry as u64) << ((i % per_long) as u32 * bits);
+    }
+    longs.into_iter().map(|long| long as i64).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_java_biomes_follow_middle_of_cells() {
+        let mut biomes = BiomeMap::default();
+        // The middle column of the second cell of the first row
+        biomes.set(6, 2, Biome::Forest);
+        // Not the middle of its cell, so it is not sampled
+        biomes.set(0, 0, Biome::Desert);
+
+        let Value::Compound(compound) = biomes.java_section_biomes(0, 0, Climate::Temperate) else {
+            panic!("biomes are not a compound");
+        };
+        assert_eq!(
+            compound["palette"],
+            Value::List(vec![
+                Value::String("minecraft:plains".to_string()),
+                Value::String("minecraft:forest".to_string()),
+            ])
+        );
+        let Value::LongArray(data) = &compound["data"] else {
+            panic!("biomes without data");
+        };
+        // One bit per cell, the second cell of the first row of each layer is the forest
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0] as u64, 0x0002_0002_0002_0002);
+
+        assert_eq!(
+            Biome::Forest.java_name(Climate::Snowy),
+            "minecraft:snowy_taiga"
+        );
+    }
+}
@@ -232,6 +232,15 @@ impl RegionToModify {
     pub fn get_chunk(&self, x: i32, z: i32) -> Option<&ChunkToModify> {
         self.chunks.get(&(x, z))
     }
+
+    /// Returns the number of placed blocks other than air
+    pub fn block_count(&self) -> u64 {
+        self.chunks
+            .values()
+            .flat_map(|chunk| chunk.sections.values())
+            .map(|section| section.blocks.iter().filter(|&&b| b != AIR).count() as u64)
+            .sum()
+    }
 }
 
 /// The entire world being modified
@@ -251,6 +260,11 @@ impl WorldToModify {
         self.regions.get(&(x, z))
     }
 
+    /// Returns the number of blocks other than air
+    pub fn block_count(&self) -> u64 {
+        self.regions.values().map(RegionToModify::block_count).sum()
+    }
+
     #[inline]
     pub fn get_block(&self, x: i32, y: i32, z: i32) -> Option<Block> {
         let chunk_x: i32 = x >> 4;
@@ -0,0 +1,92 @@
+//! Index of the named roads and places of a world with their in-world coordinates.
+//!
+//! The index is written as `index.json` into the world folder, where `arnis locate` and
+//! external map tools can read it.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+/// File name of the index inside the world folder
+const INDEX_FILE: &str = "index.json";
+
+/// A named OSM element and the position in the world to teleport to
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub name: String,
+    /// Main tag of the element, such as "highway=residential"
+    pub category: String,
+    pub kind: String,
+    pub id: u64,
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+/// Entries of all named elements, collected while the world is generated
+#[derive(Default)]
+pub(crate) struct WorldIndex {
+    entries: Vec<IndexEntry>,
+}
+
+impl WorldIndex {
+    pub fn add(&mut self, entry: IndexEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Writes the entries sorted by name into the world directory
+    pub fn write(&self, world_dir: &Path) -> io::Result<()> {
+        let mut entries = self.entries.clone();
+        entries.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+
+        let writer = BufWriter::new(File::create(world_dir.join(INDEX_FILE))?);
+        serde_json::to_writer_pretty(writer, &entries).map_err(io::Error::other)
+    }
+}
+
+/// Returns the entries whose name contains the query, ignoring case
+pub fn locate(world_dir: &Path, query: &str) -> io::Result<Vec<IndexEntry>> {
+    let contents = fs::read_to_string(world_dir.join(INDEX_FILE))?;
+    let entries: Vec<IndexEntry> = serde_json::from_str(&contents).map_err(io::Error::other)?;
+
+    let query = query.trim().to_lowercase();
+    Ok(entries
+        .into_iter()
+        .filter(|entry| entry.name.to_lowercase().contains(&query))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, id: u64) -> IndexEntry {
+        IndexEntry {
+            name: name.to_string(),
+            category: "highway=residential".to_string(),
+            kind: "way".to_string(),
+            id,
+            x: 10,
+            y: -61,
+            z: -20,
+        }
+    }
+
+    #[test]
+    fn test_locate_matches_name_ignoring_case() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut index = WorldIndex::default();
+        index.add(entry("Main Street", 1));
+        index.add(entry("Station Road", 2));
+        index.add(entry("Old Main Street", 3));
+        index.write(dir.path()).unwrap();
+
+        let found = locate(dir.path(), "main street").unwrap();
+        assert_eq!(
+            found,
+            vec![entry("Main Street", 1), entry("Old Main Street", 3)]
+        );
+        assert!(locate(dir.path(), "harbour").unwrap().is_empty());
+    }
+}
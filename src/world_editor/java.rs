@@ -2,30 +2,74 @@
 //!
 //! This module handles saving worlds in the Java Edition Anvil (.mca) format.
 
-use super::common::{Chunk, ChunkToModify, Section};
-use super::WorldEditor;
+use super::common::{Chunk, ChunkToModify, RegionToModify, Section};
+use super::{WorldEditor, WorldFormat};
 use crate::block_definitions::GRASS_BLOCK;
+use crate::i18n::tr;
 use crate::progress::emit_gui_progress_update;
 use colored::Colorize;
-use fastanvil::Region;
+use fastanvil::{CompressionScheme, Region};
 use fastnbt::Value;
-use fnv::FnvHashMap;
+use flate2::read::GzDecoder;
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use fnv::{FnvHashMap, FnvHashSet};
 use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
+use serde::Serialize;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::Write;
+use std::io::{Read, Write};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 
 #[cfg(feature = "gui")]
 use crate::telemetry::{send_log, LogLevel};
 
+/// Name of the file listing the regions that have been fully written
+const GENERATION_STATUS_FILE: &str = "generation_status.json";
+
+/// Ticks the fixed weather lasts for, in case the weather cycle is turned back on
+const FIXED_WEATHER_TICKS: i32 = 1_000_000;
+
+/// Regions written so far, kept from the first regions flushed while generating until the
+/// world is saved
+pub(super) struct RegionWriter {
+    /// Chunks rewritten in a world generated incrementally, or `None` to write all of them
+    changed_chunks: Option<FnvHashSet<(i32, i32)>>,
+    status: Mutex<GenerationStatus>,
+    /// Blocks other than air in the regions flushed before saving
+    pub(super) blocks_written: u64,
+}
+
+/// Progress of the region writing, saved next to the world so that partially
+/// generated worlds can be explored while the remaining regions are written
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationStatus {
+    total_regions: usize,
+    completed_regions: Vec<(i32, i32)>,
+    complete: bool,
+}
+
 impl<'a> WorldEditor<'a> {
+    /// Returns the path of the final region file for the given region coordinates
+    fn region_path(&self, region_x: i32, region_z: i32) -> PathBuf {
+        self.world_dir
+            .join(format!("region/r.{}.{}.mca", region_x, region_z))
+    }
+
+    /// Returns the path a region file is written to before it is complete
+    fn partial_region_path(&self, region_x: i32, region_z: i32) -> PathBuf {
+        self.world_dir
+            .join(format!("region/r.{}.{}.mca.part", region_x, region_z))
+    }
+
     /// Creates a region file for the given region coordinates.
+    /// The file is written under a temporary name until `finish_region` is called.
     pub(super) fn create_region(&self, region_x: i32, region_z: i32) -> Region<File> {
-        let out_path = self
-            .world_dir
-            .join(format!("region/r.{}.{}.mca", region_x, region_z));
+        let out_path = self.partial_region_path(region_x, region_z);
 
         const REGION_TEMPLATE: &[u8] = include_bytes!("../../assets/minecraft/region.template");
 
@@ -44,8 +88,29 @@ impl<'a> WorldEditor<'a> {
         Region::from_stream(region_file).expect("Failed to load region")
     }
 
+    /// Opens a copy of the region file written by an earlier generation, to rewrite some of its
+    /// chunks. The copy is written under a temporary name until `finish_region` is called.
+    fn reopen_region(&self, region_x: i32, region_z: i32) -> Option<Region<File>> {
+        let path = self.region_path(region_x, region_z);
+        if !path.exists() {
+            return None;
+        }
+        let out_path = self.partial_region_path(region_x, region_z);
+        std::fs::copy(&path, &out_path).ok()?;
+        let region_file = File::options()
+            .read(true)
+            .write(true)
+            .open(&out_path)
+            .ok()?;
+        Region::from_stream(region_file).ok()
+    }
+
     /// Helper function to create a base chunk with grass blocks at Y -62
-    pub(super) fn create_base_chunk(abs_chunk_x: i32, abs_chunk_z: i32) -> (Vec<u8>, bool) {
+    pub(super) fn create_base_chunk(
+        abs_chunk_x: i32,
+        abs_chunk_z: i32,
+        biomes: &Value,
+    ) -> (Vec<u8>, bool) {
         let mut chunk = ChunkToModify::default();
 
         // Fill the bottom layer with grass blocks at Y -62
@@ -65,7 +130,7 @@ impl<'a> WorldEditor<'a> {
         };
 
         // Create the Level wrapper
-        let level_data = create_level_wrapper(&chunk_data);
+        let level_data = create_level_wrapper(&chunk_data, biomes);
 
         // Serialize the chunk with Level wrapper
         let mut ser_buffer = Vec::with_capacity(8192);
@@ -74,10 +139,12 @@ impl<'a> WorldEditor<'a> {
         (ser_buffer, true)
     }
 
-    /// Saves the world in Java Edition Anvil format.
-    pub(super) fn save_java(&mut self) {
-        println!("{} Saving world...", "[7/7]".bold());
-        emit_gui_progress_update(90.0, "Saving world...");
+    /// Writes the metadata of the world and prepares writing its regions, unless the regions
+    /// flushed before did already
+    fn start_region_writing(&mut self) {
+        if self.region_writer.is_some() {
+            return;
+        }
 
         // Save metadata with error handling
         if let Err(e) = self.save_metadata() {
@@ -87,7 +154,85 @@ impl<'a> WorldEditor<'a> {
             // Continue with world saving even if metadata fails
         }
 
-        let total_regions = self.world.regions.len() as u64;
+        let world_time = self.time_of_day.fixed_world_time();
+        let rain = self.climate.fixed_rain();
+        if world_time.is_some() || rain.is_some() {
+            if let Err(e) = self.update_level_dat(world_time, rain) {
+                eprintln!("Failed to set the time and weather of the world: {}", e);
+            }
+        }
+
+        // Worlds generated incrementally only rewrite the chunks of changed elements
+        let changed_chunks = self
+            .manifest
+            .as_ref()
+            .and_then(|manifest| manifest.changed_chunks(&self.world_dir));
+        match &changed_chunks {
+            Some(chunks) => println!("Rewriting {} chunks of changed elements", chunks.len()),
+            None if self.manifest.is_some() => {
+                println!(
+                    "No manifest of an earlier generation with these settings, writing all chunks"
+                )
+            }
+            None => {}
+        }
+
+        // Every region of the bbox is written, the ground covers all of them
+        let regions_x = (self.xzbbox.max_x() >> 9) - (self.xzbbox.min_x() >> 9) + 1;
+        let regions_z = (self.xzbbox.max_z() >> 9) - (self.xzbbox.min_z() >> 9) + 1;
+        let writer = RegionWriter {
+            changed_chunks,
+            status: Mutex::new(GenerationStatus {
+                total_regions: (regions_x * regions_z) as usize,
+                completed_regions: Vec::new(),
+                complete: false,
+            }),
+            blocks_written: 0,
+        };
+        self.write_generation_status(&writer.status);
+        self.region_writer = Some(writer);
+    }
+
+    /// Writes the regions for which `is_finished` returns true while the world is still being
+    /// generated, which frees their blocks. No blocks may be placed in them afterwards. Bedrock
+    /// worlds are only written as a whole when they are saved.
+    pub fn flush_regions(&mut self, is_finished: impl Fn(i32, i32) -> bool) {
+        if self.format != WorldFormat::JavaAnvil {
+            return;
+        }
+        let finished: Vec<(i32, i32)> = self
+            .world
+            .regions
+            .keys()
+            .copied()
+            .filter(|&(region_x, region_z)| is_finished(region_x, region_z))
+            .collect();
+        if finished.is_empty() {
+            return;
+        }
+
+        self.start_region_writing();
+        let regions: Vec<((i32, i32), RegionToModify)> = finished
+            .into_iter()
+            .map(|key| (key, self.world.regions.remove(&key).unwrap()))
+            .collect();
+        if let Some(writer) = self.region_writer.as_mut() {
+            writer.blocks_written += regions
+                .iter()
+                .map(|(_, region)| region.block_count())
+                .sum::<u64>();
+        }
+        self.write_regions(&regions, || {});
+    }
+
+    /// Saves the world in Java Edition Anvil format, writing the regions not flushed before.
+    pub(super) fn save_java(&mut self) {
+        println!("{} {}", "[7/7]".bold(), tr("step_saving_world"));
+        emit_gui_progress_update(90.0, tr("step_saving_world"));
+
+        self.start_region_writing();
+        let regions: Vec<((i32, i32), RegionToModify)> = self.world.regions.drain().collect();
+        let total_regions = regions.len() as u64;
         let save_pb = ProgressBar::new(total_regions);
         save_pb.set_style(
             ProgressStyle::default_bar()
@@ -99,21 +244,72 @@ impl<'a> WorldEditor<'a> {
         );
 
         let regions_processed = AtomicU64::new(0);
+        self.write_regions(&regions, || {
+            let regions_done = regions_processed.fetch_add(1, Ordering::SeqCst) + 1;
+
+            // Update progress at regular intervals (every ~1% or at least every 10 regions)
+            // This ensures progress is visible even with many regions
+            let update_interval = (total_regions / 10).max(1);
+            if regions_done.is_multiple_of(update_interval) || regions_done == total_regions {
+                let progress = 90.0 + (regions_done as f64 / total_regions as f64) * 9.0;
+                emit_gui_progress_update(progress, "Saving world...");
+            }
 
-        self.world
-            .regions
+            save_pb.inc(1);
+        });
+        save_pb.finish();
+
+        let status = &self.region_writer.as_ref().unwrap().status;
+        status.lock().unwrap().complete = true;
+        self.write_generation_status(status);
+
+        if let Some(manifest) = &self.manifest {
+            if let Err(e) = manifest.write(&self.world_dir) {
+                eprintln!("Failed to write chunk manifest: {}", e);
+            }
+        }
+    }
+
+    /// Writes regions to their files in parallel and records them as completed, calling
+    /// `on_written` after each
+    fn write_regions(
+        &self,
+        regions: &[((i32, i32), RegionToModify)],
+        on_written: impl Fn() + Sync,
+    ) {
+        let writer = self.region_writer.as_ref().unwrap();
+        let changed_chunks = &writer.changed_chunks;
+        regions
             .par_iter()
             .for_each(|((region_x, region_z), region_to_modify)| {
-                let mut region = self.create_region(*region_x, *region_z);
+                let existing_region = changed_chunks
+                    .as_ref()
+                    .and_then(|_| self.reopen_region(*region_x, *region_z));
+                let incremental = existing_region.is_some();
+                let is_kept = |chunk_x: i32, chunk_z: i32| {
+                    incremental
+                        && !changed_chunks.as_ref().is_some_and(|chunks| {
+                            chunks.contains(&(chunk_x + region_x * 32, chunk_z + region_z * 32))
+                        })
+                };
+                let mut region =
+                    existing_region.unwrap_or_else(|| self.create_region(*region_x, *region_z));
                 let mut ser_buffer = Vec::with_capacity(8192);
 
                 for (&(chunk_x, chunk_z), chunk_to_modify) in &region_to_modify.chunks {
+                    if is_kept(chunk_x, chunk_z) {
+                        continue;
+                    }
                     if !chunk_to_modify.sections.is_empty() || !chunk_to_modify.other.is_empty() {
-                        // Read existing chunk data if it exists
-                        let existing_data = region
-                            .read_chunk(chunk_x as usize, chunk_z as usize)
-                            .unwrap()
-                            .unwrap_or_default();
+                        // Read existing chunk data if it exists, rewritten chunks start empty
+                        let existing_data = if incremental {
+                            Vec::new()
+                        } else {
+                            region
+                                .read_chunk(chunk_x as usize, chunk_z as usize)
+                                .unwrap()
+                                .unwrap_or_default()
+                        };
 
                         // Parse existing chunk or create new one
                         let mut chunk: Chunk = if !existing_data.is_empty() {
@@ -186,12 +382,18 @@ impl<'a> WorldEditor<'a> {
                         chunk.z_pos = chunk_z + (region_z * 32);
 
                         // Create Level wrapper and save
-                        let level_data = create_level_wrapper(&chunk);
+                        let biomes =
+                            self.biomes
+                                .java_section_biomes(chunk.x_pos, chunk.z_pos, self.climate);
+                        let level_data = create_level_wrapper(&chunk, &biomes);
                         ser_buffer.clear();
                         fastnbt::to_writer(&mut ser_buffer, &level_data).unwrap();
-                        region
-                            .write_chunk(chunk_x as usize, chunk_z as usize, &ser_buffer)
-                            .unwrap();
+                        self.write_chunk_data(
+                            &mut region,
+                            chunk_x as usize,
+                            chunk_z as usize,
+                            &ser_buffer,
+                        );
                     }
                 }
 
@@ -206,30 +408,169 @@ impl<'a> WorldEditor<'a> {
                             region_to_modify.chunks.contains_key(&(chunk_x, chunk_z));
 
                         // If chunk doesn't exist, create it with base layer
-                        if !chunk_exists {
-                            let (ser_buffer, _) = Self::create_base_chunk(abs_chunk_x, abs_chunk_z);
-                            region
-                                .write_chunk(chunk_x as usize, chunk_z as usize, &ser_buffer)
-                                .unwrap();
+                        if !chunk_exists && !is_kept(chunk_x, chunk_z) {
+                            let biomes = self.biomes.java_section_biomes(
+                                abs_chunk_x,
+                                abs_chunk_z,
+                                self.climate,
+                            );
+                            let (ser_buffer, _) =
+                                Self::create_base_chunk(abs_chunk_x, abs_chunk_z, &biomes);
+                            self.write_chunk_data(
+                                &mut region,
+                                chunk_x as usize,
+                                chunk_z as usize,
+                                &ser_buffer,
+                            );
                         }
                     }
                 }
 
-                // Update progress
-                let regions_done = regions_processed.fetch_add(1, Ordering::SeqCst) + 1;
+                // Rewrite the region file without the sectors left unused by relocated chunks
+                drop(region);
+                if self.region_options.compact {
+                    if let Err(e) = self.compact_region(*region_x, *region_z) {
+                        eprintln!("Failed to compact region r.{region_x}.{region_z}: {e}");
+                    }
+                }
 
-                // Update progress at regular intervals (every ~1% or at least every 10 regions)
-                // This ensures progress is visible even with many regions
-                let update_interval = (total_regions / 10).max(1);
-                if regions_done.is_multiple_of(update_interval) || regions_done == total_regions {
-                    let progress = 90.0 + (regions_done as f64 / total_regions as f64) * 9.0;
-                    emit_gui_progress_update(progress, "Saving world...");
+                // Publish the finished region so it can be loaded while the rest is written
+                if let Err(e) = self.finish_region(*region_x, *region_z) {
+                    eprintln!("Failed to finish region r.{region_x}.{region_z}: {e}");
+                } else {
+                    writer
+                        .status
+                        .lock()
+                        .unwrap()
+                        .completed_regions
+                        .push((*region_x, *region_z));
+                    self.write_generation_status(&writer.status);
                 }
 
-                save_pb.inc(1);
+                on_written();
             });
+    }
 
-        save_pb.finish();
+    /// Sets the time and weather in level.dat and stops their cycles, so the world stays at
+    /// that time and keeps its weather
+    fn update_level_dat(&self, world_time: Option<i64>, rain: Option<bool>) -> std::io::Result<()> {
+        let level_path = self.world_dir.join("level.dat");
+
+        let mut decompressed = Vec::new();
+        GzDecoder::new(File::open(&level_path)?).read_to_end(&mut decompressed)?;
+        let mut level: Value = fastnbt::from_bytes(&decompressed).map_err(std::io::Error::other)?;
+
+        if let Value::Compound(ref mut root) = level {
+            if let Some(Value::Compound(ref mut data)) = root.get_mut("Data") {
+                let mut rules: Vec<&str> = Vec::new();
+                if let Some(world_time) = world_time {
+                    data.insert("DayTime".to_string(), Value::Long(world_time));
+                    rules.push("doDaylightCycle");
+                }
+                if let Some(rain) = rain {
+                    data.insert("raining".to_string(), Value::Byte(rain as i8));
+                    data.insert("thundering".to_string(), Value::Byte(0));
+                    data.insert("rainTime".to_string(), Value::Int(FIXED_WEATHER_TICKS));
+                    data.insert(
+                        "clearWeatherTime".to_string(),
+                        Value::Int(if rain { 0 } else { FIXED_WEATHER_TICKS }),
+                    );
+                    rules.push("doWeatherCycle");
+                }
+
+                if let Some(Value::Compound(ref mut game_rules)) = data.get_mut("GameRules") {
+                    for rule in rules {
+                        game_rules.insert(rule.to_string(), Value::String("false".to_string()));
+                    }
+                }
+            }
+        }
+
+        let serialized = fastnbt::to_bytes(&level).map_err(std::io::Error::other)?;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&serialized)?;
+        std::fs::write(&level_path, encoder.finish()?)
+    }
+
+    /// Moves a completed region file from its temporary name to its final place
+    fn finish_region(&self, region_x: i32, region_z: i32) -> std::io::Result<()> {
+        std::fs::rename(
+            self.partial_region_path(region_x, region_z),
+            self.region_path(region_x, region_z),
+        )
+    }
+
+    /// Writes the list of completed regions to the status file in the world directory
+    fn write_generation_status(&self, status: &Mutex<GenerationStatus>) {
+        // Holding the lock while writing keeps concurrent updates from interleaving
+        let status = status.lock().unwrap();
+        let status_path = self.world_dir.join(GENERATION_STATUS_FILE);
+        let partial_path = status_path.with_extension("json.part");
+
+        let result = serde_json::to_vec_pretty(&*status)
+            .map_err(std::io::Error::other)
+            .and_then(|contents| std::fs::write(&partial_path, contents))
+            .and_then(|_| std::fs::rename(&partial_path, &status_path));
+
+        if let Err(e) = result {
+            eprintln!("Failed to write generation status: {}", e);
+        }
+    }
+
+    /// Writes serialized chunk data to a region, using the configured compression level
+    fn write_chunk_data(
+        &self,
+        region: &mut Region<File>,
+        chunk_x: usize,
+        chunk_z: usize,
+        data: &[u8],
+    ) {
+        match self.region_options.compression_level {
+            Some(level) => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(level));
+                encoder
+                    .write_all(data)
+                    .expect("Failed to compress chunk data");
+                let compressed = encoder.finish().expect("Failed to compress chunk data");
+                region
+                    .write_compressed_chunk(chunk_x, chunk_z, CompressionScheme::Zlib, &compressed)
+                    .unwrap();
+            }
+            None => region.write_chunk(chunk_x, chunk_z, data).unwrap(),
+        }
+    }
+
+    /// Rewrites a region file with all of its chunks packed back to back
+    fn compact_region(
+        &self,
+        region_x: i32,
+        region_z: i32,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let path = self.partial_region_path(region_x, region_z);
+
+        let mut region = Region::from_stream(File::options().read(true).write(true).open(&path)?)?;
+        let mut chunks: Vec<(usize, usize, Vec<u8>)> = Vec::new();
+        for chunk_x in 0..32 {
+            for chunk_z in 0..32 {
+                if let Some(data) = region.read_chunk(chunk_x, chunk_z)? {
+                    chunks.push((chunk_x, chunk_z, data));
+                }
+            }
+        }
+        drop(region);
+
+        let region_file: File = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        let mut compacted = Region::create(region_file)?;
+        for (chunk_x, chunk_z, data) in chunks {
+            self.write_chunk_data(&mut compacted, chunk_x, chunk_z, &data);
+        }
+
+        Ok(())
     }
 }
 
@@ -256,7 +597,7 @@ fn get_entity_coords(entity: &HashMap<String, Value>) -> (i32, i32, i32) {
 
 /// Creates a Level wrapper for chunk data (Java Edition format)
 #[inline]
-fn create_level_wrapper(chunk: &Chunk) -> HashMap<String, Value> {
+fn create_level_wrapper(chunk: &Chunk, biomes: &Value) -> HashMap<String, Value> {
     HashMap::from([(
         "Level".to_string(),
         Value::Compound(HashMap::from([
@@ -311,6 +652,7 @@ fn create_level_wrapper(chunk: &Chunk) -> HashMap<String, Value> {
                             Value::Compound(HashMap::from([
                                 ("Y".to_string(), Value::Byte(section.y)),
                                 ("block_states".to_string(), Value::Compound(block_states)),
+                                ("biomes".to_string(), biomes.clone()),
                             ]))
                         })
                         .collect(),
@@ -319,3 +661,36 @@ fn create_level_wrapper(chunk: &Chunk) -> HashMap<String, Value> {
         ])),
     )])
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::block_definitions::STONE;
+    use crate::coordinate_system::cartesian::XZBBox;
+    use crate::test_utilities::{get_llbbox_arnis, minecraft_tmpdir};
+    use crate::world_editor::WorldEditor;
+
+    #[test]
+    fn test_flushed_regions_are_written_before_saving() {
+        let world = minecraft_tmpdir();
+        // Two regions side by side
+        let xzbbox = XZBBox::rect_from_xz_lengths(700.0, 20.0).unwrap();
+        let mut editor = WorldEditor::new(world.path().to_path_buf(), &xzbbox, get_llbbox_arnis());
+        editor.set_block_absolute(STONE, 10, 0, 10, None, None);
+        editor.set_block_absolute(STONE, 600, 0, 10, None, None);
+
+        editor.flush_regions(|region_x, _| region_x == 0);
+        let region = |x: i32| world.path().join(format!("region/r.{x}.0.mca"));
+        assert!(region(0).exists());
+        assert!(!region(1).exists());
+        // The blocks of the written region are freed but still counted
+        assert!(!editor.block_at_absolute(10, 0, 10));
+        assert_eq!(editor.block_count(), 2);
+        let status = std::fs::read_to_string(world.path().join("generation_status.json")).unwrap();
+        assert!(status.contains("\"complete\": false"));
+
+        editor.save();
+        assert!(region(1).exists());
+        let status = std::fs::read_to_string(world.path().join("generation_status.json")).unwrap();
+        assert!(status.contains("\"totalRegions\": 2") && status.contains("\"complete\": true"));
+    }
+}
@@ -0,0 +1,246 @@
+//! Manifest of the chunks each OSM element placed blocks in, for regenerating a world
+//! incrementally.
+//!
+//! The manifest is written as `arnis_manifest.json` into the world folder together with a hash
+//! of every element. Generating the same area again with newer OSM data only rewrites the
+//! chunks of the elements that were added, changed or deleted since, and keeps the other chunks
+//! as they are, including what players built in them.
+
+use super::BlockOwner;
+use crate::osm_parser::{ProcessedElement, ProcessedWay};
+use fnv::{FnvHashMap, FnvHashSet, FnvHasher};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+/// File name of the manifest inside the world folder
+const MANIFEST_FILE: &str = "arnis_manifest.json";
+
+/// Chunks placed by each element and the hashes of the elements, collected while the world is
+/// generated
+#[derive(Default)]
+pub(crate) struct ChunkManifest {
+    /// Hash of the settings the world is generated with
+    settings: u64,
+    hashes: FnvHashMap<BlockOwner, u64>,
+    chunks: FnvHashMap<BlockOwner, FnvHashSet<(i32, i32)>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestFile {
+    /// Arnis version the world was generated with, as changes to the generation can move blocks
+    version: String,
+    settings: u64,
+    elements: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestEntry {
+    kind: String,
+    id: u64,
+    hash: u64,
+    chunks: Vec<(i32, i32)>,
+}
+
+impl ChunkManifest {
+    pub fn new(settings: u64, elements: &[ProcessedElement]) -> Self {
+        let mut hashes: FnvHashMap<BlockOwner, u64> = FnvHashMap::default();
+        for element in elements {
+            let owner = BlockOwner {
+                kind: element.kind(),
+                id: element.id(),
+            };
+            // Parts of the same element, such as clipped pieces, make up one hash
+            let hash = element_hash(element);
+            hashes
+                .entry(owner)
+                .and_modify(|existing| *existing = existing.rotate_left(5) ^ hash)
+                .or_insert(hash);
+        }
+        Self {
+            settings,
+            hashes,
+            chunks: FnvHashMap::default(),
+        }
+    }
+
+    /// Remembers that the owner placed a block at the given absolute position
+    pub fn record(&mut self, x: i32, z: i32, owner: BlockOwner) {
+        self.chunks
+            .entry(owner)
+            .or_default()
+            .insert((x >> 4, z >> 4));
+    }
+
+    /// Adds the chunks recorded by the manifest of another part of the world
+    pub fn merge(&mut self, other: ChunkManifest) {
+        for (owner, chunks) in other.chunks {
+            self.chunks.entry(owner).or_default().extend(chunks);
+        }
+    }
+
+    /// Returns the chunks to rewrite since the manifest written into the world folder before,
+    /// or None if there is no matching manifest and the whole world is written
+    pub fn changed_chunks(&self, world_dir: &Path) -> Option<FnvHashSet<(i32, i32)>> {
+        let contents = fs::read_to_string(world_dir.join(MANIFEST_FILE)).ok()?;
+        let previous: ManifestFile = serde_json::from_str(&contents).ok()?;
+        if previous.version != env!("CARGO_PKG_VERSION") || previous.settings != self.settings {
+            return None;
+        }
+
+        let current: HashMap<(&str, u64), u64> = self
+            .hashes
+            .iter()
+            .map(|(owner, &hash)| ((owner.kind, owner.id), hash))
+            .collect();
+        let mut changed: FnvHashSet<(i32, i32)> = FnvHashSet::default();
+        let mut previous_hashes: HashMap<(String, u64), u64> = HashMap::new();
+        for entry in previous.elements {
+            // Deleted and changed elements leave their old chunks to be rewritten
+            if current.get(&(entry.kind.as_str(), entry.id)) != Some(&entry.hash) {
+                changed.extend(entry.chunks);
+            }
+            previous_hashes.insert((entry.kind, entry.id), entry.hash);
+        }
+
+        // Added and changed elements also rewrite the chunks they place blocks in now
+        for (owner, hash) in &self.hashes {
+            if previous_hashes.get(&(owner.kind.to_string(), owner.id)) != Some(hash) {
+                changed.extend(self.chunks.get(owner).into_iter().flatten().copied());
+            }
+        }
+        Some(changed)
+    }
+
+    /// Writes the manifest into the world directory
+    pub fn write(&self, world_dir: &Path) -> io::Result<()> {
+        let mut elements: Vec<ManifestEntry> = self
+            .hashes
+            .iter()
+            .map(|(owner, &hash)| {
+                let mut chunks: Vec<(i32, i32)> = self
+                    .chunks
+                    .get(owner)
+                    .into_iter()
+                    .flatten()
+                    .copied()
+                    .collect();
+                chunks.sort_unstable();
+                ManifestEntry {
+                    kind: owner.kind.to_string(),
+                    id: owner.id,
+                    hash,
+                    chunks,
+                }
+            })
+            .collect();
+        elements.sort_by(|a, b| a.kind.cmp(&b.kind).then(a.id.cmp(&b.id)));
+
+        let manifest = ManifestFile {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            settings: self.settings,
+            elements,
+        };
+        let writer = BufWriter::new(File::create(world_dir.join(MANIFEST_FILE))?);
+        serde_json::to_writer(writer, &manifest).map_err(io::Error::other)
+    }
+}
+
+/// Hashes the tags and geometry of an element, which stay the same as long as it is not edited
+fn element_hash(element: &ProcessedElement) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hash_tags(element.tags(), &mut hasher);
+    match element {
+        ProcessedElement::Node(node) => (node.x, node.z).hash(&mut hasher),
+        ProcessedElement::Way(way) => hash_way(way, &mut hasher),
+        ProcessedElement::Relation(relation) => {
+            for member in &relation.members {
+                format!("{:?}", member.role).hash(&mut hasher);
+                hash_tags(&member.way.tags, &mut hasher);
+                hash_way(&member.way, &mut hasher);
+            }
+            for stop in &relation.stops {
+                (stop.id, stop.x, stop.z).hash(&mut hasher);
+            }
+        }
+    }
+    hasher.finish()
+}
+
+/// Hashes tags in the order of their keys, as the order of a map changes between runs
+fn hash_tags(tags: &HashMap<String, String>, hasher: &mut FnvHasher) {
+    let mut sorted: Vec<(&String, &String)> = tags.iter().collect();
+    sorted.sort_unstable();
+    sorted.hash(hasher);
+}
+
+fn hash_way(way: &ProcessedWay, hasher: &mut FnvHasher) {
+    way.id.hash(hasher);
+    for node in &way.nodes {
+        (node.id, node.x, node.z).hash(hasher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osm_parser::ProcessedNode;
+
+    fn node(id: u64, x: i32, amenity: &str) -> ProcessedElement {
+        ProcessedElement::Node(ProcessedNode {
+            id,
+            tags: HashMap::from([("amenity".to_string(), amenity.to_string())]),
+            x,
+            z: 0,
+        })
+    }
+
+    fn generate(elements: &[ProcessedElement]) -> ChunkManifest {
+        let mut manifest = ChunkManifest::new(7, elements);
+        for element in elements {
+            let ProcessedElement::Node(node) = element else {
+                continue;
+            };
+            let owner = BlockOwner {
+                kind: "node",
+                id: node.id,
+            };
+            manifest.record(node.x, node.z, owner);
+        }
+        manifest
+    }
+
+    #[test]
+    fn test_changed_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = generate(&[
+            node(1, 0, "bench"),
+            node(2, 40, "bench"),
+            node(3, 80, "cafe"),
+        ]);
+        assert_eq!(first.changed_chunks(dir.path()), None);
+        first.write(dir.path()).unwrap();
+
+        // Node 1 is moved to another chunk, node 2 is deleted and node 4 is added
+        let second = generate(&[
+            node(1, 100, "bench"),
+            node(3, 80, "cafe"),
+            node(4, 200, "bar"),
+        ]);
+        let mut changed: Vec<(i32, i32)> = second
+            .changed_chunks(dir.path())
+            .unwrap()
+            .into_iter()
+            .collect();
+        changed.sort_unstable();
+        assert_eq!(changed, vec![(0, 0), (2, 0), (6, 0), (12, 0)]);
+
+        // Other settings need the whole world to be written again
+        let mut other_settings = generate(&[node(3, 80, "cafe")]);
+        other_settings.settings = 8;
+        assert_eq!(other_settings.changed_chunks(dir.path()), None);
+    }
+}
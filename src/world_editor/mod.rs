@@ -5,18 +5,32 @@
 //!
 //! # Module Structure
 //!
+//! - `biomes` - Biomes of single columns matching the features on them
 //! - `common` - Shared data structures for world modification
 //! - `java` - Java Edition Anvil format saving
 //! - `bedrock` - Bedrock Edition .mcworld format saving (behind `bedrock` feature)
+//! - `ownership` - Debug map of the OSM elements that placed each block
+//! - `index` - Coordinates of the named roads and places of the world
+//! - `plan` - Blocks claimed by each feature and the conflicts between them, resolved by the
+//!   priorities of the features and reported for dry runs
+//! - `manifest` - Chunks of each OSM element, for rewriting only the chunks of changed ones
 
+mod biomes;
 mod common;
+pub(crate) mod index;
 mod java;
+mod manifest;
+pub(crate) mod ownership;
+pub(crate) mod plan;
 
 #[cfg(feature = "bedrock")]
 pub mod bedrock;
 
 // Re-export common types used internally
+pub use biomes::Biome;
 pub(crate) use common::WorldToModify;
+pub use index::IndexEntry;
+pub use ownership::BlockOwner;
 
 #[cfg(feature = "bedrock")]
 pub(crate) use bedrock::{BedrockSaveError, BedrockWriter};
@@ -25,14 +39,25 @@ use crate::block_definitions::*;
 use crate::coordinate_system::cartesian::{XZBBox, XZPoint};
 use crate::coordinate_system::geographic::LLBBox;
 use crate::ground::Ground;
+use crate::i18n::tr;
+use crate::osm_parser::ProcessedElement;
 use crate::progress::emit_gui_progress_update;
+use crate::theme::{Climate, Theme};
+use crate::tiles::Tile;
+use crate::time_of_day::TimeOfDay;
+use biomes::BiomeMap;
 use colored::Colorize;
 use fastnbt::Value;
+use index::WorldIndex;
+use manifest::ChunkManifest;
+use ownership::OwnershipMap;
+use plan::GenerationPlan;
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 #[cfg(feature = "gui")]
 use crate::telemetry::{send_log, LogLevel};
@@ -62,6 +87,28 @@ pub(crate) struct WorldMetadata {
     pub max_geo_lon: f64,
 }
 
+/// Tuning options for writing Java Edition region files
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RegionFileOptions {
+    /// Zlib compression level (0-9) for chunk data, uses the default level if unset
+    pub compression_level: Option<u32>,
+    /// Rewrite region files after saving so that no unused sectors remain
+    pub compact: bool,
+}
+
+/// A ground level block that could not be placed because land and water overlap
+#[derive(Clone, Copy, Debug)]
+pub struct SurfaceConflict {
+    pub x: i32,
+    pub z: i32,
+    /// The block that was rejected
+    pub block: Block,
+    /// The layer of the feature that tried to place the block
+    pub layer: i32,
+    /// The element that tried to place the block
+    pub owner: Option<BlockOwner>,
+}
+
 /// The main world editor struct for placing blocks and saving worlds.
 ///
 /// The lifetime `'a` is tied to the `XZBBox` reference, which defines
@@ -71,12 +118,49 @@ pub struct WorldEditor<'a> {
     world: WorldToModify,
     xzbbox: &'a XZBBox,
     llbbox: LLBBox,
-    ground: Option<Box<Ground>>,
+    ground: Option<Arc<Ground>>,
     format: WorldFormat,
     /// Optional level name for Bedrock worlds (e.g., "Arnis World: New York City")
     bedrock_level_name: Option<String>,
     /// Optional spawn point for Bedrock worlds (x, z coordinates)
     bedrock_spawn_point: Option<(i32, i32)>,
+    /// Compression and layout options for Java region files
+    region_options: RegionFileOptions,
+    /// Layer of the feature currently being generated, None while overlaps aren't tracked
+    surface_layer: Option<i32>,
+    /// Layers of ground level blocks placed by features on a layer other than 0
+    surface_layers: HashMap<(i32, i32), i32>,
+    /// Ground level blocks rejected where land and water features overlap
+    surface_conflicts: Vec<SurfaceConflict>,
+    /// Resolved theme of the world
+    theme: Theme,
+    /// Time of day the world is set to
+    time_of_day: TimeOfDay,
+    /// Climate setting the biome and weather of the world
+    climate: Climate,
+    /// Biomes of the columns matching the features on them
+    biomes: BiomeMap,
+    /// Columns left untouched by the feature that is generated next, such as the holes of a
+    /// multipolygon
+    excluded_columns: Option<HashSet<(i32, i32)>>,
+    /// Element that owns the blocks placed next
+    block_owner: Option<BlockOwner>,
+    /// Owners of all placed blocks, only collected when requested
+    ownership: Option<OwnershipMap>,
+    /// Named roads and places, only collected when requested
+    index: Option<WorldIndex>,
+    /// Claimed blocks and refused placements, only collected for dry runs
+    plan: Option<GenerationPlan>,
+    /// Chunks of each element, only collected for incremental generation
+    manifest: Option<ChunkManifest>,
+    /// Tile the editor is restricted to when the world is generated in tiles
+    tile: Option<Tile>,
+    /// Columns of ground level roads and plazas that are kept clear above
+    walkable_columns: HashSet<(i32, i32)>,
+    /// Columns below or on elevated roads, where blocks above the ground are intended
+    overpass_columns: HashSet<(i32, i32)>,
+    /// Regions written so far, once the first ones are flushed or the world is saved
+    region_writer: Option<java::RegionWriter>,
 }
 
 impl<'a> WorldEditor<'a> {
@@ -94,6 +178,24 @@ impl<'a> WorldEditor<'a> {
             format: WorldFormat::JavaAnvil,
             bedrock_level_name: None,
             bedrock_spawn_point: None,
+            region_options: RegionFileOptions::default(),
+            surface_layer: None,
+            surface_layers: HashMap::new(),
+            surface_conflicts: Vec::new(),
+            theme: Theme::Temperate,
+            time_of_day: TimeOfDay::Day,
+            climate: Climate::Temperate,
+            biomes: BiomeMap::default(),
+            excluded_columns: None,
+            block_owner: None,
+            ownership: None,
+            index: None,
+            plan: None,
+            manifest: None,
+            tile: None,
+            walkable_columns: HashSet::new(),
+            overpass_columns: HashSet::new(),
+            region_writer: None,
         }
     }
 
@@ -118,12 +220,303 @@ impl<'a> WorldEditor<'a> {
             format,
             bedrock_level_name,
             bedrock_spawn_point,
+            region_options: RegionFileOptions::default(),
+            surface_layer: None,
+            surface_layers: HashMap::new(),
+            surface_conflicts: Vec::new(),
+            theme: Theme::Temperate,
+            time_of_day: TimeOfDay::Day,
+            climate: Climate::Temperate,
+            biomes: BiomeMap::default(),
+            excluded_columns: None,
+            block_owner: None,
+            ownership: None,
+            index: None,
+            plan: None,
+            manifest: None,
+            tile: None,
+            walkable_columns: HashSet::new(),
+            overpass_columns: HashSet::new(),
+            region_writer: None,
         }
     }
 
     /// Sets the ground reference for elevation-based block placement
     pub fn set_ground(&mut self, ground: &Ground) {
-        self.ground = Some(Box::new(ground.clone()));
+        self.ground = Some(Arc::new(ground.clone()));
+    }
+
+    /// Sets the compression and layout options used when saving Java region files
+    pub fn set_region_options(&mut self, region_options: RegionFileOptions) {
+        self.region_options = region_options;
+    }
+
+    /// Sets the resolved theme of the world
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// Returns the resolved theme of the world
+    pub fn theme(&self) -> Theme {
+        self.theme
+    }
+
+    /// Sets the time of day the world is saved with
+    pub fn set_time_of_day(&mut self, time_of_day: TimeOfDay) {
+        self.time_of_day = time_of_day;
+    }
+
+    /// Returns the time of day the world is saved with
+    pub fn time_of_day(&self) -> TimeOfDay {
+        self.time_of_day
+    }
+
+    /// Sets the climate the biome and weather of the world are saved with
+    pub fn set_climate(&mut self, climate: Climate) {
+        self.climate = climate;
+    }
+
+    /// Returns the climate of the world
+    pub fn climate(&self) -> Climate {
+        self.climate
+    }
+
+    /// Sets the biome of a column, which otherwise takes the biome of the climate
+    pub fn set_biome(&mut self, x: i32, z: i32, biome: Biome) {
+        if self.is_column_writable(x, z) {
+            self.biomes.set(x, z, biome);
+        }
+    }
+
+    /// Starts recording which element placed each block, written as sidecar files on save
+    pub fn enable_ownership_map(&mut self) {
+        self.ownership = Some(OwnershipMap::default());
+    }
+
+    /// Sets the element that owns the blocks placed next
+    pub fn set_block_owner(&mut self, owner: Option<BlockOwner>) {
+        self.block_owner = owner;
+    }
+
+    /// Returns the element that placed the block at the given position, relative to the ground
+    #[cfg(test)]
+    pub(crate) fn block_owner_at(&self, x: i32, y: i32, z: i32) -> Option<BlockOwner> {
+        let absolute_y = self.get_absolute_y(x, y, z);
+        self.ownership.as_ref()?.owner_at(x, absolute_y, z)
+    }
+
+    /// Starts collecting the named roads and places, written as index.json on save
+    pub fn enable_index(&mut self) {
+        self.index = Some(WorldIndex::default());
+    }
+
+    /// Adds a named element to the index, standing on the ground at x/z
+    pub fn add_index_entry(
+        &mut self,
+        name: &str,
+        category: String,
+        owner: BlockOwner,
+        x: i32,
+        z: i32,
+    ) {
+        let y = self.get_absolute_y(x, 1, z);
+        if let Some(index) = self.index.as_mut() {
+            index.add(IndexEntry {
+                name: name.to_string(),
+                category,
+                kind: owner.kind.to_string(),
+                id: owner.id,
+                x,
+                y,
+                z,
+            });
+        }
+    }
+
+    /// Starts collecting the generation plan, which gives each block claimed by several
+    /// elements to the one of the highest priority when it is committed and is reported
+    /// instead of saving the world for dry runs
+    pub fn enable_plan(&mut self, elements: &[ProcessedElement]) {
+        self.plan = Some(GenerationPlan::new(elements));
+    }
+
+    /// Places the blocks the generation plan gives to elements of a higher priority than the
+    /// ones holding them and returns their number
+    pub fn commit_plan(&mut self) -> usize {
+        let Some(plan) = self.plan.as_mut() else {
+            return 0;
+        };
+        let committed = plan.commit();
+        let count = committed.len();
+        for ((x, y, z), owner, block) in committed {
+            self.world.set_block_with_properties(x, y, z, block);
+            if let Some(ownership) = self.ownership.as_mut() {
+                ownership.record(x, y, z, owner);
+            }
+            if let Some(manifest) = self.manifest.as_mut() {
+                manifest.record(x, z, owner);
+            }
+        }
+        count
+    }
+
+    /// Starts recording the chunks of each element, so that generating the world again only
+    /// rewrites the chunks of the elements changed by then
+    pub fn enable_manifest(&mut self, settings: u64, elements: &[ProcessedElement]) {
+        self.manifest = Some(ChunkManifest::new(settings, elements));
+    }
+
+    /// Returns the generation plan if it is collected
+    pub(crate) fn plan(&self) -> Option<&GenerationPlan> {
+        self.plan.as_ref()
+    }
+
+    #[inline]
+    fn record_owner(&mut self, x: i32, absolute_y: i32, z: i32) {
+        if let (Some(ownership), Some(owner)) = (self.ownership.as_mut(), self.block_owner) {
+            ownership.record(x, absolute_y, z, owner);
+        }
+        if let (Some(plan), Some(owner)) = (self.plan.as_mut(), self.block_owner) {
+            plan.claim(x, absolute_y, z, owner);
+        }
+        if let (Some(manifest), Some(owner)) = (self.manifest.as_mut(), self.block_owner) {
+            manifest.record(x, z, owner);
+        }
+    }
+
+    /// Remembers a placement that was refused because the block was already taken
+    #[inline]
+    fn record_refusal(&mut self, x: i32, absolute_y: i32, z: i32, block: BlockWithProperties) {
+        if let (Some(plan), Some(owner)) = (self.plan.as_mut(), self.block_owner) {
+            plan.refuse(x, absolute_y, z, owner, block);
+        }
+    }
+
+    /// Returns whether the generation plan lets the current element replace the block, which
+    /// it does not if an element of a higher priority holds it
+    #[inline]
+    fn plan_allows(&self, x: i32, absolute_y: i32, z: i32) -> bool {
+        match (self.plan.as_ref(), self.block_owner) {
+            (Some(plan), Some(owner)) => plan.may_replace(x, absolute_y, z, owner),
+            _ => true,
+        }
+    }
+
+    /// Creates an editor for one tile of the world with the settings of this editor, which
+    /// only accepts blocks inside the tile
+    pub fn tile_editor(&self, tile: Tile) -> WorldEditor<'a> {
+        let mut editor = Self::new_with_format_and_name(
+            self.world_dir.clone(),
+            self.xzbbox,
+            self.llbbox,
+            self.format,
+            None,
+            None,
+        );
+        editor.ground = self.ground.clone();
+        editor.theme = self.theme;
+        editor.time_of_day = self.time_of_day;
+        editor.climate = self.climate;
+        editor.ownership = self.ownership.as_ref().map(|_| OwnershipMap::default());
+        editor.plan = self.plan.as_ref().map(GenerationPlan::for_tile);
+        editor.manifest = self.manifest.as_ref().map(|_| ChunkManifest::default());
+        editor.tile = Some(tile);
+        editor
+    }
+
+    /// Moves the blocks and everything recorded by the editor of a tile into this editor
+    pub fn merge_tile(&mut self, tile_editor: WorldEditor<'a>) {
+        let Some(tile) = tile_editor.tile else {
+            return;
+        };
+
+        for ((region_x, region_z), region) in tile_editor.world.regions {
+            for ((chunk_x, chunk_z), chunk) in region.chunks {
+                // Signs of blocks refused at the border can leave chunks outside the tile
+                if tile.contains_chunk(region_x * 32 + chunk_x, region_z * 32 + chunk_z) {
+                    self.world
+                        .get_or_create_region(region_x, region_z)
+                        .chunks
+                        .insert((chunk_x, chunk_z), chunk);
+                }
+            }
+        }
+
+        self.surface_layers.extend(tile_editor.surface_layers);
+        self.biomes.extend(tile_editor.biomes);
+        self.walkable_columns.extend(tile_editor.walkable_columns);
+        self.overpass_columns.extend(tile_editor.overpass_columns);
+        self.surface_conflicts.extend(tile_editor.surface_conflicts);
+        if let (Some(ownership), Some(tile_ownership)) =
+            (self.ownership.as_mut(), tile_editor.ownership)
+        {
+            ownership.merge(tile_ownership);
+        }
+        if let (Some(plan), Some(tile_plan)) = (self.plan.as_mut(), tile_editor.plan) {
+            plan.merge(tile_plan);
+        }
+        if let (Some(manifest), Some(tile_manifest)) =
+            (self.manifest.as_mut(), tile_editor.manifest)
+        {
+            manifest.merge(tile_manifest);
+        }
+    }
+
+    /// Sets the columns the feature that is generated next must not touch, None allows all
+    pub fn set_excluded_columns(&mut self, columns: Option<HashSet<(i32, i32)>>) {
+        self.excluded_columns = columns;
+    }
+
+    /// Returns true if blocks may be placed in the given column
+    #[inline]
+    fn is_column_writable(&self, x: i32, z: i32) -> bool {
+        self.xzbbox.contains(&XZPoint::new(x, z))
+            && self.tile.is_none_or(|tile| tile.contains(x, z))
+            && !self
+                .excluded_columns
+                .as_ref()
+                .is_some_and(|columns| columns.contains(&(x, z)))
+    }
+
+    /// Sets the layer of the feature that is generated next, None stops tracking
+    /// overlaps between land and water
+    pub fn set_surface_layer(&mut self, layer: Option<i32>) {
+        self.surface_layer = layer;
+    }
+
+    /// Returns the layer of the feature that placed the ground level block at the given column
+    pub fn surface_layer_at(&self, x: i32, z: i32) -> i32 {
+        self.surface_layers.get(&(x, z)).copied().unwrap_or(0)
+    }
+
+    /// Takes the ground level blocks that were rejected where land and water overlap
+    pub fn take_surface_conflicts(&mut self) -> Vec<SurfaceConflict> {
+        std::mem::take(&mut self.surface_conflicts)
+    }
+
+    /// Marks a column whose ground level is a road or plaza surface
+    pub fn mark_walkable(&mut self, x: i32, z: i32) {
+        self.walkable_columns.insert((x, z));
+    }
+
+    /// Returns true if the ground level of the column is a road, path or plaza surface
+    pub fn is_walkable(&self, x: i32, z: i32) -> bool {
+        self.walkable_columns.contains(&(x, z))
+    }
+
+    /// Marks a column crossed by an elevated road
+    pub fn mark_overpass(&mut self, x: i32, z: i32) {
+        self.overpass_columns.insert((x, z));
+    }
+
+    /// Takes the road and plaza columns that are not crossed by an elevated road
+    pub fn take_walkable_columns(&mut self) -> Vec<(i32, i32)> {
+        let mut columns: Vec<(i32, i32)> = std::mem::take(&mut self.walkable_columns)
+            .into_iter()
+            .filter(|column| !self.overpass_columns.contains(column))
+            .collect();
+        columns.sort_unstable();
+        columns
     }
 
     /// Gets a reference to the ground data if available
@@ -160,6 +553,15 @@ impl<'a> WorldEditor<'a> {
         (self.xzbbox.max_x(), self.xzbbox.max_z())
     }
 
+    /// Returns the number of placed blocks other than air, including the flushed ones
+    pub fn block_count(&self) -> u64 {
+        self.world.block_count()
+            + self
+                .region_writer
+                .as_ref()
+                .map_or(0, |writer| writer.blocks_written)
+    }
+
     /// Checks if there's a block at the given coordinates
     #[allow(unused)]
     #[inline]
@@ -169,7 +571,7 @@ impl<'a> WorldEditor<'a> {
     }
 
     /// Sets a sign at the given coordinates
-    #[allow(clippy::too_many_arguments, dead_code)]
+    #[allow(clippy::too_many_arguments)]
     pub fn set_sign(
         &mut self,
         line1: String,
@@ -182,6 +584,39 @@ impl<'a> WorldEditor<'a> {
         _rotation: i8,
     ) {
         let absolute_y = self.get_absolute_y(x, y, z);
+        self.add_sign_block_entity([line1, line2, line3, line4], x, absolute_y, z);
+        self.set_block(SIGN, x, y, z, None, None);
+    }
+
+    /// Sets a wall sign attached to the block behind it, facing the given direction
+    pub fn set_wall_sign(
+        &mut self,
+        lines: [String; 4],
+        x: i32,
+        y: i32,
+        z: i32,
+        facing: StairFacing,
+    ) {
+        let absolute_y = self.get_absolute_y(x, y, z);
+        self.add_sign_block_entity(lines, x, absolute_y, z);
+
+        let mut properties = HashMap::new();
+        properties.insert(
+            "facing".to_string(),
+            Value::String(facing.as_str().to_string()),
+        );
+        self.set_block_with_properties_absolute(
+            BlockWithProperties::new(OAK_WALL_SIGN, Some(Value::Compound(properties))),
+            x,
+            absolute_y,
+            z,
+            None,
+            None,
+        );
+    }
+
+    /// Stores the text of a sign as a block entity in its chunk
+    fn add_sign_block_entity(&mut self, lines: [String; 4], x: i32, absolute_y: i32, z: i32) {
         let chunk_x = x >> 4;
         let chunk_z = z >> 4;
         let region_x = chunk_x >> 5;
@@ -189,12 +624,10 @@ impl<'a> WorldEditor<'a> {
 
         let mut block_entities = HashMap::new();
 
-        let messages = vec![
-            Value::String(format!("\"{line1}\"")),
-            Value::String(format!("\"{line2}\"")),
-            Value::String(format!("\"{line3}\"")),
-            Value::String(format!("\"{line4}\"")),
-        ];
+        let messages = lines
+            .iter()
+            .map(|line| Value::String(format!("\"{line}\"")))
+            .collect();
 
         let mut text_data = HashMap::new();
         text_data.insert("messages".to_string(), Value::List(messages));
@@ -225,8 +658,6 @@ impl<'a> WorldEditor<'a> {
                 Value::List(vec![Value::Compound(block_entities)]),
             );
         }
-
-        self.set_block(SIGN, x, y, z, None, None);
     }
 
     /// Sets a block of the specified type at the given coordinates.
@@ -242,15 +673,16 @@ impl<'a> WorldEditor<'a> {
         override_whitelist: Option<&[Block]>,
         override_blacklist: Option<&[Block]>,
     ) {
-        // Check if coordinates are within bounds
-        if !self.xzbbox.contains(&XZPoint::new(x, z)) {
+        // Check if coordinates are within bounds and not excluded
+        if !self.is_column_writable(x, z) {
             return;
         }
 
         // Calculate the absolute Y coordinate based on ground level
         let absolute_y = self.get_absolute_y(x, y, z);
 
-        let should_insert = if let Some(existing_block) = self.world.get_block(x, absolute_y, z) {
+        let existing_block = self.world.get_block(x, absolute_y, z);
+        let should_insert = if let Some(existing_block) = existing_block {
             // Check against whitelist and blacklist
             if let Some(whitelist) = override_whitelist {
                 whitelist
@@ -265,10 +697,30 @@ impl<'a> WorldEditor<'a> {
             }
         } else {
             true
-        };
+        } && self.plan_allows(x, absolute_y, z);
 
         if should_insert {
             self.world.set_block(x, absolute_y, z, block);
+            self.record_owner(x, absolute_y, z);
+            if let Some(layer) = self.surface_layer.filter(|&layer| y == 0 && layer != 0) {
+                self.surface_layers.insert((x, z), layer);
+            }
+        } else {
+            self.record_refusal(x, absolute_y, z, BlockWithProperties::simple(block));
+            if let Some(layer) = self.surface_layer.filter(|_| y == 0) {
+                // Remember where land and water compete for the same column
+                let is_water_conflict = existing_block
+                    .is_some_and(|existing_block| (existing_block == WATER) != (block == WATER));
+                if is_water_conflict {
+                    self.surface_conflicts.push(SurfaceConflict {
+                        x,
+                        z,
+                        block,
+                        layer,
+                        owner: self.block_owner,
+                    });
+                }
+            }
         }
     }
 
@@ -283,12 +735,13 @@ impl<'a> WorldEditor<'a> {
         override_whitelist: Option<&[Block]>,
         override_blacklist: Option<&[Block]>,
     ) {
-        // Check if coordinates are within bounds
-        if !self.xzbbox.contains(&XZPoint::new(x, z)) {
+        // Check if coordinates are within bounds and not excluded
+        if !self.is_column_writable(x, z) {
             return;
         }
 
-        let should_insert = if let Some(existing_block) = self.world.get_block(x, absolute_y, z) {
+        let existing_block = self.world.get_block(x, absolute_y, z);
+        let should_insert = if let Some(existing_block) = existing_block {
             // Check against whitelist and blacklist
             if let Some(whitelist) = override_whitelist {
                 whitelist
@@ -303,10 +756,34 @@ impl<'a> WorldEditor<'a> {
             }
         } else {
             true
-        };
+        } && self.plan_allows(x, absolute_y, z);
 
+        let is_ground_level = absolute_y == self.get_absolute_y(x, 0, z);
         if should_insert {
             self.world.set_block(x, absolute_y, z, block);
+            self.record_owner(x, absolute_y, z);
+            if let Some(layer) = self
+                .surface_layer
+                .filter(|&layer| is_ground_level && layer != 0)
+            {
+                self.surface_layers.insert((x, z), layer);
+            }
+        } else {
+            self.record_refusal(x, absolute_y, z, BlockWithProperties::simple(block));
+            if let Some(layer) = self.surface_layer.filter(|_| is_ground_level) {
+                // Remember where land and water compete for the same column
+                let is_water_conflict = existing_block
+                    .is_some_and(|existing_block| (existing_block == WATER) != (block == WATER));
+                if is_water_conflict {
+                    self.surface_conflicts.push(SurfaceConflict {
+                        x,
+                        z,
+                        block,
+                        layer,
+                        owner: self.block_owner,
+                    });
+                }
+            }
         }
     }
 
@@ -321,8 +798,8 @@ impl<'a> WorldEditor<'a> {
         override_whitelist: Option<&[Block]>,
         override_blacklist: Option<&[Block]>,
     ) {
-        // Check if coordinates are within bounds
-        if !self.xzbbox.contains(&XZPoint::new(x, z)) {
+        // Check if coordinates are within bounds and not excluded
+        if !self.is_column_writable(x, z) {
             return;
         }
 
@@ -341,11 +818,14 @@ impl<'a> WorldEditor<'a> {
             }
         } else {
             true
-        };
+        } && self.plan_allows(x, absolute_y, z);
 
         if should_insert {
             self.world
                 .set_block_with_properties(x, absolute_y, z, block_with_props);
+            self.record_owner(x, absolute_y, z);
+        } else {
+            self.record_refusal(x, absolute_y, z, block_with_props);
         }
     }
 
@@ -500,12 +980,24 @@ impl<'a> WorldEditor<'a> {
             WorldFormat::JavaAnvil => self.save_java(),
             WorldFormat::BedrockMcWorld => self.save_bedrock(),
         }
+
+        if let Some(ownership) = &self.ownership {
+            if let Err(e) = ownership.write(&self.world_dir) {
+                eprintln!("Failed to write block ownership map: {e}");
+            }
+        }
+
+        if let Some(index) = &self.index {
+            if let Err(e) = index.write(&self.world_dir) {
+                eprintln!("Failed to write world index: {e}");
+            }
+        }
     }
 
     #[allow(unreachable_code)]
     fn save_bedrock(&mut self) {
-        println!("{} Saving Bedrock world...", "[7/7]".bold());
-        emit_gui_progress_update(90.0, "Saving Bedrock world...");
+        println!("{} {}", "[7/7]".bold(), tr("step_saving_bedrock_world"));
+        emit_gui_progress_update(90.0, tr("step_saving_bedrock_world"));
 
         #[cfg(feature = "bedrock")]
         {
@@ -547,8 +1039,11 @@ impl<'a> WorldEditor<'a> {
             self.world_dir.clone(),
             level_name,
             self.bedrock_spawn_point,
-            self.ground.clone(),
+            self.ground.as_deref().cloned().map(Box::new),
         )
+        .with_fixed_time(self.time_of_day.fixed_world_time())
+        .with_climate(self.climate)
+        .with_biomes(std::mem::take(&mut self.biomes))
         .write_world(&self.world, self.xzbbox, &self.llbbox)
     }
 
@@ -0,0 +1,145 @@
+//! Debug map of which OSM element placed each block.
+//!
+//! The map is written next to the region files as one CSV sidecar per region, so the owner of
+//! a coordinate can be looked up later with `arnis inspect`.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Directory inside the world folder holding the sidecar files
+const OWNERSHIP_DIR: &str = "arnis_ownership";
+
+/// OSM element that placed a block
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlockOwner {
+    pub kind: &'static str,
+    pub id: u64,
+}
+
+/// Absolute position of a written block and its owner
+type OwnedBlock = (i32, i32, i32, BlockOwner);
+
+/// Owners of all written blocks, grouped by region
+#[derive(Default)]
+pub(crate) struct OwnershipMap {
+    regions: HashMap<(i32, i32), Vec<OwnedBlock>>,
+}
+
+impl OwnershipMap {
+    /// Remembers the owner of the block at the given absolute position
+    pub fn record(&mut self, x: i32, y: i32, z: i32, owner: BlockOwner) {
+        self.regions
+            .entry((x >> 9, z >> 9))
+            .or_default()
+            .push((x, y, z, owner));
+    }
+
+    /// Adds the owners recorded by another map
+    pub fn merge(&mut self, other: OwnershipMap) {
+        for (region, blocks) in other.regions {
+            self.regions.entry(region).or_default().extend(blocks);
+        }
+    }
+
+    /// Returns the last owner recorded at the given absolute position
+    #[cfg(test)]
+    pub fn owner_at(&self, x: i32, y: i32, z: i32) -> Option<BlockOwner> {
+        self.regions
+            .get(&(x >> 9, z >> 9))?
+            .iter()
+            .rev()
+            .find(|&&(block_x, block_y, block_z, _)| (block_x, block_y, block_z) == (x, y, z))
+            .map(|&(_, _, _, owner)| owner)
+    }
+
+    /// Writes one sidecar file per region into the world directory
+    pub fn write(&self, world_dir: &Path) -> io::Result<()> {
+        let dir = world_dir.join(OWNERSHIP_DIR);
+        fs::create_dir_all(&dir)?;
+
+        for (&(region_x, region_z), blocks) in &self.regions {
+            let mut writer = BufWriter::new(File::create(sidecar_path(&dir, region_x, region_z))?);
+            writeln!(writer, "x,y,z,type,id")?;
+            for &(x, y, z, owner) in blocks {
+                writeln!(writer, "{x},{y},{z},{},{}", owner.kind, owner.id)?;
+            }
+            writer.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+fn sidecar_path(dir: &Path, region_x: i32, region_z: i32) -> PathBuf {
+    dir.join(format!("r.{region_x}.{region_z}.csv"))
+}
+
+/// Returns the owners of all blocks in the column at x/z as (y, type, id), lowest block first
+pub fn inspect(world_dir: &Path, x: i32, z: i32) -> io::Result<Vec<(i32, String, u64)>> {
+    let path = sidecar_path(&world_dir.join(OWNERSHIP_DIR), x >> 9, z >> 9);
+    let reader = BufReader::new(File::open(path)?);
+
+    let mut owners: Vec<(i32, String, u64)> = Vec::new();
+    for line in reader.lines().skip(1) {
+        let line = line?;
+        let fields: Vec<&str> = line.split(',').collect();
+        let [block_x, y, block_z, kind, id] = fields[..] else {
+            continue;
+        };
+        if block_x.parse::<i32>() != Ok(x) || block_z.parse::<i32>() != Ok(z) {
+            continue;
+        }
+        if let (Ok(y), Ok(id)) = (y.parse::<i32>(), id.parse::<u64>()) {
+            // Later writes replace earlier ones at the same height
+            owners.retain(|(owner_y, _, _)| *owner_y != y);
+            owners.push((y, kind.to_string(), id));
+        }
+    }
+
+    owners.sort_by_key(|(y, _, _)| *y);
+    Ok(owners)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect_reads_the_owners_of_a_column() {
+        let world = tempfile::tempdir().unwrap();
+        let road = BlockOwner { kind: "way", id: 7 };
+        let lamp = BlockOwner {
+            kind: "node",
+            id: 8,
+        };
+        let park = BlockOwner {
+            kind: "relation",
+            id: 9,
+        };
+
+        let mut map = OwnershipMap::default();
+        map.record(-3, 5, 700, lamp);
+        map.record(-3, 4, 700, park);
+        // Replaced by the road later on
+        map.record(-3, 4, 700, lamp);
+        let mut tile = OwnershipMap::default();
+        tile.record(-3, 4, 700, road);
+        tile.record(-2, 4, 700, park);
+        map.merge(tile);
+        map.write(world.path()).unwrap();
+
+        assert_eq!(
+            inspect(world.path(), -3, 700).unwrap(),
+            vec![(4, "way".to_string(), 7), (5, "node".to_string(), 8)]
+        );
+        assert_eq!(
+            inspect(world.path(), -2, 700).unwrap(),
+            vec![(4, "relation".to_string(), 9)]
+        );
+        assert!(inspect(world.path(), -1, 700).unwrap().is_empty());
+        // No blocks were written in other regions
+        assert!(inspect(world.path(), 600, 700).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+//! Generation plan of a world, recording which feature claimed each block and which
+//! placements were refused because another feature had claimed the block first.
+//!
+//! Generating the features is the planning pass: a feature cannot replace the blocks of a
+//! feature of a higher priority, and its placements refused in favour of a feature of a lower
+//! priority are kept as intents. The commit pass places these once all features are generated,
+//! so that each block goes to the feature of the highest priority whatever order the features
+//! were generated in. `--dry-run` reports the plan instead of saving the world.
+
+use super::BlockOwner;
+use crate::block_definitions::BlockWithProperties;
+use crate::osm_parser::{get_priority, ProcessedElement};
+use fnv::FnvHashMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Default)]
+pub(crate) struct GenerationPlan {
+    claims: FnvHashMap<(i32, i32, i32), BlockOwner>,
+    /// Refused placements as (refused owner, owner of the block) with their count
+    conflicts: HashMap<(BlockOwner, BlockOwner), u64>,
+    /// Priorities of the elements, the lowest first
+    priorities: Arc<HashMap<BlockOwner, usize>>,
+    /// Placements refused in favour of a feature of a lower priority, the one of the highest
+    /// priority for each block
+    intents: FnvHashMap<(i32, i32, i32), Intent>,
+}
+
+/// Block a feature is to hold once the plan is committed
+struct Intent {
+    owner: BlockOwner,
+    priority: usize,
+    block: BlockWithProperties,
+}
+
+impl GenerationPlan {
+    /// Creates a plan that resolves conflicts by the priorities of the given elements
+    pub fn new(elements: &[ProcessedElement]) -> Self {
+        let priorities = elements
+            .iter()
+            .map(|element| {
+                let owner = BlockOwner {
+                    kind: element.kind(),
+                    id: element.id(),
+                };
+                (owner, get_priority(element))
+            })
+            .collect();
+        Self {
+            priorities: Arc::new(priorities),
+            ..Self::default()
+        }
+    }
+
+    /// Creates an empty plan for a part of the world, resolving conflicts like this one
+    pub fn for_tile(&self) -> Self {
+        Self {
+            priorities: self.priorities.clone(),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the priority of an owner, the lowest one for blocks of unknown features
+    fn priority(&self, owner: BlockOwner) -> usize {
+        self.priorities.get(&owner).copied().unwrap_or(usize::MAX)
+    }
+
+    /// Records that the owner placed the block at the given absolute position
+    pub fn claim(&mut self, x: i32, y: i32, z: i32, owner: BlockOwner) {
+        self.claims.insert((x, y, z), owner);
+    }
+
+    /// Returns whether the owner may replace the block at the given absolute position, which
+    /// it may not if a feature of a higher priority holds it
+    pub fn may_replace(&self, x: i32, y: i32, z: i32, owner: BlockOwner) -> bool {
+        self.claims
+            .get(&(x, y, z))
+            .is_none_or(|&holder| self.priority(owner) <= self.priority(holder))
+    }
+
+    /// Records that the owner could not place a block because another feature holds it. If
+    /// that feature has a lower priority, the block is placed when the plan is committed.
+    pub fn refuse(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        owner: BlockOwner,
+        block: BlockWithProperties,
+    ) {
+        let Some(&holder) = self.claims.get(&(x, y, z)) else {
+            return;
+        };
+        if holder == owner {
+            return;
+        }
+        *self.conflicts.entry((owner, holder)).or_default() += 1;
+
+        let priority = self.priority(owner);
+        if priority < self.priority(holder) {
+            let intent = Intent {
+                owner,
+                priority,
+                block,
+            };
+            self.add_intent((x, y, z), intent);
+        }
+    }
+
+    fn add_intent(&mut self, position: (i32, i32, i32), intent: Intent) {
+        let kept = self.intents.get(&position);
+        if kept.is_none_or(|kept| intent.priority < kept.priority) {
+            self.intents.insert(position, intent);
+        }
+    }
+
+    /// Returns the blocks to place in the commit pass, which go to features of a higher
+    /// priority than the ones holding them, and records the new owners as holding them
+    pub fn commit(&mut self) -> Vec<((i32, i32, i32), BlockOwner, BlockWithProperties)> {
+        let mut committed = Vec::new();
+        for (position, intent) in std::mem::take(&mut self.intents) {
+            let holder = self.claims.get(&position).copied();
+            if holder.is_some_and(|holder| intent.priority < self.priority(holder)) {
+                self.claims.insert(position, intent.owner);
+                committed.push((position, intent.owner, intent.block));
+            }
+        }
+        committed.sort_unstable_by_key(|&(position, _, _)| position);
+        committed
+    }
+
+    /// Adds the claims, conflicts and intents recorded by the plan of another part of the world
+    pub fn merge(&mut self, other: GenerationPlan) {
+        self.claims.extend(other.claims);
+        for (pair, count) in other.conflicts {
+            *self.conflicts.entry(pair).or_default() += count;
+        }
+        for (position, intent) in other.intents {
+            self.add_intent(position, intent);
+        }
+    }
+
+    /// Returns the number of blocks each owner holds
+    pub fn claimed_blocks(&self) -> HashMap<BlockOwner, u64> {
+        let mut counts: HashMap<BlockOwner, u64> = HashMap::new();
+        for owner in self.claims.values() {
+            *counts.entry(*owner).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Returns the refused placements as (refused owner, owner of the block) with their count
+    pub fn conflicts(&self) -> &HashMap<(BlockOwner, BlockOwner), u64> {
+        &self.conflicts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_definitions::{OAK_PLANKS, STONE};
+
+    #[test]
+    fn test_refused_placements_count_against_holder() {
+        let building = BlockOwner { kind: "way", id: 1 };
+        let road = BlockOwner { kind: "way", id: 2 };
+        let stone = || BlockWithProperties::simple(STONE);
+
+        let mut plan = GenerationPlan::default();
+        plan.claim(0, 0, 0, building);
+        plan.refuse(0, 0, 0, road, stone());
+        plan.refuse(0, 0, 0, building, stone());
+        plan.refuse(5, 0, 5, road, stone());
+
+        assert_eq!(plan.conflicts().get(&(road, building)), Some(&1));
+        assert_eq!(plan.conflicts().len(), 1);
+        assert_eq!(plan.claimed_blocks().get(&building), Some(&1));
+        // Without priorities, conflicts are only reported
+        assert!(plan.may_replace(0, 0, 0, road));
+        assert!(plan.commit().is_empty());
+    }
+
+    #[test]
+    fn test_intent_of_the_highest_priority_is_committed() {
+        let building = BlockOwner { kind: "way", id: 1 };
+        let road = BlockOwner { kind: "way", id: 2 };
+        let barrier = BlockOwner { kind: "way", id: 3 };
+        let priorities = HashMap::from([(building, 1), (road, 2), (barrier, 5)]);
+        let mut plan = GenerationPlan {
+            priorities: Arc::new(priorities),
+            ..GenerationPlan::default()
+        };
+
+        plan.claim(0, 0, 0, barrier);
+        plan.refuse(0, 0, 0, road, BlockWithProperties::simple(STONE));
+        plan.refuse(0, 0, 0, building, BlockWithProperties::simple(OAK_PLANKS));
+        plan.claim(1, 0, 0, building);
+        plan.refuse(1, 0, 0, road, BlockWithProperties::simple(STONE));
+        assert!(!plan.may_replace(1, 0, 0, road));
+        assert!(plan.may_replace(0, 0, 0, building));
+
+        let committed = plan.commit();
+        assert_eq!(committed.len(), 1);
+        let (position, owner, block) = &committed[0];
+        assert_eq!((*position, *owner), ((0, 0, 0), building));
+        assert_eq!(block.block, OAK_PLANKS);
+        assert_eq!(plan.claimed_blocks().get(&building), Some(&2));
+    }
+}